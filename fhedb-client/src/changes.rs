@@ -0,0 +1,174 @@
+//! # Change Streams
+//!
+//! Provides the reader of the changes a server streams for a database or one of its collections.
+//!
+//! The server sends the changes as server-sent events, each identified by its sequence number.
+//! A stream that was cut off can be reopened from the sequence number following
+//! [`ChangeStream::last_sequence`] to pick up where it left off, as long as the
+//! [`Change::epoch`] of the feed is still the same.
+
+use crate::{
+    client::{Client, from_json},
+    encryption::FieldTypes,
+    error::ClientError,
+};
+use bson::{Bson, Document};
+use serde::Deserialize;
+use std::io::{BufRead, BufReader, Lines};
+use ureq::BodyReader;
+
+/// A write made to a collection, as streamed by the server.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Change {
+    /// The epoch of the database's change feed, which changes whenever the server loads the
+    /// database again and restarts the sequence numbers.
+    pub epoch: u64,
+    /// The position of the change in the database's change feed.
+    pub sequence: u64,
+    /// The name of the written collection.
+    pub collection: String,
+    /// The operation, one of `INSERT`, `UPDATE` and `DELETE`.
+    pub operation: String,
+    /// The RFC 3339 timestamp of the operation.
+    pub timestamp: String,
+    /// The ID of the written document.
+    pub id: Bson,
+    /// The document after an insert or update, or as it was before a delete, decrypted.
+    pub document: Document,
+}
+
+/// A blocking reader of a change stream, obtained from
+/// [`DatabaseClient::changes`](crate::client::DatabaseClient::changes).
+///
+/// Each call to [`Iterator::next`] waits until the server sends the next change.
+/// The iterator ends when the server closes the stream.
+pub struct ChangeStream {
+    /// The lines of the response body.
+    lines: Lines<BufReader<BodyReader<'static>>>,
+    /// The client that opened the stream, holding the keys the changes are decrypted with.
+    client: Client,
+    /// The name of the database the changes are made to.
+    db_name: String,
+    /// The field types of the collections changes were received for, fetched with the
+    /// first change to each collection.
+    field_types: FieldTypes,
+    /// The sequence number of the last change received.
+    last_sequence: Option<u64>,
+    /// Whether the server ended the stream with an error.
+    finished: bool,
+}
+
+impl ChangeStream {
+    /// Creates a reader of a change stream response.
+    ///
+    /// ## Arguments
+    ///
+    /// * `body` - The [`BodyReader`] of the response.
+    /// * `client` - The [`Client`] that opened the stream.
+    /// * `db_name` - The name of the database the changes are made to.
+    pub(crate) fn new(body: BodyReader<'static>, client: Client, db_name: String) -> Self {
+        Self {
+            lines: BufReader::new(body).lines(),
+            client,
+            db_name,
+            field_types: FieldTypes::new(),
+            last_sequence: None,
+            finished: false,
+        }
+    }
+
+    /// Returns the sequence number of the last change received, if any.
+    pub fn last_sequence(&self) -> Option<u64> {
+        self.last_sequence
+    }
+
+    /// Decrypts the document of a change against the schema of its collection.
+    ///
+    /// ## Arguments
+    ///
+    /// * `change` - The [`Change`] as received.
+    fn decrypt(&mut self, mut change: Change) -> Result<Change, ClientError> {
+        if self.client.encrypted_fields().is_empty() {
+            return Ok(change);
+        }
+        self.client
+            .fetch_field_types(&self.db_name, &change.collection, &mut self.field_types)?;
+        change.document = self.client.encrypted_fields().decrypt_document(
+            &change.collection,
+            change.document,
+            &self.field_types,
+        )?;
+        Ok(change)
+    }
+
+    /// Reads the next event of the stream, skipping keep-alive comments.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`Some`]\(`(name, data)`)) with the next event,
+    /// [`Ok`]\([`None`]) if the stream is closed, or [`Err`]\([`ClientError`]) if it cannot be read.
+    fn read_event(&mut self) -> Result<Option<(String, String)>, ClientError> {
+        let mut name = String::from("message");
+        let mut data: Option<String> = None;
+        for line in self.lines.by_ref() {
+            let line = line.map_err(|e| ClientError::Transport(e.to_string()))?;
+            if line.is_empty() {
+                if let Some(data) = data.take() {
+                    return Ok(Some((name, data)));
+                }
+                name = String::from("message");
+                continue;
+            }
+
+            let (field, value) = line.split_once(':').unwrap_or((&line, ""));
+            let value = value.strip_prefix(' ').unwrap_or(value);
+            match field {
+                "event" => name = value.to_string(),
+                "data" => match &mut data {
+                    Some(data) => {
+                        data.push('\n');
+                        data.push_str(value);
+                    }
+                    None => data = Some(value.to_string()),
+                },
+                _ => {}
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl Iterator for ChangeStream {
+    type Item = Result<Change, ClientError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+
+        loop {
+            let (name, data) = match self.read_event() {
+                Ok(Some(event)) => event,
+                Ok(None) => return None,
+                Err(err) => return Some(Err(err)),
+            };
+            match name.as_str() {
+                "change" => {
+                    let change = serde_json::from_str(&data)
+                        .map_err(|e| ClientError::InvalidResponse(e.to_string()))
+                        .and_then(from_json::<Change>)
+                        .and_then(|change| self.decrypt(change));
+                    if let Ok(change) = &change {
+                        self.last_sequence = Some(change.sequence);
+                    }
+                    return Some(change);
+                }
+                "error" => {
+                    self.finished = true;
+                    return Some(Err(ClientError::StreamEnded(data)));
+                }
+                _ => continue,
+            }
+        }
+    }
+}
@@ -0,0 +1,902 @@
+//! # Client
+//!
+//! Provides the HTTP client for the Fhedb server.
+//!
+//! Every method renders its query into query text and posts it to the server: database
+//! queries to `/`, and queries within a database to `/<database name>`. Change streams are
+//! read from `/<database name>/changes`.
+
+use crate::{
+    changes::ChangeStream,
+    encryption::{EncryptedFields, FieldTypes},
+    error::ClientError,
+    query::{Condition, Selection, bson_literal, render_contextual_query, render_database_query},
+};
+use bson::{Bson, Document};
+use fhedb_types::{
+    CollectionQuery, ContextualQuery, DatabaseQuery, DocumentQuery, FieldCondition,
+    FieldModification, FieldType, Schema,
+};
+use serde::Deserialize;
+use serde_json::Value as JsonValue;
+use std::collections::HashMap;
+use ureq::Agent;
+
+/// The result of a SUM query.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SumResult {
+    /// The sum, decrypted if the field is encrypted under a held key.
+    pub sum: Bson,
+    /// The number of values summed.
+    pub count: usize,
+}
+
+/// A log entry of a document, as returned by a HISTORY query.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct HistoryEntry {
+    /// The RFC 3339 timestamp of the operation.
+    pub timestamp: String,
+    /// The operation, one of `INSERT`, `UPDATE` and `DELETE`.
+    pub operation: String,
+    /// The document after the operation, decrypted, or before it for deletes.
+    pub document: Document,
+    /// The timestamp of the document's original insert, for entries compaction merged
+    /// later updates into.
+    #[serde(default)]
+    pub inserted_at: Option<String>,
+}
+
+/// A client of a Fhedb server.
+#[derive(Debug, Clone)]
+pub struct Client {
+    /// The base URL of the server, without a trailing slash.
+    base_url: String,
+    /// The agent requests are sent with.
+    agent: Agent,
+    /// The fields encrypted before they are sent to the server.
+    encrypted_fields: EncryptedFields,
+}
+
+impl Client {
+    /// Creates a client of the server at the given URL.
+    ///
+    /// ## Arguments
+    ///
+    /// * `base_url` - The base URL of the server, such as `http://localhost:3000`.
+    pub fn new(base_url: impl Into<String>) -> Self {
+        let agent = Agent::config_builder()
+            .http_status_as_error(false)
+            .build()
+            .into();
+        Self {
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            agent,
+            encrypted_fields: EncryptedFields::new(),
+        }
+    }
+
+    /// Sets the fields to encrypt client-side, returning the updated client.
+    ///
+    /// ## Arguments
+    ///
+    /// * `encrypted_fields` - The [`EncryptedFields`] with their keys.
+    pub fn with_encrypted_fields(mut self, encrypted_fields: EncryptedFields) -> Self {
+        self.encrypted_fields = encrypted_fields;
+        self
+    }
+
+    /// Returns the fields encrypted client-side.
+    pub fn encrypted_fields(&self) -> &EncryptedFields {
+        &self.encrypted_fields
+    }
+
+    /// Returns a handle for queries within a database.
+    ///
+    /// ## Arguments
+    ///
+    /// * `name` - The name of the database.
+    pub fn database(&self, name: impl Into<String>) -> DatabaseClient<'_> {
+        DatabaseClient {
+            client: self,
+            name: name.into(),
+        }
+    }
+
+    /// Executes a database query given as query text.
+    ///
+    /// ## Arguments
+    ///
+    /// * `query` - The query text.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`JsonValue`]) with the response of the server,
+    /// or [`Err`]\([`ClientError`]) if the request fails or the server rejects the query.
+    pub fn execute(&self, query: &str) -> Result<JsonValue, ClientError> {
+        self.post(&self.base_url, query)
+    }
+
+    /// Executes a query within a database given as query text.
+    ///
+    /// ## Arguments
+    ///
+    /// * `db_name` - The name of the database.
+    /// * `query` - The query text.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`JsonValue`]) with the response of the server,
+    /// or [`Err`]\([`ClientError`]) if the request fails or the server rejects the query.
+    pub fn execute_in(&self, db_name: &str, query: &str) -> Result<JsonValue, ClientError> {
+        self.post(&format!("{}/{}", self.base_url, db_name), query)
+    }
+
+    /// Fetches the field types of a collection and of the collections it references,
+    /// directly or through others, skipping the collections whose types are already known.
+    ///
+    /// ## Arguments
+    ///
+    /// * `db_name` - The name of the database.
+    /// * `collection_name` - The name of the collection.
+    /// * `field_types` - The [`FieldTypes`] to add the fetched types to.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\(()) once the types are known,
+    /// or [`Err`]\([`ClientError`]) if a schema could not be fetched or understood.
+    pub(crate) fn fetch_field_types(
+        &self,
+        db_name: &str,
+        collection_name: &str,
+        field_types: &mut FieldTypes,
+    ) -> Result<(), ClientError> {
+        let mut pending = vec![collection_name.to_string()];
+        while let Some(name) = pending.pop() {
+            if field_types.contains_key(&name) {
+                continue;
+            }
+            let query =
+                ContextualQuery::Collection(CollectionQuery::GetSchema { name: name.clone() });
+            let response = self.execute_in(db_name, &render_contextual_query(&query)?)?;
+            let types = schema_field_types(&response)?;
+            for field_type in types.values() {
+                referenced_collections(field_type, &mut pending);
+            }
+            field_types.insert(name, types);
+        }
+        Ok(())
+    }
+
+    /// Creates a database.
+    ///
+    /// ## Arguments
+    ///
+    /// * `name` - The name of the database.
+    /// * `drop_if_exists` - Whether to drop an existing database with the same name first.
+    pub fn create_database(&self, name: &str, drop_if_exists: bool) -> Result<(), ClientError> {
+        self.run(&DatabaseQuery::Create {
+            name: name.to_string(),
+            drop_if_exists,
+        })
+        .map(|_| ())
+    }
+
+    /// Drops a database.
+    ///
+    /// ## Arguments
+    ///
+    /// * `name` - The name of the database.
+    pub fn drop_database(&self, name: &str) -> Result<(), ClientError> {
+        self.run(&DatabaseQuery::Drop {
+            name: name.to_string(),
+        })
+        .map(|_| ())
+    }
+
+    /// Lists the names of all databases.
+    pub fn list_databases(&self) -> Result<Vec<String>, ClientError> {
+        from_json(self.run(&DatabaseQuery::List)?)
+    }
+
+    /// Backs up a database into an archive file on the server.
+    ///
+    /// ## Arguments
+    ///
+    /// * `name` - The name of the database.
+    /// * `path` - The path of the archive, relative to the server's backup directory.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`JsonValue`]) with the backup report of the server,
+    /// or [`Err`]\([`ClientError`]) on failure.
+    pub fn backup_database(&self, name: &str, path: &str) -> Result<JsonValue, ClientError> {
+        self.run(&DatabaseQuery::Backup {
+            name: name.to_string(),
+            path: path.to_string(),
+        })
+    }
+
+    /// Restores a database from an archive file on the server, replacing any existing one.
+    ///
+    /// ## Arguments
+    ///
+    /// * `name` - The name to restore the database under.
+    /// * `path` - The path of the archive, relative to the server's backup directory.
+    pub fn restore_database(&self, name: &str, path: &str) -> Result<(), ClientError> {
+        self.run(&DatabaseQuery::Restore {
+            name: name.to_string(),
+            path: path.to_string(),
+        })
+        .map(|_| ())
+    }
+
+    /// Renders and executes a database query.
+    ///
+    /// ## Arguments
+    ///
+    /// * `query` - The [`DatabaseQuery`] to execute.
+    fn run(&self, query: &DatabaseQuery) -> Result<JsonValue, ClientError> {
+        self.execute(&render_database_query(query)?)
+    }
+
+    /// Posts query text to the server and reads the response.
+    ///
+    /// ## Arguments
+    ///
+    /// * `url` - The URL to post to.
+    /// * `query` - The query text.
+    fn post(&self, url: &str, query: &str) -> Result<JsonValue, ClientError> {
+        let mut response = self
+            .agent
+            .post(url)
+            .content_type("text/plain")
+            .send(query)?;
+        let status = response.status();
+        let body = response.body_mut().read_to_string()?;
+
+        if !status.is_success() {
+            return Err(ClientError::Server {
+                status: status.as_u16(),
+                message: body,
+            });
+        }
+        serde_json::from_str(&body).map_err(|e| ClientError::InvalidResponse(e.to_string()))
+    }
+}
+
+/// A handle for queries within a database, obtained from [`Client::database`].
+#[derive(Debug, Clone)]
+pub struct DatabaseClient<'a> {
+    /// The client queries are sent with.
+    client: &'a Client,
+    /// The name of the database.
+    name: String,
+}
+
+impl DatabaseClient<'_> {
+    /// Returns the name of the database.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Creates a collection.
+    ///
+    /// ## Arguments
+    ///
+    /// * `name` - The name of the collection.
+    /// * `schema` - The [`Schema`] of the collection.
+    /// * `drop_if_exists` - Whether to drop an existing collection with the same name first.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`JsonValue`]) with the schema as created by the server,
+    /// or [`Err`]\([`ClientError`]) on failure.
+    pub fn create_collection(
+        &self,
+        name: &str,
+        schema: Schema,
+        drop_if_exists: bool,
+    ) -> Result<JsonValue, ClientError> {
+        self.run_collection(CollectionQuery::Create {
+            name: name.to_string(),
+            drop_if_exists,
+            schema,
+        })
+    }
+
+    /// Drops a collection.
+    ///
+    /// ## Arguments
+    ///
+    /// * `name` - The name of the collection.
+    pub fn drop_collection(&self, name: &str) -> Result<(), ClientError> {
+        self.run_collection(CollectionQuery::Drop {
+            name: name.to_string(),
+        })
+        .map(|_| ())
+    }
+
+    /// Modifies the schema of a collection.
+    ///
+    /// ## Arguments
+    ///
+    /// * `name` - The name of the collection.
+    /// * `modifications` - The [`FieldModification`] of each field to change.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`JsonValue`]) with the modified schema,
+    /// or [`Err`]\([`ClientError`]) on failure.
+    pub fn modify_collection(
+        &self,
+        name: &str,
+        modifications: HashMap<String, FieldModification>,
+    ) -> Result<JsonValue, ClientError> {
+        self.run_collection(CollectionQuery::Modify {
+            name: name.to_string(),
+            modifications,
+        })
+    }
+
+    /// Lists the names of all collections in the database.
+    pub fn list_collections(&self) -> Result<Vec<String>, ClientError> {
+        from_json(self.run_collection(CollectionQuery::List)?)
+    }
+
+    /// Gets the schema of a collection and its indexes.
+    ///
+    /// ## Arguments
+    ///
+    /// * `name` - The name of the collection.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`JsonValue`]) with the fields under `fields` and the fields of
+    /// every index under `indexes`, as reported by the server,
+    /// or [`Err`]\([`ClientError`]) on failure.
+    pub fn get_schema(&self, name: &str) -> Result<JsonValue, ClientError> {
+        self.run_collection(CollectionQuery::GetSchema {
+            name: name.to_string(),
+        })
+    }
+
+    /// Compacts the log of a collection.
+    ///
+    /// ## Arguments
+    ///
+    /// * `name` - The name of the collection.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`JsonValue`]) with the compaction report of the server,
+    /// or [`Err`]\([`ClientError`]) on failure.
+    pub fn compact_collection(&self, name: &str) -> Result<JsonValue, ClientError> {
+        self.run_collection(CollectionQuery::Compact {
+            name: name.to_string(),
+        })
+    }
+
+    /// Creates an index on a field of a collection.
+    ///
+    /// ## Arguments
+    ///
+    /// * `name` - The name of the collection.
+    /// * `field_name` - The name of the field to index.
+    pub fn create_index(&self, name: &str, field_name: &str) -> Result<(), ClientError> {
+        self.create_compound_index(name, &[field_name])
+    }
+
+    /// Creates a compound index on an ordered list of fields of a collection.
+    ///
+    /// ## Arguments
+    ///
+    /// * `name` - The name of the collection.
+    /// * `field_names` - The names of the fields to index, in order.
+    pub fn create_compound_index(
+        &self,
+        name: &str,
+        field_names: &[&str],
+    ) -> Result<(), ClientError> {
+        self.run_collection(CollectionQuery::CreateIndex {
+            name: name.to_string(),
+            field_names: field_names.iter().map(|field| field.to_string()).collect(),
+        })
+        .map(|_| ())
+    }
+
+    /// Drops the index on a field of a collection.
+    ///
+    /// ## Arguments
+    ///
+    /// * `name` - The name of the collection.
+    /// * `field_name` - The name of the indexed field.
+    pub fn drop_index(&self, name: &str, field_name: &str) -> Result<(), ClientError> {
+        self.drop_compound_index(name, &[field_name])
+    }
+
+    /// Drops the compound index on an ordered list of fields of a collection.
+    ///
+    /// ## Arguments
+    ///
+    /// * `name` - The name of the collection.
+    /// * `field_names` - The names of the indexed fields, in order.
+    pub fn drop_compound_index(&self, name: &str, field_names: &[&str]) -> Result<(), ClientError> {
+        self.run_collection(CollectionQuery::DropIndex {
+            name: name.to_string(),
+            field_names: field_names.iter().map(|field| field.to_string()).collect(),
+        })
+        .map(|_| ())
+    }
+
+    /// Inserts a document, encrypting the values of its encrypted fields first.
+    ///
+    /// ## Arguments
+    ///
+    /// * `collection_name` - The name of the collection.
+    /// * `document` - The plaintext [`Document`] to insert.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`Document`]) with the inserted document, including its ID,
+    /// or [`Err`]\([`ClientError`]) on failure.
+    pub fn insert_document(
+        &self,
+        collection_name: &str,
+        document: &Document,
+    ) -> Result<Document, ClientError> {
+        let fields = self.assignments(collection_name, document)?;
+        let response = self.run_document(DocumentQuery::Insert {
+            collection_name: collection_name.to_string(),
+            fields,
+        })?;
+        self.documents(collection_name, response)?
+            .pop()
+            .ok_or_else(|| ClientError::InvalidResponse("No document was inserted".to_string()))
+    }
+
+    /// Gets the documents matching the conditions.
+    ///
+    /// ## Arguments
+    ///
+    /// * `collection_name` - The name of the collection.
+    /// * `conditions` - The plaintext [`Condition`]s documents must match.
+    /// * `selection` - The [`Selection`] of fields to return.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`Vec<Document>`]) with the decrypted documents,
+    /// or [`Err`]\([`ClientError`]) on failure.
+    pub fn get_documents(
+        &self,
+        collection_name: &str,
+        conditions: &[Condition],
+        selection: &Selection,
+    ) -> Result<Vec<Document>, ClientError> {
+        self.get(collection_name, conditions, selection, None)
+    }
+
+    /// Gets the documents that matched the conditions at a past instant.
+    ///
+    /// ## Arguments
+    ///
+    /// * `collection_name` - The name of the collection.
+    /// * `as_of` - The RFC 3339 timestamp to read the collection at.
+    /// * `conditions` - The plaintext [`Condition`]s documents must match.
+    /// * `selection` - The [`Selection`] of fields to return.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`Vec<Document>`]) with the decrypted documents,
+    /// or [`Err`]\([`ClientError`]) on failure.
+    pub fn get_documents_as_of(
+        &self,
+        collection_name: &str,
+        as_of: &str,
+        conditions: &[Condition],
+        selection: &Selection,
+    ) -> Result<Vec<Document>, ClientError> {
+        self.get(
+            collection_name,
+            conditions,
+            selection,
+            Some(as_of.to_string()),
+        )
+    }
+
+    /// Runs a GET query and decrypts the documents it returns.
+    fn get(
+        &self,
+        collection_name: &str,
+        conditions: &[Condition],
+        selection: &Selection,
+        as_of: Option<String>,
+    ) -> Result<Vec<Document>, ClientError> {
+        let response = self.run_document(DocumentQuery::Get {
+            collection_name: collection_name.to_string(),
+            conditions: self.conditions(collection_name, conditions)?,
+            selectors: selection.to_selectors(),
+            as_of,
+        })?;
+        self.documents(collection_name, response)
+    }
+
+    /// Updates the documents matching the conditions, encrypting the values of encrypted
+    /// fields first.
+    ///
+    /// ## Arguments
+    ///
+    /// * `collection_name` - The name of the collection.
+    /// * `conditions` - The plaintext [`Condition`]s documents must match.
+    /// * `updates` - The plaintext values of the fields to update.
+    /// * `selection` - The [`Selection`] of fields to return.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`Vec<Document>`]) with the decrypted updated documents,
+    /// or [`Err`]\([`ClientError`]) on failure.
+    pub fn update_documents(
+        &self,
+        collection_name: &str,
+        conditions: &[Condition],
+        updates: &Document,
+        selection: &Selection,
+    ) -> Result<Vec<Document>, ClientError> {
+        let response = self.run_document(DocumentQuery::Update {
+            collection_name: collection_name.to_string(),
+            conditions: self.conditions(collection_name, conditions)?,
+            updates: self.assignments(collection_name, updates)?,
+            selectors: selection.to_selectors(),
+        })?;
+        self.documents(collection_name, response)
+    }
+
+    /// Deletes the documents matching the conditions.
+    ///
+    /// ## Arguments
+    ///
+    /// * `collection_name` - The name of the collection.
+    /// * `conditions` - The plaintext [`Condition`]s documents must match.
+    /// * `selection` - The [`Selection`] of fields to return.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`Vec<Document>`]) with the decrypted deleted documents,
+    /// or [`Err`]\([`ClientError`]) on failure.
+    pub fn delete_documents(
+        &self,
+        collection_name: &str,
+        conditions: &[Condition],
+        selection: &Selection,
+    ) -> Result<Vec<Document>, ClientError> {
+        let response = self.run_document(DocumentQuery::Delete {
+            collection_name: collection_name.to_string(),
+            conditions: self.conditions(collection_name, conditions)?,
+            selectors: selection.to_selectors(),
+        })?;
+        self.documents(collection_name, response)
+    }
+
+    /// Sums a numeric field over the documents matching the conditions.
+    ///
+    /// The sum of an encrypted field is computed by the server without decrypting it, and
+    /// decrypted by the client.
+    ///
+    /// ## Arguments
+    ///
+    /// * `collection_name` - The name of the collection.
+    /// * `field_name` - The name of the field to sum.
+    /// * `conditions` - The plaintext [`Condition`]s documents must match.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`SumResult`]) with the decrypted sum,
+    /// or [`Err`]\([`ClientError`]) on failure.
+    pub fn sum(
+        &self,
+        collection_name: &str,
+        field_name: &str,
+        conditions: &[Condition],
+    ) -> Result<SumResult, ClientError> {
+        let response = self.run_document(DocumentQuery::Sum {
+            collection_name: collection_name.to_string(),
+            field_name: field_name.to_string(),
+            conditions: self.conditions(collection_name, conditions)?,
+        })?;
+
+        let mut result: Document = from_json(response)?;
+        let sum = result.remove("sum").unwrap_or(Bson::Null);
+        let count = match result.get("count") {
+            Some(Bson::Int64(count)) => *count as usize,
+            Some(Bson::Int32(count)) => *count as usize,
+            _ => {
+                return Err(ClientError::InvalidResponse(
+                    "Sum response has no count".to_string(),
+                ));
+            }
+        };
+        let field_types = self.field_types(collection_name)?;
+        let sum = match field_types
+            .get(collection_name)
+            .and_then(|types| types.get(field_name))
+        {
+            Some(field_type) => {
+                self.client
+                    .encrypted_fields
+                    .decrypt_value(field_type, sum, &field_types)?
+            }
+            None => sum,
+        };
+        Ok(SumResult { sum, count })
+    }
+
+    /// Lists the log entries of the document selected by a condition on its ID, oldest first.
+    ///
+    /// ## Arguments
+    ///
+    /// * `collection_name` - The name of the collection.
+    /// * `id_condition` - The equality [`Condition`] on the collection's ID field.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`Vec<HistoryEntry>`]) with the decrypted entries,
+    /// or [`Err`]\([`ClientError`]) on failure.
+    pub fn document_history(
+        &self,
+        collection_name: &str,
+        id_condition: &Condition,
+    ) -> Result<Vec<HistoryEntry>, ClientError> {
+        let response = self.run_document(DocumentQuery::History {
+            collection_name: collection_name.to_string(),
+            conditions: self.conditions(collection_name, std::slice::from_ref(id_condition))?,
+        })?;
+
+        let field_types = self.field_types(collection_name)?;
+        from_json::<Vec<HistoryEntry>>(response)?
+            .into_iter()
+            .map(|mut entry| {
+                entry.document = self.client.encrypted_fields.decrypt_document(
+                    collection_name,
+                    entry.document,
+                    &field_types,
+                )?;
+                Ok(entry)
+            })
+            .collect()
+    }
+
+    /// Opens the stream of the changes made to the database or one of its collections.
+    ///
+    /// ## Arguments
+    ///
+    /// * `collection_name` - The name of the collection to stream the changes of,
+    ///   or [`None`] for every collection.
+    /// * `from` - The sequence number of the first change to receive,
+    ///   or [`None`] to only receive changes made from now on.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`ChangeStream`]) reading the decrypted changes, or [`Err`]\([`ClientError`])
+    /// if the collection does not exist or the changes from `from` are no longer available.
+    pub fn changes(
+        &self,
+        collection_name: Option<&str>,
+        from: Option<u64>,
+    ) -> Result<ChangeStream, ClientError> {
+        let mut request = self
+            .client
+            .agent
+            .get(format!("{}/{}/changes", self.client.base_url, self.name));
+        if let Some(collection_name) = collection_name {
+            request = request.query("collection", collection_name);
+        }
+        if let Some(from) = from {
+            request = request.query("from", from.to_string());
+        }
+
+        let mut response = request.call()?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(ClientError::Server {
+                status: status.as_u16(),
+                message: response.body_mut().read_to_string()?,
+            });
+        }
+        Ok(ChangeStream::new(
+            response.into_body().into_reader(),
+            self.client.clone(),
+            self.name.clone(),
+        ))
+    }
+
+    /// Renders the assignments of a document, encrypting the values of encrypted fields.
+    ///
+    /// ## Arguments
+    ///
+    /// * `collection_name` - The name of the collection of the document.
+    /// * `document` - The plaintext [`Document`].
+    fn assignments(
+        &self,
+        collection_name: &str,
+        document: &Document,
+    ) -> Result<HashMap<String, String>, ClientError> {
+        self.client
+            .encrypted_fields
+            .encrypt_document(collection_name, document)?
+            .iter()
+            .map(|(field_name, value)| Ok((field_name.clone(), bson_literal(value)?)))
+            .collect()
+    }
+
+    /// Renders conditions, encrypting the values of conditions on encrypted fields.
+    ///
+    /// ## Arguments
+    ///
+    /// * `collection_name` - The name of the collection the conditions apply to.
+    /// * `conditions` - The plaintext [`Condition`]s.
+    fn conditions(
+        &self,
+        collection_name: &str,
+        conditions: &[Condition],
+    ) -> Result<Vec<FieldCondition>, ClientError> {
+        conditions
+            .iter()
+            .map(|condition| {
+                self.client
+                    .encrypted_fields
+                    .encrypt_condition(collection_name, condition)?
+                    .to_field_condition()
+            })
+            .collect()
+    }
+
+    /// Converts a response holding an array of documents, decrypting them.
+    ///
+    /// ## Arguments
+    ///
+    /// * `collection_name` - The name of the collection of the documents.
+    /// * `response` - The response of the server.
+    fn documents(
+        &self,
+        collection_name: &str,
+        response: JsonValue,
+    ) -> Result<Vec<Document>, ClientError> {
+        let field_types = self.field_types(collection_name)?;
+        from_json::<Vec<Document>>(response)?
+            .into_iter()
+            .map(|document| {
+                self.client.encrypted_fields.decrypt_document(
+                    collection_name,
+                    document,
+                    &field_types,
+                )
+            })
+            .collect()
+    }
+
+    /// Fetches the field types that results from a collection are decrypted against.
+    ///
+    /// They are only fetched if the client holds keys, as nothing can be decrypted otherwise.
+    ///
+    /// ## Arguments
+    ///
+    /// * `collection_name` - The name of the collection.
+    fn field_types(&self, collection_name: &str) -> Result<FieldTypes, ClientError> {
+        let mut field_types = FieldTypes::new();
+        if !self.client.encrypted_fields.is_empty() {
+            self.client
+                .fetch_field_types(&self.name, collection_name, &mut field_types)?;
+        }
+        Ok(field_types)
+    }
+
+    /// Executes a collection query within the database.
+    ///
+    /// ## Arguments
+    ///
+    /// * `query` - The [`CollectionQuery`] to execute.
+    fn run_collection(&self, query: CollectionQuery) -> Result<JsonValue, ClientError> {
+        self.run(&ContextualQuery::Collection(query))
+    }
+
+    /// Executes a document query within the database.
+    ///
+    /// ## Arguments
+    ///
+    /// * `query` - The [`DocumentQuery`] to execute.
+    fn run_document(&self, query: DocumentQuery) -> Result<JsonValue, ClientError> {
+        self.run(&ContextualQuery::Document(query))
+    }
+
+    /// Renders and executes a query within the database.
+    ///
+    /// ## Arguments
+    ///
+    /// * `query` - The [`ContextualQuery`] to execute.
+    fn run(&self, query: &ContextualQuery) -> Result<JsonValue, ClientError> {
+        self.client
+            .execute_in(&self.name, &render_contextual_query(query)?)
+    }
+}
+
+/// Reads the field types from the response to a GET SCHEMA query.
+///
+/// ## Arguments
+///
+/// * `response` - The response of the server.
+fn schema_field_types(response: &JsonValue) -> Result<HashMap<String, FieldType>, ClientError> {
+    let invalid = || ClientError::InvalidResponse("Schema response has no fields".to_string());
+    response["fields"]
+        .as_object()
+        .ok_or_else(invalid)?
+        .iter()
+        .map(|(field_name, definition)| {
+            let text = definition["type"].as_str().ok_or_else(invalid)?;
+            let field_type = parse_field_type(text).ok_or_else(|| {
+                ClientError::InvalidResponse(format!("Unknown field type '{}'", text))
+            })?;
+            let field_type = match definition["nullable"].as_bool() {
+                Some(true) => FieldType::Nullable(Box::new(field_type)),
+                _ => field_type,
+            };
+            Ok((field_name.clone(), field_type))
+        })
+        .collect()
+}
+
+/// Parses a field type as the server describes it in schemas, such as `array(reference(users))`.
+///
+/// ## Arguments
+///
+/// * `text` - The description of the type.
+fn parse_field_type(text: &str) -> Option<FieldType> {
+    let argument = |prefix: &str| text.strip_prefix(prefix)?.strip_suffix(')');
+    if let Some(inner) = argument("array(") {
+        return parse_field_type(inner).map(|inner| FieldType::Array(Box::new(inner)));
+    }
+    if let Some(inner) = argument("nullable(") {
+        return parse_field_type(inner).map(|inner| FieldType::Nullable(Box::new(inner)));
+    }
+    if let Some(collection_name) = argument("reference(") {
+        return Some(FieldType::Reference(collection_name.to_string()));
+    }
+    match text {
+        "int" => Some(FieldType::Int),
+        "float" => Some(FieldType::Float),
+        "boolean" => Some(FieldType::Boolean),
+        "string" => Some(FieldType::String),
+        "id_int" => Some(FieldType::IdInt),
+        "id_string" => Some(FieldType::IdString),
+        "encrypted_int" => Some(FieldType::EncryptedInt),
+        "encrypted_string" => Some(FieldType::EncryptedString),
+        _ => None,
+    }
+}
+
+/// Collects the names of the collections a field type references.
+///
+/// ## Arguments
+///
+/// * `field_type` - The [`FieldType`] to inspect.
+/// * `names` - The names to add the referenced collections to.
+fn referenced_collections(field_type: &FieldType, names: &mut Vec<String>) {
+    match field_type {
+        FieldType::Reference(collection_name) => names.push(collection_name.clone()),
+        FieldType::Array(inner) | FieldType::Nullable(inner) => {
+            referenced_collections(inner, names)
+        }
+        _ => {}
+    }
+}
+
+/// Converts a JSON response into the type the query returns.
+///
+/// ## Arguments
+///
+/// * `response` - The response of the server.
+pub(crate) fn from_json<T: serde::de::DeserializeOwned>(
+    response: JsonValue,
+) -> Result<T, ClientError> {
+    let value = bson::serialize_to_bson(&response)
+        .map_err(|e| ClientError::InvalidResponse(e.to_string()))?;
+    bson::deserialize_from_bson(value).map_err(|e| ClientError::InvalidResponse(e.to_string()))
+}
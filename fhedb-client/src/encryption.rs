@@ -0,0 +1,390 @@
+//! # Field Encryption
+//!
+//! Provides client-side encryption of designated fields, so that the server only ever
+//! stores and processes their ciphertexts.
+//!
+//! Integer values are encrypted into `encrypted_int` values and string values into
+//! `encrypted_string` values, which the fields must be declared as in the schema.
+//! Results are decrypted against the schemas of their collections: only the values of
+//! `encrypted_int` and `encrypted_string` fields are decrypted, under whichever held key
+//! they are encrypted with, including in documents joined in from referenced collections.
+//! Encrypted values under other keys are returned as they are.
+//!
+//! Keys are kept across runs in key files, written by [`save_key`] and read back by
+//! [`load_key`]. A key file holds the whole key, secret parts included, so it must be
+//! kept as private as the plaintexts it protects.
+
+use crate::{error::ClientError, query::Condition};
+use bson::{Bson, Document};
+use fhedb_core::fhe::{EncryptedInt, EncryptedString, FheKey};
+use fhedb_types::{FieldType, QueryOperator};
+use std::{
+    collections::HashMap,
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::Path,
+    sync::Arc,
+};
+
+/// The types of the fields of collections, by collection and field name, which results are
+/// decrypted against.
+pub type FieldTypes = HashMap<String, HashMap<String, FieldType>>;
+
+/// The fields encrypted client-side, with the keys they are encrypted under.
+#[derive(Debug, Clone, Default)]
+pub struct EncryptedFields {
+    /// The distinct keys held.
+    keys: Vec<Arc<FheKey>>,
+    /// The index of the key of each encrypted field, by collection and field name.
+    fields: HashMap<String, HashMap<String, usize>>,
+}
+
+impl EncryptedFields {
+    /// Creates an empty set of encrypted fields.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Designates a field to be encrypted under a key, returning the updated set.
+    ///
+    /// ## Arguments
+    ///
+    /// * `collection_name` - The name of the collection of the field.
+    /// * `field_name` - The name of the field.
+    /// * `key` - The [`FheKey`] to encrypt the field's values under.
+    pub fn with_field(
+        mut self,
+        collection_name: impl Into<String>,
+        field_name: impl Into<String>,
+        key: Arc<FheKey>,
+    ) -> Self {
+        self.insert(collection_name, field_name, key);
+        self
+    }
+
+    /// Designates a field to be encrypted under a key, replacing any previous key.
+    ///
+    /// ## Arguments
+    ///
+    /// * `collection_name` - The name of the collection of the field.
+    /// * `field_name` - The name of the field.
+    /// * `key` - The [`FheKey`] to encrypt the field's values under.
+    pub fn insert(
+        &mut self,
+        collection_name: impl Into<String>,
+        field_name: impl Into<String>,
+        key: Arc<FheKey>,
+    ) {
+        let index = match self.keys.iter().position(|held| Arc::ptr_eq(held, &key)) {
+            Some(index) => index,
+            None => {
+                self.keys.push(key);
+                self.keys.len() - 1
+            }
+        };
+        self.fields
+            .entry(collection_name.into())
+            .or_default()
+            .insert(field_name.into(), index);
+    }
+
+    /// Returns the key a field is encrypted under, if it is encrypted.
+    ///
+    /// ## Arguments
+    ///
+    /// * `collection_name` - The name of the collection of the field.
+    /// * `field_name` - The name of the field.
+    pub fn key(&self, collection_name: &str, field_name: &str) -> Option<&FheKey> {
+        let index = self.fields.get(collection_name)?.get(field_name)?;
+        Some(&self.keys[*index])
+    }
+
+    /// Checks whether a field is encrypted.
+    ///
+    /// ## Arguments
+    ///
+    /// * `collection_name` - The name of the collection of the field.
+    /// * `field_name` - The name of the field.
+    pub fn is_encrypted(&self, collection_name: &str, field_name: &str) -> bool {
+        self.key(collection_name, field_name).is_some()
+    }
+
+    /// Checks whether no field is encrypted.
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    /// Encrypts a value of a field, if the field is encrypted.
+    ///
+    /// ## Arguments
+    ///
+    /// * `collection_name` - The name of the collection of the field.
+    /// * `field_name` - The name of the field.
+    /// * `value` - The plaintext value.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`Bson`]) with the encrypted value, or the value itself if the field is
+    /// not encrypted or the value is null, or [`Err`]\([`ClientError`]) if the value is
+    /// neither an integer nor a string.
+    pub fn encrypt_value(
+        &self,
+        collection_name: &str,
+        field_name: &str,
+        value: &Bson,
+    ) -> Result<Bson, ClientError> {
+        let Some(key) = self.key(collection_name, field_name) else {
+            return Ok(value.clone());
+        };
+        match value {
+            Bson::Null => Ok(Bson::Null),
+            Bson::Int32(n) => Ok(Bson::String(key.encrypt_int(*n as i64).to_string())),
+            Bson::Int64(n) => Ok(Bson::String(key.encrypt_int(*n).to_string())),
+            Bson::String(s) => Ok(Bson::String(
+                key.encrypt_string(s)
+                    .map_err(ClientError::Encryption)?
+                    .to_string(),
+            )),
+            _ => Err(ClientError::Encryption(format!(
+                "Value of encrypted field '{}' must be an integer or a string",
+                field_name
+            ))),
+        }
+    }
+
+    /// Encrypts the values of the encrypted fields of a document.
+    ///
+    /// ## Arguments
+    ///
+    /// * `collection_name` - The name of the collection of the document.
+    /// * `document` - The plaintext [`Document`].
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`Document`]) with the encrypted document,
+    /// or [`Err`]\([`ClientError`]) if a value cannot be encrypted.
+    pub fn encrypt_document(
+        &self,
+        collection_name: &str,
+        document: &Document,
+    ) -> Result<Document, ClientError> {
+        document
+            .iter()
+            .map(|(field_name, value)| {
+                let value = self.encrypt_value(collection_name, field_name, value)?;
+                Ok((field_name.clone(), value))
+            })
+            .collect()
+    }
+
+    /// Encrypts the value of a condition on an encrypted field.
+    ///
+    /// ## Arguments
+    ///
+    /// * `collection_name` - The name of the collection the condition applies to.
+    /// * `condition` - The plaintext [`Condition`].
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`Condition`]) with the encrypted condition, or
+    /// [`Err`]\([`ClientError`]) if the field is encrypted and the operator is neither
+    /// `=` nor `!=`, or the value cannot be encrypted.
+    pub fn encrypt_condition(
+        &self,
+        collection_name: &str,
+        condition: &Condition,
+    ) -> Result<Condition, ClientError> {
+        if !self.is_encrypted(collection_name, &condition.field_name) {
+            return Ok(condition.clone());
+        }
+        if !matches!(
+            condition.operator,
+            QueryOperator::Equal | QueryOperator::NotEqual
+        ) {
+            return Err(ClientError::Encryption(format!(
+                "Operator '{}' is not supported on encrypted field '{}', only '=' and '!=' are",
+                condition.operator, condition.field_name
+            )));
+        }
+        Ok(Condition {
+            value: self.encrypt_value(collection_name, &condition.field_name, &condition.value)?,
+            ..condition.clone()
+        })
+    }
+
+    /// Decrypts a value of a field of the given type, if the type is encrypted and the value
+    /// is encrypted under a held key, recursing into arrays and joined documents.
+    ///
+    /// ## Arguments
+    ///
+    /// * `field_type` - The [`FieldType`] of the field in its collection's schema.
+    /// * `value` - The value to decrypt.
+    /// * `field_types` - The [`FieldTypes`] of the collections joined documents come from.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`Bson`]) with the decrypted value,
+    /// or [`Err`]\([`ClientError`]) if an encrypted sum overflows a 64-bit integer.
+    pub fn decrypt_value(
+        &self,
+        field_type: &FieldType,
+        value: Bson,
+        field_types: &FieldTypes,
+    ) -> Result<Bson, ClientError> {
+        match (field_type, value) {
+            (FieldType::Nullable(inner), value) => self.decrypt_value(inner, value, field_types),
+            (FieldType::EncryptedInt, Bson::String(s)) => self.decrypt_int(s),
+            (FieldType::EncryptedString, Bson::String(s)) => Ok(self.decrypt_string(s)),
+            (FieldType::Array(inner), Bson::Array(items)) => Ok(Bson::Array(
+                items
+                    .into_iter()
+                    .map(|item| self.decrypt_value(inner, item, field_types))
+                    .collect::<Result<_, _>>()?,
+            )),
+            (FieldType::Reference(collection_name), Bson::Document(document)) => Ok(
+                Bson::Document(self.decrypt_document(collection_name, document, field_types)?),
+            ),
+            (_, value) => Ok(value),
+        }
+    }
+
+    /// Decrypts the values of the encrypted fields of a document under the held keys.
+    ///
+    /// Fields that are missing from the collection's schema are returned as they are.
+    ///
+    /// ## Arguments
+    ///
+    /// * `collection_name` - The name of the collection of the document.
+    /// * `document` - The [`Document`] to decrypt.
+    /// * `field_types` - The [`FieldTypes`] of the collection and of the collections
+    ///   joined documents come from.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`Document`]) with the decrypted document,
+    /// or [`Err`]\([`ClientError`]) if an encrypted sum overflows a 64-bit integer.
+    pub fn decrypt_document(
+        &self,
+        collection_name: &str,
+        document: Document,
+        field_types: &FieldTypes,
+    ) -> Result<Document, ClientError> {
+        let types = field_types.get(collection_name);
+        document
+            .into_iter()
+            .map(|(field_name, value)| {
+                let value = match types.and_then(|types| types.get(&field_name)) {
+                    Some(field_type) => self.decrypt_value(field_type, value, field_types)?,
+                    None => value,
+                };
+                Ok((field_name, value))
+            })
+            .collect()
+    }
+
+    /// Decrypts an `encrypted_int` value, if it is encrypted under a held key.
+    ///
+    /// ## Arguments
+    ///
+    /// * `value` - The encrypted value.
+    fn decrypt_int(&self, value: String) -> Result<Bson, ClientError> {
+        let Ok(encrypted) = value.parse::<EncryptedInt>() else {
+            return Ok(Bson::String(value));
+        };
+        match self
+            .keys
+            .iter()
+            .find(|key| key.public_key() == encrypted.public_key())
+        {
+            Some(key) => key
+                .decrypt_int(&encrypted)
+                .map(Bson::Int64)
+                .map_err(ClientError::Encryption),
+            None => Ok(Bson::String(value)),
+        }
+    }
+
+    /// Decrypts an `encrypted_string` value, if it is encrypted under a held key.
+    ///
+    /// ## Arguments
+    ///
+    /// * `value` - The encrypted value.
+    fn decrypt_string(&self, value: String) -> Bson {
+        let Ok(encrypted) = value.parse::<EncryptedString>() else {
+            return Bson::String(value);
+        };
+        self.keys
+            .iter()
+            .find_map(|key| key.decrypt_string(&encrypted).ok())
+            .map_or(Bson::String(value), Bson::String)
+    }
+}
+
+/// Reads a key from a key file written by [`save_key`].
+///
+/// ## Arguments
+///
+/// * `path` - The path to the key file.
+///
+/// ## Returns
+///
+/// Returns [`Ok`]\([`Arc<FheKey>`]) with the key, ready to be passed to
+/// [`EncryptedFields::with_field`], or [`Err`]\([`ClientError`]) if the file could not be read
+/// or holds no valid key.
+pub fn load_key(path: impl AsRef<Path>) -> Result<Arc<FheKey>, ClientError> {
+    let path = path.as_ref();
+    let bytes = fs::read(path).map_err(|e| key_file_error(path, e))?;
+    FheKey::from_bytes(&bytes)
+        .map(Arc::new)
+        .map_err(|e| ClientError::KeyFile(format!("{}: {}", path.display(), e)))
+}
+
+/// Writes a key to a key file, replacing any existing file.
+///
+/// The key is written to a temporary file first and then renamed over the target, so an
+/// existing key file is never left partially written. On Unix, the file is only readable
+/// by its owner.
+///
+/// ## Arguments
+///
+/// * `key` - The [`FheKey`] to write.
+/// * `path` - The path to the key file.
+///
+/// ## Returns
+///
+/// Returns [`Ok`]\(()) if the key was written,
+/// or [`Err`]\([`ClientError`]) if the file could not be written.
+pub fn save_key(key: &FheKey, path: impl AsRef<Path>) -> Result<(), ClientError> {
+    let path = path.as_ref();
+    let mut temp_name = path.file_name().unwrap_or_default().to_os_string();
+    temp_name.push(".tmp");
+    let temp_path = path.with_file_name(temp_name);
+
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+    let result = options
+        .open(&temp_path)
+        .and_then(|mut file| {
+            file.write_all(&key.to_bytes())?;
+            file.sync_all()
+        })
+        .and_then(|_| fs::rename(&temp_path, path));
+    if let Err(e) = result {
+        let _ = fs::remove_file(&temp_path);
+        return Err(key_file_error(path, e));
+    }
+    Ok(())
+}
+
+/// Wraps an I/O error on a key file into a [`ClientError`].
+///
+/// ## Arguments
+///
+/// * `path` - The path to the key file.
+/// * `error` - The [`io::Error`] that occurred.
+fn key_file_error(path: &Path, error: io::Error) -> ClientError {
+    ClientError::KeyFile(format!("{}: {}", path.display(), error))
+}
@@ -0,0 +1,53 @@
+//! # Client Errors
+//!
+//! Provides the error type returned by client operations.
+
+use std::fmt;
+
+/// An error returned by a client operation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClientError {
+    /// The request could not be sent, or its response could not be read.
+    Transport(String),
+    /// The server rejected the query.
+    Server {
+        /// The HTTP status code of the response.
+        status: u16,
+        /// The error message returned by the server.
+        message: String,
+    },
+    /// The server responded with something other than what the query returns.
+    InvalidResponse(String),
+    /// A value or name cannot be expressed in a query.
+    InvalidQuery(String),
+    /// A value of an encrypted field could not be encrypted or decrypted.
+    Encryption(String),
+    /// A key file could not be read or written, or holds no valid key.
+    KeyFile(String),
+    /// The server ended a change stream because it cannot continue it.
+    StreamEnded(String),
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientError::Transport(message) => write!(f, "Request failed: {}", message),
+            ClientError::Server { status, message } => {
+                write!(f, "Server returned {}: {}", status, message)
+            }
+            ClientError::InvalidResponse(message) => write!(f, "Invalid response: {}", message),
+            ClientError::InvalidQuery(message) => write!(f, "Invalid query: {}", message),
+            ClientError::Encryption(message) => write!(f, "Encryption failed: {}", message),
+            ClientError::KeyFile(message) => write!(f, "Invalid key file: {}", message),
+            ClientError::StreamEnded(message) => write!(f, "Change stream ended: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {}
+
+impl From<ureq::Error> for ClientError {
+    fn from(error: ureq::Error) -> Self {
+        ClientError::Transport(error.to_string())
+    }
+}
@@ -0,0 +1,31 @@
+//! # Fhedb Client
+//!
+//! This crate provides a client for the Fhedb server, with typed methods for its queries.
+//!
+//! The client can hold the keys of encrypted fields, in which case their values are
+//! encrypted before they are sent and decrypted once they are received, so that the
+//! server never sees their plaintexts. Keys are saved to and loaded from key files, so
+//! that values stay readable across runs.
+
+/// Readers of the change streams of databases.
+pub mod changes;
+/// The HTTP client and its database handles.
+pub mod client;
+/// Client-side encryption of designated fields.
+pub mod encryption;
+/// Error types returned by the client.
+pub mod error;
+/// Typed query building blocks and query rendering.
+pub mod query;
+
+/// Commonly used types re-exported for easy access.
+pub mod prelude {
+    pub use crate::{
+        changes::{Change, ChangeStream},
+        client::{Client, DatabaseClient, HistoryEntry, SumResult},
+        encryption::{EncryptedFields, FieldTypes, load_key, save_key},
+        error::ClientError,
+        query::{Condition, Selection},
+    };
+    pub use fhedb_core::fhe::FheKey;
+}
@@ -0,0 +1,503 @@
+//! # Query Rendering
+//!
+//! Provides the typed building blocks of client queries, and renders query ASTs into the
+//! query text the server parses.
+
+use crate::error::ClientError;
+use bson::Bson;
+use fhedb_types::{
+    CollectionQuery, ContextualQuery, DatabaseQuery, DocumentQuery, FieldCondition,
+    FieldDefinition, FieldModification, FieldSelector, FieldType, ParsedDocContent, QueryOperator,
+};
+use std::collections::HashMap;
+
+/// A condition on a document field, with a typed value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Condition {
+    /// The name of the field to apply the condition to.
+    pub field_name: String,
+    /// The comparison operator to use.
+    pub operator: QueryOperator,
+    /// The value to compare the field against.
+    pub value: Bson,
+}
+
+impl Condition {
+    /// Creates a condition.
+    ///
+    /// ## Arguments
+    ///
+    /// * `field_name` - The name of the field to apply the condition to.
+    /// * `operator` - The [`QueryOperator`] to compare with.
+    /// * `value` - The value to compare the field against.
+    pub fn new(
+        field_name: impl Into<String>,
+        operator: QueryOperator,
+        value: impl Into<Bson>,
+    ) -> Self {
+        Self {
+            field_name: field_name.into(),
+            operator,
+            value: value.into(),
+        }
+    }
+
+    /// Creates a condition that the field equals the value.
+    pub fn eq(field_name: impl Into<String>, value: impl Into<Bson>) -> Self {
+        Self::new(field_name, QueryOperator::Equal, value)
+    }
+
+    /// Creates a condition that the field does not equal the value.
+    pub fn ne(field_name: impl Into<String>, value: impl Into<Bson>) -> Self {
+        Self::new(field_name, QueryOperator::NotEqual, value)
+    }
+
+    /// Creates a condition that the field is greater than the value.
+    pub fn gt(field_name: impl Into<String>, value: impl Into<Bson>) -> Self {
+        Self::new(field_name, QueryOperator::GreaterThan, value)
+    }
+
+    /// Creates a condition that the field is greater than or equal to the value.
+    pub fn ge(field_name: impl Into<String>, value: impl Into<Bson>) -> Self {
+        Self::new(field_name, QueryOperator::GreaterThanOrEqual, value)
+    }
+
+    /// Creates a condition that the field is less than the value.
+    pub fn lt(field_name: impl Into<String>, value: impl Into<Bson>) -> Self {
+        Self::new(field_name, QueryOperator::LessThan, value)
+    }
+
+    /// Creates a condition that the field is less than or equal to the value.
+    pub fn le(field_name: impl Into<String>, value: impl Into<Bson>) -> Self {
+        Self::new(field_name, QueryOperator::LessThanOrEqual, value)
+    }
+
+    /// Creates a condition that the field is similar to the value.
+    pub fn similar(field_name: impl Into<String>, value: impl Into<Bson>) -> Self {
+        Self::new(field_name, QueryOperator::Similar, value)
+    }
+
+    /// Converts the condition into its AST form, with the value rendered as a literal.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`FieldCondition`]) if successful,
+    /// or [`Err`]\([`ClientError`]) if the value cannot be expressed in a query.
+    pub fn to_field_condition(&self) -> Result<FieldCondition, ClientError> {
+        Ok(FieldCondition {
+            field_name: self.field_name.clone(),
+            operator: self.operator.clone(),
+            value: bson_literal(&self.value)?,
+        })
+    }
+}
+
+/// The fields of documents to return from a query.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Selection {
+    /// Return all fields, leaving references as IDs.
+    All,
+    /// Return all fields, recursively resolving references.
+    AllRecursive,
+    /// Return only the named fields.
+    Fields(Vec<String>),
+}
+
+impl Selection {
+    /// Converts the selection into its AST form.
+    pub fn to_selectors(&self) -> Vec<FieldSelector> {
+        match self {
+            Selection::All => vec![FieldSelector::AllFields],
+            Selection::AllRecursive => vec![FieldSelector::AllFieldsRecursive],
+            Selection::Fields(names) => names.iter().cloned().map(FieldSelector::Field).collect(),
+        }
+    }
+}
+
+/// Renders a value as a query literal.
+///
+/// ## Arguments
+///
+/// * `value` - The [`Bson`] value to render.
+///
+/// ## Returns
+///
+/// Returns [`Ok`]\([`String`]) with the literal, or [`Err`]\([`ClientError`]) if the value
+/// has no literal form, which is the case for documents, non-finite floats and the other
+/// BSON-specific types.
+pub fn bson_literal(value: &Bson) -> Result<String, ClientError> {
+    match value {
+        Bson::Null => Ok("null".to_string()),
+        Bson::Boolean(b) => Ok(b.to_string()),
+        Bson::Int32(n) => Ok(n.to_string()),
+        Bson::Int64(n) => Ok(n.to_string()),
+        Bson::Double(n) if n.is_finite() => {
+            let literal = n.to_string();
+            if literal.contains('.') {
+                Ok(literal)
+            } else {
+                Ok(format!("{}.0", literal))
+            }
+        }
+        Bson::String(s) => Ok(string_literal(s)),
+        Bson::Array(items) => {
+            let items: Result<Vec<_>, _> = items.iter().map(bson_literal).collect();
+            Ok(format!("[{}]", items?.join(", ")))
+        }
+        other => Err(ClientError::InvalidQuery(format!(
+            "Value {} cannot be expressed in a query",
+            other
+        ))),
+    }
+}
+
+/// Renders a string as a double-quoted query literal, escaping its special characters.
+///
+/// ## Arguments
+///
+/// * `value` - The string to render.
+pub fn string_literal(value: &str) -> String {
+    let mut literal = String::with_capacity(value.len() + 2);
+    literal.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => literal.push_str("\\\""),
+            '\\' => literal.push_str("\\\\"),
+            '\n' => literal.push_str("\\n"),
+            '\r' => literal.push_str("\\r"),
+            '\t' => literal.push_str("\\t"),
+            '\0' => literal.push_str("\\0"),
+            c => literal.push(c),
+        }
+    }
+    literal.push('"');
+    literal
+}
+
+/// Renders an archive path as a query literal.
+///
+/// Archive paths are taken verbatim by the server, so they are not escaped.
+///
+/// ## Arguments
+///
+/// * `path` - The path to render.
+///
+/// ## Returns
+///
+/// Returns [`Ok`]\([`String`]) with the literal, or [`Err`]\([`ClientError`]) if the path
+/// contains a double quote or ends with a backslash.
+fn path_literal(path: &str) -> Result<String, ClientError> {
+    if path.contains('"') || path.ends_with('\\') {
+        return Err(ClientError::InvalidQuery(format!(
+            "Archive path '{}' cannot be expressed in a query",
+            path
+        )));
+    }
+    Ok(format!("\"{}\"", path))
+}
+
+/// Renders a database-level query as query text.
+///
+/// ## Arguments
+///
+/// * `query` - The [`DatabaseQuery`] to render.
+///
+/// ## Returns
+///
+/// Returns [`Ok`]\([`String`]) with the query text,
+/// or [`Err`]\([`ClientError`]) if an archive path cannot be expressed in a query.
+pub fn render_database_query(query: &DatabaseQuery) -> Result<String, ClientError> {
+    Ok(match query {
+        DatabaseQuery::Create {
+            name,
+            drop_if_exists,
+        } => format!(
+            "CREATE DATABASE {}{}",
+            name,
+            drop_if_exists_clause(*drop_if_exists)
+        ),
+        DatabaseQuery::Drop { name } => format!("DROP DATABASE {}", name),
+        DatabaseQuery::List => "LIST DATABASES".to_string(),
+        DatabaseQuery::Backup { name, path } => {
+            format!("BACKUP DATABASE {} TO {}", name, path_literal(path)?)
+        }
+        DatabaseQuery::Restore { name, path } => {
+            format!("RESTORE DATABASE {} FROM {}", name, path_literal(path)?)
+        }
+    })
+}
+
+/// Renders a query within a database context as query text.
+///
+/// Condition and assignment values are expected to already be literals, as produced by
+/// [`bson_literal`].
+///
+/// ## Arguments
+///
+/// * `query` - The [`ContextualQuery`] to render.
+///
+/// ## Returns
+///
+/// Returns [`Ok`]\([`String`]) with the query text, or [`Err`]\([`ClientError`]) if a field
+/// type or default value cannot be expressed in a query.
+pub fn render_contextual_query(query: &ContextualQuery) -> Result<String, ClientError> {
+    match query {
+        ContextualQuery::Collection(query) => render_collection_query(query),
+        ContextualQuery::Document(query) => Ok(render_document_query(query)),
+    }
+}
+
+/// Renders a collection query as query text.
+///
+/// ## Arguments
+///
+/// * `query` - The [`CollectionQuery`] to render.
+fn render_collection_query(query: &CollectionQuery) -> Result<String, ClientError> {
+    Ok(match query {
+        CollectionQuery::Create {
+            name,
+            drop_if_exists,
+            schema,
+        } => {
+            let fields: Result<Vec<_>, ClientError> = schema
+                .fields
+                .iter()
+                .map(|(field_name, def)| {
+                    Ok(format!("{}: {}", field_name, render_field_definition(def)?))
+                })
+                .collect();
+            format!(
+                "CREATE COLLECTION {}{} {{ {} }}",
+                name,
+                drop_if_exists_clause(*drop_if_exists),
+                fields?.join(", ")
+            )
+        }
+        CollectionQuery::Drop { name } => format!("DROP COLLECTION {}", name),
+        CollectionQuery::Modify {
+            name,
+            modifications,
+        } => {
+            let fields: Result<Vec<_>, ClientError> = modifications
+                .iter()
+                .map(|(field_name, modification)| {
+                    let modification = match modification {
+                        FieldModification::Drop => "DROP".to_string(),
+                        FieldModification::Set(def) => render_field_definition(def)?,
+                    };
+                    Ok(format!("{}: {}", field_name, modification))
+                })
+                .collect();
+            format!("MODIFY COLLECTION {} {{ {} }}", name, fields?.join(", "))
+        }
+        CollectionQuery::List => "LIST COLLECTIONS".to_string(),
+        CollectionQuery::GetSchema { name } => format!("GET SCHEMA FROM {}", name),
+        CollectionQuery::Compact { name } => format!("COMPACT COLLECTION {}", name),
+        CollectionQuery::CreateIndex { name, field_names } => {
+            format!("CREATE INDEX ON {} ({})", name, field_names.join(", "))
+        }
+        CollectionQuery::DropIndex { name, field_names } => {
+            format!("DROP INDEX ON {} ({})", name, field_names.join(", "))
+        }
+    })
+}
+
+/// Renders a document query as query text.
+///
+/// ## Arguments
+///
+/// * `query` - The [`DocumentQuery`] to render.
+fn render_document_query(query: &DocumentQuery) -> String {
+    let no_conditions: &[FieldCondition] = &[];
+    match query {
+        DocumentQuery::Insert {
+            collection_name,
+            fields,
+        } => format!(
+            "INSERT DOCUMENT INTO {} {}",
+            collection_name,
+            render_body(fields, no_conditions, &[])
+        ),
+        DocumentQuery::Update {
+            collection_name,
+            conditions,
+            updates,
+            selectors,
+        } => format!(
+            "UPDATE DOCUMENTS IN {} {}",
+            collection_name,
+            render_body(updates, conditions, selectors)
+        ),
+        DocumentQuery::Delete {
+            collection_name,
+            conditions,
+            selectors,
+        } => format!(
+            "DELETE DOCUMENTS FROM {} {}",
+            collection_name,
+            render_body(&HashMap::new(), conditions, selectors)
+        ),
+        DocumentQuery::Get {
+            collection_name,
+            conditions,
+            selectors,
+            as_of,
+        } => format!(
+            "GET DOCUMENTS FROM {}{} {}",
+            collection_name,
+            as_of
+                .as_ref()
+                .map(|as_of| format!(" AS OF {}", string_literal(as_of)))
+                .unwrap_or_default(),
+            render_body(&HashMap::new(), conditions, selectors)
+        ),
+        DocumentQuery::Sum {
+            collection_name,
+            field_name,
+            conditions,
+        } => format!(
+            "GET SUM({}) FROM {} {}",
+            field_name,
+            collection_name,
+            render_body(&HashMap::new(), conditions, &[])
+        ),
+        DocumentQuery::History {
+            collection_name,
+            conditions,
+        } => format!(
+            "GET HISTORY FROM {} {}",
+            collection_name,
+            render_body(&HashMap::new(), conditions, &[])
+        ),
+    }
+}
+
+/// Renders a document body, made of assignments, conditions and selectors.
+///
+/// ## Arguments
+///
+/// * `assignments` - The field assignments, with literal values.
+/// * `conditions` - The field conditions, with literal values.
+/// * `selectors` - The field selectors.
+fn render_body(
+    assignments: &HashMap<String, String>,
+    conditions: &[FieldCondition],
+    selectors: &[FieldSelector],
+) -> String {
+    let mut items: Vec<String> = assignments
+        .iter()
+        .map(|(field_name, value)| format!("{}: {}", field_name, value))
+        .collect();
+    items.extend(conditions.iter().map(|condition| {
+        format!(
+            "{} {} {}",
+            condition.field_name, condition.operator, condition.value
+        )
+    }));
+    items.extend(selectors.iter().map(render_selector));
+
+    if items.is_empty() {
+        "{}".to_string()
+    } else {
+        format!("{{ {} }}", items.join(", "))
+    }
+}
+
+/// Renders a field selector.
+///
+/// ## Arguments
+///
+/// * `selector` - The [`FieldSelector`] to render.
+fn render_selector(selector: &FieldSelector) -> String {
+    match selector {
+        FieldSelector::Field(field_name) => field_name.clone(),
+        FieldSelector::AllFields => "*".to_string(),
+        FieldSelector::AllFieldsRecursive => "**".to_string(),
+        FieldSelector::SubDocument {
+            field_name,
+            content:
+                ParsedDocContent {
+                    assignments,
+                    conditions,
+                    selectors,
+                },
+        } => format!(
+            "{} {}",
+            field_name,
+            render_body(assignments, conditions, selectors)
+        ),
+    }
+}
+
+/// Renders a field definition, with its constraints.
+///
+/// ## Arguments
+///
+/// * `def` - The [`FieldDefinition`] to render.
+fn render_field_definition(def: &FieldDefinition) -> Result<String, ClientError> {
+    let (field_type, nullable) = match &def.field_type {
+        FieldType::Nullable(inner) => (inner.as_ref(), true),
+        field_type => (field_type, false),
+    };
+
+    let mut constraints = Vec::new();
+    if nullable {
+        constraints.push("nullable".to_string());
+    }
+    if def.unique {
+        constraints.push("unique".to_string());
+    }
+    if let Some(default) = &def.default_value {
+        constraints.push(format!("default = {}", bson_literal(default)?));
+    }
+
+    let field_type = render_field_type(field_type)?;
+    if constraints.is_empty() {
+        Ok(field_type)
+    } else {
+        Ok(format!("{}({})", field_type, constraints.join(", ")))
+    }
+}
+
+/// Renders a field type.
+///
+/// ## Arguments
+///
+/// * `field_type` - The [`FieldType`] to render.
+///
+/// ## Returns
+///
+/// Returns [`Ok`]\([`String`]) with the type, or [`Err`]\([`ClientError`]) for nullable
+/// types nested in arrays, which queries cannot express.
+fn render_field_type(field_type: &FieldType) -> Result<String, ClientError> {
+    Ok(match field_type {
+        FieldType::Int => "int".to_string(),
+        FieldType::Float => "float".to_string(),
+        FieldType::Boolean => "boolean".to_string(),
+        FieldType::String => "string".to_string(),
+        FieldType::IdString => "id_string".to_string(),
+        FieldType::IdInt => "id_int".to_string(),
+        FieldType::EncryptedInt => "encrypted_int".to_string(),
+        FieldType::EncryptedString => "encrypted_string".to_string(),
+        FieldType::Array(inner) => format!("array<{}>", render_field_type(inner)?),
+        FieldType::Reference(collection) => format!("ref<{}>", collection),
+        FieldType::Nullable(_) => {
+            return Err(ClientError::InvalidQuery(
+                "Nested nullable types cannot be expressed in a query".to_string(),
+            ));
+        }
+    })
+}
+
+/// Returns the DROP IF EXISTS clause of a CREATE query, if it is requested.
+///
+/// ## Arguments
+///
+/// * `drop_if_exists` - Whether the clause is requested.
+fn drop_if_exists_clause(drop_if_exists: bool) -> &'static str {
+    if drop_if_exists {
+        " DROP IF EXISTS"
+    } else {
+        ""
+    }
+}
@@ -0,0 +1,33 @@
+#![allow(dead_code)]
+use fhedb_client::prelude::*;
+use fhedb_core::prelude::FieldType;
+use std::{
+    collections::HashMap,
+    sync::{Arc, OnceLock},
+};
+
+/// Generating keys is slow, so the tests share a small one.
+pub fn test_key() -> Arc<FheKey> {
+    static KEY: OnceLock<Arc<FheKey>> = OnceLock::new();
+    KEY.get_or_init(|| Arc::new(FheKey::generate_with_bits(512).unwrap()))
+        .clone()
+}
+
+pub fn make_encrypted_fields() -> EncryptedFields {
+    EncryptedFields::new()
+        .with_field("accounts", "balance", test_key())
+        .with_field("accounts", "email", test_key())
+}
+
+pub fn make_field_types() -> FieldTypes {
+    let accounts = HashMap::from([
+        ("id".to_string(), FieldType::IdInt),
+        ("owner".to_string(), FieldType::String),
+        ("balance".to_string(), FieldType::EncryptedInt),
+        (
+            "email".to_string(),
+            FieldType::Nullable(Box::new(FieldType::EncryptedString)),
+        ),
+    ]);
+    HashMap::from([("accounts".to_string(), accounts)])
+}
@@ -0,0 +1,549 @@
+use axum::{
+    Router,
+    handler::Handler,
+    middleware,
+    routing::{get, post},
+};
+use bson::{Bson, doc};
+use fhedb_client::{error::ClientError, prelude::*};
+use fhedb_core::{
+    fhe::{ENCRYPTED_INT_PREFIX, ENCRYPTED_STRING_PREFIX},
+    prelude::{Compression, Durability, FieldDefinition, FieldType, Schema},
+};
+use fhedb_server::prelude::{ServerState, check_database, handle_base, handle_changes, handle_db};
+use fhedb_types::FieldModification;
+use std::{collections::HashMap, fs, net::TcpListener, path::Path, thread};
+use tempfile::tempdir;
+
+mod common;
+use common::{make_encrypted_fields, test_key};
+
+/// Starts a server storing its databases and backups in the given directory, returning its URL.
+fn start_server(dir: &Path) -> String {
+    let data_dir = dir.join("data");
+    let backup_dir = dir.join("backups");
+    fs::create_dir_all(&data_dir).unwrap();
+    fs::create_dir_all(&backup_dir).unwrap();
+    let state = ServerState::new(
+        data_dir,
+        backup_dir,
+        Durability::Never,
+        1024 * 1024,
+        0.5,
+        None,
+        Compression::default(),
+        None,
+    );
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    listener.set_nonblocking(true).unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+
+    thread::spawn(move || {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        runtime.block_on(async move {
+            let db_handler = handle_db.layer(middleware::from_fn_with_state(
+                state.clone(),
+                check_database,
+            ));
+            let changes_handler = handle_changes.layer(middleware::from_fn_with_state(
+                state.clone(),
+                check_database,
+            ));
+            let app = Router::new()
+                .route("/", get(|| async { "Hello, FHEDB!" }))
+                .route("/", post(handle_base))
+                .route("/{db_name}", post(db_handler))
+                .route("/{db_name}/changes", get(changes_handler))
+                .with_state(state);
+            let listener = tokio::net::TcpListener::from_std(listener).unwrap();
+            axum::serve(listener, app).await.unwrap();
+        });
+    });
+    url
+}
+
+fn make_accounts_schema() -> Schema {
+    let mut fields = HashMap::new();
+    fields.insert("id".to_string(), FieldDefinition::new(FieldType::IdInt));
+    fields.insert("owner".to_string(), FieldDefinition::new(FieldType::String));
+    fields.insert(
+        "balance".to_string(),
+        FieldDefinition::new(FieldType::EncryptedInt),
+    );
+    fields.insert(
+        "email".to_string(),
+        FieldDefinition::new(FieldType::Nullable(Box::new(FieldType::EncryptedString))),
+    );
+    Schema { fields }
+}
+
+#[test]
+fn database_and_collection_management() {
+    let temp_dir = tempdir().unwrap();
+    let client = Client::new(start_server(temp_dir.path()) + "/");
+
+    client.create_database("shop", false).unwrap();
+    client.create_database("scratch", false).unwrap();
+    client.drop_database("scratch").unwrap();
+    assert_eq!(client.list_databases().unwrap(), vec!["shop".to_string()]);
+
+    let db = client.database("shop");
+    db.create_collection("accounts", make_accounts_schema(), false)
+        .unwrap();
+    assert_eq!(db.list_collections().unwrap(), vec!["accounts".to_string()]);
+    let schema = db.get_schema("accounts").unwrap();
+    assert_eq!(schema["fields"]["balance"]["type"], "encrypted_int");
+    assert_eq!(schema["fields"]["email"]["nullable"], true);
+    assert_eq!(schema["indexes"], serde_json::json!([]));
+
+    let mut modifications = HashMap::new();
+    modifications.insert(
+        "nickname".to_string(),
+        FieldModification::Set(FieldDefinition::with_default(
+            FieldType::String,
+            Bson::String("none".to_string()),
+        )),
+    );
+    let schema = db.modify_collection("accounts", modifications).unwrap();
+    assert_eq!(schema["nickname"]["default"], "none");
+
+    db.drop_collection("accounts").unwrap();
+    assert!(db.list_collections().unwrap().is_empty());
+}
+
+#[test]
+fn encrypted_fields_round_trip() {
+    let temp_dir = tempdir().unwrap();
+    let url = start_server(temp_dir.path());
+    let client = Client::new(url.clone()).with_encrypted_fields(make_encrypted_fields());
+    client.create_database("bank", true).unwrap();
+    let db = client.database("bank");
+    db.create_collection("accounts", make_accounts_schema(), false)
+        .unwrap();
+
+    let inserted = db
+        .insert_document(
+            "accounts",
+            &doc! { "owner": "Alice", "balance": 150i64, "email": "alice@example.com" },
+        )
+        .unwrap();
+    db.insert_document(
+        "accounts",
+        &doc! { "owner": "Bob", "balance": -20i64, "email": "bob@example.com" },
+    )
+    .unwrap();
+    db.insert_document(
+        "accounts",
+        &doc! { "owner": "Carol \"C\"", "balance": 70i64, "email": Bson::Null },
+    )
+    .unwrap();
+
+    assert_eq!(inserted.get_i64("balance").unwrap(), 150);
+    assert_eq!(inserted.get_str("email").unwrap(), "alice@example.com");
+
+    let plain_client = Client::new(url);
+    let stored = plain_client
+        .database("bank")
+        .get_documents("accounts", &[], &Selection::All)
+        .unwrap();
+    assert_eq!(stored.len(), 3);
+    for document in &stored {
+        assert!(
+            document
+                .get_str("balance")
+                .unwrap()
+                .starts_with(ENCRYPTED_INT_PREFIX)
+        );
+    }
+    assert!(
+        stored[0]
+            .get_str("email")
+            .unwrap()
+            .starts_with(ENCRYPTED_STRING_PREFIX)
+    );
+
+    let found = db
+        .get_documents(
+            "accounts",
+            &[Condition::eq("email", "bob@example.com")],
+            &Selection::All,
+        )
+        .unwrap();
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].get_str("owner").unwrap(), "Bob");
+    assert_eq!(found[0].get_i64("balance").unwrap(), -20);
+
+    let found = db
+        .get_documents(
+            "accounts",
+            &[Condition::eq("owner", "Carol \"C\"")],
+            &Selection::Fields(vec!["balance".to_string()]),
+        )
+        .unwrap();
+    assert_eq!(found[0].get_i64("balance").unwrap(), 70);
+
+    let sum = db.sum("accounts", "balance", &[]).unwrap();
+    assert_eq!(
+        sum,
+        SumResult {
+            sum: Bson::Int64(200),
+            count: 3
+        }
+    );
+
+    let updated = db
+        .update_documents(
+            "accounts",
+            &[Condition::eq("balance", 150)],
+            &doc! { "balance": 500i64 },
+            &Selection::All,
+        )
+        .unwrap();
+    assert_eq!(updated.len(), 1);
+    assert_eq!(updated[0].get_i64("balance").unwrap(), 500);
+
+    let deleted = db
+        .delete_documents(
+            "accounts",
+            &[Condition::ne("email", "alice@example.com")],
+            &Selection::Fields(vec!["owner".to_string()]),
+        )
+        .unwrap();
+    assert_eq!(deleted.len(), 2);
+    let sum = db.sum("accounts", "balance", &[]).unwrap();
+    assert_eq!(sum.sum, Bson::Int64(500));
+    assert_eq!(sum.count, 1);
+}
+
+#[test]
+fn saved_keys_read_by_another_client() {
+    let temp_dir = tempdir().unwrap();
+    let url = start_server(temp_dir.path());
+    let key_path = temp_dir.path().join("accounts.key");
+    save_key(&test_key(), &key_path).unwrap();
+
+    let writer = Client::new(url.clone()).with_encrypted_fields(make_encrypted_fields());
+    writer.create_database("bank", false).unwrap();
+    let db = writer.database("bank");
+    db.create_collection("accounts", make_accounts_schema(), false)
+        .unwrap();
+    db.insert_document(
+        "accounts",
+        &doc! { "owner": "Alice", "balance": 150i64, "email": "alice@example.com" },
+    )
+    .unwrap();
+    drop(writer);
+
+    let key = load_key(&key_path).unwrap();
+    let reader = Client::new(url).with_encrypted_fields(
+        EncryptedFields::new()
+            .with_field("accounts", "balance", key.clone())
+            .with_field("accounts", "email", key),
+    );
+    let db = reader.database("bank");
+    let found = db
+        .get_documents(
+            "accounts",
+            &[Condition::eq("email", "alice@example.com")],
+            &Selection::All,
+        )
+        .unwrap();
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].get_i64("balance").unwrap(), 150);
+    assert_eq!(found[0].get_str("email").unwrap(), "alice@example.com");
+    assert_eq!(
+        db.sum("accounts", "balance", &[]).unwrap().sum,
+        Bson::Int64(150)
+    );
+}
+
+#[test]
+fn plain_fields_not_decrypted() {
+    let temp_dir = tempdir().unwrap();
+    let client =
+        Client::new(start_server(temp_dir.path())).with_encrypted_fields(make_encrypted_fields());
+    client.create_database("bank", false).unwrap();
+    let db = client.database("bank");
+    db.create_collection("accounts", make_accounts_schema(), false)
+        .unwrap();
+
+    let ciphertext = test_key().encrypt_int(42).to_string();
+    db.insert_document(
+        "accounts",
+        &doc! { "owner": ciphertext.clone(), "balance": 7i64, "email": Bson::Null },
+    )
+    .unwrap();
+
+    let found = db.get_documents("accounts", &[], &Selection::All).unwrap();
+    assert_eq!(found[0].get_str("owner").unwrap(), ciphertext);
+    assert_eq!(found[0].get_i64("balance").unwrap(), 7);
+}
+
+#[test]
+fn documents_as_of() {
+    let temp_dir = tempdir().unwrap();
+    let client = Client::new(start_server(temp_dir.path()));
+    client.create_database("bank", false).unwrap();
+    let db = client.database("bank");
+    let mut fields = HashMap::new();
+    fields.insert("id".to_string(), FieldDefinition::new(FieldType::IdInt));
+    fields.insert("owner".to_string(), FieldDefinition::new(FieldType::String));
+    db.create_collection("accounts", Schema { fields }, false)
+        .unwrap();
+    db.insert_document("accounts", &doc! { "owner": "Alice" })
+        .unwrap();
+
+    let past = db
+        .get_documents_as_of("accounts", "2000-01-01T00:00:00Z", &[], &Selection::All)
+        .unwrap();
+    assert!(past.is_empty());
+    let future = db
+        .get_documents_as_of(
+            "accounts",
+            "2999-01-01T00:00:00+02:00",
+            &[Condition::eq("owner", "Alice")],
+            &Selection::All,
+        )
+        .unwrap();
+    assert_eq!(
+        future,
+        db.get_documents("accounts", &[], &Selection::All).unwrap()
+    );
+
+    let result = db.get_documents_as_of("accounts", "last tuesday", &[], &Selection::All);
+    assert!(matches!(
+        result,
+        Err(ClientError::Server { status: 400, .. })
+    ));
+}
+
+#[test]
+fn document_history() {
+    let temp_dir = tempdir().unwrap();
+    let client =
+        Client::new(start_server(temp_dir.path())).with_encrypted_fields(make_encrypted_fields());
+    client.create_database("bank", false).unwrap();
+    let db = client.database("bank");
+    db.create_collection("accounts", make_accounts_schema(), false)
+        .unwrap();
+
+    let inserted = db
+        .insert_document(
+            "accounts",
+            &doc! { "owner": "Alice", "balance": 10i64, "email": Bson::Null },
+        )
+        .unwrap();
+    let id = inserted.get_i64("id").unwrap();
+    db.update_documents(
+        "accounts",
+        &[Condition::eq("id", id)],
+        &doc! { "balance": 25i64 },
+        &Selection::All,
+    )
+    .unwrap();
+    db.delete_documents("accounts", &[Condition::eq("id", id)], &Selection::All)
+        .unwrap();
+
+    let history = db
+        .document_history("accounts", &Condition::eq("id", id))
+        .unwrap();
+    let operations: Vec<_> = history
+        .iter()
+        .map(|entry| entry.operation.as_str())
+        .collect();
+    assert_eq!(operations, vec!["INSERT", "UPDATE", "DELETE"]);
+    assert_eq!(history[0].document.get_i64("balance").unwrap(), 10);
+    assert_eq!(history[1].document.get_i64("balance").unwrap(), 25);
+    assert_eq!(history[1].document.get_str("owner").unwrap(), "Alice");
+    assert_eq!(history[2].document, history[1].document);
+    assert!(history.iter().all(|entry| entry.inserted_at.is_none()));
+
+    let result = db.document_history("accounts", &Condition::eq("owner", "Alice"));
+    assert!(matches!(result, Err(ClientError::Server { .. })));
+}
+
+#[test]
+fn change_stream() {
+    let temp_dir = tempdir().unwrap();
+    let client =
+        Client::new(start_server(temp_dir.path())).with_encrypted_fields(make_encrypted_fields());
+    client.create_database("bank", false).unwrap();
+    let db = client.database("bank");
+    db.create_collection("accounts", make_accounts_schema(), false)
+        .unwrap();
+
+    let mut changes = db.changes(Some("accounts"), None).unwrap();
+    let inserted = db
+        .insert_document(
+            "accounts",
+            &doc! { "owner": "Alice", "balance": 10i64, "email": Bson::Null },
+        )
+        .unwrap();
+    let id = inserted.get_i64("id").unwrap();
+    db.update_documents(
+        "accounts",
+        &[Condition::eq("id", id)],
+        &doc! { "balance": 25i64 },
+        &Selection::All,
+    )
+    .unwrap();
+    db.delete_documents("accounts", &[Condition::eq("id", id)], &Selection::All)
+        .unwrap();
+
+    let received: Vec<Change> = changes.by_ref().take(3).map(Result::unwrap).collect();
+    let operations: Vec<_> = received
+        .iter()
+        .map(|change| change.operation.as_str())
+        .collect();
+    assert_eq!(operations, vec!["INSERT", "UPDATE", "DELETE"]);
+    assert!(
+        received
+            .iter()
+            .all(|change| change.collection == "accounts")
+    );
+    assert!(received.iter().all(|change| change.id == Bson::Int64(id)));
+    assert_eq!(received[0].document.get_i64("balance").unwrap(), 10);
+    assert_eq!(received[1].document.get_i64("balance").unwrap(), 25);
+    assert_eq!(received[2].document.get_str("owner").unwrap(), "Alice");
+    assert!(
+        received
+            .iter()
+            .all(|change| change.epoch == received[0].epoch)
+    );
+    assert!(received[0].sequence < received[1].sequence);
+    assert!(received[1].sequence < received[2].sequence);
+    assert_eq!(changes.last_sequence(), Some(received[2].sequence));
+
+    let mut resumed = db.changes(None, Some(received[1].sequence)).unwrap();
+    let change = resumed.next().unwrap().unwrap();
+    assert_eq!(change.sequence, received[1].sequence);
+    assert_eq!(change.document.get_i64("balance").unwrap(), 25);
+
+    let result = db.changes(Some("missing"), None);
+    assert!(matches!(
+        result,
+        Err(ClientError::Server { status: 404, .. })
+    ));
+    let result = db.changes(None, Some(received[2].sequence + 2));
+    assert!(matches!(result, Err(ClientError::Server { .. })));
+}
+
+#[test]
+fn secondary_indexes() {
+    let temp_dir = tempdir().unwrap();
+    let client =
+        Client::new(start_server(temp_dir.path())).with_encrypted_fields(make_encrypted_fields());
+    client.create_database("bank", false).unwrap();
+    let db = client.database("bank");
+    db.create_collection("accounts", make_accounts_schema(), false)
+        .unwrap();
+    for (owner, balance) in [("Alice", 10i64), ("Bob", 20), ("Alice", 30)] {
+        db.insert_document(
+            "accounts",
+            &doc! { "owner": owner, "balance": balance, "email": Bson::Null },
+        )
+        .unwrap();
+    }
+
+    db.create_index("accounts", "owner").unwrap();
+    let result = db.create_index("accounts", "owner");
+    assert!(matches!(result, Err(ClientError::Server { .. })));
+    let result = db.create_index("accounts", "balance");
+    assert!(matches!(result, Err(ClientError::Server { .. })));
+
+    let found = db
+        .get_documents(
+            "accounts",
+            &[Condition::eq("owner", "Alice")],
+            &Selection::All,
+        )
+        .unwrap();
+    let balances: Vec<i64> = found
+        .iter()
+        .map(|document| document.get_i64("balance").unwrap())
+        .collect();
+    assert_eq!(balances, vec![10, 30]);
+
+    db.drop_index("accounts", "owner").unwrap();
+    let result = db.drop_index("accounts", "owner");
+    assert!(matches!(result, Err(ClientError::Server { .. })));
+}
+
+#[test]
+fn unique_conflict_rolls_back_update() {
+    let temp_dir = tempdir().unwrap();
+    let client = Client::new(start_server(temp_dir.path()));
+    client.create_database("bank", false).unwrap();
+    let db = client.database("bank");
+    let mut fields = HashMap::new();
+    fields.insert("id".to_string(), FieldDefinition::new(FieldType::IdInt));
+    fields.insert("owner".to_string(), FieldDefinition::new(FieldType::String));
+    fields.insert(
+        "email".to_string(),
+        FieldDefinition::new(FieldType::String).with_unique(true),
+    );
+    db.create_collection("users", Schema { fields }, false)
+        .unwrap();
+    for (owner, email) in [
+        ("Alice", "alice@example.com"),
+        ("Alice", "alice@example.org"),
+        ("Bob", "bob@example.com"),
+    ] {
+        db.insert_document("users", &doc! { "owner": owner, "email": email })
+            .unwrap();
+    }
+
+    let result = db.update_documents(
+        "users",
+        &[Condition::eq("owner", "Alice")],
+        &doc! { "email": "shared@example.com" },
+        &Selection::All,
+    );
+    let Err(ClientError::Server { message, .. }) = result else {
+        panic!("Expected the update to be rejected");
+    };
+    assert!(message.contains("rolled back"), "{}", message);
+
+    let found = db.get_documents("users", &[], &Selection::All).unwrap();
+    let emails: Vec<&str> = found
+        .iter()
+        .map(|document| document.get_str("email").unwrap())
+        .collect();
+    assert_eq!(
+        emails,
+        vec!["alice@example.com", "alice@example.org", "bob@example.com"]
+    );
+    let found = db
+        .get_documents(
+            "users",
+            &[Condition::eq("email", "shared@example.com")],
+            &Selection::All,
+        )
+        .unwrap();
+    assert!(found.is_empty());
+}
+
+#[test]
+fn server_errors_reported() {
+    let temp_dir = tempdir().unwrap();
+    let client = Client::new(start_server(temp_dir.path()));
+
+    let result = client.database("missing").list_collections();
+    assert!(matches!(
+        result,
+        Err(ClientError::Server { status: 404, .. })
+    ));
+
+    client.create_database("shop", false).unwrap();
+    let result = client.create_database("shop", false);
+    assert!(matches!(result, Err(ClientError::Server { .. })));
+
+    let result = client.execute("CREATE DATABSE oops");
+    assert!(matches!(
+        result,
+        Err(ClientError::Server { status: 400, .. })
+    ));
+}
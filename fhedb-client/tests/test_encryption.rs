@@ -0,0 +1,246 @@
+use bson::{Bson, doc};
+use fhedb_client::{error::ClientError, prelude::*};
+use fhedb_core::{
+    fhe::{ENCRYPTED_INT_PREFIX, ENCRYPTED_STRING_PREFIX},
+    prelude::FieldType,
+};
+use std::{collections::HashMap, fs, sync::Arc};
+use tempfile::tempdir;
+
+mod common;
+use common::{make_encrypted_fields, make_field_types, test_key};
+
+#[test]
+fn encrypt_and_decrypt_document() {
+    let fields = make_encrypted_fields();
+    let document = doc! {
+        "owner": "Alice",
+        "balance": 150i64,
+        "email": "alice@example.com",
+        "note": Bson::Null,
+    };
+
+    let encrypted = fields.encrypt_document("accounts", &document).unwrap();
+
+    assert_eq!(encrypted.get_str("owner").unwrap(), "Alice");
+    assert!(
+        encrypted
+            .get_str("balance")
+            .unwrap()
+            .starts_with(ENCRYPTED_INT_PREFIX)
+    );
+    assert!(
+        encrypted
+            .get_str("email")
+            .unwrap()
+            .starts_with(ENCRYPTED_STRING_PREFIX)
+    );
+    assert_eq!(encrypted.get("note"), Some(&Bson::Null));
+    assert_eq!(
+        fields
+            .decrypt_document("accounts", encrypted, &make_field_types())
+            .unwrap(),
+        document
+    );
+}
+
+#[test]
+fn only_designated_fields_encrypted() {
+    let fields = make_encrypted_fields();
+
+    assert!(fields.is_encrypted("accounts", "balance"));
+    assert!(!fields.is_encrypted("accounts", "owner"));
+    assert!(!fields.is_encrypted("users", "balance"));
+    assert_eq!(
+        fields
+            .encrypt_value("users", "balance", &Bson::Int64(5))
+            .unwrap(),
+        Bson::Int64(5)
+    );
+    assert!(matches!(
+        fields.encrypt_value("accounts", "balance", &Bson::Double(1.5)),
+        Err(ClientError::Encryption(_))
+    ));
+}
+
+#[test]
+fn decrypts_nested_values() {
+    let fields = make_encrypted_fields();
+    let key = test_key();
+    let mut field_types = make_field_types();
+    field_types.insert(
+        "transfers".to_string(),
+        HashMap::from([
+            (
+                "account".to_string(),
+                FieldType::Reference("accounts".to_string()),
+            ),
+            (
+                "amounts".to_string(),
+                FieldType::Array(Box::new(FieldType::EncryptedInt)),
+            ),
+        ]),
+    );
+    let document = doc! {
+        "account": {
+            "balance": key.encrypt_int(-40).to_string(),
+            "email": key.encrypt_string("bob@example.com").unwrap().to_string(),
+        },
+        "amounts": [key.encrypt_int(10).to_string(), key.encrypt_int(20).to_string()],
+    };
+
+    let decrypted = fields
+        .decrypt_document("transfers", document, &field_types)
+        .unwrap();
+
+    assert_eq!(
+        decrypted,
+        doc! {
+            "account": { "balance": -40i64, "email": "bob@example.com" },
+            "amounts": [10i64, 20i64],
+        }
+    );
+}
+
+#[test]
+fn only_encrypted_field_types_decrypted() {
+    let fields = make_encrypted_fields();
+    let key = test_key();
+    let balance = key.encrypt_int(-40).to_string();
+    let email = key.encrypt_string("bob@example.com").unwrap().to_string();
+    let document = doc! {
+        "owner": balance.clone(),
+        "nickname": email.clone(),
+        "balance": email.clone(),
+        "email": balance.clone(),
+    };
+
+    let decrypted = fields
+        .decrypt_document("accounts", document.clone(), &make_field_types())
+        .unwrap();
+    assert_eq!(decrypted, document);
+
+    let decrypted = fields
+        .decrypt_document("audits", document.clone(), &make_field_types())
+        .unwrap();
+    assert_eq!(decrypted, document);
+}
+
+#[test]
+fn values_under_other_keys_left_encrypted() {
+    let fields = make_encrypted_fields();
+    let other = FheKey::generate_with_bits(256).unwrap();
+    let int = Bson::String(other.encrypt_int(1).to_string());
+    let string = Bson::String(other.encrypt_string("secret").unwrap().to_string());
+    let lookalike = Bson::String(format!("{}not a value", ENCRYPTED_INT_PREFIX));
+
+    let field_types = FieldTypes::new();
+
+    assert_eq!(
+        fields
+            .decrypt_value(&FieldType::EncryptedInt, int.clone(), &field_types)
+            .unwrap(),
+        int
+    );
+    assert_eq!(
+        fields
+            .decrypt_value(&FieldType::EncryptedString, string.clone(), &field_types)
+            .unwrap(),
+        string
+    );
+    assert_eq!(
+        fields
+            .decrypt_value(&FieldType::EncryptedInt, lookalike.clone(), &field_types)
+            .unwrap(),
+        lookalike
+    );
+}
+
+#[test]
+fn encrypted_conditions() {
+    let fields = make_encrypted_fields();
+    let key = test_key();
+
+    let condition = fields
+        .encrypt_condition("accounts", &Condition::eq("email", "alice@example.com"))
+        .unwrap();
+    let Bson::String(token) = &condition.value else {
+        panic!("expected an encrypted value");
+    };
+    let stored = key.encrypt_string("alice@example.com").unwrap();
+    assert!(stored.matches(&token.parse().unwrap()));
+
+    let condition = fields
+        .encrypt_condition("accounts", &Condition::ne("balance", 5))
+        .unwrap();
+    let Bson::String(token) = &condition.value else {
+        panic!("expected an encrypted value");
+    };
+    assert!(key.encrypt_int(5).matches(&token.parse().unwrap()).unwrap());
+
+    let plain = Condition::gt("owner", "A");
+    assert_eq!(fields.encrypt_condition("accounts", &plain).unwrap(), plain);
+    assert!(matches!(
+        fields.encrypt_condition("accounts", &Condition::gt("balance", 5)),
+        Err(ClientError::Encryption(_))
+    ));
+}
+
+#[test]
+fn keys_shared_between_fields() {
+    let key = test_key();
+    let other = Arc::new(FheKey::generate_with_bits(256).unwrap());
+    let fields = EncryptedFields::new()
+        .with_field("accounts", "balance", key.clone())
+        .with_field("accounts", "email", other.clone())
+        .with_field("audits", "amount", key.clone());
+
+    let amount = fields
+        .encrypt_value("audits", "amount", &Bson::Int64(9))
+        .unwrap();
+    let email = fields
+        .encrypt_value("accounts", "email", &Bson::String("x@y.z".to_string()))
+        .unwrap();
+
+    let field_types = FieldTypes::new();
+    assert_eq!(
+        fields
+            .decrypt_value(&FieldType::EncryptedInt, amount, &field_types)
+            .unwrap(),
+        Bson::Int64(9)
+    );
+    assert_eq!(
+        fields
+            .decrypt_value(&FieldType::EncryptedString, email, &field_types)
+            .unwrap(),
+        Bson::String("x@y.z".to_string())
+    );
+}
+
+#[test]
+fn key_files() {
+    let temp_dir = tempdir().unwrap();
+    let path = temp_dir.path().join("fields.key");
+    save_key(&test_key(), &path).unwrap();
+    save_key(&test_key(), &path).unwrap();
+
+    let key = load_key(&path).unwrap();
+    assert_eq!(key.public_key(), test_key().public_key());
+    assert_eq!(key.decrypt_int(&test_key().encrypt_int(-7)).unwrap(), -7);
+    assert!(!temp_dir.path().join("fields.key.tmp").exists());
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        assert_eq!(
+            fs::metadata(&path).unwrap().permissions().mode() & 0o777,
+            0o600
+        );
+    }
+
+    assert!(matches!(
+        load_key(temp_dir.path().join("missing.key")),
+        Err(ClientError::KeyFile(_))
+    ));
+    fs::write(&path, b"not a key").unwrap();
+    assert!(matches!(load_key(&path), Err(ClientError::KeyFile(_))));
+}
@@ -0,0 +1,255 @@
+use bson::{Bson, doc};
+use fhedb_client::{
+    error::ClientError,
+    prelude::*,
+    query::{bson_literal, render_contextual_query, render_database_query},
+};
+use fhedb_core::prelude::ValueParseable;
+use fhedb_query::prelude::*;
+use fhedb_types::{
+    CollectionQuery, ContextualQuery, DatabaseQuery, DocumentQuery, FieldDefinition,
+    FieldModification, FieldSelector, FieldType, ParsedDocContent, QueryOperator, Schema,
+};
+use std::collections::HashMap;
+
+fn round_trip_database(query: DatabaseQuery) {
+    let text = render_database_query(&query).unwrap();
+    assert_eq!(parse_database_query(&text).unwrap(), query, "{}", text);
+}
+
+fn round_trip_contextual(query: ContextualQuery) {
+    let text = render_contextual_query(&query).unwrap();
+    assert_eq!(parse_contextual_query(&text).unwrap(), query, "{}", text);
+}
+
+#[test]
+fn database_queries() {
+    round_trip_database(DatabaseQuery::Create {
+        name: "shop".to_string(),
+        drop_if_exists: true,
+    });
+    round_trip_database(DatabaseQuery::Create {
+        name: "shop".to_string(),
+        drop_if_exists: false,
+    });
+    round_trip_database(DatabaseQuery::Drop {
+        name: "shop".to_string(),
+    });
+    round_trip_database(DatabaseQuery::List);
+    round_trip_database(DatabaseQuery::Backup {
+        name: "shop".to_string(),
+        path: "/var/backups/shop.fhdb".to_string(),
+    });
+    round_trip_database(DatabaseQuery::Restore {
+        name: "shop".to_string(),
+        path: r"C:\backups\shop.fhdb".to_string(),
+    });
+}
+
+#[test]
+fn unexpressible_archive_path() {
+    let query = DatabaseQuery::Backup {
+        name: "shop".to_string(),
+        path: "say \"cheese\"".to_string(),
+    };
+
+    assert!(matches!(
+        render_database_query(&query),
+        Err(ClientError::InvalidQuery(_))
+    ));
+}
+
+#[test]
+fn collection_queries() {
+    let mut fields = HashMap::new();
+    fields.insert("id".to_string(), FieldDefinition::new(FieldType::IdInt));
+    fields.insert(
+        "name".to_string(),
+        FieldDefinition::with_default(FieldType::String, Bson::String("anon".to_string())),
+    );
+    fields.insert(
+        "tags".to_string(),
+        FieldDefinition::new(FieldType::Nullable(Box::new(FieldType::Array(Box::new(
+            FieldType::String,
+        ))))),
+    );
+    fields.insert(
+        "score".to_string(),
+        FieldDefinition::with_default(
+            FieldType::Nullable(Box::new(FieldType::Float)),
+            Bson::Double(1.0),
+        ),
+    );
+    fields.insert(
+        "owner".to_string(),
+        FieldDefinition::new(FieldType::Reference("users".to_string())),
+    );
+    fields.insert(
+        "balance".to_string(),
+        FieldDefinition::new(FieldType::EncryptedInt),
+    );
+    fields.insert(
+        "email".to_string(),
+        FieldDefinition::new(FieldType::Nullable(Box::new(FieldType::String))).with_unique(true),
+    );
+    round_trip_contextual(ContextualQuery::Collection(CollectionQuery::Create {
+        name: "items".to_string(),
+        drop_if_exists: true,
+        schema: Schema { fields },
+    }));
+
+    let mut modifications = HashMap::new();
+    modifications.insert("tags".to_string(), FieldModification::Drop);
+    modifications.insert(
+        "email".to_string(),
+        FieldModification::Set(FieldDefinition::new(FieldType::EncryptedString)),
+    );
+    round_trip_contextual(ContextualQuery::Collection(CollectionQuery::Modify {
+        name: "items".to_string(),
+        modifications,
+    }));
+
+    round_trip_contextual(ContextualQuery::Collection(CollectionQuery::List));
+    round_trip_contextual(ContextualQuery::Collection(CollectionQuery::Drop {
+        name: "items".to_string(),
+    }));
+    round_trip_contextual(ContextualQuery::Collection(CollectionQuery::GetSchema {
+        name: "items".to_string(),
+    }));
+    round_trip_contextual(ContextualQuery::Collection(CollectionQuery::Compact {
+        name: "items".to_string(),
+    }));
+    round_trip_contextual(ContextualQuery::Collection(CollectionQuery::CreateIndex {
+        name: "items".to_string(),
+        field_names: vec!["name".to_string()],
+    }));
+    round_trip_contextual(ContextualQuery::Collection(CollectionQuery::CreateIndex {
+        name: "items".to_string(),
+        field_names: vec!["category".to_string(), "price".to_string()],
+    }));
+    round_trip_contextual(ContextualQuery::Collection(CollectionQuery::DropIndex {
+        name: "items".to_string(),
+        field_names: vec!["name".to_string()],
+    }));
+    round_trip_contextual(ContextualQuery::Collection(CollectionQuery::DropIndex {
+        name: "items".to_string(),
+        field_names: vec!["category".to_string(), "price".to_string()],
+    }));
+}
+
+#[test]
+fn document_queries() {
+    let conditions = vec![
+        Condition::eq("name", "Alice").to_field_condition().unwrap(),
+        Condition::ge("age", 18).to_field_condition().unwrap(),
+        Condition::similar("bio", "rust")
+            .to_field_condition()
+            .unwrap(),
+    ];
+    let mut fields = HashMap::new();
+    fields.insert("name".to_string(), bson_literal(&"Bob".into()).unwrap());
+    fields.insert("age".to_string(), bson_literal(&Bson::Int64(30)).unwrap());
+    fields.insert(
+        "tags".to_string(),
+        bson_literal(&Bson::Array(vec!["a".into(), "b".into()])).unwrap(),
+    );
+
+    round_trip_contextual(ContextualQuery::Document(DocumentQuery::Insert {
+        collection_name: "users".to_string(),
+        fields: fields.clone(),
+    }));
+    round_trip_contextual(ContextualQuery::Document(DocumentQuery::Get {
+        collection_name: "users".to_string(),
+        conditions: conditions.clone(),
+        selectors: Selection::Fields(vec!["name".to_string(), "age".to_string()]).to_selectors(),
+        as_of: None,
+    }));
+    round_trip_contextual(ContextualQuery::Document(DocumentQuery::Get {
+        collection_name: "users".to_string(),
+        conditions: vec![],
+        selectors: vec![
+            FieldSelector::Field("name".to_string()),
+            FieldSelector::SubDocument {
+                field_name: "friend".to_string(),
+                content: ParsedDocContent {
+                    assignments: HashMap::new(),
+                    conditions: conditions[..1].to_vec(),
+                    selectors: vec![FieldSelector::AllFieldsRecursive],
+                },
+            },
+        ],
+        as_of: Some("2026-10-01T00:00:00+02:00".to_string()),
+    }));
+    round_trip_contextual(ContextualQuery::Document(DocumentQuery::Update {
+        collection_name: "users".to_string(),
+        conditions: conditions.clone(),
+        updates: fields,
+        selectors: Selection::All.to_selectors(),
+    }));
+    round_trip_contextual(ContextualQuery::Document(DocumentQuery::Delete {
+        collection_name: "users".to_string(),
+        conditions: conditions.clone(),
+        selectors: vec![],
+    }));
+    round_trip_contextual(ContextualQuery::Document(DocumentQuery::History {
+        collection_name: "users".to_string(),
+        conditions: vec![Condition::eq("id", 5).to_field_condition().unwrap()],
+    }));
+    round_trip_contextual(ContextualQuery::Document(DocumentQuery::Sum {
+        collection_name: "users".to_string(),
+        field_name: "age".to_string(),
+        conditions,
+    }));
+}
+
+#[test]
+fn literals() {
+    assert_eq!(bson_literal(&Bson::Null).unwrap(), "null");
+    assert_eq!(bson_literal(&Bson::Boolean(false)).unwrap(), "false");
+    assert_eq!(bson_literal(&Bson::Int32(-7)).unwrap(), "-7");
+    assert_eq!(bson_literal(&Bson::Double(2.0)).unwrap(), "2.0");
+    assert_eq!(bson_literal(&Bson::Double(-0.25)).unwrap(), "-0.25");
+    assert_eq!(
+        bson_literal(&Bson::Array(vec![Bson::Int64(1), Bson::Array(vec![])])).unwrap(),
+        "[1, []]"
+    );
+
+    assert!(bson_literal(&Bson::Double(f64::NAN)).is_err());
+    assert!(bson_literal(&Bson::Document(doc! { "a": 1 })).is_err());
+}
+
+#[test]
+fn string_literals_survive_parsing() {
+    let values = [
+        "plain",
+        "with \"double\" and 'single' quotes",
+        r"back\slash \n not a newline",
+        "trailing backslash \\",
+        "line\nbreak\ttab\r\0",
+    ];
+
+    for value in values {
+        let condition = Condition::eq("name", value).to_field_condition().unwrap();
+        let query = ContextualQuery::Document(DocumentQuery::Get {
+            collection_name: "users".to_string(),
+            conditions: vec![condition],
+            selectors: vec![],
+            as_of: None,
+        });
+        let text = render_contextual_query(&query).unwrap();
+
+        let ContextualQuery::Document(DocumentQuery::Get { conditions, .. }) =
+            parse_contextual_query(&text).unwrap()
+        else {
+            panic!("expected a GET query");
+        };
+        assert_eq!(conditions[0].operator, QueryOperator::Equal);
+        assert_eq!(
+            conditions[0]
+                .value
+                .parse_as_bson(&FieldType::String)
+                .unwrap(),
+            Bson::String(value.to_string())
+        );
+    }
+}
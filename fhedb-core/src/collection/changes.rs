@@ -0,0 +1,379 @@
+//! # Change Feed
+//!
+//! Provides the feed of the writes made to collections, for other services to react to.
+//!
+//! Every logged insert, update and delete is published to the feed of its collection as a
+//! [`ChangeEvent`] carrying the document's image and a sequence number. Collections of a
+//! [`Database`](crate::database::Database) share the database's feed, so sequence numbers
+//! increase across all of them. The feed keeps its latest events in memory, so a
+//! [`Subscription`] can resume from the sequence number following the last event it saw,
+//! as long as that event is still held.
+//!
+//! Sequence numbers start over when the feed is created, such as when the database is loaded
+//! again. Every feed gets a random epoch, so a sequence number saved from an earlier feed can
+//! be told apart from one of the current feed rather than resuming at an unrelated event.
+
+use crate::{
+    collection::{Collection, file::Operation},
+    document::DocId,
+};
+use bson::Document as BsonDocument;
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError},
+    time::{Duration, Instant},
+};
+use tokio::sync::Notify;
+use uuid::Uuid;
+
+/// The default number of events a change feed holds for subscriptions to resume from.
+pub const DEFAULT_CHANGE_FEED_CAPACITY: usize = 10_000;
+
+/// A write made to a collection.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChangeEvent {
+    /// The position of the event in its feed, starting at one.
+    pub sequence: u64,
+    /// The name of the written collection.
+    pub collection: String,
+    /// The logged operation.
+    pub operation: Operation,
+    /// The RFC 3339 timestamp of the log entry.
+    pub timestamp: String,
+    /// The ID of the written document.
+    pub id: DocId,
+    /// The document after an insert or update, or as it was before a delete.
+    pub document: BsonDocument,
+}
+
+/// The events held by a change feed.
+#[derive(Debug)]
+struct FeedState {
+    /// The latest events, in sequence order.
+    events: VecDeque<ChangeEvent>,
+    /// The sequence number of the next event.
+    next_sequence: u64,
+    /// The maximum number of events held.
+    capacity: usize,
+}
+
+impl FeedState {
+    /// Returns the sequence number of the oldest event held,
+    /// or of the next event if none is held.
+    fn oldest_sequence(&self) -> u64 {
+        self.events
+            .front()
+            .map_or(self.next_sequence, |event| event.sequence)
+    }
+}
+
+/// The state of a change feed shared between its clones and subscriptions.
+#[derive(Debug)]
+struct FeedShared {
+    /// The held events.
+    state: Mutex<FeedState>,
+    /// The condition blocking subscriptions wait on.
+    published: Condvar,
+    /// The notification asynchronous subscriptions wait on.
+    notify: Notify,
+}
+
+/// The feed of the writes made to one or more collections, shared between clones.
+#[derive(Debug, Clone)]
+pub struct ChangeFeed {
+    /// The random value telling the feed apart from the earlier feeds of the same writes.
+    epoch: u64,
+    /// The held events along with what subscriptions wait on.
+    shared: Arc<FeedShared>,
+}
+
+impl Default for ChangeFeed {
+    fn default() -> Self {
+        Self::new(DEFAULT_CHANGE_FEED_CAPACITY)
+    }
+}
+
+impl ChangeFeed {
+    /// Creates an empty [`ChangeFeed`].
+    ///
+    /// ## Arguments
+    ///
+    /// * `capacity` - The number of events held for subscriptions to resume from.
+    pub fn new(capacity: usize) -> Self {
+        let state = FeedState {
+            events: VecDeque::new(),
+            next_sequence: 1,
+            capacity: capacity.max(1),
+        };
+        Self {
+            // Kept to 53 bits, so the epoch reads exactly as a JSON number.
+            epoch: Uuid::new_v4().as_u64_pair().1 >> 11,
+            shared: Arc::new(FeedShared {
+                state: Mutex::new(state),
+                published: Condvar::new(),
+                notify: Notify::new(),
+            }),
+        }
+    }
+
+    /// Returns the epoch of the feed, which sequence numbers are only meaningful within.
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    /// Returns the sequence number the next event will get.
+    pub fn next_sequence(&self) -> u64 {
+        self.lock_state()
+            .map(|state| state.next_sequence)
+            .unwrap_or_default()
+    }
+
+    /// Returns the sequence number of the oldest event a subscription can still resume from.
+    pub fn oldest_sequence(&self) -> u64 {
+        self.lock_state()
+            .map(|state| state.oldest_sequence())
+            .unwrap_or_default()
+    }
+
+    /// Publishes a write, waking up the subscriptions waiting for it.
+    ///
+    /// The write is already logged by then, so a poisoned lock is recovered from
+    /// rather than failing it.
+    ///
+    /// ## Arguments
+    ///
+    /// * `collection` - The name of the written collection.
+    /// * `operation` - The logged [`Operation`].
+    /// * `timestamp` - The timestamp of the log entry.
+    /// * `id` - The [`DocId`] of the written document.
+    /// * `document` - The document image of the event.
+    pub(crate) fn publish(
+        &self,
+        collection: &str,
+        operation: &Operation,
+        timestamp: &str,
+        id: &DocId,
+        document: &BsonDocument,
+    ) {
+        let mut state = self
+            .shared
+            .state
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let sequence = state.next_sequence;
+        state.next_sequence += 1;
+        if state.events.len() == state.capacity {
+            state.events.pop_front();
+        }
+        state.events.push_back(ChangeEvent {
+            sequence,
+            collection: collection.to_string(),
+            operation: operation.clone(),
+            timestamp: timestamp.to_string(),
+            id: id.clone(),
+            document: document.clone(),
+        });
+        drop(state);
+
+        self.shared.published.notify_all();
+        self.shared.notify.notify_waiters();
+    }
+
+    /// Subscribes to the feed.
+    ///
+    /// ## Arguments
+    ///
+    /// * `from` - The sequence number of the first event to receive,
+    ///   or [`None`] to only receive events published from now on.
+    /// * `collection` - The name of the collection to receive events of,
+    ///   or [`None`] for every collection.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`Subscription`]) positioned at the first event to receive,
+    /// or [`Err`]\([`String`]) if that event is no longer held or is yet to be published.
+    pub fn subscribe(
+        &self,
+        from: Option<u64>,
+        collection: Option<&str>,
+    ) -> Result<Subscription, String> {
+        let state = self.lock_state()?;
+        let next_sequence = match from {
+            None => state.next_sequence,
+            Some(from) if from < state.oldest_sequence() => {
+                return Err(format!(
+                    "Changes before sequence {} are no longer available",
+                    state.oldest_sequence()
+                ));
+            }
+            Some(from) if from > state.next_sequence => {
+                return Err(format!(
+                    "Sequence {} is ahead of the change feed, which is at {}",
+                    from, state.next_sequence
+                ));
+            }
+            Some(from) => from,
+        };
+
+        Ok(Subscription {
+            feed: self.clone(),
+            collection: collection.map(str::to_string),
+            next_sequence,
+        })
+    }
+
+    /// Locks the events held by the feed.
+    fn lock_state(&self) -> Result<MutexGuard<'_, FeedState>, String> {
+        self.shared
+            .state
+            .lock()
+            .map_err(|_| "Change feed lock is poisoned".to_string())
+    }
+}
+
+/// A reader of a [`ChangeFeed`], receiving its events in sequence order.
+#[derive(Debug, Clone)]
+pub struct Subscription {
+    /// The feed the events are read from.
+    feed: ChangeFeed,
+    /// The name of the collection whose events are received, or [`None`] for all of them.
+    collection: Option<String>,
+    /// The sequence number of the next event to receive.
+    next_sequence: u64,
+}
+
+impl Subscription {
+    /// Returns the epoch of the feed the events are read from.
+    pub fn epoch(&self) -> u64 {
+        self.feed.epoch
+    }
+
+    /// Returns the sequence number of the next event to receive, to resume from later.
+    pub fn next_sequence(&self) -> u64 {
+        self.next_sequence
+    }
+
+    /// Receives the next event if it was already published.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`Some`]\([`ChangeEvent`])) with the next event, [`Ok`]\([`None`])
+    /// if there is none yet, or [`Err`]\([`String`]) if the subscription fell so far behind
+    /// that events it did not receive were dropped from the feed.
+    pub fn try_next(&mut self) -> Result<Option<ChangeEvent>, String> {
+        let feed = self.feed.clone();
+        let state = feed.lock_state()?;
+        self.take_next(&state)
+    }
+
+    /// Receives the next event, waiting for it to be published.
+    ///
+    /// ## Arguments
+    ///
+    /// * `timeout` - How long to wait for the event.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`Some`]\([`ChangeEvent`])) with the next event, [`Ok`]\([`None`])
+    /// if none was published in time, or [`Err`]\([`String`]) if the subscription fell so
+    /// far behind that events it did not receive were dropped from the feed.
+    pub fn next_timeout(&mut self, timeout: Duration) -> Result<Option<ChangeEvent>, String> {
+        let deadline = Instant::now() + timeout;
+        let feed = self.feed.clone();
+        let mut state = feed.lock_state()?;
+        loop {
+            if let Some(event) = self.take_next(&state)? {
+                return Ok(Some(event));
+            }
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Ok(None);
+            }
+            state = feed
+                .shared
+                .published
+                .wait_timeout(state, remaining)
+                .map_err(|_| "Change feed lock is poisoned".to_string())?
+                .0;
+        }
+    }
+
+    /// Receives the next event, waiting for it to be published without blocking the thread.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`ChangeEvent`]) with the next event, or [`Err`]\([`String`]) if the
+    /// subscription fell so far behind that events it did not receive were dropped from the feed.
+    pub async fn next_async(&mut self) -> Result<ChangeEvent, String> {
+        let feed = self.feed.clone();
+        loop {
+            // Created before checking for the event, so one published in between still wakes it.
+            let notified = feed.shared.notify.notified();
+            if let Some(event) = self.try_next()? {
+                return Ok(event);
+            }
+            notified.await;
+        }
+    }
+
+    /// Takes the next event of the subscribed collection from the held events,
+    /// skipping over the events of other collections.
+    ///
+    /// ## Arguments
+    ///
+    /// * `state` - The locked events of the feed.
+    fn take_next(&mut self, state: &FeedState) -> Result<Option<ChangeEvent>, String> {
+        let oldest = state.oldest_sequence();
+        if self.next_sequence < oldest {
+            return Err(format!(
+                "Subscription fell behind: changes before sequence {} are no longer available",
+                oldest
+            ));
+        }
+
+        let start = (self.next_sequence - oldest) as usize;
+        for event in state.events.iter().skip(start) {
+            self.next_sequence = event.sequence + 1;
+            if self
+                .collection
+                .as_ref()
+                .is_none_or(|collection| *collection == event.collection)
+            {
+                return Ok(Some(event.clone()));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Change feed subscriptions of the collection.
+impl Collection {
+    /// Returns the feed the collection's writes are published to.
+    pub fn change_feed(&self) -> &ChangeFeed {
+        &self.changes
+    }
+
+    /// Sets the feed the collection's writes are published to.
+    ///
+    /// ## Arguments
+    ///
+    /// * `feed` - The [`ChangeFeed`], which may be shared with other collections.
+    pub fn set_change_feed(&mut self, feed: ChangeFeed) {
+        self.changes = feed;
+    }
+
+    /// Subscribes to the writes made to the collection.
+    ///
+    /// ## Arguments
+    ///
+    /// * `from` - The sequence number of the first event to receive,
+    ///   or [`None`] to only receive events published from now on.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`Subscription`]) positioned at the first event to receive,
+    /// or [`Err`]\([`String`]) if that event is no longer held or is yet to be published.
+    pub fn subscribe_changes(&self, from: Option<u64>) -> Result<Subscription, String> {
+        self.changes.subscribe(from, Some(&self.name))
+    }
+}
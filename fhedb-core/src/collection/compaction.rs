@@ -0,0 +1,735 @@
+//! # Online Compaction
+//!
+//! Provides compaction of sealed log segments while the collection stays available.
+//!
+//! Compaction runs in three steps, so that callers sharing a collection between threads
+//! only need exclusive access for the short first and last ones:
+//!
+//! 1. [`Collection::plan_compaction`] seals the active segment, so that every entry
+//!    that can be garbage lives in a segment that is never appended to again.
+//! 2. [`Collection::prepare_compaction`] copies the live entries of each sealed segment
+//!    into a temporary file next to it, without touching the segments readers use.
+//! 3. [`Collection::commit_compaction`] swaps the temporary files in and repoints the
+//!    primary index at the copied entries.
+//!
+//! With a history retention window set, only the oldest sealed segments whose every entry
+//! is older than the window are compacted, and the state a document had at the end of them
+//! is kept even if it was superseded since, so the history within the window stays complete.
+
+use crate::{
+    collection::{
+        Collection,
+        durability::Durability,
+        file::{LogEntry, Operation, sync_dir},
+        history::parse_timestamp,
+        segment::LogPosition,
+    },
+    document::DocId,
+    format::{FORMAT_HEADER_SIZE, FileKind, encode_format_header},
+};
+use chrono::{DateTime, Utc};
+use std::{
+    collections::HashMap,
+    fmt,
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+    sync::{
+        Arc, MutexGuard,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
+
+/// The default share of garbage entries in the log above which compaction is needed.
+pub const DEFAULT_COMPACTION_THRESHOLD: f64 = 0.5;
+
+/// The extension of the temporary files compacted segments are written to.
+const COMPACTION_FILE_EXTENSION: &str = "compact";
+
+/// Counts of the entries in a collection's log.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LogStats {
+    /// The number of entries in the log.
+    pub entries: u64,
+    /// The number of entries superseded by a later update or delete, including deletes.
+    pub garbage: u64,
+}
+
+impl LogStats {
+    /// Returns the share of the log's entries that are garbage, between zero and one.
+    pub fn garbage_ratio(&self) -> f64 {
+        if self.entries == 0 {
+            return 0.0;
+        }
+        self.garbage.min(self.entries) as f64 / self.entries as f64
+    }
+
+    /// Accounts for a newly logged entry.
+    ///
+    /// An update supersedes the document's previous entry, and a delete supersedes
+    /// the previous entry as well as itself.
+    ///
+    /// ## Arguments
+    ///
+    /// * `operation` - The [`Operation`] of the logged entry.
+    pub(crate) fn record(&mut self, operation: &Operation) {
+        self.entries += 1;
+        self.garbage += match operation {
+            Operation::Insert => 0,
+            Operation::Update => 1,
+            Operation::Delete => 2,
+        };
+    }
+}
+
+/// The outcome of a compaction.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CompactionReport {
+    /// The number of segments that were rewritten.
+    pub segments_compacted: usize,
+    /// The number of segments that were removed because no live entry was left in them.
+    pub segments_removed: usize,
+    /// The number of garbage entries removed from the log.
+    pub entries_removed: u64,
+    /// The number of bytes the log shrank by.
+    pub bytes_reclaimed: u64,
+}
+
+impl fmt::Display for CompactionReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} segments compacted, {} removed, {} entries and {} bytes reclaimed",
+            self.segments_compacted,
+            self.segments_removed,
+            self.entries_removed,
+            self.bytes_reclaimed
+        )
+    }
+}
+
+/// Marks a collection as being compacted until dropped.
+#[derive(Debug)]
+struct CompactionGuard(Arc<AtomicBool>);
+
+impl Drop for CompactionGuard {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::SeqCst);
+    }
+}
+
+/// The sealed segments selected by [`Collection::plan_compaction`].
+///
+/// No other compaction of the collection can start while a plan, or the
+/// [`PreparedCompaction`] made from it, is alive.
+#[derive(Debug)]
+pub struct CompactionPlan {
+    /// The numbers of the sealed segments to compact, in ascending order.
+    segments: Vec<u64>,
+    /// The guard keeping other compactions from starting.
+    guard: CompactionGuard,
+}
+
+impl CompactionPlan {
+    /// Returns the numbers of the sealed segments to compact.
+    pub fn segments(&self) -> &[u64] {
+        &self.segments
+    }
+}
+
+/// The latest state of a live document, to be written where the document was inserted.
+#[derive(Debug)]
+pub(crate) struct CompactedDocument {
+    /// The ID of the document.
+    pub(crate) doc_id: DocId,
+    /// The position of the document's latest entry.
+    pub(crate) latest: LogPosition,
+    /// The timestamp of the document's original insert.
+    pub(crate) inserted_at: String,
+    /// The timestamp of the document's latest insert or update.
+    pub(crate) updated_at: String,
+}
+
+/// A log segment whose compacted entries were written to a temporary file.
+#[derive(Debug)]
+pub(crate) struct CompactedSegment {
+    /// Every written document with the position of its latest entry and its new position.
+    pub(crate) moved: Vec<(DocId, LogPosition, LogPosition)>,
+    /// The number of entries in the segment before compaction.
+    pub(crate) entries: u64,
+    /// The size of the temporary file.
+    pub(crate) length: usize,
+    /// Whether damaged bytes were skipped while reading the segment.
+    pub(crate) damaged: bool,
+    /// The timestamp of the newest entry in the segment before compaction.
+    pub(crate) newest: Option<DateTime<Utc>>,
+}
+
+impl CompactedSegment {
+    /// Checks whether the compacted segment is identical to the original one.
+    pub(crate) fn is_unchanged(&self) -> bool {
+        !self.damaged
+            && self.moved.len() as u64 == self.entries
+            && self
+                .moved
+                .iter()
+                .all(|(_, latest, position)| latest == position)
+    }
+}
+
+/// A sealed segment whose live entries were copied to a temporary file.
+#[derive(Debug)]
+struct PreparedSegment {
+    /// The number of the segment.
+    segment: u64,
+    /// The temporary file holding the copied entries.
+    temp_path: PathBuf,
+    /// The copied entries.
+    compacted: CompactedSegment,
+    /// The size of the segment before compaction.
+    bytes_before: u64,
+}
+
+/// The compacted copies of sealed segments, ready to be swapped in by
+/// [`Collection::commit_compaction`]. Dropping it discards the copies.
+#[derive(Debug)]
+pub struct PreparedCompaction {
+    /// The copied segments, in ascending order.
+    segments: Vec<PreparedSegment>,
+    /// The plan the copies were made from.
+    plan: CompactionPlan,
+}
+
+impl Drop for PreparedCompaction {
+    fn drop(&mut self) {
+        for segment in &self.segments {
+            let _ = fs::remove_file(&segment.temp_path);
+        }
+    }
+}
+
+/// Online compaction of the collection's sealed log segments.
+impl Collection {
+    /// Returns the counts of the entries in the collection's log.
+    pub fn log_stats(&self) -> LogStats {
+        self.lock_log_stats()
+            .map(|stats| *stats)
+            .unwrap_or_default()
+    }
+
+    /// Returns the share of garbage entries in the collection's log.
+    pub fn garbage_ratio(&self) -> f64 {
+        self.log_stats().garbage_ratio()
+    }
+
+    /// Returns the garbage ratio above which the collection needs compaction.
+    pub fn compaction_threshold(&self) -> f64 {
+        self.compaction_threshold
+    }
+
+    /// Sets the garbage ratio above which the collection needs compaction.
+    ///
+    /// ## Arguments
+    ///
+    /// * `threshold` - The share of garbage entries, between zero and one.
+    pub fn set_compaction_threshold(&mut self, threshold: f64) {
+        self.compaction_threshold = threshold;
+    }
+
+    /// Returns how long compaction keeps log entries untouched,
+    /// or [`None`] if it discards the history of documents.
+    pub fn history_retention(&self) -> Option<Duration> {
+        self.history_retention
+    }
+
+    /// Sets how long compaction keeps log entries untouched.
+    ///
+    /// History is kept a whole segment at a time: a sealed segment is only compacted once
+    /// every entry in it is older than the window. The active segment is then left to roll
+    /// over on its own, instead of being sealed by every compaction.
+    ///
+    /// ## Arguments
+    ///
+    /// * `retention` - The retention window, or [`None`] to discard history.
+    pub fn set_history_retention(&mut self, retention: Option<Duration>) {
+        self.history_retention = retention;
+    }
+
+    /// Checks whether the garbage ratio crossed the compaction threshold
+    /// and no compaction is already running.
+    pub fn needs_compaction(&self) -> bool {
+        let stats = self.log_stats();
+        stats.garbage > 0
+            && stats.garbage_ratio() >= self.compaction_threshold
+            && !self.compacting.load(Ordering::SeqCst)
+    }
+
+    /// Compacts the collection's log, running every step of the compaction in turn.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`CompactionReport`]) describing the space reclaimed, which is empty
+    /// if there was nothing to compact or another compaction is running,
+    /// or [`Err`]\([`io::Error`]) if the compaction failed.
+    pub fn compact(&mut self) -> io::Result<CompactionReport> {
+        let Some(plan) = self.plan_compaction()? else {
+            return Ok(CompactionReport::default());
+        };
+        let prepared = self.prepare_compaction(plan)?;
+        self.commit_compaction(prepared)
+    }
+
+    /// Starts a compaction by sealing the active segment and selecting every sealed segment.
+    ///
+    /// All sealed segments are selected together, so that deletes can be dropped along
+    /// with every older entry of the documents they removed. The active segment is only
+    /// sealed if no history retention window is set.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`Some`]\([`CompactionPlan`])) with the segments to compact,
+    /// [`Ok`]\([`None`]) if the log is empty or another compaction is running,
+    /// or [`Err`]\([`io::Error`]) if the active segment could not be sealed.
+    pub fn plan_compaction(&mut self) -> io::Result<Option<CompactionPlan>> {
+        if self.compacting.swap(true, Ordering::SeqCst) {
+            return Ok(None);
+        }
+        let guard = CompactionGuard(self.compacting.clone());
+
+        let end = self.log.end()?;
+        if end.offset > FORMAT_HEADER_SIZE && self.history_retention.is_none() {
+            self.seal_segment(end.segment)?;
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(self.log.segment_path(end.segment + 1))?
+                .write_all(&encode_format_header(
+                    FileKind::LogSegment,
+                    self.log.encryption_key().is_some(),
+                ))?;
+            if self.durability != Durability::Never {
+                sync_dir(&self.base_path)?;
+            }
+
+            let next = LogPosition::new(end.segment + 1, FORMAT_HEADER_SIZE);
+            if self.index.checkpoint()? == end {
+                self.save_index_checkpoint(next)?;
+            }
+        }
+
+        let active_segment = self.log.end()?.segment;
+        let segments: Vec<u64> = self
+            .log
+            .segments()?
+            .into_iter()
+            .filter(|&segment| segment < active_segment)
+            .collect();
+        if segments.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(CompactionPlan { segments, guard }))
+    }
+
+    /// Copies the live entries of every planned segment into temporary files.
+    ///
+    /// Only reads the collection, so it can run alongside document reads.
+    /// Segments without garbage are left out, as are segments holding entries within
+    /// the history retention window and every segment after them.
+    ///
+    /// ## Arguments
+    ///
+    /// * `plan` - The [`CompactionPlan`] returned by [`Collection::plan_compaction`].
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`PreparedCompaction`]) holding the copies,
+    /// or [`Err`]\([`io::Error`]) if a segment could not be copied.
+    pub fn prepare_compaction(&self, plan: CompactionPlan) -> io::Result<PreparedCompaction> {
+        let mut prepared = PreparedCompaction {
+            segments: Vec::new(),
+            plan,
+        };
+        let segments = self.segments_past_retention(&prepared.plan.segments)?;
+        let (Some(&first), Some(&last)) = (segments.first(), segments.last()) else {
+            return Ok(prepared);
+        };
+
+        // Documents whose latest entry lies in a later segment are left where they are,
+        // unless their state at the end of the compacted segments is part of the history kept.
+        let retain_history = self.history_retention.is_some();
+        let layout = self.compaction_layout(first..=last, |doc_id, latest| {
+            Ok(retain_history || self.index.get(doc_id)? == Some(latest))
+        })?;
+
+        for &segment in segments {
+            let temp_path = self
+                .log
+                .segment_path(segment)
+                .with_extension(COMPACTION_FILE_EXTENSION);
+            let compacted = self.write_compacted_segment(segment, &layout, &temp_path)?;
+            if compacted.is_unchanged() {
+                fs::remove_file(temp_path)?;
+                continue;
+            }
+
+            prepared.segments.push(PreparedSegment {
+                segment,
+                temp_path,
+                compacted,
+                bytes_before: self.log.segment_len(segment)? as u64,
+            });
+        }
+
+        Ok(prepared)
+    }
+
+    /// Selects the leading segments whose every entry is older than the history
+    /// retention window, all of them if no window is set.
+    ///
+    /// ## Arguments
+    ///
+    /// * `segments` - The numbers of the sealed segments, in ascending order.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`&[u64]`]) with the segments that can be compacted,
+    /// or [`Err`]\([`io::Error`]) if their entries could not be read.
+    fn segments_past_retention<'a>(&self, segments: &'a [u64]) -> io::Result<&'a [u64]> {
+        let (Some(retention), Some(&first)) = (self.history_retention, segments.first()) else {
+            return Ok(segments);
+        };
+        let retention = chrono::Duration::from_std(retention).unwrap_or(chrono::Duration::MAX);
+        let cutoff = Utc::now()
+            .checked_sub_signed(retention)
+            .unwrap_or(DateTime::<Utc>::MIN_UTC);
+
+        for item in self.log.entries_from(LogPosition::new(first, 0))? {
+            let (log_entry, position) = item?;
+            if segments.last() < Some(&position.segment) {
+                break;
+            }
+            let timestamp = parse_timestamp(&log_entry.timestamp)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            if timestamp > cutoff {
+                let past = segments.partition_point(|&segment| segment < position.segment);
+                return Ok(&segments[..past]);
+            }
+        }
+        Ok(segments)
+    }
+
+    /// Works out where the live documents of a range of segments are compacted into.
+    ///
+    /// Each live document is placed at the entry that inserted it, which keeps documents in
+    /// insertion order. Entries written by an earlier compaction count as inserts, and carry
+    /// the original insert timestamp along.
+    ///
+    /// ## Arguments
+    ///
+    /// * `segments` - The range of segment numbers to compact.
+    /// * `is_live` - Checks whether a document's latest entry in the range is still current.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`HashMap`]<[`LogPosition`], [`CompactedDocument`]>) with the live
+    /// documents keyed by the position of the entry that inserted them,
+    /// or [`Err`]\([`io::Error`]) if the segments could not be read.
+    pub(crate) fn compaction_layout(
+        &self,
+        segments: RangeInclusive<u64>,
+        is_live: impl Fn(&DocId, LogPosition) -> io::Result<bool>,
+    ) -> io::Result<HashMap<LogPosition, CompactedDocument>> {
+        let mut documents: HashMap<DocId, (LogPosition, CompactedDocument)> = HashMap::new();
+        for item in self
+            .log
+            .entries_from(LogPosition::new(*segments.start(), 0))?
+        {
+            let (log_entry, position) = item?;
+            if position.segment > *segments.end() {
+                break;
+            }
+
+            let doc_id = self.log_entry_doc_id(&log_entry.document, position)?;
+            match (&log_entry.operation, documents.get_mut(&doc_id)) {
+                (Operation::Delete, _) => {
+                    documents.remove(&doc_id);
+                }
+                (Operation::Update, Some((_, document))) => {
+                    document.latest = position;
+                    document.updated_at = log_entry.timestamp;
+                }
+                _ => {
+                    let document = CompactedDocument {
+                        doc_id: doc_id.clone(),
+                        latest: position,
+                        inserted_at: log_entry.inserted_at().to_string(),
+                        updated_at: log_entry.timestamp,
+                    };
+                    documents.insert(doc_id, (position, document));
+                }
+            }
+        }
+
+        let mut layout = HashMap::with_capacity(documents.len());
+        for (origin, document) in documents.into_values() {
+            if is_live(&document.doc_id, document.latest)? {
+                layout.insert(origin, document);
+            }
+        }
+        Ok(layout)
+    }
+
+    /// Writes the compacted entries of a single segment into a temporary file.
+    ///
+    /// Every entry of the segment that inserted a live document is replaced by the document's
+    /// latest state, stamped with the time of its last update and of its original insert.
+    /// All other entries are dropped.
+    ///
+    /// ## Arguments
+    ///
+    /// * `segment` - The number of the segment to compact.
+    /// * `layout` - The live documents keyed by the position they are compacted into.
+    /// * `temp_path` - The path of the temporary file to write.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`CompactedSegment`]) describing the written entries,
+    /// or [`Err`]\([`io::Error`]) if the segment could not be compacted.
+    pub(crate) fn write_compacted_segment(
+        &self,
+        segment: u64,
+        layout: &HashMap<LogPosition, CompactedDocument>,
+        temp_path: &Path,
+    ) -> io::Result<CompactedSegment> {
+        let mut temp_file = OpenOptions::new()
+            .create(true)
+            .truncate(true)
+            .write(true)
+            .open(temp_path)?;
+        temp_file.write_all(&encode_format_header(
+            FileKind::LogSegment,
+            self.log.encryption_key().is_some(),
+        ))?;
+
+        let mut compacted = CompactedSegment {
+            moved: Vec::new(),
+            entries: 0,
+            length: FORMAT_HEADER_SIZE,
+            damaged: false,
+            newest: None,
+        };
+        let mut entries = self.log.entries_from(LogPosition::new(segment, 0))?;
+        for item in entries.by_ref() {
+            let (log_entry, position) = item?;
+            if position.segment != segment {
+                break;
+            }
+            compacted.entries += 1;
+            let timestamp = parse_timestamp(&log_entry.timestamp)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            compacted.newest = compacted.newest.max(Some(timestamp));
+
+            let Some(document) = layout.get(&position) else {
+                continue;
+            };
+            let latest = if document.latest == position {
+                self.resolve_document(log_entry)?
+            } else {
+                self.resolve_document(self.log.read_at(document.latest)?)?
+            };
+            let frame = LogEntry {
+                timestamp: document.updated_at.clone(),
+                operation: Operation::Insert,
+                document: latest,
+                inserted_at: Some(document.inserted_at.clone())
+                    .filter(|at| *at != document.updated_at),
+                base: None,
+            }
+            .into_frame(
+                self.compression,
+                self.log
+                    .entry_seal(LogPosition::new(segment, compacted.length)),
+            )?;
+            temp_file.write_all(&frame)?;
+
+            compacted.moved.push((
+                document.doc_id.clone(),
+                document.latest,
+                LogPosition::new(segment, compacted.length),
+            ));
+            compacted.length += frame.len();
+        }
+        compacted.damaged = entries
+            .report()
+            .skipped
+            .iter()
+            .any(|region| region.position.segment == segment);
+
+        if self.durability != Durability::Never {
+            temp_file.sync_all()?;
+        }
+        Ok(compacted)
+    }
+
+    /// Swaps the compacted copies in and repoints the primary index at them.
+    ///
+    /// Documents written to since the copies were made keep their newer entries.
+    /// The index checkpoint is moved back to the first compacted segment until every
+    /// copied document is repointed, so a crash in between is repaired on the next load.
+    ///
+    /// ## Arguments
+    ///
+    /// * `prepared` - The [`PreparedCompaction`] returned by [`Collection::prepare_compaction`].
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`CompactionReport`]) describing the space reclaimed,
+    /// or [`Err`]\([`io::Error`]) if the copies were made for another collection
+    /// or could not be swapped in.
+    pub fn commit_compaction(
+        &mut self,
+        prepared: PreparedCompaction,
+    ) -> io::Result<CompactionReport> {
+        if !Arc::ptr_eq(&prepared.plan.guard.0, &self.compacting) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Compaction was prepared for another collection",
+            ));
+        }
+
+        let mut report = CompactionReport::default();
+        let Some(first) = prepared.segments.first() else {
+            return Ok(report);
+        };
+
+        let checkpoint = self.index.checkpoint()?;
+        let compacted_from = LogPosition::new(first.segment, FORMAT_HEADER_SIZE);
+        if checkpoint > compacted_from {
+            self.save_index_checkpoint(compacted_from)?;
+        }
+
+        for segment in &prepared.segments {
+            let segment_path = self.log.segment_path(segment.segment);
+            if segment.compacted.moved.is_empty() {
+                fs::remove_file(&segment.temp_path)?;
+                fs::remove_file(segment_path)?;
+                report.segments_removed += 1;
+            } else {
+                fs::rename(&segment.temp_path, segment_path)?;
+                report.segments_compacted += 1;
+            }
+            report.entries_removed +=
+                segment.compacted.entries - segment.compacted.moved.len() as u64;
+            report.bytes_reclaimed += segment
+                .bytes_before
+                .saturating_sub(segment.compacted.length as u64);
+        }
+        if self.durability != Durability::Never {
+            sync_dir(&self.base_path)?;
+        }
+        self.log.reset()?;
+
+        for segment in &prepared.segments {
+            for (doc_id, from, to) in &segment.compacted.moved {
+                if self.index.get(doc_id)? == Some(*from) {
+                    self.index.insert(doc_id, *to)?;
+                }
+            }
+        }
+
+        // Entries after the compacted segments did not move, so a checkpoint among them stays valid.
+        let checkpoint_moved = prepared
+            .segments
+            .iter()
+            .any(|segment| segment.segment == checkpoint.segment);
+        if checkpoint > compacted_from && !checkpoint_moved {
+            self.save_index_checkpoint(checkpoint)?;
+        }
+
+        {
+            let mut stats = self.lock_log_stats()?;
+            stats.entries = stats.entries.saturating_sub(report.entries_removed);
+            stats.garbage = stats.garbage.saturating_sub(report.entries_removed);
+        }
+        for segment in &prepared.segments {
+            self.advance_history_complete_from(&segment.compacted)?;
+        }
+        self.write_metadata()?;
+
+        Ok(report)
+    }
+
+    /// Returns the instant from which the history in the collection's log is complete,
+    /// or [`None`] if compaction never discarded any.
+    ///
+    /// The state of the collection at an earlier instant can no longer be reconstructed.
+    pub fn history_complete_from(&self) -> Option<DateTime<Utc>> {
+        self.lock_history_complete_from()
+            .map(|complete_from| *complete_from)
+            .unwrap_or_default()
+    }
+
+    /// Moves the instant the history is complete from past a compacted segment,
+    /// unless compaction left the segment as it was.
+    ///
+    /// ## Arguments
+    ///
+    /// * `compacted` - The [`CompactedSegment`] written in place of the segment.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\(()) if the instant was moved, or [`Err`]\([`io::Error`]) if its lock
+    /// is poisoned.
+    pub(crate) fn advance_history_complete_from(
+        &self,
+        compacted: &CompactedSegment,
+    ) -> io::Result<()> {
+        if !compacted.is_unchanged() {
+            let mut complete_from = self.lock_history_complete_from()?;
+            *complete_from = (*complete_from).max(compacted.newest);
+        }
+        Ok(())
+    }
+
+    /// Locks the instant from which the history in the collection's log is complete.
+    pub(crate) fn lock_history_complete_from(
+        &self,
+    ) -> io::Result<MutexGuard<'_, Option<DateTime<Utc>>>> {
+        self.history_complete_from
+            .lock()
+            .map_err(|_| io::Error::other("History lock is poisoned"))
+    }
+
+    /// Locks the counts of the entries in the collection's log.
+    pub(crate) fn lock_log_stats(&self) -> io::Result<MutexGuard<'_, LogStats>> {
+        self.stats
+            .lock()
+            .map_err(|_| io::Error::other("Log statistics lock is poisoned"))
+    }
+}
+
+/// Removes the temporary files left behind by a compaction that was interrupted.
+///
+/// ## Arguments
+///
+/// * `collection_dir` - The directory of the collection.
+pub(crate) fn remove_stale_compaction_files(collection_dir: &Path) -> io::Result<()> {
+    let entries = match fs::read_dir(collection_dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e),
+    };
+
+    for entry in entries {
+        let path = entry?.path();
+        if path.extension().and_then(|extension| extension.to_str())
+            == Some(COMPACTION_FILE_EXTENSION)
+        {
+            fs::remove_file(path)?;
+        }
+    }
+    Ok(())
+}
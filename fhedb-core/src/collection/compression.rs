@@ -0,0 +1,151 @@
+//! # Compression
+//!
+//! Provides the [`Compression`] codec applied to the payloads of a collection's log entries.
+//!
+//! A compressed payload is stored as a BSON document holding the name of the codec and the
+//! compressed bytes of the original payload. Payloads are decoded by their own codec rather
+//! than the collection's, so changing a collection's codec leaves existing entries readable.
+
+use bson::{Bson, Document as BsonDocument, spec::BinarySubtype};
+use std::{fmt, io, str::FromStr};
+
+/// The key of a compressed payload holding the name of its codec.
+const CODEC_KEY: &str = "codec";
+
+/// The key of a compressed payload holding the compressed bytes.
+const DATA_KEY: &str = "data";
+
+/// The codec used to compress the payloads of new log entries.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Compression {
+    /// Payloads are written as plain BSON.
+    #[default]
+    None,
+    /// Payloads are compressed with LZ4.
+    Lz4,
+}
+
+impl Compression {
+    /// Returns the string representation of the codec.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Compression::None => "none",
+            Compression::Lz4 => "lz4",
+        }
+    }
+
+    /// Encodes the BSON bytes of a log entry as the payload of its frame.
+    ///
+    /// The bytes are kept as they are if compressing them does not make them smaller.
+    ///
+    /// ## Arguments
+    ///
+    /// * `bson_bytes` - The BSON bytes of the log entry.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`Vec`]<[`u8`]>) with the payload,
+    /// or [`Err`]\([`io::Error`]) if the compressed payload could not be serialized.
+    pub(crate) fn encode(&self, bson_bytes: Vec<u8>) -> io::Result<Vec<u8>> {
+        let compressed = match self {
+            Compression::None => return Ok(bson_bytes),
+            Compression::Lz4 => lz4_flex::compress_prepend_size(&bson_bytes),
+        };
+
+        let mut payload = BsonDocument::new();
+        payload.insert(CODEC_KEY, Bson::String(self.as_str().to_string()));
+        payload.insert(
+            DATA_KEY,
+            Bson::Binary(bson::Binary {
+                subtype: BinarySubtype::Generic,
+                bytes: compressed,
+            }),
+        );
+        let payload = payload
+            .to_vec()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+        Ok(if payload.len() < bson_bytes.len() {
+            payload
+        } else {
+            bson_bytes
+        })
+    }
+
+    /// Decodes a log entry's payload parsed as BSON back into the log entry's document.
+    ///
+    /// ## Arguments
+    ///
+    /// * `payload` - The payload of the entry's frame, parsed as a BSON document.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`BsonDocument`]) with the log entry's document, which is the payload
+    /// itself if it is not compressed, or [`Err`]\([`io::Error`]) if the codec is unknown or
+    /// the payload could not be decompressed.
+    pub(crate) fn decode(payload: BsonDocument) -> io::Result<BsonDocument> {
+        let Ok(codec) = payload.get_str(CODEC_KEY) else {
+            return Ok(payload);
+        };
+        let codec = codec
+            .parse::<Compression>()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let data = payload.get_binary_generic(DATA_KEY).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Invalid compressed payload: {}", e),
+            )
+        })?;
+
+        let bson_bytes = match codec {
+            Compression::None => data.clone(),
+            Compression::Lz4 => lz4_flex::decompress_size_prepended(data)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?,
+        };
+        BsonDocument::from_reader(bson_bytes.as_slice()).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Failed to parse BSON: {}", e),
+            )
+        })
+    }
+}
+
+impl fmt::Display for Compression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Error returned when parsing an unknown compression codec name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseCompressionError(String);
+
+impl fmt::Display for ParseCompressionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unrecognized compression codec: {:?}", self.0)
+    }
+}
+
+impl std::error::Error for ParseCompressionError {}
+
+impl FromStr for Compression {
+    type Err = ParseCompressionError;
+
+    /// Converts a codec name to a [`Compression`].
+    ///
+    /// ## Arguments
+    ///
+    /// * `s` - The name of the codec.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`Compression`]) if the name is known, or [`Err`]\([`ParseCompressionError`]) if not.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(Compression::None),
+            "lz4" => Ok(Compression::Lz4),
+            _ => Err(ParseCompressionError(s.to_string())),
+        }
+    }
+}
@@ -3,7 +3,10 @@
 //! Provides schema modification and data consistency operations for collections.
 
 use crate::{
-    collection::{Collection, Operation},
+    collection::{
+        Collection, Operation,
+        indexes::{check_unique_definition, is_indexable},
+    },
     document::DocId,
     schema::{FieldDefinition, FieldType, IdType, SchemaOps},
 };
@@ -37,7 +40,7 @@ impl Collection {
         self.schema.validate_document(doc)
     }
 
-    /// Adds a new field to the collection's schema.
+    /// Adds a new field to the collection's schema, indexing it if it is unique.
     ///
     /// ## Arguments
     ///
@@ -77,11 +80,19 @@ impl Collection {
             field_definition.default_value = Some(bson::Bson::Null);
         }
 
-        if !is_nullable && !has_default && !self.document_indices.is_empty() {
+        let document_count = self.existing_document_count()?;
+        if !is_nullable && !has_default && document_count > 0 {
             return Err(format!(
-                "Cannot add non-nullable field '{}' without a default value because the collection contains {} existing documents",
-                field_name,
-                self.document_indices.len()
+                "Cannot add non-nullable field '{}' without a default value because the collection contains existing documents",
+                field_name
+            ));
+        }
+
+        check_unique_definition(&field_name, &field_definition)?;
+        if field_definition.unique && shares_default(&field_definition, document_count) {
+            return Err(format!(
+                "Cannot add unique field '{}' with a non-null default value because the collection contains several existing documents",
+                field_name
             ));
         }
 
@@ -96,10 +107,13 @@ impl Collection {
             return Err(e);
         }
 
+        if field_definition.unique {
+            self.create_index(&field_name)?;
+        }
         Ok(())
     }
 
-    /// Removes a field from the collection's schema.
+    /// Removes a field from the collection's schema, dropping every index including it.
     ///
     /// ## Arguments
     ///
@@ -117,6 +131,9 @@ impl Collection {
             ));
         }
         let is_id_field = field_name == self.id_field;
+        for name in self.indexes_including(field_name) {
+            self.remove_index(&name)?;
+        }
 
         self.schema.fields.remove(field_name);
 
@@ -138,7 +155,8 @@ impl Collection {
         Ok(())
     }
 
-    /// Modifies an existing field's definition in the collection's schema.
+    /// Modifies an existing field's definition in the collection's schema, indexing it if it
+    /// becomes unique.
     ///
     /// ## Arguments
     ///
@@ -170,11 +188,11 @@ impl Collection {
             new_definition.default_value = Some(bson::Bson::Null);
         }
 
-        if !self.document_indices.is_empty() && !is_nullable && !has_default {
+        let document_count = self.existing_document_count()?;
+        if document_count > 0 && !is_nullable && !has_default {
             return Err(format!(
-                "Cannot modify field '{}' to non-nullable without a default value because the collection contains {} existing documents",
-                field_name,
-                self.document_indices.len()
+                "Cannot modify field '{}' to non-nullable without a default value because the collection contains existing documents",
+                field_name
             ));
         }
 
@@ -195,6 +213,23 @@ impl Collection {
             ));
         }
 
+        if !self.indexes_including(field_name).is_empty()
+            && !is_indexable(&new_definition.field_type)
+        {
+            return Err(format!(
+                "Cannot modify indexed field '{}' to a type that cannot be indexed; drop its index first",
+                field_name
+            ));
+        }
+
+        check_unique_definition(field_name, &new_definition)?;
+        if new_definition.unique && shares_default(&new_definition, document_count) {
+            return Err(format!(
+                "Cannot modify field '{}' to unique with a non-null default value because the collection contains several existing documents",
+                field_name
+            ));
+        }
+
         self.schema
             .fields
             .insert(field_name.to_string(), new_definition.clone());
@@ -216,11 +251,11 @@ impl Collection {
             self.id_field = "id".to_string();
             self.id_type = IdType::Int;
 
-            if !self.document_indices.is_empty() && new_definition.default_value.is_some() {
+            if document_count > 0 && new_definition.default_value.is_some() {
                 self.add_ids_to_all_documents(field_name, "id")?;
                 self.apply_defaults_to_existing(field_name, &new_definition)?;
             }
-        } else if !self.document_indices.is_empty() {
+        } else if document_count > 0 {
             self.cleanup_removed_field(field_name)?;
 
             if new_definition.default_value.is_some() {
@@ -228,11 +263,14 @@ impl Collection {
             }
         }
 
+        if new_definition.unique && !self.has_index(field_name) {
+            self.create_index(field_name)?;
+        }
         Ok(())
     }
 
     /// Renames a field in the collection's schema.
-    /// Preserves the field's definition and data.
+    /// Preserves the field's definition, data and indexes.
     ///
     /// ## Arguments
     ///
@@ -251,6 +289,23 @@ impl Collection {
             return Err(format!("Field '{}' already exists", new_name));
         }
 
+        let mut renamed_indexes = Vec::new();
+        for name in self.indexes_including(old_name) {
+            let fields: Vec<String> = self.indexes[&name]
+                .fields()
+                .iter()
+                .map(|field| {
+                    if field == old_name {
+                        new_name.clone()
+                    } else {
+                        field.clone()
+                    }
+                })
+                .collect();
+            self.remove_index(&name)?;
+            renamed_indexes.push(fields);
+        }
+
         let field_definition = self.schema.fields.remove(old_name).unwrap();
         self.schema
             .fields
@@ -261,9 +316,23 @@ impl Collection {
 
         self.rename_field_in_documents(old_name, &new_name)?;
 
+        for fields in renamed_indexes {
+            self.create_compound_index(&fields)?;
+        }
         Ok(())
     }
 
+    /// Counts the existing documents a schema change has to account for, up to two,
+    /// which is enough to tell whether a shared default value would collide.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`usize`]) with the number of documents, capped at two,
+    /// or [`Err`]\([`String`]) if the primary index could not be read.
+    fn existing_document_count(&self) -> Result<usize, String> {
+        self.index.len_up_to(2).map_err(|e| e.to_string())
+    }
+
     /// Applies default values to existing documents when a new field with a default is added.
     ///
     /// ## Arguments
@@ -286,7 +355,7 @@ impl Collection {
             .ok_or_else(|| format!("Field '{}' has no default value", field_name))?;
 
         let mut updated_document_ids = Vec::new();
-        let document_ids: Vec<DocId> = self.document_indices.keys().cloned().collect();
+        let document_ids = self.document_ids().map_err(|e| e.to_string())?;
 
         let mut update_doc = bson::Document::new();
         update_doc.insert(field_name, default_value.clone());
@@ -321,7 +390,7 @@ impl Collection {
     /// or [`Err`]\([`String`]) with an error message.
     pub fn cleanup_removed_field(&mut self, field_name: &str) -> Result<Vec<DocId>, String> {
         let mut updated_document_ids = Vec::new();
-        let document_ids: Vec<DocId> = self.document_indices.keys().cloned().collect();
+        let document_ids = self.document_ids().map_err(|e| e.to_string())?;
 
         for doc_id in document_ids {
             if let Some(document) = self.get_document(doc_id.clone())
@@ -330,9 +399,8 @@ impl Collection {
                 let mut cleaned_doc = document.data.clone();
                 cleaned_doc.remove(field_name);
 
-                match self.append_to_log(&Operation::Update, &cleaned_doc) {
-                    Ok(new_offset) => {
-                        self.document_indices.insert(doc_id.clone(), new_offset);
+                match self.write_log_entry(&Operation::Update, &doc_id, &cleaned_doc) {
+                    Ok(_) => {
                         updated_document_ids.push(doc_id);
                     }
                     Err(e) => {
@@ -365,7 +433,7 @@ impl Collection {
         new_field_name: &str,
     ) -> Result<Vec<DocId>, String> {
         let mut updated_document_ids = Vec::new();
-        let document_ids: Vec<DocId> = self.document_indices.keys().cloned().collect();
+        let document_ids = self.document_ids().map_err(|e| e.to_string())?;
 
         for doc_id in document_ids {
             if let Some(document) = self.get_document(doc_id.clone())
@@ -375,9 +443,8 @@ impl Collection {
                 updated_doc.remove(old_field_name);
                 updated_doc.insert(new_field_name, field_value.clone());
 
-                match self.append_to_log(&Operation::Update, &updated_doc) {
-                    Ok(new_offset) => {
-                        self.document_indices.insert(doc_id.clone(), new_offset);
+                match self.write_log_entry(&Operation::Update, &doc_id, &updated_doc) {
+                    Ok(_) => {
                         updated_document_ids.push(doc_id);
                     }
                     Err(e) => {
@@ -417,9 +484,9 @@ impl Collection {
         self.inserts = 0;
 
         let mut documents_to_readd = Vec::new();
-        let document_ids: Vec<DocId> = self.document_indices.keys().cloned().collect();
+        let document_ids = self.document_ids().map_err(|e| e.to_string())?;
         for doc_id in document_ids {
-            if let Some(document) = self.remove_document(doc_id) {
+            if let Some(document) = self.remove_document(doc_id)? {
                 documents_to_readd.push(document.data);
             }
         }
@@ -446,3 +513,18 @@ impl Collection {
         Ok(updated_document_ids)
     }
 }
+
+/// Checks whether applying a field's default value to the existing documents would give
+/// several of them the same non-null value.
+///
+/// ## Arguments
+///
+/// * `field_definition` - The [`FieldDefinition`] of the field.
+/// * `document_count` - The number of existing documents, which may be capped at two.
+fn shares_default(field_definition: &FieldDefinition, document_count: usize) -> bool {
+    document_count > 1
+        && field_definition
+            .default_value
+            .as_ref()
+            .is_some_and(|default| *default != bson::Bson::Null)
+}
@@ -0,0 +1,29 @@
+//! # Durability
+//!
+//! Provides the [`Durability`] setting controlling when log writes are flushed to disk.
+
+use std::time::Duration;
+
+/// The group commit interval used by [`Durability::default`].
+pub const DEFAULT_GROUP_COMMIT_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Controls when writes to a collection's logfile are flushed to stable storage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Durability {
+    /// Every write is flushed to disk before it is acknowledged.
+    Always,
+    /// Writes are flushed together once the interval has passed since the oldest unflushed write.
+    ///
+    /// Writes made within the interval may be lost on power failure.
+    /// Callers are expected to also flush periodically, so that writes
+    /// followed by a quiet period do not stay unflushed indefinitely.
+    GroupCommit(Duration),
+    /// Writes are never flushed explicitly, leaving it to the operating system.
+    Never,
+}
+
+impl Default for Durability {
+    fn default() -> Self {
+        Durability::GroupCommit(DEFAULT_GROUP_COMMIT_INTERVAL)
+    }
+}
@@ -3,19 +3,49 @@
 //! Provides file I/O operations for collection persistence.
 
 use crate::{
-    collection::Collection,
-    schema::{schema_from_document, schema_to_document},
+    collection::{
+        Collection,
+        compaction::{CompactedDocument, LogStats, remove_stale_compaction_files},
+        compression::Compression,
+        durability::Durability,
+        history::parse_timestamp,
+        indexes::{index_file_name, index_name, is_indexable_id},
+        reader::{EntrySeal, LogEntries, frame_len, seal_frame},
+        recovery::RecoveryReport,
+        segment::{LEGACY_LOGFILE, LogPosition, list_segments, segment_file_name},
+    },
+    document::DocId,
+    format::{
+        FORMAT_HEADER_SIZE, FileKind, check_format_header, encode_format_header,
+        encryption::EncryptionKey, upgrade::upgrade_collection,
+    },
+    index::secondary::SecondaryIndex,
+    schema::{IdType, schema_from_document, schema_to_document},
 };
 use bson::{Bson, Document as BsonDocument};
 use std::{
     collections::HashMap,
     fmt,
-    fs::{self, OpenOptions},
-    io::{self, Seek, Write},
+    fs::{self, File, OpenOptions},
+    io::{self, Read, Write},
     path::{Path, PathBuf},
     str::FromStr,
+    sync::MutexGuard,
+    time::Instant,
 };
 
+/// The name of the collection's metadata file.
+pub(crate) const METADATA_FILE: &str = "metadata.bin";
+
+/// The name of the temporary file new metadata is written to before replacing the current file.
+pub(crate) const METADATA_TEMP_FILE: &str = "metadata.tmp";
+
+/// The name of the file holding the previous generation of the metadata.
+pub(crate) const METADATA_PREVIOUS_FILE: &str = "metadata.prev.bin";
+
+/// The name of the collection's primary index file.
+pub(crate) const INDEX_FILE: &str = "index.bin";
+
 /// Represents a database operation type.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Operation {
@@ -70,6 +100,38 @@ impl FromStr for Operation {
     }
 }
 
+/// The location of an entry appended to the log.
+#[derive(Debug, Clone)]
+struct AppendedEntry {
+    /// The timestamp of the entry.
+    timestamp: String,
+    /// The end of the log before the entry was appended.
+    previous_end: LogPosition,
+    /// The position where the entry starts.
+    position: LogPosition,
+    /// The position just past the end of the entry.
+    end: LogPosition,
+    /// Whether the segment was flushed to disk after the entry was written.
+    synced: bool,
+}
+
+/// The document written with an appended log entry.
+#[derive(Debug, Clone, Copy)]
+enum LogPayload<'a> {
+    /// The full document.
+    Full(&'a BsonDocument),
+    /// The fields of an updated document changed since the full image at `base`,
+    /// written as the full document instead whenever a delta would not pay off.
+    Delta {
+        /// The position of the full image the changes apply to.
+        base: LogPosition,
+        /// The document ID along with every field changed since the full image.
+        changes: &'a BsonDocument,
+        /// The full updated document.
+        full: &'a BsonDocument,
+    },
+}
+
 /// A log entry representing a database operation.
 #[derive(Debug, Clone)]
 pub struct LogEntry {
@@ -79,6 +141,14 @@ pub struct LogEntry {
     pub operation: Operation,
     /// The BSON document associated with the operation.
     pub document: BsonDocument,
+    /// The timestamp of the document's original insert, for entries merged by compaction
+    /// whose document was updated after it was inserted.
+    pub inserted_at: Option<String>,
+    /// The position of the full image a delta-encoded update applies to.
+    ///
+    /// The document of a delta-encoded update only holds the document ID and the fields
+    /// changed since that image. Deletes never carry more than the document ID.
+    pub base: Option<LogPosition>,
 }
 
 impl LogEntry {
@@ -93,20 +163,153 @@ impl LogEntry {
             timestamp: chrono::Utc::now().to_rfc3339(),
             operation,
             document,
+            inserted_at: None,
+            base: None,
         }
     }
+
+    /// Returns the timestamp of the document's original insert, which is the entry's own
+    /// timestamp unless compaction merged later updates into it.
+    pub fn inserted_at(&self) -> &str {
+        self.inserted_at.as_deref().unwrap_or(&self.timestamp)
+    }
+
+    /// Decodes a [`LogEntry`] from the payload of its frame, decompressing it if needed.
+    ///
+    /// ## Arguments
+    ///
+    /// * `bytes` - The payload bytes, holding a BSON log document or a compressed one.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`LogEntry`]) if the bytes decode to a valid BSON document,
+    /// or [`Err`]\([`io::Error`]) if decompression or parsing failed.
+    pub(crate) fn from_bytes(bytes: &[u8]) -> io::Result<Self> {
+        let payload = BsonDocument::from_reader(bytes).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Failed to parse BSON: {}", e),
+            )
+        })?;
+        let log_doc = Compression::decode(payload)?;
+
+        let timestamp = log_doc
+            .get_str("timestamp")
+            .unwrap_or("unknown")
+            .to_string();
+        let operation_str = log_doc.get_str("operation").unwrap_or("unknown");
+        let operation = operation_str
+            .parse::<Operation>()
+            .unwrap_or(Operation::Insert);
+        let document = log_doc
+            .get_document("document")
+            .cloned()
+            .unwrap_or_default();
+        let inserted_at = log_doc.get_str("inserted_at").ok().map(str::to_string);
+        let base = log_doc.get_document("base").ok().map(|base| {
+            LogPosition::new(
+                base.get_i64("segment").unwrap_or(0) as u64,
+                base.get_i64("offset").unwrap_or(0) as usize,
+            )
+        });
+
+        Ok(Self {
+            timestamp,
+            operation,
+            document,
+            inserted_at,
+            base,
+        })
+    }
+
+    /// Encodes the [`LogEntry`] as a frame ready to be written to a log segment.
+    ///
+    /// The payload is compressed before it is encrypted, since ciphertext does not compress.
+    ///
+    /// ## Arguments
+    ///
+    /// * `compression` - The [`Compression`] codec to apply to the payload.
+    /// * `seal` - The [`EntrySeal`] for the position the frame is written to, or [`None`]
+    ///   to store it in the clear. Must match the encryption of the segment.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`Vec`]<[`u8`]>) with the framed entry,
+    /// or [`Err`]\([`io::Error`]) if the entry could not be serialized or encrypted.
+    pub(crate) fn into_frame(
+        self,
+        compression: Compression,
+        seal: Option<EntrySeal<'_>>,
+    ) -> io::Result<Vec<u8>> {
+        seal_frame(&self.into_payload(compression)?, seal)
+    }
+
+    /// Encodes the [`LogEntry`] as the compressed BSON payload of a frame.
+    ///
+    /// ## Arguments
+    ///
+    /// * `compression` - The [`Compression`] codec to apply to the payload.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`Vec`]<[`u8`]>) with the payload,
+    /// or [`Err`]\([`io::Error`]) if the entry could not be serialized or compressed.
+    pub(crate) fn into_payload(self, compression: Compression) -> io::Result<Vec<u8>> {
+        let mut log_entry = BsonDocument::new();
+        log_entry.insert("timestamp", Bson::String(self.timestamp));
+        log_entry.insert(
+            "operation",
+            Bson::String(self.operation.as_str().to_string()),
+        );
+        log_entry.insert("document", Bson::Document(self.document));
+        if let Some(inserted_at) = self.inserted_at {
+            log_entry.insert("inserted_at", Bson::String(inserted_at));
+        }
+        if let Some(base) = self.base {
+            let mut position = BsonDocument::new();
+            position.insert("segment", Bson::Int64(base.segment as i64));
+            position.insert("offset", Bson::Int64(base.offset as i64));
+            log_entry.insert("base", Bson::Document(position));
+        }
+
+        let bson_bytes = log_entry
+            .to_vec()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        compression.encode(bson_bytes)
+    }
 }
 
 /// File I/O operations for collection persistence.
 impl Collection {
-    /// Gets the path to the collection's logfile.
+    /// Gets the path to the collection's active log segment, which new entries are appended to.
     pub fn logfile_path(&self) -> PathBuf {
-        self.base_path.join("logfile.log")
+        let segment = self.log.end().map(|end| end.segment).unwrap_or(0);
+        self.log.segment_path(segment)
+    }
+
+    /// Gets the paths to all of the collection's log segments, oldest first.
+    pub fn segment_paths(&self) -> io::Result<Vec<PathBuf>> {
+        Ok(self
+            .log
+            .segments()?
+            .into_iter()
+            .map(|segment| self.log.segment_path(segment))
+            .collect())
     }
 
     /// Gets the path to the collection's metadata file.
     pub fn metadata_path(&self) -> PathBuf {
-        self.base_path.join("metadata.bin")
+        self.base_path.join(METADATA_FILE)
+    }
+
+    /// Gets the path to the previous generation of the collection's metadata file.
+    pub fn previous_metadata_path(&self) -> PathBuf {
+        self.base_path.join(METADATA_PREVIOUS_FILE)
+    }
+
+    /// Gets the path to the collection's primary index file.
+    pub fn index_path(&self) -> PathBuf {
+        self.index.path().to_path_buf()
     }
 
     /// Ensures the collection's directory exists.
@@ -114,7 +317,7 @@ impl Collection {
         fs::create_dir_all(&self.base_path)
     }
 
-    /// Appends a document operation to the collection's logfile.
+    /// Appends a document operation to the collection's log.
     ///
     /// ## Arguments
     ///
@@ -123,304 +326,811 @@ impl Collection {
     ///
     /// ## Returns
     ///
-    /// Returns [`Ok`]\([`usize`]) with the file offset where the entry was written,
+    /// Returns [`Ok`]\([`LogPosition`]) with the position where the entry was written,
     /// or [`Err`]\([`io::Error`]) if the write failed.
     pub fn append_to_log(
         &self,
         operation: &Operation,
         document: &BsonDocument,
-    ) -> io::Result<usize> {
+    ) -> io::Result<LogPosition> {
+        self.append_log_entry(operation, LogPayload::Full(document))
+            .map(|appended| appended.position)
+    }
+
+    /// Appends a document operation to the log and records it in the primary index.
+    ///
+    /// ## Arguments
+    ///
+    /// * `operation` - The [`Operation`] to append.
+    /// * `id` - The [`DocId`] of the document the operation applies to.
+    /// * `document` - The [`BsonDocument`] to append.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`LogPosition`]) with the position where the entry was written,
+    /// or [`Err`]\([`io::Error`]) if the write or the index update failed.
+    pub(crate) fn write_log_entry(
+        &self,
+        operation: &Operation,
+        id: &DocId,
+        document: &BsonDocument,
+    ) -> io::Result<LogPosition> {
+        self.record_log_entry(operation, id, LogPayload::Full(document), document)
+    }
+
+    /// Appends a delete to the log as a tombstone holding the document ID,
+    /// and removes the document from the primary index.
+    ///
+    /// ## Arguments
+    ///
+    /// * `id` - The [`DocId`] of the deleted document.
+    /// * `document` - The document as it was before the delete, published to the change feed.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`LogPosition`]) with the position where the entry was written,
+    /// or [`Err`]\([`io::Error`]) if the write or the index update failed.
+    pub(crate) fn write_delete_entry(
+        &self,
+        id: &DocId,
+        document: &BsonDocument,
+    ) -> io::Result<LogPosition> {
+        let tombstone = bson::doc! { &self.id_field: id.to_bson() };
+        self.record_log_entry(
+            &Operation::Delete,
+            id,
+            LogPayload::Full(&tombstone),
+            document,
+        )
+    }
+
+    /// Appends an update to the log as the fields changed since a full image of the document,
+    /// and records it in the primary index.
+    ///
+    /// The full document is written instead if the image lies in an earlier segment, or if
+    /// the changes take up more than half the space of the full document. Keeping every delta
+    /// in the segment of its image lets compaction rewrite sealed segments on their own.
+    ///
+    /// ## Arguments
+    ///
+    /// * `id` - The [`DocId`] of the updated document.
+    /// * `base` - The [`LogPosition`] of the document's latest full image.
+    /// * `changes` - The document ID along with every field changed since the full image.
+    /// * `document` - The full updated [`BsonDocument`].
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`LogPosition`]) with the position where the entry was written,
+    /// or [`Err`]\([`io::Error`]) if the write or the index update failed.
+    pub(crate) fn write_update_entry(
+        &self,
+        id: &DocId,
+        base: LogPosition,
+        changes: &BsonDocument,
+        document: &BsonDocument,
+    ) -> io::Result<LogPosition> {
+        let payload = LogPayload::Delta {
+            base,
+            changes,
+            full: document,
+        };
+        self.record_log_entry(&Operation::Update, id, payload, document)
+    }
+
+    /// Appends an entry to the log, records it in the primary and secondary indexes
+    /// and publishes it to the collection's change feed.
+    ///
+    /// ## Arguments
+    ///
+    /// * `operation` - The [`Operation`] to append.
+    /// * `id` - The [`DocId`] of the document the operation applies to.
+    /// * `payload` - The [`LogPayload`] to append.
+    /// * `image` - The document image published to the change feed.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`LogPosition`]) with the position where the entry was written,
+    /// or [`Err`]\([`io::Error`]) if the write or the index update failed.
+    fn record_log_entry(
+        &self,
+        operation: &Operation,
+        id: &DocId,
+        payload: LogPayload<'_>,
+        image: &BsonDocument,
+    ) -> io::Result<LogPosition> {
+        let previous = match operation {
+            Operation::Update => self.indexed_image(id)?,
+            Operation::Insert | Operation::Delete => None,
+        };
+        let appended = self.append_log_entry(operation, payload)?;
+
+        match operation {
+            Operation::Insert | Operation::Update => {
+                self.index_document_change(id, previous.as_ref(), Some(image))?;
+                self.index.insert(id, appended.position)?
+            }
+            Operation::Delete => {
+                self.index_document_change(id, Some(image), None)?;
+                self.index.remove(id)?
+            }
+        }
+        self.lock_log_stats()?.record(operation);
+
+        // Entries appended without going through the index leave a gap before this entry,
+        // which is left for `sync_index` to replay instead of being skipped over.
+        if self.index.checkpoint()? == appended.previous_end {
+            self.advance_index_checkpoint(appended.end)?;
+        }
+        if appended.synced {
+            self.commit_index_checkpoint()?;
+        }
+
+        self.changes
+            .publish(&self.name, operation, &appended.timestamp, id, image);
+        Ok(appended.position)
+    }
+
+    /// Appends a document operation to the active log segment, first rolling over to a new
+    /// segment if the entry would take the active one past the collection's maximum size,
+    /// or if the active one was written with a different encryption setting.
+    ///
+    /// ## Arguments
+    ///
+    /// * `operation` - The [`Operation`] to append.
+    /// * `payload` - The [`LogPayload`] to append.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`AppendedEntry`]) with the location of the entry,
+    /// or [`Err`]\([`io::Error`]) if the write failed.
+    fn append_log_entry(
+        &self,
+        operation: &Operation,
+        payload: LogPayload<'_>,
+    ) -> io::Result<AppendedEntry> {
         self.ensure_collection_dir()?;
 
-        let logfile_path = self.logfile_path();
+        let previous_end = self.log.end()?;
+        let key = self.log.encryption_key();
+        let mismatched = previous_end.offset >= FORMAT_HEADER_SIZE
+            && self.log.is_encrypted(previous_end.segment)? != key.is_some();
+        let timestamp = chrono::Utc::now().to_rfc3339();
+        let encode = |document: &BsonDocument, base| {
+            let mut log_entry = LogEntry::new(operation.clone(), document.clone());
+            log_entry.timestamp = timestamp.clone();
+            log_entry.base = base;
+            log_entry.into_payload(self.compression)
+        };
+        let framed = |entry: &Vec<u8>| frame_len(entry.len(), key.is_some());
+        let entry = match payload {
+            LogPayload::Full(document) => encode(document, None)?,
+            LogPayload::Delta {
+                base,
+                changes,
+                full,
+            } => {
+                let delta = encode(changes, Some(base))?;
+                let full = encode(full, None)?;
+                let fits = !mismatched
+                    && base.segment == previous_end.segment
+                    && previous_end.offset + framed(&delta) <= self.max_segment_size;
+                if fits && framed(&delta) * 2 <= framed(&full) {
+                    delta
+                } else {
+                    full
+                }
+            }
+        };
+        let mut segment = previous_end.segment;
+        let roll_over = mismatched
+            || (previous_end.offset > FORMAT_HEADER_SIZE
+                && previous_end.offset + framed(&entry) > self.max_segment_size);
+        if roll_over {
+            self.seal_segment(segment)?;
+            segment += 1;
+        }
+
         let mut file = OpenOptions::new()
             .create(true)
             .read(true)
             .append(true)
-            .open(logfile_path)?;
+            .open(self.log.segment_path(segment))?;
+        if roll_over && self.durability != Durability::Never {
+            sync_dir(&self.base_path)?;
+        }
 
-        let timestamp = chrono::Utc::now().to_rfc3339();
-        let mut log_entry = BsonDocument::new();
-        log_entry.insert("timestamp", Bson::String(timestamp));
-        log_entry.insert("operation", Bson::String(operation.as_str().to_string()));
-        log_entry.insert("document", Bson::Document(document.clone()));
+        if file.metadata()?.len() == 0 {
+            file.write_all(&encode_format_header(FileKind::LogSegment, key.is_some()))?;
+        }
+        // The entry is only sealed now, since it is bound to the position it is written at.
+        let position = LogPosition::new(segment, file.metadata()?.len() as usize);
+        let frame = seal_frame(&entry, self.log.entry_seal(position))?;
+        file.write_all(&frame)?;
+        let synced = self.sync_after_write(&file)?;
 
-        let bson_bytes = log_entry
-            .to_vec()
-            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        Ok(AppendedEntry {
+            timestamp,
+            previous_end,
+            position,
+            end: LogPosition::new(segment, position.offset + frame.len()),
+            synced,
+        })
+    }
 
-        file.write_all(&bson_bytes)?;
-        writeln!(file)?; // Add a newline to separate entries
+    /// Flushes a segment that is about to stop being the active one to disk,
+    /// so that writes still waiting for a group commit are not left behind in it.
+    ///
+    /// ## Arguments
+    ///
+    /// * `segment` - The number of the segment being sealed.
+    pub(crate) fn seal_segment(&self, segment: u64) -> io::Result<()> {
+        if self.durability == Durability::Never {
+            return Ok(());
+        }
+        File::open(self.log.segment_path(segment))?.sync_data()
+    }
+
+    /// Flushes a logfile write to disk as required by the collection's [`Durability`].
+    ///
+    /// ## Arguments
+    ///
+    /// * `file` - The logfile handle the write went through.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`bool`]) with whether the write was flushed,
+    /// or [`Err`]\([`io::Error`]) if the flush failed.
+    fn sync_after_write(&self, file: &File) -> io::Result<bool> {
+        match self.durability {
+            Durability::Always => file.sync_data().map(|_| true),
+            Durability::GroupCommit(interval) => {
+                let mut unsynced_since = self.lock_unsynced_since()?;
+                let since = *unsynced_since.get_or_insert_with(Instant::now);
+                if since.elapsed() < interval {
+                    return Ok(false);
+                }
+                file.sync_data()?;
+                *unsynced_since = None;
+                Ok(true)
+            }
+            Durability::Never => Ok(false),
+        }
+    }
 
-        let offset = file.stream_position()? as usize - bson_bytes.len() - 1;
+    /// Records the log position up to which the indexes are in sync.
+    ///
+    /// With [`Durability::Never`], the checkpoint is written to the index file right away.
+    /// Otherwise it is only staged, and reaches the disk with the next
+    /// [`Collection::commit_index_checkpoint`], once the log entries it covers are flushed.
+    ///
+    /// ## Arguments
+    ///
+    /// * `checkpoint` - The [`LogPosition`] just past the last entry covered by the indexes.
+    fn advance_index_checkpoint(&self, checkpoint: LogPosition) -> io::Result<()> {
+        match self.durability {
+            Durability::Never => self.index.set_checkpoint(checkpoint),
+            _ => self.index.stage_checkpoint(checkpoint),
+        }
+    }
 
-        Ok(offset)
+    /// Flushes the pages of every index to disk, then persists the staged checkpoint.
+    ///
+    /// Flushing the pages first ensures that a checkpoint found on disk after a crash is
+    /// never ahead of the index contents, which would leave the entries in between
+    /// out of the index without them being replayed.
+    /// Does nothing if the collection's [`Durability`] is [`Durability::Never`].
+    fn commit_index_checkpoint(&self) -> io::Result<()> {
+        if self.durability == Durability::Never {
+            return Ok(());
+        }
+        for index in self.indexes.values() {
+            index.sync()?;
+        }
+        self.index.commit_checkpoint()
     }
 
-    /// Reads all log entries from the collection's logfile.
+    /// Records and persists the log position up to which the indexes are in sync.
+    ///
+    /// ## Arguments
+    ///
+    /// * `checkpoint` - The [`LogPosition`] just past the last entry covered by the indexes.
+    pub(crate) fn save_index_checkpoint(&self, checkpoint: LogPosition) -> io::Result<()> {
+        self.advance_index_checkpoint(checkpoint)?;
+        self.commit_index_checkpoint()
+    }
+
+    /// Flushes any logfile writes still waiting for a group commit to disk,
+    /// along with the index pages and the checkpoint covering them.
+    ///
+    /// Does nothing if every write has already been flushed,
+    /// or if the collection's [`Durability`] is [`Durability::Never`].
     ///
     /// ## Returns
     ///
-    /// Returns [`Ok`]\([`Vec`]<\([`LogEntry`], [`usize`])>) with entries and their offsets,
-    /// or [`Err`]\([`io::Error`]) if the read failed.
-    pub fn read_log_entries(&self) -> io::Result<Vec<(LogEntry, usize)>> {
-        let logfile_path = self.logfile_path();
+    /// Returns [`Ok`]\(()) if no unflushed writes remain,
+    /// or [`Err`]\([`io::Error`]) if the flush failed.
+    pub fn flush(&self) -> io::Result<()> {
+        let mut unsynced_since = self.lock_unsynced_since()?;
+        if unsynced_since.is_none() {
+            return Ok(());
+        }
 
-        if !logfile_path.exists() {
-            return Ok(Vec::new());
+        match File::open(self.logfile_path()) {
+            Ok(file) => file.sync_data()?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
         }
+        *unsynced_since = None;
+        self.commit_index_checkpoint()
+    }
 
-        let contents = fs::read(&logfile_path)?;
-        let mut entries = Vec::new();
-        let mut offset = 0;
+    /// Checks whether the logfile has writes that are not yet flushed to disk.
+    pub fn has_unflushed_writes(&self) -> bool {
+        self.lock_unsynced_since()
+            .map(|unsynced_since| unsynced_since.is_some())
+            .unwrap_or(false)
+    }
 
-        while offset < contents.len() {
-            if offset + 4 >= contents.len() {
-                break;
-            }
+    /// Locks the time of the oldest unflushed logfile write.
+    fn lock_unsynced_since(&self) -> io::Result<MutexGuard<'_, Option<Instant>>> {
+        self.unsynced_since
+            .lock()
+            .map_err(|_| io::Error::other("Durability state lock is poisoned"))
+    }
 
-            let length = u32::from_le_bytes([
-                contents[offset],
-                contents[offset + 1],
-                contents[offset + 2],
-                contents[offset + 3],
-            ]) as usize;
+    /// Reads all log entries from the collection's log segments.
+    ///
+    /// Delta-encoded updates are returned with the full updated document.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`Vec`]<\([`LogEntry`], [`LogPosition`])>) with entries and their positions,
+    /// or [`Err`]\([`io::Error`]) if the read failed.
+    pub fn read_log_entries(&self) -> io::Result<Vec<(LogEntry, LogPosition)>> {
+        self.read_log_entries_from(LogPosition::default())
+    }
 
-            if offset + length > contents.len() {
-                break;
-            }
+    /// Reads the log entries starting at the specified position of the collection's log.
+    ///
+    /// Delta-encoded updates are returned with the full updated document, so replaying the
+    /// entries in order yields every document's state. Deletes only carry the document ID.
+    ///
+    /// ## Arguments
+    ///
+    /// * `start` - The [`LogPosition`] where the first entry begins.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`Vec`]<\([`LogEntry`], [`LogPosition`])>) with entries and their positions,
+    /// or [`Err`]\([`io::Error`]) if the read failed.
+    pub fn read_log_entries_from(
+        &self,
+        start: LogPosition,
+    ) -> io::Result<Vec<(LogEntry, LogPosition)>> {
+        let mut entries: Vec<(LogEntry, LogPosition)> =
+            self.log.entries_from(start)?.collect::<io::Result<_>>()?;
+
+        let mut images = HashMap::new();
+        for slot in 0..entries.len() {
+            let (log_entry, position) = &entries[slot];
+            let Some(base) = log_entry.base else {
+                images.insert(*position, slot);
+                continue;
+            };
 
-            let entry_bytes = &contents[offset..offset + length];
-            match bson::Document::from_reader(entry_bytes) {
-                Ok(log_doc) => {
-                    let timestamp = log_doc
-                        .get_str("timestamp")
-                        .unwrap_or("unknown")
-                        .to_string();
-                    let operation_str = log_doc.get_str("operation").unwrap_or("unknown");
-                    let operation = operation_str
-                        .parse::<Operation>()
-                        .unwrap_or(Operation::Insert);
-                    let document = log_doc
-                        .get_document("document")
-                        .cloned()
-                        .unwrap_or_default();
-
-                    entries.push((
-                        LogEntry {
-                            timestamp,
-                            operation,
-                            document,
-                        },
-                        offset,
-                    ));
-
-                    offset += length;
-
-                    if offset < contents.len() && contents[offset] == b'\n' {
-                        offset += 1;
-                    }
-                }
-                Err(_) => {
-                    let newline_pos = contents[offset..].iter().position(|&b| b == b'\n');
-                    if let Some(pos) = newline_pos {
-                        offset += pos + 1;
-                    } else {
-                        break;
-                    }
-                }
-            }
+            let mut document = match images.get(&base) {
+                Some(&image) => entries[image].0.document.clone(),
+                None => self.read_full_image(base)?,
+            };
+            document.extend(entries[slot].0.document.clone());
+            entries[slot].0.document = document;
         }
-
         Ok(entries)
     }
 
-    /// Reads a single log entry at the specified offset.
+    /// Opens a sequential reader over all entries of the collection's log segments.
+    ///
+    /// Prefer this over [`Collection::read_log_entries`] for full scans, as entries are
+    /// decoded one at a time through a buffered reader instead of being loaded at once.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`LogEntries`]) yielding entries and their positions,
+    /// or [`Err`]\([`io::Error`]) if the segments could not be listed.
+    pub fn scan_log_entries(&self) -> io::Result<LogEntries> {
+        self.log.entries_from(LogPosition::default())
+    }
+
+    /// Reads a single log entry at the specified position.
+    ///
+    /// Uses a positioned read on the collection's open handle to the entry's segment,
+    /// so only the bytes of the requested entry are read.
     ///
     /// ## Arguments
     ///
-    /// * `offset` - The byte offset in the logfile where the entry begins.
+    /// * `position` - The [`LogPosition`] where the entry begins.
     ///
     /// ## Returns
     ///
     /// Returns [`Ok`]\([`LogEntry`]) if successful,
-    /// or [`Err`]\([`io::Error`]) if the offset is invalid or the read failed.
-    pub fn read_log_entry_at_offset(&self, offset: usize) -> io::Result<LogEntry> {
-        let logfile_path = self.logfile_path();
+    /// or [`Err`]\([`io::Error`]) if the position is invalid or the read failed.
+    pub fn read_log_entry_at_offset(&self, position: LogPosition) -> io::Result<LogEntry> {
+        self.log.read_at(position)
+    }
 
-        if !logfile_path.exists() {
-            return Err(io::Error::new(
-                io::ErrorKind::NotFound,
-                "Logfile does not exist",
-            ));
-        }
+    /// Compacts the log by reconstructing the final state of each document.
+    ///
+    /// Every segment is rewritten on its own. The latest state of each live document is
+    /// written where the document was inserted, keeping its original insert and last update
+    /// timestamps, so documents stay in insertion order. Segments left empty are removed,
+    /// except for the active one.
+    /// Rebuilds the primary index to point at the compacted entries and rewrites the metadata.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\(()) if the log was compacted successfully,
+    /// or [`Err`]\([`io::Error`]) if the compaction failed.
+    pub fn compact_logfile(&self) -> io::Result<()> {
+        self.compact_and_index().map(|_| ())
+    }
+
+    /// Compacts the log as [`Collection::compact_logfile`] does, leaving out of the primary
+    /// index the documents whose ID is too long to be indexed.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`Vec<LogPosition>`]) with the positions of the compacted entries
+    /// left out of the index, or [`Err`]\([`io::Error`]) if the compaction failed.
+    fn compact_and_index(&self) -> io::Result<Vec<LogPosition>> {
+        let segments = self.log.segments()?;
+        let (Some(&first_segment), Some(&active_segment)) = (segments.first(), segments.last())
+        else {
+            return Ok(Vec::new());
+        };
 
-        let contents = fs::read(&logfile_path)?;
+        let layout = self.compaction_layout(first_segment..=active_segment, |_, _| Ok(true))?;
 
-        if offset >= contents.len() {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidInput,
-                "Offset is beyond end of file",
-            ));
+        // The index points into the segments about to be rewritten, so it is removed first.
+        // A crash before it is rebuilt below makes the next load compact the log again.
+        self.index.clear()?;
+
+        let mut positions = Vec::with_capacity(layout.len());
+        for segment in segments {
+            positions.extend(self.compact_segment(segment, &layout, segment == active_segment)?);
         }
 
-        if offset + 4 >= contents.len() {
-            return Err(io::Error::new(
-                io::ErrorKind::UnexpectedEof,
-                "Not enough bytes for BSON length header",
-            ));
+        if self.durability != Durability::Never {
+            sync_dir(&self.base_path)?;
         }
+        self.log.reset()?;
+        *self.lock_unsynced_since()? = None;
+
+        *self.lock_log_stats()? = LogStats {
+            entries: positions.len() as u64,
+            garbage: 0,
+        };
+        let mut unindexed = Vec::new();
+        for (doc_id, position) in positions {
+            if is_indexable_id(&doc_id) {
+                self.index.insert(&doc_id, position)?;
+            } else {
+                unindexed.push(position);
+            }
+        }
+        self.save_index_checkpoint(self.log.end()?)?;
 
-        let length = u32::from_le_bytes([
-            contents[offset],
-            contents[offset + 1],
-            contents[offset + 2],
-            contents[offset + 3],
-        ]) as usize;
+        // The log end recorded in the metadata no longer matches the compacted segments.
+        self.write_metadata()?;
+        Ok(unindexed)
+    }
 
-        if offset + length > contents.len() {
+    /// Rewrites a single log segment, keeping only the entries placed in it by the layout.
+    ///
+    /// ## Arguments
+    ///
+    /// * `segment` - The number of the segment to rewrite.
+    /// * `layout` - The live documents keyed by the position they are compacted into.
+    /// * `keep_empty` - Whether to keep the segment file even if no entry remains in it.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`Vec`]<\([`DocId`], [`LogPosition`])>) with the new positions of the
+    /// kept entries, or [`Err`]\([`io::Error`]) if the segment could not be rewritten.
+    fn compact_segment(
+        &self,
+        segment: u64,
+        layout: &HashMap<LogPosition, CompactedDocument>,
+        keep_empty: bool,
+    ) -> io::Result<Vec<(DocId, LogPosition)>> {
+        let segment_path = self.log.segment_path(segment);
+        let temp_path = segment_path.with_extension("tmp");
+        let compacted = self.write_compacted_segment(segment, layout, &temp_path)?;
+        self.advance_history_complete_from(&compacted)?;
+
+        if compacted.moved.is_empty() && !keep_empty {
+            fs::remove_file(temp_path)?;
+            fs::remove_file(segment_path)?;
+        } else {
+            fs::rename(temp_path, segment_path)?;
+        }
+
+        Ok(compacted
+            .moved
+            .into_iter()
+            .map(|(doc_id, _, position)| (doc_id, position))
+            .collect())
+    }
+
+    /// Returns the full document of a log entry, merging the changed fields of a
+    /// delta-encoded update into the full image it applies to.
+    ///
+    /// ## Arguments
+    ///
+    /// * `log_entry` - The [`LogEntry`] as read from the log.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`BsonDocument`]) with the full document,
+    /// or [`Err`]\([`io::Error`]) if the full image could not be read.
+    pub fn resolve_document(&self, log_entry: LogEntry) -> io::Result<BsonDocument> {
+        let Some(base) = log_entry.base else {
+            return Ok(log_entry.document);
+        };
+
+        let mut document = self.read_full_image(base)?;
+        document.extend(log_entry.document);
+        Ok(document)
+    }
+
+    /// Reads the full image a delta-encoded update applies to.
+    ///
+    /// ## Arguments
+    ///
+    /// * `base` - The [`LogPosition`] of the full image.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`BsonDocument`]) with the document of the image,
+    /// or [`Err`]\([`io::Error`]) if it could not be read or is itself a delta.
+    fn read_full_image(&self, base: LogPosition) -> io::Result<BsonDocument> {
+        let image = self.log.read_at(base)?;
+        if image.base.is_some() {
             return Err(io::Error::new(
-                io::ErrorKind::UnexpectedEof,
-                "BSON entry extends beyond end of file",
+                io::ErrorKind::InvalidData,
+                format!("Log entry at {} is not a full image", base),
             ));
         }
+        Ok(image.document)
+    }
 
-        let entry_bytes = &contents[offset..offset + length];
-        let log_doc: BsonDocument = bson::Document::from_reader(entry_bytes).map_err(|e| {
+    /// Extracts the document ID from a document read from the log.
+    ///
+    /// ## Arguments
+    ///
+    /// * `document` - The [`BsonDocument`] stored in the log entry.
+    /// * `position` - The [`LogPosition`] of the log entry, used in the error message.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`DocId`]) if the ID field is present,
+    /// or [`Err`]\([`io::Error`]) if it is missing or of an unsupported type.
+    pub(crate) fn log_entry_doc_id(
+        &self,
+        document: &BsonDocument,
+        position: LogPosition,
+    ) -> io::Result<DocId> {
+        self.get_doc_id_from_bson(document).ok_or_else(|| {
             io::Error::new(
                 io::ErrorKind::InvalidData,
-                format!("Failed to parse BSON: {}", e),
+                format!(
+                    "Could not extract document ID from log entry at {}",
+                    position
+                ),
             )
-        })?;
-
-        let timestamp = log_doc
-            .get_str("timestamp")
-            .unwrap_or("unknown")
-            .to_string();
-        let operation_str = log_doc.get_str("operation").unwrap_or("unknown");
-        let operation = operation_str
-            .parse::<Operation>()
-            .unwrap_or(Operation::Insert);
-        let document = log_doc
-            .get_document("document")
-            .cloned()
-            .unwrap_or_default();
-
-        Ok(LogEntry {
-            timestamp,
-            operation,
-            document,
         })
     }
 
-    /// Compacts the logfile by reconstructing the final state of each document.
+    /// Replays log entries written after the index checkpoint into the primary index.
+    ///
+    /// Falls back to a full [`Collection::rebuild_index`] if the checkpoint lies beyond
+    /// the end of the log, which means segments were replaced without updating the index.
     ///
     /// ## Returns
     ///
-    /// Returns [`Ok`]\(()) if the logfile was compacted successfully,
-    /// or [`Err`]\([`io::Error`]) if the compaction failed.
-    pub fn compact_logfile(&self) -> io::Result<()> {
-        let logfile_path = self.logfile_path();
-
-        let entries = self.read_log_entries()?;
-        if entries.is_empty() {
-            return Ok(());
+    /// Returns [`Ok`]\([`Vec<LogPosition>`]) with the positions of the replayed entries
+    /// whose document ID is too long to be indexed, if the index is in sync with the log,
+    /// or [`Err`]\([`io::Error`]) if the replay failed.
+    pub fn sync_index(&self) -> io::Result<Vec<LogPosition>> {
+        let checkpoint = self.index.checkpoint()?;
+        let end = self.log.end()?;
+
+        if !self.log.contains(checkpoint)? {
+            return self.rebuild_index();
+        }
+        if checkpoint == end {
+            return Ok(Vec::new());
         }
 
-        let mut current_state: HashMap<String, BsonDocument> = HashMap::new();
-        for (log_entry, log_offset) in entries {
-            let document = log_entry.document;
-            let operation = log_entry.operation;
-
-            let doc_id = match document.get(&self.id_field) {
-                Some(bson::Bson::String(s)) => s.clone(),
-                Some(bson::Bson::Int32(i)) => i.to_string(),
-                Some(bson::Bson::Int64(i)) => i.to_string(),
-                _ => {
-                    return Err(io::Error::new(
-                        io::ErrorKind::InvalidData,
-                        format!(
-                            "Could not extract document ID from log entry at offset {}",
-                            log_offset
-                        ),
-                    ));
-                }
-            };
+        self.replay_into_index(checkpoint, end)
+    }
 
-            match operation {
-                Operation::Insert => {
-                    current_state.insert(doc_id, document);
-                }
-                Operation::Delete => {
-                    current_state.remove(&doc_id);
-                }
-                Operation::Update => {
-                    current_state.insert(doc_id, document);
-                }
-            }
+    /// Rebuilds the primary index from scratch by replaying every log segment,
+    /// along with the secondary indexes.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`Vec<LogPosition>`]) with the positions of the entries whose
+    /// document ID is too long to be indexed, if the index was rebuilt,
+    /// or [`Err`]\([`io::Error`]) if the replay failed.
+    pub fn rebuild_index(&self) -> io::Result<Vec<LogPosition>> {
+        self.index.clear()?;
+        for index in self.indexes.values() {
+            index.clear()?;
+        }
+        if self.log.segments()?.is_empty() {
+            return Ok(Vec::new());
         }
 
-        let temp_path = logfile_path.with_extension("tmp");
-        let mut temp_file = OpenOptions::new()
-            .create(true)
-            .truncate(true)
-            .write(true)
-            .open(&temp_path)?;
-
-        for (_, document) in current_state {
-            let timestamp = chrono::Utc::now().to_rfc3339();
-            let mut log_entry = BsonDocument::new();
-            log_entry.insert("timestamp", Bson::String(timestamp));
-            log_entry.insert(
-                "operation",
-                Bson::String(Operation::Insert.as_str().to_string()),
-            );
-            log_entry.insert("document", Bson::Document(document));
+        self.replay_into_index(LogPosition::default(), self.log.end()?)
+    }
 
-            let bson_bytes = log_entry
-                .to_vec()
-                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    /// Applies the log entries in `[start, end)` to the primary and secondary indexes
+    /// and advances the checkpoint to `end`.
+    ///
+    /// The secondary indexes are updated against the document the primary index points at
+    /// before each entry, so replaying an entry they already hold leaves them unchanged.
+    /// Entries whose document ID is too long to be indexed are skipped, as they can only
+    /// have been written before such IDs were rejected.
+    ///
+    /// ## Arguments
+    ///
+    /// * `start` - The position of the first entry to replay.
+    /// * `end` - The end of the log the index will be in sync with afterwards.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`Vec<LogPosition>`]) with the positions of the skipped entries,
+    /// or [`Err`]\([`io::Error`]) if the replay failed.
+    fn replay_into_index(
+        &self,
+        start: LogPosition,
+        end: LogPosition,
+    ) -> io::Result<Vec<LogPosition>> {
+        let mut unindexed = Vec::new();
+        for item in self.log.entries_from(start)? {
+            let (log_entry, position) = item?;
+            if position >= end {
+                break;
+            }
 
-            temp_file.write_all(&bson_bytes)?;
-            writeln!(temp_file)?;
+            let doc_id = self.log_entry_doc_id(&log_entry.document, position)?;
+            if !is_indexable_id(&doc_id) {
+                unindexed.push(position);
+                continue;
+            }
+            if !self.indexes.is_empty() {
+                let previous = self.indexed_image(&doc_id)?;
+                let current = match log_entry.operation {
+                    Operation::Insert | Operation::Update => {
+                        Some(self.resolve_document(log_entry.clone())?)
+                    }
+                    Operation::Delete => None,
+                };
+                self.index_document_change(&doc_id, previous.as_ref(), current.as_ref())?;
+            }
+            match log_entry.operation {
+                Operation::Insert | Operation::Update => self.index.insert(&doc_id, position)?,
+                Operation::Delete => self.index.remove(&doc_id)?,
+            }
         }
 
-        fs::rename(temp_path, logfile_path)?;
-
-        Ok(())
+        self.save_index_checkpoint(end)?;
+        Ok(unindexed)
     }
 
     /// Writes the collection's metadata to the metadata file.
     ///
+    /// The metadata is written to a temporary file and flushed to disk before it replaces
+    /// the current file, which is kept as the previous generation. A crash at any point
+    /// therefore leaves at least one complete generation for [`Collection::read_metadata`].
+    ///
     /// ## Returns
     ///
     /// Returns [`Ok`]\(()) if successful,
     /// or [`Err`]\([`io::Error`]) if the write failed.
     pub fn write_metadata(&self) -> io::Result<()> {
         self.ensure_collection_dir()?;
+        let contents = self.encode_metadata(self.log.end()?)?;
 
         let metadata_path = self.metadata_path();
-        let mut file = OpenOptions::new()
+        let temp_path = self.base_path.join(METADATA_TEMP_FILE);
+        let mut temp_file = OpenOptions::new()
             .create(true)
             .truncate(true)
             .write(true)
-            .open(metadata_path)?;
+            .open(&temp_path)?;
+        temp_file.write_all(&contents)?;
+        temp_file.sync_all()?;
 
+        if metadata_path.exists() {
+            fs::rename(&metadata_path, self.previous_metadata_path())?;
+        }
+        fs::rename(&temp_path, &metadata_path)?;
+        sync_dir(&self.base_path)
+    }
+
+    /// Encodes the collection's metadata as the contents of a metadata file,
+    /// encrypting it if the collection has an encryption key.
+    ///
+    /// ## Arguments
+    ///
+    /// * `log_end` - The end of the log to record alongside the insert counter.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`Vec`]<[`u8`]>) with the format header followed by the metadata,
+    /// or [`Err`]\([`io::Error`]) if the metadata could not be serialized or encrypted.
+    pub(crate) fn encode_metadata(&self, log_end: LogPosition) -> io::Result<Vec<u8>> {
         let mut metadata = BsonDocument::new();
         metadata.insert("name", Bson::String(self.name.to_string()));
         metadata.insert("inserts", Bson::Int64(self.inserts as i64));
+        metadata.insert("log_segment", Bson::Int64(log_end.segment as i64));
+        metadata.insert("log_length", Bson::Int64(log_end.offset as i64));
+        let stats = self.log_stats();
+        metadata.insert("log_entries", Bson::Int64(stats.entries as i64));
+        metadata.insert("garbage_entries", Bson::Int64(stats.garbage as i64));
+        if let Some(complete_from) = self.history_complete_from() {
+            metadata.insert(
+                "history_complete_from",
+                Bson::String(complete_from.to_rfc3339()),
+            );
+        }
+        metadata.insert(
+            "compression",
+            Bson::String(self.compression.as_str().to_string()),
+        );
         metadata.insert("schema", Bson::Document(schema_to_document(&self.schema)));
+        metadata.insert(
+            "indexes",
+            Bson::Array(
+                self.indexes
+                    .values()
+                    .map(|index| match index.fields() {
+                        [field] => Bson::String(field.clone()),
+                        fields => Bson::Array(
+                            fields
+                                .iter()
+                                .map(|field| Bson::String(field.clone()))
+                                .collect(),
+                        ),
+                    })
+                    .collect(),
+            ),
+        );
 
-        let bson_bytes = metadata
+        let mut bson_bytes = metadata
             .to_vec()
             .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let key = self.log.encryption_key();
+        if let Some(key) = key {
+            bson_bytes = key.encrypt(&metadata_aad(&self.name), &bson_bytes)?;
+        }
 
-        file.write_all(&bson_bytes)?;
-        Ok(())
+        let mut contents = encode_format_header(FileKind::Metadata, key.is_some()).to_vec();
+        contents.extend(bson_bytes);
+        Ok(contents)
     }
 
     /// Reads the collection's metadata from the metadata file.
     ///
+    /// Collections written in an older storage format version are upgraded first,
+    /// and collections written in a newer one are rejected.
+    ///
+    /// If the metadata file is missing or cannot be parsed, falls back to the temporary
+    /// file of an interrupted [`Collection::write_metadata`] and then to the previous
+    /// generation, recording the fallback in the collection's
+    /// [`Collection::recovery_report`].
+    ///
     /// ## Arguments
     ///
     /// * `base_path` - The base directory path where collections are stored.
@@ -429,23 +1139,73 @@ impl Collection {
     /// ## Returns
     ///
     /// Returns [`Ok`]\([`Collection`]) if successful,
-    /// or [`Err`]\([`io::Error`]) if the read failed.
+    /// or [`Err`]\([`io::Error`]) if the collection could not be upgraded
+    /// or no generation of the metadata could be read.
     pub fn read_metadata(base_path: impl AsRef<Path>, name: &str) -> io::Result<Collection> {
+        Self::read_metadata_with_key(base_path, name, None)
+    }
+
+    /// Reads the metadata of a collection whose files may be encrypted.
+    ///
+    /// Behaves like [`Collection::read_metadata`], decrypting the metadata with the given key,
+    /// which the returned collection keeps for its subsequent reads and writes.
+    ///
+    /// ## Arguments
+    ///
+    /// * `base_path` - The base directory path where collections are stored.
+    /// * `name` - The name of the collection to read.
+    /// * `key` - The [`EncryptionKey`] of the collection, or [`None`] if it is not encrypted.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`Collection`]) if successful,
+    /// or [`Err`]\([`io::Error`]) if the collection could not be upgraded
+    /// or no generation of the metadata could be read or decrypted.
+    pub fn read_metadata_with_key(
+        base_path: impl AsRef<Path>,
+        name: &str,
+        key: Option<EncryptionKey>,
+    ) -> io::Result<Collection> {
         let collection_dir = base_path.as_ref().join(name);
-        let metadata_path = collection_dir.join("metadata.bin");
+        migrate_legacy_logfile(&collection_dir)?;
+        upgrade_collection(&collection_dir)?;
 
-        if !metadata_path.exists() {
+        let metadata_path = collection_dir.join(METADATA_FILE);
+        let candidates = [
+            metadata_path.clone(),
+            collection_dir.join(METADATA_TEMP_FILE),
+            collection_dir.join(METADATA_PREVIOUS_FILE),
+        ];
+
+        if !candidates.iter().any(|path| path.exists()) {
             return Err(io::Error::new(
                 io::ErrorKind::NotFound,
                 format!("Metadata file not found: {}", metadata_path.display()),
             ));
         }
 
-        let contents = fs::read(&metadata_path)?;
-        let metadata: BsonDocument = bson::Document::from_reader(&mut contents.as_slice())
-            .map_err(|e| {
-                io::Error::new(io::ErrorKind::InvalidData, format!("Invalid BSON: {}", e))
-            })?;
+        let mut first_error = None;
+        let mut loaded = None;
+        for (generation, path) in candidates.iter().enumerate() {
+            if !path.exists() {
+                continue;
+            }
+            match read_metadata_file(path, name, key.as_ref()) {
+                Ok(metadata) => {
+                    loaded = Some((metadata, generation > 0));
+                    break;
+                }
+                Err(e) => {
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+
+        let Some((metadata, restored)) = loaded else {
+            return Err(first_error
+                .unwrap_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Invalid BSON")));
+        };
+
         let stored_name = metadata.get_str("name").unwrap_or("unknown");
         let inserts = metadata.get_i64("inserts").unwrap_or(0) as u64;
         let schema =
@@ -455,12 +1215,110 @@ impl Collection {
             Collection::new(stored_name, schema, base_path.as_ref()).map_err(|e| {
                 io::Error::new(io::ErrorKind::InvalidData, format!("Invalid schema: {}", e))
             })?;
+        // Single-field indexes are recorded by the name of their field, and compound ones
+        // by the array of their fields.
+        for entry in metadata.get_array("indexes").into_iter().flatten() {
+            let fields: Vec<String> = match entry {
+                Bson::String(field) => vec![field.clone()],
+                Bson::Array(fields) => fields
+                    .iter()
+                    .filter_map(|field| field.as_str().map(str::to_string))
+                    .collect(),
+                _ => continue,
+            };
+            if fields.is_empty() {
+                continue;
+            }
+            let name = index_name(&fields);
+            let path = collection.base_path.join(index_file_name(&name));
+            collection
+                .indexes
+                .insert(name, SecondaryIndex::compound(fields, path));
+        }
+        collection.set_encryption_key(key);
         collection.inserts = inserts;
+        collection.compression = match metadata.get_str("compression") {
+            Ok(codec) => codec
+                .parse()
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?,
+            Err(_) => Compression::None,
+        };
+        collection.recovery.metadata_restored = restored;
+        *collection.lock_log_stats()? = LogStats {
+            entries: metadata.get_i64("log_entries").unwrap_or(0) as u64,
+            garbage: metadata.get_i64("garbage_entries").unwrap_or(0) as u64,
+        };
+        *collection.lock_history_complete_from()? = match metadata.get_str("history_complete_from")
+        {
+            Ok(complete_from) => Some(
+                parse_timestamp(complete_from)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?,
+            ),
+            Err(_) => None,
+        };
+
+        if let Ok(log_length) = metadata.get_i64("log_length") {
+            let log_segment = metadata.get_i64("log_segment").unwrap_or(0);
+            collection
+                .recover_counters(LogPosition::new(log_segment as u64, log_length as usize))?;
+        }
         Ok(collection)
     }
 
+    /// Advances the insert counter and the log statistics past the entries logged
+    /// after the metadata was written.
+    ///
+    /// Every logged insert increments the counter, and integer IDs are never reissued
+    /// even if the documents holding them were deleted before a crash.
+    ///
+    /// ## Arguments
+    ///
+    /// * `log_end` - The end of the log recorded alongside the persisted counter.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\(()) if the counter was recovered,
+    /// or [`Err`]\([`io::Error`]) if the log could not be read.
+    fn recover_counters(&mut self, log_end: LogPosition) -> io::Result<()> {
+        let start = if self.log.contains(log_end)? {
+            log_end
+        } else {
+            LogPosition::default()
+        };
+
+        let mut logged_inserts = 0;
+        let mut next_id = 0;
+        let mut stats = if start == log_end {
+            self.log_stats()
+        } else {
+            LogStats::default()
+        };
+        for (log_entry, _) in self.log.entries_from(start)?.map_while(Result::ok) {
+            stats.record(&log_entry.operation);
+            if log_entry.operation != Operation::Insert {
+                continue;
+            }
+            logged_inserts += 1;
+            if self.id_type == IdType::Int
+                && let Some(DocId::U64(id)) = self.get_doc_id_from_bson(&log_entry.document)
+            {
+                next_id = next_id.max(id + 1);
+            }
+        }
+
+        self.inserts = (self.inserts + logged_inserts).max(next_id);
+        *self.lock_log_stats()? = stats;
+        Ok(())
+    }
+
     /// Creates a [`Collection`] from existing files on disk.
     ///
+    /// Collections with a primary index only replay the log entries written after the
+    /// index checkpoint. Collections without one are compacted and indexed from scratch.
+    /// Index files found damaged are discarded and rebuilt by replaying the whole log.
+    /// Any damage found in the log beforehand is recorded in the collection's
+    /// [`Collection::recovery_report`].
+    ///
     /// ## Arguments
     ///
     /// * `base_path` - The base directory path where collections are stored.
@@ -471,36 +1329,176 @@ impl Collection {
     /// Returns [`Ok`]\([`Collection`]) if successful,
     /// or [`Err`]\([`io::Error`]) if the load failed.
     pub fn from_files(base_path: impl AsRef<Path>, name: &str) -> io::Result<Collection> {
-        let mut collection = Self::read_metadata(base_path.as_ref(), name)?;
-        collection.compact_logfile()?;
-        let log_entries = collection.read_log_entries()?;
-
-        for (log_entry, log_offset) in log_entries {
-            let doc_id = collection
-                .get_doc_id_from_bson(&log_entry.document)
-                .ok_or_else(|| {
-                    io::Error::new(
-                        io::ErrorKind::InvalidData,
-                        format!(
-                            "Could not extract document ID from log entry at offset {}",
-                            log_offset
-                        ),
-                    )
-                })?;
+        Self::from_files_with_key(base_path, name, None)
+    }
 
-            match log_entry.operation {
-                Operation::Insert | Operation::Update => {
-                    collection.document_indices.insert(doc_id, log_offset);
-                }
-                Operation::Delete => {
-                    collection.document_indices.remove(&doc_id);
-                }
+    /// Creates a [`Collection`] from existing files on disk that may be encrypted.
+    ///
+    /// Behaves like [`Collection::from_files`]. If a key is given while some of the files
+    /// are still stored in the clear, the whole collection is rewritten encrypted: the log
+    /// is compacted into encrypted segments, the index rebuilt and the plaintext metadata
+    /// generations removed.
+    ///
+    /// ## Arguments
+    ///
+    /// * `base_path` - The base directory path where collections are stored.
+    /// * `name` - The name of the collection to load.
+    /// * `key` - The [`EncryptionKey`] of the collection, or [`None`] if it is not encrypted.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`Collection`]) if successful,
+    /// or [`Err`]\([`io::Error`]) if the load failed, or the files are encrypted and
+    /// the key is missing or wrong.
+    pub fn from_files_with_key(
+        base_path: impl AsRef<Path>,
+        name: &str,
+        key: Option<EncryptionKey>,
+    ) -> io::Result<Collection> {
+        let mut collection = Self::read_metadata_with_key(base_path.as_ref(), name, key)?;
+        remove_stale_compaction_files(&collection.base_path)?;
+
+        // Indexes only hold what was derived from the log, so damaged ones are rebuilt from it.
+        let index_damaged = is_damaged(collection.index.verify())?;
+        if index_damaged {
+            collection.index.clear()?;
+        }
+        let mut indexes_damaged = false;
+        for index in collection.indexes.values() {
+            if is_damaged(index.verify())? {
+                index.clear()?;
+                indexes_damaged = true;
+            }
+        }
+
+        let encrypting =
+            collection.log.encryption_key().is_some() && collection.has_plaintext_files()?;
+        if encrypting {
+            // Without an index, the load compacts the log, rewriting every segment encrypted.
+            collection.index.clear()?;
+            for index in collection.indexes.values() {
+                index.clear()?;
+            }
+        }
+
+        let metadata_restored = collection.recovery.metadata_restored;
+        collection.recovery = collection.recover_logfile()?;
+        collection.recovery.metadata_restored = metadata_restored;
+        collection.recovery.index_rebuilt = index_damaged || indexes_damaged;
+        if metadata_restored {
+            collection.write_metadata()?;
+        }
+
+        // Secondary indexes are rebuilt from the documents if their file is missing, or if
+        // the primary index is, as the log is then not replayed into them. Rebuilding a
+        // damaged primary index replays the log into every index instead.
+        let rebuild_index = index_damaged && !encrypting;
+        let rebuilt_indexes: Vec<String> = collection
+            .indexes
+            .iter()
+            .filter(|(_, index)| !rebuild_index && (!collection.index.exists() || !index.exists()))
+            .map(|(name, _)| name.clone())
+            .collect();
+        collection.recovery.unindexed = if rebuild_index {
+            collection.rebuild_index()?
+        } else if collection.index.exists() {
+            collection.sync_index()?
+        } else {
+            collection.compact_and_index()?
+        };
+        for name in rebuilt_indexes {
+            collection.rebuild_secondary_index(&name)?;
+        }
+
+        if encrypting {
+            collection.write_metadata()?;
+            let previous_path = collection.previous_metadata_path();
+            if previous_path.exists() {
+                fs::remove_file(previous_path)?;
             }
         }
 
         Ok(collection)
     }
 
+    /// Checks whether any of the collection's files is stored in the clear.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`bool`]) with whether a log segment, an index or a metadata
+    /// generation is not encrypted, or [`Err`]\([`io::Error`]) if a header could not be read.
+    fn has_plaintext_files(&self) -> io::Result<bool> {
+        for segment in self.log.segments()? {
+            if self.log.segment_len(segment)? >= FORMAT_HEADER_SIZE
+                && !self.log.is_encrypted(segment)?
+            {
+                return Ok(true);
+            }
+        }
+        if self.index.exists() && !self.index.is_encrypted()? {
+            return Ok(true);
+        }
+        for index in self.indexes.values() {
+            if index.exists() && !index.is_encrypted()? {
+                return Ok(true);
+            }
+        }
+
+        for path in [self.metadata_path(), self.previous_metadata_path()] {
+            let mut header = [0u8; FORMAT_HEADER_SIZE];
+            match File::open(&path).and_then(|mut file| file.read_exact(&mut header)) {
+                Ok(()) => {}
+                Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e),
+            }
+            if !check_format_header(FileKind::Metadata, &header)? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Verifies the part of the log not yet covered by the primary index and
+    /// truncates a torn tail left behind by an interrupted write.
+    ///
+    /// Damaged entries followed by valid ones are skipped rather than truncated,
+    /// so that no acknowledged write after them is lost.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`RecoveryReport`]) describing the damage found,
+    /// or [`Err`]\([`io::Error`]) if the log could not be read or truncated.
+    pub fn recover_logfile(&self) -> io::Result<RecoveryReport> {
+        if self.log.segments()?.is_empty() {
+            return Ok(RecoveryReport::default());
+        }
+
+        let checkpoint = self.index.checkpoint()?;
+        let start = if self.log.contains(checkpoint)? {
+            checkpoint
+        } else {
+            LogPosition::default()
+        };
+
+        let mut entries = self.log.entries_from(start)?;
+        for item in entries.by_ref() {
+            item?;
+        }
+        let report = entries.into_report();
+
+        if let Some(position) = report.truncated_at {
+            let file = OpenOptions::new()
+                .write(true)
+                .open(self.log.segment_path(position.segment))?;
+            file.set_len(position.offset as u64)?;
+            if self.durability != Durability::Never {
+                file.sync_all()?;
+            }
+        }
+
+        Ok(report)
+    }
+
     /// Deletes the entire collection directory and all its files.
     ///
     /// ## Returns
@@ -508,9 +1506,121 @@ impl Collection {
     /// Returns [`Ok`]\(()) if successful,
     /// or [`Err`]\([`io::Error`]) if the deletion failed.
     pub fn delete_collection_files(&self) -> io::Result<()> {
+        self.log.reset()?;
         if self.base_path.exists() {
             fs::remove_dir_all(&self.base_path)?;
         }
         Ok(())
     }
 }
+
+/// Moves the single logfile written before logs were split into segments into segment zero.
+///
+/// Index entries and checkpoints written before the split decode as positions in
+/// segment zero, so they stay valid after the move.
+///
+/// ## Arguments
+///
+/// * `collection_dir` - The directory of the collection.
+pub(crate) fn migrate_legacy_logfile(collection_dir: &Path) -> io::Result<()> {
+    let legacy_path = collection_dir.join(LEGACY_LOGFILE);
+    if !legacy_path.exists() || !list_segments(collection_dir)?.is_empty() {
+        return Ok(());
+    }
+
+    fs::rename(legacy_path, collection_dir.join(segment_file_name(0)))?;
+    sync_dir(collection_dir)
+}
+
+/// Builds the associated data of a metadata file from the metadata magic and the name of
+/// its collection, so it only decrypts as the metadata of that collection.
+///
+/// ## Arguments
+///
+/// * `collection` - The name of the collection the metadata belongs to.
+fn metadata_aad(collection: &str) -> Vec<u8> {
+    let mut aad = Vec::with_capacity(4 + collection.len());
+    aad.extend_from_slice(FileKind::Metadata.magic());
+    aad.extend_from_slice(collection.as_bytes());
+    aad
+}
+
+/// Reads and parses a single metadata file.
+///
+/// ## Arguments
+///
+/// * `path` - The path to the metadata file.
+/// * `collection` - The name of the collection the metadata belongs to.
+/// * `key` - The [`EncryptionKey`] to decrypt the metadata with, if it is encrypted.
+///
+/// ## Returns
+///
+/// Returns [`Ok`]\([`BsonDocument`]) if the file holds a current format header followed by
+/// a complete BSON document, or [`Err`]\([`io::Error`]) if the read, the header check, the
+/// decryption or the parse failed.
+fn read_metadata_file(
+    path: &Path,
+    collection: &str,
+    key: Option<&EncryptionKey>,
+) -> io::Result<BsonDocument> {
+    let contents = fs::read(path)?;
+    let encrypted = check_format_header(FileKind::Metadata, &contents)?;
+    let body = &contents[FORMAT_HEADER_SIZE..];
+    let decrypted;
+    let bson_bytes = match (encrypted, key) {
+        (false, _) => body,
+        (true, Some(key)) => {
+            decrypted = key.decrypt(&metadata_aad(collection), body)?;
+            &decrypted[..]
+        }
+        (true, None) => {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "The metadata file is encrypted, but no encryption key was given",
+            ));
+        }
+    };
+    BsonDocument::from_reader(&mut &bson_bytes[..])
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("Invalid BSON: {}", e)))
+}
+
+/// Flushes a directory's entries to disk, making renames within it durable.
+///
+/// ## Arguments
+///
+/// * `path` - The path to the directory.
+pub(crate) fn sync_dir(path: &Path) -> io::Result<()> {
+    #[cfg(unix)]
+    File::open(path)?.sync_all()?;
+    #[cfg(not(unix))]
+    let _ = path;
+    Ok(())
+}
+
+/// Checks the outcome of verifying an index file for damage.
+///
+/// ## Arguments
+///
+/// * `verified` - The result of verifying the index file.
+///
+/// ## Returns
+///
+/// Returns [`Ok`]\([`bool`]) with whether the file holds a page that is torn, fails its
+/// checksum or points outside the file, or [`Err`]\([`io::Error`]) if the verification
+/// failed for another reason, such as a missing key or a failed read.
+fn is_damaged(verified: io::Result<()>) -> io::Result<bool> {
+    match verified {
+        Ok(()) => Ok(false),
+        Err(e)
+            if matches!(
+                e.kind(),
+                io::ErrorKind::InvalidData
+                    | io::ErrorKind::InvalidInput
+                    | io::ErrorKind::UnexpectedEof
+            ) =>
+        {
+            Ok(true)
+        }
+        Err(e) => Err(e),
+    }
+}
@@ -0,0 +1,152 @@
+//! # History
+//!
+//! Provides reads of a collection's past states, reconstructed from its log.
+//!
+//! Every log entry carries the RFC 3339 timestamp of its operation, so the state of the
+//! collection at any past instant is the result of replaying the entries written up to it.
+//! Compaction merges the updates of live documents into a single entry and drops deleted
+//! documents along with their entries, so only the history it kept can be reconstructed.
+//! Each compaction records the instant from which the history it left is complete, and
+//! reads of earlier instants are rejected. Setting a history retention window keeps
+//! compaction away from recent entries.
+
+use crate::{
+    collection::{
+        Collection,
+        file::{LogEntry, Operation},
+    },
+    document::{DocId, Document},
+};
+use bson::Document as BsonDocument;
+use chrono::{DateTime, Utc};
+use std::{collections::BTreeMap, io};
+
+/// Parses an RFC 3339 timestamp, such as those of log entries, into a UTC instant.
+///
+/// ## Arguments
+///
+/// * `timestamp` - The timestamp to parse, such as `2026-10-01T00:00:00Z`.
+///
+/// ## Returns
+///
+/// Returns [`Ok`]\([`DateTime<Utc>`]) with the instant,
+/// or [`Err`]\([`String`]) if the timestamp is not valid RFC 3339.
+pub fn parse_timestamp(timestamp: &str) -> Result<DateTime<Utc>, String> {
+    DateTime::parse_from_rfc3339(timestamp)
+        .map(|timestamp| timestamp.with_timezone(&Utc))
+        .map_err(|e| format!("Invalid timestamp '{}': {}", timestamp, e))
+}
+
+/// Point-in-time reads of the collection.
+impl Collection {
+    /// Reconstructs the documents of the collection as they were at a past instant,
+    /// by replaying the log entries written up to it.
+    ///
+    /// ## Arguments
+    ///
+    /// * `at` - The instant to reconstruct the collection at.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`Vec<Document>`]) with the documents ordered by ID, like
+    /// [`Collection::get_documents`], or [`Err`]\([`io::Error`]) if the log could not be
+    /// read or the state at that instant was discarded by compaction.
+    pub fn documents_as_of(&self, at: DateTime<Utc>) -> io::Result<Vec<Document>> {
+        if let Some(complete_from) = self.history_complete_from()
+            && at < complete_from
+        {
+            return Err(io::Error::other(format!(
+                "The state of collection '{}' at {} was discarded by compaction, \
+                 its history is only kept from {}",
+                self.name,
+                at.to_rfc3339(),
+                complete_from.to_rfc3339()
+            )));
+        }
+
+        let mut documents: BTreeMap<Vec<u8>, Document> = BTreeMap::new();
+
+        for item in self.scan_log_entries()? {
+            let (log_entry, position) = item?;
+            let invalid = |e: String| io::Error::new(io::ErrorKind::InvalidData, e);
+            let timestamp = parse_timestamp(&log_entry.timestamp).map_err(invalid)?;
+            if timestamp > at {
+                let inserted_at = parse_timestamp(log_entry.inserted_at()).map_err(invalid)?;
+                if inserted_at <= at {
+                    return Err(io::Error::other(format!(
+                        "The state of collection '{}' at {} was discarded by compaction",
+                        self.name,
+                        at.to_rfc3339()
+                    )));
+                }
+                continue;
+            }
+
+            let doc_id = self.log_entry_doc_id(&log_entry.document, position)?;
+            let key = doc_id.to_key_bytes();
+            match log_entry.operation {
+                Operation::Insert | Operation::Update => {
+                    let data: BsonDocument = self.resolve_document(log_entry)?;
+                    documents.insert(key, Document::new(doc_id, data));
+                }
+                Operation::Delete => {
+                    documents.remove(&key);
+                }
+            }
+        }
+
+        Ok(documents.into_values().collect())
+    }
+
+    /// Lists the log entries of a single document, oldest first.
+    ///
+    /// Delta-encoded updates are returned with the full updated document, and deletes with
+    /// the document as it was before the delete. An entry compaction merged updates into is returned
+    /// as an insert stamped with the time of the last merged update, with
+    /// [`LogEntry::inserted_at`] holding the time of the original insert.
+    ///
+    /// ## Arguments
+    ///
+    /// * `id` - The [`DocId`] of the document.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`Vec<LogEntry>`]) with the document's entries, which is empty if the
+    /// log holds none, or [`Err`]\([`io::Error`]) if the log could not be read.
+    pub fn document_history(&self, id: &DocId) -> io::Result<Vec<LogEntry>> {
+        let mut history = Vec::new();
+        for (mut log_entry, position) in self.read_log_entries()? {
+            if self.log_entry_doc_id(&log_entry.document, position)? != *id {
+                continue;
+            }
+            let timestamp = parse_timestamp(&log_entry.timestamp)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            log_entry.base = None;
+            history.push((timestamp, log_entry));
+        }
+
+        // Compaction writes merged entries where documents were inserted,
+        // so the log is not in timestamp order.
+        history.sort_by_key(|(timestamp, _)| *timestamp);
+
+        // Delete entries are tombstones holding only the ID,
+        // so they take the document of the entry before them.
+        let mut previous: Option<BsonDocument> = None;
+        Ok(history
+            .into_iter()
+            .map(|(_, mut log_entry)| {
+                match log_entry.operation {
+                    Operation::Delete => {
+                        if let Some(document) = previous.take() {
+                            log_entry.document = document;
+                        }
+                    }
+                    Operation::Insert | Operation::Update => {
+                        previous = Some(log_entry.document.clone());
+                    }
+                }
+                log_entry
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,707 @@
+//! # Collection Indexes
+//!
+//! Provides the secondary indexes of a collection, which map the values of a field to the
+//! documents holding them. Compound indexes map the values of an ordered list of fields,
+//! recording only the documents holding every one of them.
+//!
+//! Unique fields are always indexed, their index being checked for a document holding the
+//! same value before every write.
+//!
+//! The indexed fields are recorded in the collection's metadata. Every logged write updates
+//! the indexes, and log entries replayed into the primary index on load are replayed into
+//! them as well. An index whose file is missing is rebuilt from the documents on load, as
+//! are all of them when the primary index had to be rebuilt without replaying the log.
+
+use crate::{
+    collection::Collection,
+    document::{DocId, Document},
+    index::{primary::PrimaryIndex, secondary::SecondaryIndex},
+    query::{BsonComparable, ValueParseable},
+    schema::{FieldDefinition, FieldType},
+};
+use bson::{Bson, Document as BsonDocument};
+use fhedb_types::{FieldCondition, QueryOperator};
+use std::{cmp::Reverse, io, ops::Bound};
+
+/// Returns the name of an index over fields, which is the name of the field unless the
+/// index is compound.
+///
+/// ## Arguments
+///
+/// * `fields` - The names of the indexed fields, in order.
+pub(crate) fn index_name(fields: &[String]) -> String {
+    fields.join(",")
+}
+
+/// Returns the name of the file holding an index.
+///
+/// ## Arguments
+///
+/// * `name` - The name of the index, as returned by [`index_name`].
+pub(crate) fn index_file_name(name: &str) -> String {
+    format!("index.{}.bin", name)
+}
+
+/// Describes the fields of an index for messages, as in `field 'a'` or `fields 'a', 'b'`.
+///
+/// ## Arguments
+///
+/// * `name` - The name of the index, as returned by [`index_name`].
+fn describe_index(name: &str) -> String {
+    if name.contains(',') {
+        format!("fields '{}'", name.replace(',', "', '"))
+    } else {
+        format!("field '{}'", name)
+    }
+}
+
+/// Returns the values of fields held by a document.
+///
+/// ## Arguments
+///
+/// * `document` - The document.
+/// * `fields` - The names of the fields, in order.
+///
+/// ## Returns
+///
+/// Returns [`Some`]\([`Vec<Bson>`]) with the values, or [`None`] if a field is missing.
+fn field_values(document: &BsonDocument, fields: &[String]) -> Option<Vec<Bson>> {
+    fields
+        .iter()
+        .map(|field| document.get(field).cloned())
+        .collect()
+}
+
+/// Checks whether the values of a field type can be recorded in a secondary index.
+///
+/// Arrays hold several values, and encrypted values are randomized, so neither can be.
+///
+/// ## Arguments
+///
+/// * `field_type` - The declared type of the field.
+pub fn is_indexable(field_type: &FieldType) -> bool {
+    match field_type {
+        FieldType::Int
+        | FieldType::Float
+        | FieldType::Boolean
+        | FieldType::String
+        | FieldType::Reference(_) => true,
+        FieldType::Nullable(inner) => is_indexable(inner),
+        _ => false,
+    }
+}
+
+/// Checks whether a document ID can be recorded in the primary index and in the entries of
+/// secondary indexes, whose keys must fit in a single page.
+///
+/// ## Arguments
+///
+/// * `id` - The [`DocId`] to check.
+pub(crate) fn is_indexable_id(id: &DocId) -> bool {
+    PrimaryIndex::fits(id) && SecondaryIndex::fits(id)
+}
+
+/// Checks that a field can be unique, which requires its values to be indexable.
+///
+/// ## Arguments
+///
+/// * `field_name` - The name of the field.
+/// * `field_definition` - The [`FieldDefinition`] of the field.
+///
+/// ## Returns
+///
+/// Returns [`Ok`]\(()) if the field is not unique or can be,
+/// or [`Err`]\([`String`]) if it is unique but its values cannot be indexed.
+pub(crate) fn check_unique_definition(
+    field_name: &str,
+    field_definition: &FieldDefinition,
+) -> Result<(), String> {
+    if field_definition.unique && !is_indexable(&field_definition.field_type) {
+        return Err(format!(
+            "Field '{}' cannot be unique, as its values cannot be indexed",
+            field_name
+        ));
+    }
+    Ok(())
+}
+
+/// Secondary index management and lookups.
+impl Collection {
+    /// Returns the names of the fields with an index of their own, in alphabetical order.
+    pub fn indexed_fields(&self) -> Vec<String> {
+        self.indexes
+            .values()
+            .filter(|index| index.fields().len() == 1)
+            .map(|index| index.field().to_string())
+            .collect()
+    }
+
+    /// Returns the fields of every index, compound indexes included, ordered by the names
+    /// of their fields.
+    pub fn list_indexes(&self) -> Vec<Vec<String>> {
+        self.indexes
+            .values()
+            .map(|index| index.fields().to_vec())
+            .collect()
+    }
+
+    /// Checks whether a field has an index of its own.
+    ///
+    /// ## Arguments
+    ///
+    /// * `field_name` - The name of the field.
+    pub fn has_index(&self, field_name: &str) -> bool {
+        self.indexes.contains_key(field_name)
+    }
+
+    /// Returns the index of a field, if it has one of its own.
+    ///
+    /// ## Arguments
+    ///
+    /// * `field_name` - The name of the field.
+    pub fn secondary_index(&self, field_name: &str) -> Option<&SecondaryIndex> {
+        self.indexes.get(field_name)
+    }
+
+    /// Returns the index over an ordered list of fields, if there is one.
+    ///
+    /// ## Arguments
+    ///
+    /// * `field_names` - The names of the indexed fields, in order.
+    pub fn compound_index(&self, field_names: &[String]) -> Option<&SecondaryIndex> {
+        self.indexes.get(&index_name(field_names))
+    }
+
+    /// Returns the names of the indexes including a field.
+    ///
+    /// ## Arguments
+    ///
+    /// * `field_name` - The name of the field.
+    pub(crate) fn indexes_including(&self, field_name: &str) -> Vec<String> {
+        self.indexes
+            .iter()
+            .filter(|(_, index)| index.fields().iter().any(|field| field == field_name))
+            .map(|(name, _)| name.clone())
+            .collect()
+    }
+
+    /// Creates an index on a field, recording the values of the existing documents.
+    ///
+    /// ## Arguments
+    ///
+    /// * `field_name` - The name of the field to index.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\(()) if the index was created, or [`Err`]\([`String`]) if the field
+    /// does not exist, cannot be indexed or is already indexed, or the index could not be built.
+    pub fn create_index(&mut self, field_name: &str) -> Result<(), String> {
+        self.create_compound_index(&[field_name.to_string()])
+    }
+
+    /// Creates an index over an ordered list of fields, recording the values of the existing
+    /// documents. The index serves conditions requiring the first fields to be equal to
+    /// values, and optionally the next one to be within a range.
+    ///
+    /// ## Arguments
+    ///
+    /// * `field_names` - The names of the fields to index, in order.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\(()) if the index was created, or [`Err`]\([`String`]) if no fields
+    /// are given, a field is given twice, does not exist or cannot be indexed, the fields
+    /// are already indexed in this order, or the index could not be built.
+    pub fn create_compound_index(&mut self, field_names: &[String]) -> Result<(), String> {
+        if field_names.is_empty() {
+            return Err("An index needs at least one field".to_string());
+        }
+        for (position, field_name) in field_names.iter().enumerate() {
+            if field_names[..position].contains(field_name) {
+                return Err(format!(
+                    "Field '{}' is given more than once for the index",
+                    field_name
+                ));
+            }
+            self.check_indexable(field_name)?;
+        }
+
+        let name = index_name(field_names);
+        if self.indexes.contains_key(&name) {
+            return Err(format!("Index on {} already exists", describe_index(&name)));
+        }
+
+        let mut index = SecondaryIndex::compound(
+            field_names.to_vec(),
+            self.base_path.join(index_file_name(&name)),
+        );
+        index.set_encryption_key(self.log.encryption_key().cloned());
+        self.build_index(&index)
+            .map_err(|e| format!("Failed to build index on {}: {}", describe_index(&name), e))?;
+
+        self.indexes.insert(name, index);
+        self.write_metadata()
+            .map_err(|e| format!("Failed to write metadata: {}", e))
+    }
+
+    /// Checks that a field can be indexed.
+    ///
+    /// ## Arguments
+    ///
+    /// * `field_name` - The name of the field.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\(()) if it can be, or [`Err`]\([`String`]) if the field does not
+    /// exist, is the ID field or its values are arrays or encrypted.
+    fn check_indexable(&self, field_name: &str) -> Result<(), String> {
+        let field_definition = self
+            .schema
+            .fields
+            .get(field_name)
+            .ok_or_else(|| format!("Field '{}' does not exist in the schema", field_name))?;
+        if field_name == self.id_field {
+            return Err(format!(
+                "Field '{}' is the ID field, which is always indexed",
+                field_name
+            ));
+        }
+        if !is_indexable(&field_definition.field_type) {
+            return Err(format!(
+                "Field '{}' cannot be indexed, as its values are arrays or encrypted",
+                field_name
+            ));
+        }
+        Ok(())
+    }
+
+    /// Drops the index on a field, deleting its file.
+    ///
+    /// ## Arguments
+    ///
+    /// * `field_name` - The name of the indexed field.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\(()) if the index was dropped, or [`Err`]\([`String`]) if the field is
+    /// not indexed, is unique, or the index could not be deleted.
+    pub fn drop_index(&mut self, field_name: &str) -> Result<(), String> {
+        if self
+            .schema
+            .fields
+            .get(field_name)
+            .is_some_and(|field_definition| field_definition.unique)
+        {
+            return Err(format!(
+                "Index on field '{}' enforces its unique constraint and cannot be dropped",
+                field_name
+            ));
+        }
+        self.remove_index(field_name)
+    }
+
+    /// Drops the index over an ordered list of fields, deleting its file.
+    ///
+    /// ## Arguments
+    ///
+    /// * `field_names` - The names of the indexed fields, in order.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\(()) if the index was dropped, or [`Err`]\([`String`]) if there is
+    /// no such index, it enforces a unique constraint, or it could not be deleted.
+    pub fn drop_compound_index(&mut self, field_names: &[String]) -> Result<(), String> {
+        match field_names {
+            [field_name] => self.drop_index(field_name),
+            _ => self.remove_index(&index_name(field_names)),
+        }
+    }
+
+    /// Drops an index, deleting its file, even if it enforces a unique constraint.
+    ///
+    /// ## Arguments
+    ///
+    /// * `name` - The name of the index, as returned by [`index_name`].
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\(()) if the index was dropped,
+    /// or [`Err`]\([`String`]) if there is no such index or it could not be deleted.
+    pub(crate) fn remove_index(&mut self, name: &str) -> Result<(), String> {
+        let index = self
+            .indexes
+            .remove(name)
+            .ok_or_else(|| format!("No index on {} exists", describe_index(name)))?;
+        self.write_metadata()
+            .map_err(|e| format!("Failed to write metadata: {}", e))?;
+        index
+            .clear()
+            .map_err(|e| format!("Failed to delete index on {}: {}", describe_index(name), e))
+    }
+
+    /// Rebuilds an index from the documents of the collection.
+    ///
+    /// ## Arguments
+    ///
+    /// * `name` - The name of the indexed field, or the names of the fields of a compound
+    ///   index joined by commas.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\(()) if the index was rebuilt or there is no such index,
+    /// or [`Err`]\([`io::Error`]) if the documents could not be read or the index written.
+    pub fn rebuild_secondary_index(&self, name: &str) -> io::Result<()> {
+        match self.indexes.get(name) {
+            Some(index) => self.build_index(index),
+            None => Ok(()),
+        }
+    }
+
+    /// Replaces the contents of an index with the values of the current documents.
+    ///
+    /// ## Arguments
+    ///
+    /// * `index` - The [`SecondaryIndex`] to build.
+    fn build_index(&self, index: &SecondaryIndex) -> io::Result<()> {
+        index.clear()?;
+        for document in self.get_documents()? {
+            if let Some(values) = field_values(&document.data, index.fields()) {
+                index.insert_values(&values, &document.id)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that a document holds no value of a unique field that another document
+    /// already holds. Null values are never in conflict.
+    ///
+    /// ## Arguments
+    ///
+    /// * `id` - The [`DocId`] of the document being written.
+    /// * `document` - The document being written.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\(()) if no value is in conflict, or [`Err`]\([`String`]) naming the
+    /// document already holding a value, or if an index could not be read.
+    pub(crate) fn check_unique(&self, id: &DocId, document: &BsonDocument) -> Result<(), String> {
+        for (field_name, field_definition) in &self.schema.fields {
+            if !field_definition.unique {
+                continue;
+            }
+            let (Some(index), Some(value)) =
+                (self.indexes.get(field_name), document.get(field_name))
+            else {
+                continue;
+            };
+            if *value == Bson::Null {
+                continue;
+            }
+
+            let holders = index
+                .lookup(value)
+                .map_err(|e| format!("Failed to read index on field '{}': {}", field_name, e))?;
+            // Long strings are truncated in the index, so the holders are checked.
+            for holder in holders {
+                if holder == *id {
+                    continue;
+                }
+                if self
+                    .get_document(holder.clone())
+                    .is_some_and(|existing| existing.data.get(field_name) == Some(value))
+                {
+                    return Err(format!(
+                        "Unique field '{}' already holds value {} in document '{}'.",
+                        field_name, value, holder
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Records a change of a document in every index whose fields changed.
+    ///
+    /// ## Arguments
+    ///
+    /// * `id` - The [`DocId`] of the changed document.
+    /// * `previous` - The document before the change, or [`None`] if it was inserted.
+    /// * `current` - The document after the change, or [`None`] if it was deleted.
+    pub(crate) fn index_document_change(
+        &self,
+        id: &DocId,
+        previous: Option<&BsonDocument>,
+        current: Option<&BsonDocument>,
+    ) -> io::Result<()> {
+        for index in self.indexes.values() {
+            let previous_values =
+                previous.and_then(|document| field_values(document, index.fields()));
+            let current_values =
+                current.and_then(|document| field_values(document, index.fields()));
+            if previous_values == current_values {
+                continue;
+            }
+            if let Some(values) = previous_values {
+                index.remove_values(&values, id)?;
+            }
+            if let Some(values) = current_values {
+                index.insert_values(&values, id)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads the latest version of a document through the primary index, if there are
+    /// indexes that need it to record a change of the document.
+    ///
+    /// ## Arguments
+    ///
+    /// * `id` - The [`DocId`] of the document.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`Some`]\([`BsonDocument`])) with the document,
+    /// [`Ok`]\([`None`]) if it is not indexed or there are no secondary indexes,
+    /// or [`Err`]\([`io::Error`]) if it could not be read.
+    pub(crate) fn indexed_image(&self, id: &DocId) -> io::Result<Option<BsonDocument>> {
+        if self.indexes.is_empty() {
+            return Ok(None);
+        }
+        match self.index.get(id)? {
+            Some(position) => {
+                let log_entry = self.read_log_entry_at_offset(position)?;
+                self.resolve_document(log_entry).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Finds the documents that can match the conditions through an index.
+    ///
+    /// Every index is matched against the conditions: `=` conditions on its first fields
+    /// are looked up together, and the `<`, `<=`, `>` and `>=` conditions on the next field
+    /// are combined into a single range scanned after them. The index matching the most
+    /// `=` conditions is used, then one with a range, then the one whose first matched
+    /// condition comes first. The returned documents still have to be checked against
+    /// every condition.
+    ///
+    /// ## Arguments
+    ///
+    /// * `conditions` - The conditions to apply (AND logic).
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`Some`]\([`Vec<Document>`])) with the candidate documents ordered
+    /// by ID, [`Ok`]\([`None`]) if no condition can be looked up in an index,
+    /// or [`Err`]\([`String`]) if the index could not be read.
+    pub(crate) fn indexed_candidates(
+        &self,
+        conditions: &[FieldCondition],
+    ) -> Result<Option<Vec<Document>>, String> {
+        let plan = self
+            .indexes
+            .values()
+            .filter_map(|index| self.index_plan(index, conditions))
+            .max_by_key(|plan| {
+                (
+                    plan.values.len(),
+                    plan.range.is_some(),
+                    Reverse(plan.first_condition),
+                )
+            });
+        let Some(plan) = plan else {
+            return Ok(None);
+        };
+        let ids = match &plan.range {
+            Some((lower, upper)) => {
+                plan.index
+                    .range_after(&plan.values, lower.as_ref(), upper.as_ref())
+            }
+            None => plan.index.lookup_values(&plan.values),
+        };
+
+        // Index entries are ordered by value first, and long strings are truncated in the
+        // index, so that a lookup may span several values.
+        let mut ids = ids.map_err(|e| e.to_string())?;
+        ids.sort_by_key(DocId::to_key_bytes);
+        ids.dedup();
+        Ok(Some(
+            ids.into_iter()
+                .filter_map(|id| self.get_document(id))
+                .collect(),
+        ))
+    }
+
+    /// Matches an index against conditions.
+    ///
+    /// ## Arguments
+    ///
+    /// * `index` - The [`SecondaryIndex`] to match.
+    /// * `conditions` - The conditions to apply (AND logic).
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Some`]\([`IndexPlan`]) with the values of the first fields and the range of
+    /// the next one, or [`None`] if no condition applies to the first field.
+    fn index_plan<'a>(
+        &self,
+        index: &'a SecondaryIndex,
+        conditions: &[FieldCondition],
+    ) -> Option<IndexPlan<'a>> {
+        let mut values = Vec::new();
+        let mut first_condition = usize::MAX;
+        for field in index.fields() {
+            let lookup = conditions
+                .iter()
+                .enumerate()
+                .find_map(|(position, condition)| {
+                    if condition.operator != QueryOperator::Equal || condition.field_name != *field
+                    {
+                        return None;
+                    }
+                    Some((position, self.condition_value(condition)?))
+                });
+            let Some((position, value)) = lookup else {
+                break;
+            };
+            first_condition = first_condition.min(position);
+            values.push(value);
+        }
+
+        let range = index
+            .fields()
+            .get(values.len())
+            .and_then(|field| self.condition_range(field, conditions))
+            .map(|(position, lower, upper)| {
+                first_condition = first_condition.min(position);
+                (lower, upper)
+            });
+        if values.is_empty() && range.is_none() {
+            return None;
+        }
+        Some(IndexPlan {
+            index,
+            values,
+            range,
+            first_condition,
+        })
+    }
+
+    /// Parses the value of a condition as a value of its field.
+    ///
+    /// ## Arguments
+    ///
+    /// * `condition` - The condition.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Some`]\([`Bson`]) with the condition's value,
+    /// or [`None`] if the field does not exist or the value does not parse.
+    fn condition_value(&self, condition: &FieldCondition) -> Option<Bson> {
+        let field_type = &self.schema.fields.get(&condition.field_name)?.field_type;
+        condition.value.parse_as_bson(field_type).ok()
+    }
+
+    /// Combines the comparison conditions on a field into the narrowest range they allow.
+    ///
+    /// Only integers, floating point numbers and strings are ordered by comparisons, so
+    /// conditions on other values are left to be checked against the documents.
+    ///
+    /// ## Arguments
+    ///
+    /// * `field` - The name of the field.
+    /// * `conditions` - The conditions to combine (AND logic).
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Some`] with the position of the first combined condition and the lower
+    /// and upper [`Bound`]s of the range, or [`None`] if no comparison condition applies
+    /// to the field.
+    fn condition_range(
+        &self,
+        field: &str,
+        conditions: &[FieldCondition],
+    ) -> Option<(usize, Bound<Bson>, Bound<Bson>)> {
+        let mut lower = Bound::Unbounded;
+        let mut upper = Bound::Unbounded;
+        let mut first_condition = None;
+        for (position, condition) in conditions.iter().enumerate() {
+            if condition.field_name != field
+                || !matches!(
+                    condition.operator,
+                    QueryOperator::GreaterThan
+                        | QueryOperator::GreaterThanOrEqual
+                        | QueryOperator::LessThan
+                        | QueryOperator::LessThanOrEqual
+                )
+            {
+                continue;
+            }
+            let Some(value) = self.condition_value(condition) else {
+                continue;
+            };
+            if !matches!(value, Bson::Int64(_) | Bson::Double(_) | Bson::String(_)) {
+                continue;
+            }
+            first_condition.get_or_insert(position);
+            match condition.operator {
+                QueryOperator::GreaterThan => lower = narrow(lower, Bound::Excluded(value), true),
+                QueryOperator::GreaterThanOrEqual => {
+                    lower = narrow(lower, Bound::Included(value), true)
+                }
+                QueryOperator::LessThan => upper = narrow(upper, Bound::Excluded(value), false),
+                _ => upper = narrow(upper, Bound::Included(value), false),
+            }
+        }
+        Some((first_condition?, lower, upper))
+    }
+}
+
+/// The way an index serves conditions: the values its first fields must be equal to, and
+/// the range the next field must be within.
+struct IndexPlan<'a> {
+    /// The index.
+    index: &'a SecondaryIndex,
+    /// The values of the first fields of the index.
+    values: Vec<Bson>,
+    /// The lower and upper [`Bound`]s of the next field, if it is compared.
+    range: Option<(Bound<Bson>, Bound<Bson>)>,
+    /// The position of the first condition the plan serves.
+    first_condition: usize,
+}
+
+/// Returns the narrower of two bounds on the same side of a range.
+///
+/// ## Arguments
+///
+/// * `current` - The current [`Bound`].
+/// * `candidate` - The [`Bound`] of another condition.
+/// * `lower` - Whether the bounds are lower bounds, which narrow as they grow.
+fn narrow(current: Bound<Bson>, candidate: Bound<Bson>, lower: bool) -> Bound<Bson> {
+    let (current_value, candidate_value) = match (&current, &candidate) {
+        (Bound::Unbounded, _) => return candidate,
+        (_, Bound::Unbounded) => return current,
+        (
+            Bound::Included(current_value) | Bound::Excluded(current_value),
+            Bound::Included(candidate_value) | Bound::Excluded(candidate_value),
+        ) => (current_value, candidate_value),
+    };
+    let inward = if lower {
+        QueryOperator::GreaterThan
+    } else {
+        QueryOperator::LessThan
+    };
+    let outward = if lower {
+        QueryOperator::LessThan
+    } else {
+        QueryOperator::GreaterThan
+    };
+
+    let inward_value = candidate_value.compare_to(current_value, &inward) == Ok(true);
+    let equal_value = candidate_value.compare_to(current_value, &outward) == Ok(false);
+    if inward_value || (equal_value && matches!(candidate, Bound::Excluded(_))) {
+        candidate
+    } else {
+        current
+    }
+}
@@ -2,17 +2,49 @@
 //!
 //! Provides the core [`Collection`] type and its document management operations.
 
+pub mod changes;
+pub mod compaction;
+pub mod compression;
 pub mod data;
+pub mod durability;
 pub mod file;
+pub mod history;
+pub mod indexes;
+pub mod reader;
+pub mod recovery;
+pub mod segment;
 
 use crate::{
     document::{DocId, Document},
+    format::encryption::EncryptionKey,
+    index::{primary::PrimaryIndex, secondary::SecondaryIndex},
     schema::{IdType, Schema, SchemaOps},
 };
+use changes::ChangeFeed;
+use chrono::{DateTime, Utc};
+use compaction::{DEFAULT_COMPACTION_THRESHOLD, LogStats};
+use compression::Compression;
+use durability::Durability;
 use file::Operation;
-use std::{collections::HashMap, path::PathBuf};
+use indexes::{check_unique_definition, index_file_name, is_indexable_id};
+use reader::LogReader;
+use recovery::RecoveryReport;
+use segment::{DEFAULT_MAX_SEGMENT_SIZE, LogPosition};
+use std::{
+    collections::{BTreeMap, HashMap},
+    io,
+    path::PathBuf,
+    sync::{Arc, Mutex, atomic::AtomicBool},
+    time::{Duration, Instant},
+};
 use uuid::Uuid;
 
+/// The number of inserts between metadata writes that persist the insert counter.
+///
+/// The counter is recovered from the logfile entries written since the last metadata
+/// write on load, so this only bounds how much of the logfile that recovery has to read.
+pub const METADATA_INSERT_BATCH: u64 = 1024;
+
 /// A collection of documents with a shared [`Schema`].
 #[derive(Debug, Clone)]
 pub struct Collection {
@@ -20,21 +52,53 @@ pub struct Collection {
     pub name: String,
     /// The schema describing the structure of documents in this collection.
     pub(crate) schema: Schema,
-    /// The in-memory storage of document indices, mapping document IDs to log file offsets.
-    pub(crate) document_indices: HashMap<DocId, usize>,
+    /// The persistent primary index, mapping document IDs to log positions.
+    pub(crate) index: PrimaryIndex,
+    /// The persistent secondary indexes, keyed by the name of the indexed field.
+    /// The indexed fields are persisted with the metadata.
+    pub(crate) indexes: BTreeMap<String, SecondaryIndex>,
+    /// The reader holding the open log segment handles used for document reads.
+    pub(crate) log: LogReader,
     /// The name of the field in the schema with type Id, or "id" if not present in the schema.
     pub(crate) id_field: String,
     /// The type of ID used in this collection (string or integer).
     pub(crate) id_type: IdType,
     /// Counter for generating sequential u64 IDs. Starts at 0 and increments on each insert.
+    /// Persisted in batches of [`METADATA_INSERT_BATCH`] and recovered from the logfile on load.
     pub(crate) inserts: u64,
     /// The base path for the collection.
     pub(crate) base_path: PathBuf,
+    /// The damage found in the logfile when the collection was loaded from disk.
+    pub(crate) recovery: RecoveryReport,
+    /// When writes to the logfile are flushed to disk.
+    pub(crate) durability: Durability,
+    /// The time of the oldest logfile write not yet flushed to disk, shared between clones.
+    pub(crate) unsynced_since: Arc<Mutex<Option<Instant>>>,
+    /// The size in bytes after which writes roll over to a new log segment.
+    pub(crate) max_segment_size: usize,
+    /// The counts of the entries in the log, shared between clones.
+    /// Persisted with the metadata and recovered from the logfile on load.
+    pub(crate) stats: Arc<Mutex<LogStats>>,
+    /// The garbage ratio above which the collection needs compaction.
+    pub(crate) compaction_threshold: f64,
+    /// How long compaction keeps log entries untouched, or [`None`] to discard history.
+    pub(crate) history_retention: Option<Duration>,
+    /// Whether a compaction of the collection is in progress, shared between clones.
+    pub(crate) compacting: Arc<AtomicBool>,
+    /// The instant from which compaction left the history in the log complete, or [`None`]
+    /// if it never discarded any, shared between clones. Persisted with the metadata.
+    pub(crate) history_complete_from: Arc<Mutex<Option<DateTime<Utc>>>>,
+    /// The codec applied to the payloads of new log entries. Persisted with the metadata.
+    pub(crate) compression: Compression,
+    /// The feed the collection's writes are published to, shared between clones.
+    pub(crate) changes: ChangeFeed,
 }
 
 impl Collection {
     /// Creates a new [`Collection`] with the given name and schema.
     ///
+    /// Every unique field of the schema is indexed, its index enforcing the constraint.
+    ///
     /// ## Arguments
     ///
     /// * `name` - The name of the collection.
@@ -44,7 +108,7 @@ impl Collection {
     /// ## Returns
     ///
     /// Returns [`Ok`]\([`Collection`]) if created successfully,
-    /// or [`Err`]\([`String`]) if the schema is invalid.
+    /// or [`Err`]\([`String`]) if the schema is invalid or a unique field cannot be indexed.
     pub fn new(
         name: impl Into<String>,
         mut schema: Schema,
@@ -54,15 +118,39 @@ impl Collection {
         let name = name.into();
         let temp_path = base_path.into();
         let base_path = temp_path.join(&name);
+        let index = PrimaryIndex::new(base_path.join(file::INDEX_FILE));
+        let log = LogReader::new(name.clone(), base_path.clone());
+
+        let mut indexes = BTreeMap::new();
+        for (field_name, field_definition) in &schema.fields {
+            check_unique_definition(field_name, field_definition)?;
+            if field_definition.unique {
+                let path = base_path.join(index_file_name(field_name));
+                indexes.insert(field_name.clone(), SecondaryIndex::new(field_name, path));
+            }
+        }
 
         Ok(Self {
             name,
             schema,
-            document_indices: HashMap::new(),
+            index,
+            indexes,
+            log,
             id_field,
             id_type,
             inserts: 0,
             base_path,
+            recovery: RecoveryReport::default(),
+            durability: Durability::default(),
+            unsynced_since: Arc::new(Mutex::new(None)),
+            max_segment_size: DEFAULT_MAX_SEGMENT_SIZE,
+            stats: Arc::new(Mutex::new(LogStats::default())),
+            compaction_threshold: DEFAULT_COMPACTION_THRESHOLD,
+            history_retention: None,
+            compacting: Arc::new(AtomicBool::new(false)),
+            history_complete_from: Arc::new(Mutex::new(None)),
+            compression: Compression::default(),
+            changes: ChangeFeed::default(),
         })
     }
 
@@ -74,8 +162,8 @@ impl Collection {
     ///
     /// ## Returns
     ///
-    /// Returns [`Ok`]\([`DocId`]) of the added document,
-    /// or [`Err`]\([`Vec<String>`]) with validation errors.
+    /// Returns [`Ok`]\([`DocId`]) of the added document, or [`Err`]\([`Vec<String>`]) with
+    /// validation errors, including a value of a unique field another document holds.
     pub fn add_document(&mut self, mut doc: bson::Document) -> Result<DocId, Vec<String>> {
         self.schema.apply_defaults(&mut doc);
 
@@ -90,25 +178,33 @@ impl Collection {
             }
         };
 
-        if self.document_indices.contains_key(&doc_id) {
+        // The entry would be logged before the index rejects it, so it is checked first.
+        if !is_indexable_id(&doc_id) {
+            return Err(vec![format!(
+                "Document ID of {} bytes is too long to be indexed.",
+                doc_id.to_string().len()
+            )]);
+        }
+        if self
+            .index
+            .contains(&doc_id)
+            .map_err(|e| vec![e.to_string()])?
+        {
             return Err(vec![format!(
                 "Document with ID '{}' already exists.",
                 doc_id.to_string()
             )]);
         }
+        self.check_unique(&doc_id, &doc).map_err(|e| vec![e])?;
 
-        match self.append_to_log(&Operation::Insert, &doc) {
-            Ok(offset) => {
-                self.document_indices.insert(doc_id.clone(), offset);
-            }
-            Err(e) => {
-                return Err(vec![e.to_string()]);
-            }
-        }
+        self.write_log_entry(&Operation::Insert, &doc_id, &doc)
+            .map_err(|e| vec![e.to_string()])?;
 
         self.inserts += 1;
-        self.write_metadata()
-            .map_err(|e| vec![format!("Failed to write metadata: {}", e)])?;
+        if (self.inserts - 1).is_multiple_of(METADATA_INSERT_BATCH) {
+            self.write_metadata()
+                .map_err(|e| vec![format!("Failed to write metadata: {}", e)])?;
+        }
         Ok(doc_id)
     }
 
@@ -151,8 +247,8 @@ impl Collection {
     ///
     /// ## Returns
     ///
-    /// Returns [`Ok`]\([`Document`]) with the updated document,
-    /// or [`Err`]\([`Vec<String>`]) with validation errors.
+    /// Returns [`Ok`]\([`Document`]) with the updated document, or [`Err`]\([`Vec<String>`])
+    /// with validation errors, including a value of a unique field another document holds.
     pub fn update_document(
         &mut self,
         id: DocId,
@@ -162,29 +258,37 @@ impl Collection {
             return Err(vec![format!("Cannot update ID field '{}'", self.id_field)]);
         }
 
-        let offset = match self.document_indices.get(&id) {
-            Some(&offset) => offset,
-            None => return Err(vec![format!("Document with ID {:?} not found", id)]),
+        let position = match self.index.get(&id) {
+            Ok(Some(position)) => position,
+            Ok(None) => return Err(vec![format!("Document with ID {:?} not found", id)]),
+            Err(e) => return Err(vec![format!("Failed to read index: {}", e)]),
         };
 
-        let current_log_entry = match self.read_log_entry_at_offset(offset) {
+        let current_log_entry = match self.read_log_entry_at_offset(position) {
             Ok(entry) => entry,
             Err(e) => return Err(vec![format!("Failed to read document: {}", e)]),
         };
 
-        let mut updated_doc = current_log_entry.document.clone();
+        // Changes accumulate against the latest full image, so reads never follow more than one delta.
+        let (base, mut changes) = match current_log_entry.base {
+            Some(base) => (base, current_log_entry.document.clone()),
+            None => (position, bson::doc! { &self.id_field: id.to_bson() }),
+        };
+        let mut updated_doc = match self.resolve_document(current_log_entry) {
+            Ok(document) => document,
+            Err(e) => return Err(vec![format!("Failed to read document: {}", e)]),
+        };
 
         for (key, value) in update_doc {
-            updated_doc.insert(key, value);
+            updated_doc.insert(key.clone(), value.clone());
+            changes.insert(key, value);
         }
 
         self.validate_document(&updated_doc)?;
+        self.check_unique(&id, &updated_doc).map_err(|e| vec![e])?;
 
-        match self.append_to_log(&Operation::Update, &updated_doc) {
-            Ok(new_offset) => {
-                self.document_indices.insert(id.clone(), new_offset);
-                Ok(Document::new(id, updated_doc))
-            }
+        match self.write_update_entry(&id, base, &changes, &updated_doc) {
+            Ok(_) => Ok(Document::new(id, updated_doc)),
             Err(e) => Err(vec![format!(
                 "Failed to write updated document to log: {}",
                 e
@@ -194,22 +298,30 @@ impl Collection {
 
     /// Removes a document from the collection by its ID.
     ///
+    /// The delete is logged as a tombstone holding only the document ID.
+    ///
     /// ## Arguments
     ///
     /// * `id` - The [`DocId`] of the document to remove.
     ///
     /// ## Returns
     ///
-    /// Returns [`Some`]\([`Document`]) if removed, or [`None`] if not found.
-    pub fn remove_document(&mut self, id: DocId) -> Option<Document> {
-        if let Some(offset) = self.document_indices.remove(&id)
-            && let Ok(log_entry) = self.read_log_entry_at_offset(offset)
-        {
-            self.append_to_log(&Operation::Delete, &log_entry.document)
-                .ok();
-            return Some(Document::new(id, log_entry.document));
-        }
-        None
+    /// Returns [`Ok`]\([`Some`]\([`Document`])) if removed, [`Ok`]\([`None`]) if not found,
+    /// or [`Err`]\([`String`]) if the document could not be read or its removal logged.
+    pub fn remove_document(&mut self, id: DocId) -> Result<Option<Document>, String> {
+        let position = match self.index.get(&id) {
+            Ok(Some(position)) => position,
+            Ok(None) => return Ok(None),
+            Err(e) => return Err(format!("Failed to read index: {}", e)),
+        };
+        let document = self
+            .read_log_entry_at_offset(position)
+            .and_then(|log_entry| self.resolve_document(log_entry))
+            .map_err(|e| format!("Failed to read document: {}", e))?;
+
+        self.write_delete_entry(&id, &document)
+            .map_err(|e| format!("Failed to write document removal to log: {}", e))?;
+        Ok(Some(Document::new(id, document)))
     }
 
     /// Retrieves a document by its ID.
@@ -222,23 +334,56 @@ impl Collection {
     ///
     /// Returns [`Some`]\([`Document`]) if found, or [`None`] if not present.
     pub fn get_document(&self, id: DocId) -> Option<Document> {
-        if let Some(&offset) = self.document_indices.get(&id)
-            && let Ok(log_entry) = self.read_log_entry_at_offset(offset)
+        if let Ok(Some(position)) = self.index.get(&id)
+            && let Ok(log_entry) = self.read_log_entry_at_offset(position)
+            && let Ok(document) = self.resolve_document(log_entry)
         {
-            return Some(Document::new(id, log_entry.document));
+            return Some(Document::new(id, document));
         }
         None
     }
 
-    /// Returns all documents in the collection.
-    pub fn get_documents(&self) -> Vec<Document> {
-        let mut entries = Vec::new();
-        for (id, offset) in &self.document_indices {
-            if let Ok(log_entry) = self.read_log_entry_at_offset(*offset) {
-                entries.push(Document::new(id.clone(), log_entry.document));
+    /// Returns all documents in the collection, ordered by ID.
+    ///
+    /// Reads the log in a single sequential pass, keeping only the entries
+    /// the primary index points at.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`Vec<Document>`]) with the documents, or [`Err`]\([`io::Error`])
+    /// if the primary index or the log could not be read.
+    pub fn get_documents(&self) -> io::Result<Vec<Document>> {
+        let index_entries = self.index.entries()?;
+        let slots: HashMap<LogPosition, usize> = index_entries
+            .iter()
+            .enumerate()
+            .map(|(slot, (_, position))| (*position, slot))
+            .collect();
+
+        let mut documents: Vec<Option<bson::Document>> = vec![None; index_entries.len()];
+        for item in self.scan_log_entries()? {
+            let (log_entry, position) = item?;
+            if let Some(&slot) = slots.get(&position) {
+                documents[slot] = Some(self.resolve_document(log_entry)?);
             }
         }
-        entries
+
+        index_entries
+            .into_iter()
+            .zip(documents)
+            .map(|((id, position), document)| {
+                let data = document.ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!(
+                            "The primary index points document {} at {}, where the log holds no entry",
+                            id, position
+                        ),
+                    )
+                })?;
+                Ok(Document::new(id, data))
+            })
+            .collect()
     }
 
     /// Returns the schema of this collection.
@@ -261,8 +406,105 @@ impl Collection {
         &self.id_field
     }
 
-    /// Returns the document indices map containing [`DocId`] to log offset mappings.
-    pub fn document_indices(&self) -> &HashMap<DocId, usize> {
-        &self.document_indices
+    /// Returns the durability setting of this collection.
+    pub fn durability(&self) -> Durability {
+        self.durability
+    }
+
+    /// Sets when writes to this collection's logfile are flushed to disk.
+    ///
+    /// ## Arguments
+    ///
+    /// * `durability` - The [`Durability`] to use for subsequent writes.
+    pub fn set_durability(&mut self, durability: Durability) {
+        self.durability = durability;
+    }
+
+    /// Returns the size in bytes after which writes roll over to a new log segment.
+    pub fn max_segment_size(&self) -> usize {
+        self.max_segment_size
+    }
+
+    /// Sets the size in bytes after which writes roll over to a new log segment.
+    ///
+    /// Entries are never split across segments, so an entry larger than the limit
+    /// still gets a segment of its own.
+    ///
+    /// ## Arguments
+    ///
+    /// * `max_segment_size` - The maximum segment size to use for subsequent writes.
+    pub fn set_max_segment_size(&mut self, max_segment_size: usize) {
+        self.max_segment_size = max_segment_size;
+    }
+
+    /// Returns the codec applied to the payloads of new log entries.
+    pub fn compression(&self) -> Compression {
+        self.compression
+    }
+
+    /// Sets the codec applied to the payloads of new log entries.
+    ///
+    /// Existing entries keep their codec until compaction rewrites them with the new one.
+    /// The codec is recorded with the next metadata write.
+    ///
+    /// ## Arguments
+    ///
+    /// * `compression` - The [`Compression`] codec to use for subsequent writes.
+    pub fn set_compression(&mut self, compression: Compression) {
+        self.compression = compression;
+    }
+
+    /// Returns the key encrypting the collection's files, if any.
+    pub fn encryption_key(&self) -> Option<&EncryptionKey> {
+        self.log.encryption_key()
+    }
+
+    /// Sets the key encrypting the collection's files.
+    ///
+    /// New log segments, metadata writes and newly created indexes are encrypted with the
+    /// key, while existing segments keep their encryption until compaction rewrites them.
+    /// The key itself is never persisted, and must be given again through
+    /// [`Collection::from_files_with_key`] when the collection is loaded.
+    ///
+    /// ## Arguments
+    ///
+    /// * `key` - The [`EncryptionKey`] to use, or [`None`] to store new files in the clear.
+    pub fn set_encryption_key(&mut self, key: Option<EncryptionKey>) {
+        self.index.set_encryption_key(key.clone());
+        for index in self.indexes.values_mut() {
+            index.set_encryption_key(key.clone());
+        }
+        self.log.set_encryption_key(key);
+    }
+
+    /// Returns the report of the damage found in the logfile when the collection was loaded.
+    pub fn recovery_report(&self) -> &RecoveryReport {
+        &self.recovery
+    }
+
+    /// Returns the primary index containing [`DocId`] to log position mappings.
+    pub fn primary_index(&self) -> &PrimaryIndex {
+        &self.index
+    }
+
+    /// Returns the IDs of all documents in the collection.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`Vec<DocId>`]) with the IDs ordered by key,
+    /// or [`Err`]\([`io::Error`]) if the primary index could not be read.
+    pub fn document_ids(&self) -> io::Result<Vec<DocId>> {
+        let entries = self.index.entries()?;
+        Ok(entries.into_iter().map(|(id, _)| id).collect())
+    }
+
+    /// Returns the number of documents in the collection, counted from the primary index.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`usize`]) with the number of documents,
+    /// or [`Err`]\([`io::Error`]) if the primary index could not be read.
+    pub fn document_count(&self) -> io::Result<usize> {
+        self.index.len()
     }
 }
@@ -0,0 +1,836 @@
+//! # Log Reader
+//!
+//! Provides the log entry framing, along with positioned and sequential reads
+//! over a collection's log segments.
+//!
+//! Every segment starts with a format header, followed by the entries. Every entry is
+//! stored as a frame made of a little-endian `u32` payload length, a little-endian `u32`
+//! CRC32C checksum of the payload, and the BSON payload itself. In segments whose header
+//! is flagged as encrypted, the payload is sealed with the collection's [`EncryptionKey`]
+//! and the checksum covers the sealed bytes. The sealed payload is bound to the name of
+//! the collection and the position of the frame, so it only decrypts where it was written.
+
+use crate::{
+    collection::{
+        file::LogEntry,
+        recovery::{RecoveryReport, SkippedRegion},
+        segment::{LogPosition, list_segments, segment_file_name},
+    },
+    format::{
+        FORMAT_HEADER_SIZE, FileKind, check_format_header,
+        encryption::{ENCRYPTION_OVERHEAD, EncryptionKey},
+    },
+};
+use std::{
+    collections::{HashMap, VecDeque, hash_map::Entry},
+    fs::{self, File},
+    io::{self, BufReader, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+/// The size of the frame header preceding every log entry payload.
+pub const FRAME_HEADER_SIZE: usize = 8;
+
+/// Wraps a log entry payload in a frame carrying its length and CRC32C checksum.
+///
+/// ## Arguments
+///
+/// * `payload` - The BSON bytes of the log entry.
+pub(crate) fn encode_frame(payload: &[u8]) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(FRAME_HEADER_SIZE + payload.len());
+    frame.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    frame.extend_from_slice(&crc32c::crc32c(payload).to_le_bytes());
+    frame.extend_from_slice(payload);
+    frame
+}
+
+/// Wraps a log entry payload in a frame, sealing it first if the segment is encrypted.
+///
+/// ## Arguments
+///
+/// * `payload` - The compressed BSON bytes of the log entry.
+/// * `seal` - The [`EntrySeal`] to encrypt the payload with, or [`None`] to store it in the clear.
+///
+/// ## Returns
+///
+/// Returns [`Ok`]\([`Vec`]<[`u8`]>) with the framed entry,
+/// or [`Err`]\([`io::Error`]) if the payload could not be encrypted.
+pub(crate) fn seal_frame(payload: &[u8], seal: Option<EntrySeal<'_>>) -> io::Result<Vec<u8>> {
+    match seal {
+        Some(seal) => Ok(encode_frame(&seal.seal(payload)?)),
+        None => Ok(encode_frame(payload)),
+    }
+}
+
+/// Returns the size of the frame holding a payload of the given size.
+///
+/// ## Arguments
+///
+/// * `payload_len` - The size of the compressed BSON bytes of the log entry.
+/// * `encrypted` - Whether the payload is sealed before it is framed.
+pub(crate) fn frame_len(payload_len: usize, encrypted: bool) -> usize {
+    let overhead = if encrypted { ENCRYPTION_OVERHEAD } else { 0 };
+    FRAME_HEADER_SIZE + overhead + payload_len
+}
+
+/// The key and location a log entry in an encrypted segment is sealed with.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct EntrySeal<'a> {
+    /// The key sealing the payload.
+    pub key: &'a EncryptionKey,
+    /// The name of the collection the entry belongs to.
+    pub collection: &'a str,
+    /// The position of the entry's frame.
+    pub position: LogPosition,
+}
+
+impl EntrySeal<'_> {
+    /// Builds the associated data of the entry from the segment magic, its position
+    /// and the name of its collection.
+    fn aad(&self) -> Vec<u8> {
+        let mut aad = Vec::with_capacity(20 + self.collection.len());
+        aad.extend_from_slice(FileKind::LogSegment.magic());
+        aad.extend_from_slice(&self.position.segment.to_le_bytes());
+        aad.extend_from_slice(&(self.position.offset as u64).to_le_bytes());
+        aad.extend_from_slice(self.collection.as_bytes());
+        aad
+    }
+
+    /// Encrypts the payload of the entry.
+    ///
+    /// ## Arguments
+    ///
+    /// * `payload` - The compressed BSON bytes of the entry.
+    pub(crate) fn seal(&self, payload: &[u8]) -> io::Result<Vec<u8>> {
+        self.key.encrypt(&self.aad(), payload)
+    }
+
+    /// Decrypts the payload of the entry.
+    ///
+    /// ## Arguments
+    ///
+    /// * `sealed` - The sealed payload, as stored in the frame.
+    fn open(&self, sealed: &[u8]) -> io::Result<Vec<u8>> {
+        self.key.decrypt(&self.aad(), sealed)
+    }
+}
+
+/// Splits a frame header into the payload length and the expected checksum.
+///
+/// ## Arguments
+///
+/// * `header` - The header bytes of the frame.
+fn decode_header(header: &[u8; FRAME_HEADER_SIZE]) -> (usize, u32) {
+    let length = u32::from_le_bytes([header[0], header[1], header[2], header[3]]) as usize;
+    let checksum = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+    (length, checksum)
+}
+
+/// Decodes the frame at the start of the given bytes.
+///
+/// ## Arguments
+///
+/// * `bytes` - The bytes starting at the frame header.
+/// * `seal` - The [`EntrySeal`] of the payload, or [`None`] if it is stored in the clear.
+///
+/// ## Returns
+///
+/// Returns [`Ok`]\([`Some`]\(([`LogEntry`], [`usize`]))) with the entry and the total frame size
+/// if the frame is complete, its checksum matches and its payload decodes,
+/// [`Ok`]\([`None`]) if it does not, or [`Err`]\([`io::Error`]) if an intact payload
+/// could not be decrypted.
+pub(crate) fn decode_frame(
+    bytes: &[u8],
+    seal: Option<EntrySeal<'_>>,
+) -> io::Result<Option<(LogEntry, usize)>> {
+    let Some(header) = bytes.get(..FRAME_HEADER_SIZE) else {
+        return Ok(None);
+    };
+    let (length, checksum) = decode_header(header.try_into().unwrap());
+    let Some(payload) = bytes.get(FRAME_HEADER_SIZE..FRAME_HEADER_SIZE + length) else {
+        return Ok(None);
+    };
+    if crc32c::crc32c(payload) != checksum {
+        return Ok(None);
+    }
+    Ok(decode_payload(payload, seal)?.map(|entry| (entry, FRAME_HEADER_SIZE + length)))
+}
+
+/// Decodes the payload of a frame whose checksum matched.
+///
+/// ## Arguments
+///
+/// * `payload` - The payload bytes of the frame.
+/// * `seal` - The [`EntrySeal`] of the payload, or [`None`] if it is stored in the clear.
+///
+/// ## Returns
+///
+/// Returns [`Ok`]\([`Some`]\([`LogEntry`])) if the payload decodes,
+/// [`Ok`]\([`None`]) if it is not a valid entry, or [`Err`]\([`io::Error`]) if it
+/// could not be decrypted. An intact payload failing to decrypt means the key is wrong
+/// or the entry was moved, which must not be mistaken for damage to the log.
+fn decode_payload(payload: &[u8], seal: Option<EntrySeal<'_>>) -> io::Result<Option<LogEntry>> {
+    match seal {
+        Some(seal) => {
+            let plaintext = seal.open(payload)?;
+            Ok(LogEntry::from_bytes(&plaintext).ok())
+        }
+        None => Ok(LogEntry::from_bytes(payload).ok()),
+    }
+}
+
+/// A reader over the log segments of a collection that keeps their file handles open
+/// between reads.
+///
+/// Each segment's handle is opened lazily on the first read from it and shared between
+/// clones of the owning collection. Since segments are only ever appended to, the handles
+/// stay valid until a segment is replaced, at which point [`LogReader::reset`] must be called.
+#[derive(Debug, Clone)]
+pub struct LogReader {
+    /// The name of the collection the log belongs to, which encrypted entries are bound to.
+    collection: String,
+    /// The directory holding the log segments.
+    dir: PathBuf,
+    /// The lazily opened file handles used for positioned reads, keyed by segment number,
+    /// along with whether the segment is encrypted.
+    files: Arc<Mutex<HashMap<u64, (File, bool)>>>,
+    /// The key decrypting encrypted segments, if one was given.
+    key: Option<EncryptionKey>,
+}
+
+impl LogReader {
+    /// Creates a new [`LogReader`] for the log segments in the given directory.
+    ///
+    /// ## Arguments
+    ///
+    /// * `collection` - The name of the collection the log belongs to.
+    /// * `dir` - The directory holding the segments. No file is opened until the first read.
+    pub fn new(collection: impl Into<String>, dir: impl Into<PathBuf>) -> Self {
+        Self {
+            collection: collection.into(),
+            dir: dir.into(),
+            files: Arc::new(Mutex::new(HashMap::new())),
+            key: None,
+        }
+    }
+
+    /// Returns the directory holding the log segments.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Returns the key decrypting encrypted segments, if one was given.
+    pub fn encryption_key(&self) -> Option<&EncryptionKey> {
+        self.key.as_ref()
+    }
+
+    /// Sets the key decrypting encrypted segments.
+    ///
+    /// ## Arguments
+    ///
+    /// * `key` - The [`EncryptionKey`] to use, or [`None`] to read plaintext segments only.
+    pub fn set_encryption_key(&mut self, key: Option<EncryptionKey>) {
+        self.key = key;
+    }
+
+    /// Returns the [`EntrySeal`] an entry written at the given position is encrypted with.
+    ///
+    /// ## Arguments
+    ///
+    /// * `position` - The [`LogPosition`] of the entry's frame.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Some`]\([`EntrySeal`]) if a key was given, or [`None`] if entries are
+    /// stored in the clear.
+    pub(crate) fn entry_seal(&self, position: LogPosition) -> Option<EntrySeal<'_>> {
+        self.key.as_ref().map(|key| EntrySeal {
+            key,
+            collection: &self.collection,
+            position,
+        })
+    }
+
+    /// Returns the key to decode the entries of a segment with.
+    ///
+    /// ## Arguments
+    ///
+    /// * `segment` - The number of the segment, used in the error message.
+    /// * `encrypted` - Whether the segment's header is flagged as encrypted.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`Some`]\([`EncryptionKey`])) for an encrypted segment,
+    /// [`Ok`]\([`None`]) for a plaintext one, or [`Err`]\([`io::Error`]) if the segment
+    /// is encrypted but no key was given.
+    fn segment_key(&self, segment: u64, encrypted: bool) -> io::Result<Option<&EncryptionKey>> {
+        if !encrypted {
+            return Ok(None);
+        }
+        match &self.key {
+            Some(key) => Ok(Some(key)),
+            None => Err(missing_key_error(segment)),
+        }
+    }
+
+    /// Runs an operation against the open handle of a segment, opening it first if needed.
+    ///
+    /// ## Arguments
+    ///
+    /// * `segment` - The number of the segment.
+    /// * `operation` - The operation to run against the handle and the segment's encryption flag.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`] with the operation's result,
+    /// or [`Err`]\([`io::Error`]) if the segment does not exist, is not in the current
+    /// format version, or the operation failed.
+    fn with_segment<T>(
+        &self,
+        segment: u64,
+        operation: impl FnOnce(&mut File, bool) -> io::Result<T>,
+    ) -> io::Result<T> {
+        let mut guard = self
+            .files
+            .lock()
+            .map_err(|_| io::Error::other("Log reader lock is poisoned"))?;
+
+        let (file, encrypted) = match guard.entry(segment) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let mut file =
+                    File::open(self.segment_path(segment)).map_err(|e| match e.kind() {
+                        io::ErrorKind::NotFound => io::Error::new(
+                            io::ErrorKind::NotFound,
+                            format!("Log segment {} does not exist", segment),
+                        ),
+                        _ => e,
+                    })?;
+                let encrypted = check_segment_header(&mut file, segment)?;
+                entry.insert((file, encrypted))
+            }
+        };
+        operation(file, *encrypted)
+    }
+
+    /// Checks whether the given segment is encrypted.
+    ///
+    /// ## Arguments
+    ///
+    /// * `segment` - The number of the segment.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`bool`]) with whether the segment's header is flagged as encrypted,
+    /// or [`Err`]\([`io::Error`]) if the segment does not exist or its header is invalid.
+    pub fn is_encrypted(&self, segment: u64) -> io::Result<bool> {
+        self.with_segment(segment, |_, encrypted| Ok(encrypted))
+    }
+
+    /// Returns the path to the given log segment.
+    ///
+    /// ## Arguments
+    ///
+    /// * `segment` - The number of the segment.
+    pub fn segment_path(&self, segment: u64) -> PathBuf {
+        self.dir.join(segment_file_name(segment))
+    }
+
+    /// Returns the numbers of the segments present on disk, in ascending order.
+    pub fn segments(&self) -> io::Result<Vec<u64>> {
+        list_segments(&self.dir)
+    }
+
+    /// Returns the current length of the given segment, or zero if it does not exist.
+    ///
+    /// ## Arguments
+    ///
+    /// * `segment` - The number of the segment.
+    pub fn segment_len(&self, segment: u64) -> io::Result<usize> {
+        match fs::metadata(self.segment_path(segment)) {
+            Ok(metadata) => Ok(metadata.len() as usize),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(0),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Returns the position just past the last entry of the log.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`LogPosition`]) with the last segment and its length, which is the
+    /// start of segment zero if no segment exists yet, or [`Err`]\([`io::Error`]) on I/O failure.
+    pub fn end(&self) -> io::Result<LogPosition> {
+        match self.segments()?.last() {
+            Some(&segment) => Ok(LogPosition::new(segment, self.segment_len(segment)?)),
+            None => Ok(LogPosition::default()),
+        }
+    }
+
+    /// Checks whether the given position lies within the entries currently on disk.
+    ///
+    /// ## Arguments
+    ///
+    /// * `position` - The [`LogPosition`] to check.
+    pub fn contains(&self, position: LogPosition) -> io::Result<bool> {
+        Ok(position <= self.end()? && position.offset <= self.segment_len(position.segment)?)
+    }
+
+    /// Closes every open file handle, so the next reads reopen the segments.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\(()) if the handles were closed,
+    /// or [`Err`]\([`io::Error`]) if the handle lock is poisoned.
+    pub fn reset(&self) -> io::Result<()> {
+        let mut guard = self
+            .files
+            .lock()
+            .map_err(|_| io::Error::other("Log reader lock is poisoned"))?;
+        guard.clear();
+        Ok(())
+    }
+
+    /// Reads the single log entry starting at the given position.
+    ///
+    /// Only the bytes of the entry itself are read: its frame header first,
+    /// followed by the payload, which is verified against the stored checksum
+    /// and decrypted if the segment is encrypted.
+    /// The segment's format header is checked when the segment is first opened.
+    ///
+    /// ## Arguments
+    ///
+    /// * `position` - The [`LogPosition`] where the entry's frame begins.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`LogEntry`]) if successful,
+    /// or [`Err`]\([`io::Error`]) if the position is invalid, the segment is not in the
+    /// current format version, the checksum does not match, the entry could not be
+    /// decrypted, or the read failed.
+    pub fn read_at(&self, position: LogPosition) -> io::Result<LogEntry> {
+        self.with_segment(position.segment, |file, encrypted| {
+            let seal = self
+                .segment_key(position.segment, encrypted)?
+                .map(|key| EntrySeal {
+                    key,
+                    collection: &self.collection,
+                    position,
+                });
+            read_entry_at(file, position, seal)
+        })
+    }
+
+    /// Opens a sequential reader over the log entries starting at the given position.
+    ///
+    /// The returned iterator uses its own buffered file handles, so full scans
+    /// do not contend with positioned reads. It moves on to the following segments
+    /// once the starting one is exhausted.
+    ///
+    /// ## Arguments
+    ///
+    /// * `start` - The [`LogPosition`] where the first entry's frame begins. If its segment
+    ///   no longer exists, the scan starts at the beginning of the next one.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`LogEntries`]) positioned at `start`, which yields nothing if no
+    /// segment exists, or [`Err`]\([`io::Error`]) if the segments could not be listed.
+    pub fn entries_from(&self, start: LogPosition) -> io::Result<LogEntries> {
+        let pending = self
+            .segments()?
+            .into_iter()
+            .filter(|&segment| segment >= start.segment)
+            .collect();
+
+        Ok(LogEntries {
+            collection: self.collection.clone(),
+            dir: self.dir.clone(),
+            key: self.key.clone(),
+            pending,
+            start,
+            reader: None,
+            encrypted: false,
+            position: start,
+            file_len: 0,
+            report: RecoveryReport::default(),
+        })
+    }
+}
+
+/// A sequential iterator over the entries of a collection's log segments.
+///
+/// Yields each valid entry together with its position. When a frame fails its checksum,
+/// the scan resynchronizes on the next valid frame and records the damaged bytes as a
+/// [`SkippedRegion`]. Damage running to the end of a segment is skipped as well if later
+/// segments follow, and is otherwise considered a torn tail that ends the scan.
+/// Both are available through [`LogEntries::report`].
+#[derive(Debug)]
+pub struct LogEntries {
+    /// The name of the collection the log belongs to.
+    collection: String,
+    /// The directory holding the log segments.
+    dir: PathBuf,
+    /// The key decrypting encrypted segments, if one was given.
+    key: Option<EncryptionKey>,
+    /// The segments still to be read, in ascending order.
+    pending: VecDeque<u64>,
+    /// The position the scan started at.
+    start: LogPosition,
+    /// The buffered reader over the current segment, or [`None`] between segments.
+    reader: Option<BufReader<File>>,
+    /// Whether the current segment is encrypted.
+    encrypted: bool,
+    /// The position of the next frame to read.
+    position: LogPosition,
+    /// The length of the current segment when it was opened.
+    file_len: usize,
+    /// The damage found so far.
+    report: RecoveryReport,
+}
+
+impl LogEntries {
+    /// Returns the damage found by the scan so far.
+    ///
+    /// The report is complete once the iterator has been exhausted.
+    pub fn report(&self) -> &RecoveryReport {
+        &self.report
+    }
+
+    /// Consumes the iterator, returning the damage found by the scan.
+    pub fn into_report(self) -> RecoveryReport {
+        self.report
+    }
+
+    /// Opens the next pending segment that still exists and holds a format header.
+    ///
+    /// Empty segments are passed over. A segment cut off within its header is treated
+    /// like damage running to its end.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\(`true`) if a segment was opened, [`Ok`]\(`false`) if none is left,
+    /// or [`Err`]\([`io::Error`]) if a segment could not be opened, is not in the
+    /// current format version, or is encrypted while no key was given.
+    fn open_next_segment(&mut self) -> io::Result<bool> {
+        while let Some(segment) = self.pending.pop_front() {
+            let mut file = match File::open(self.dir.join(segment_file_name(segment))) {
+                Ok(file) => file,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e),
+            };
+
+            self.file_len = file.metadata()?.len() as usize;
+            if self.file_len == 0 {
+                continue;
+            }
+            if self.file_len < FORMAT_HEADER_SIZE {
+                let position = LogPosition::new(segment, 0);
+                if self.pending.is_empty() {
+                    self.report.truncated_at = Some(position);
+                    self.report.bytes_truncated = self.file_len;
+                    return Ok(false);
+                }
+                self.report.skipped.push(SkippedRegion {
+                    position,
+                    length: self.file_len,
+                });
+                continue;
+            }
+            self.encrypted = check_segment_header(&mut file, segment)?;
+            if self.encrypted && self.key.is_none() {
+                return Err(missing_key_error(segment));
+            }
+
+            let offset = if segment == self.start.segment {
+                self.start.offset.max(FORMAT_HEADER_SIZE)
+            } else {
+                FORMAT_HEADER_SIZE
+            };
+            let mut reader = BufReader::new(file);
+            reader.seek(SeekFrom::Start(offset as u64))?;
+            self.reader = Some(reader);
+            self.position = LogPosition::new(segment, offset);
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    /// Reads the next valid frame, resynchronizing past any damaged ones.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`Some`]\(([`LogEntry`], [`LogPosition`]))) with the next entry and its
+    /// position, [`Ok`]\([`None`]) at the end of the log, or [`Err`]\([`io::Error`]) if the read failed.
+    fn read_next(&mut self) -> io::Result<Option<(LogEntry, LogPosition)>> {
+        loop {
+            if self.reader.is_none() && !self.open_next_segment()? {
+                return Ok(None);
+            }
+            let Some(reader) = self.reader.as_mut() else {
+                return Ok(None);
+            };
+
+            let entry_position = self.position;
+            let offset = entry_position.offset;
+            if offset >= self.file_len {
+                self.reader = None;
+                continue;
+            }
+
+            let seal = self
+                .key
+                .as_ref()
+                .filter(|_| self.encrypted)
+                .map(|key| EntrySeal {
+                    key,
+                    collection: &self.collection,
+                    position: entry_position,
+                });
+            if let Some((entry, frame_len)) = read_frame(reader, offset, self.file_len, seal)? {
+                self.position.offset += frame_len;
+                self.report.entries_verified += 1;
+                return Ok(Some((entry, entry_position)));
+            }
+
+            match find_next_frame(reader, offset + 1, self.file_len, seal)? {
+                Some((entry, next_offset, frame_len)) => {
+                    self.report.skipped.push(SkippedRegion {
+                        position: entry_position,
+                        length: next_offset - offset,
+                    });
+                    reader.seek(SeekFrom::Start((next_offset + frame_len) as u64))?;
+                    self.position.offset = next_offset + frame_len;
+                    self.report.entries_verified += 1;
+                    return Ok(Some((
+                        entry,
+                        LogPosition::new(entry_position.segment, next_offset),
+                    )));
+                }
+                None if self.pending.is_empty() => {
+                    self.report.truncated_at = Some(entry_position);
+                    self.report.bytes_truncated = self.file_len - offset;
+                    return Ok(None);
+                }
+                None => {
+                    self.report.skipped.push(SkippedRegion {
+                        position: entry_position,
+                        length: self.file_len - offset,
+                    });
+                    self.reader = None;
+                }
+            }
+        }
+    }
+}
+
+impl Iterator for LogEntries {
+    type Item = io::Result<(LogEntry, LogPosition)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.read_next() {
+            Ok(Some(entry)) => Some(Ok(entry)),
+            Ok(None) => {
+                self.reader = None;
+                self.pending.clear();
+                None
+            }
+            Err(e) => {
+                self.reader = None;
+                self.pending.clear();
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+/// Checks the format header at the start of a segment.
+///
+/// ## Arguments
+///
+/// * `file` - The file handle of the segment, which is left positioned past the header.
+/// * `segment` - The number of the segment, used in the error message.
+///
+/// ## Returns
+///
+/// Returns [`Ok`]\([`bool`]) with whether the segment is encrypted if it is in the current
+/// format version, or [`Err`]\([`io::Error`]) if it is not or the header could not be read.
+fn check_segment_header(file: &mut File, segment: u64) -> io::Result<bool> {
+    let mut header = [0u8; FORMAT_HEADER_SIZE];
+    file.seek(SeekFrom::Start(0))?;
+    file.read_exact(&mut header)
+        .and_then(|()| check_format_header(FileKind::LogSegment, &header))
+        .map_err(|e| io::Error::new(e.kind(), format!("Log segment {}: {}", segment, e)))
+}
+
+/// Builds the error returned when an encrypted segment is read without a key.
+///
+/// ## Arguments
+///
+/// * `segment` - The number of the segment.
+fn missing_key_error(segment: u64) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::PermissionDenied,
+        format!(
+            "Log segment {} is encrypted, but no encryption key was given",
+            segment
+        ),
+    )
+}
+
+/// Reads the single log entry starting at the given position of a segment.
+///
+/// ## Arguments
+///
+/// * `file` - The file handle of the segment.
+/// * `position` - The [`LogPosition`] where the entry's frame begins.
+/// * `seal` - The [`EntrySeal`] of the payload, or [`None`] if it is stored in the clear.
+///
+/// ## Returns
+///
+/// Returns [`Ok`]\([`LogEntry`]) if successful,
+/// or [`Err`]\([`io::Error`]) if the position is invalid, the checksum does not match,
+/// the entry could not be decrypted, or the read failed.
+fn read_entry_at(
+    file: &mut File,
+    position: LogPosition,
+    seal: Option<EntrySeal<'_>>,
+) -> io::Result<LogEntry> {
+    let offset = position.offset;
+    if offset < FORMAT_HEADER_SIZE {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Offset lies within the segment header",
+        ));
+    }
+    let file_len = file.metadata()?.len() as usize;
+    if offset >= file_len {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Offset is beyond end of file",
+        ));
+    }
+    if offset + FRAME_HEADER_SIZE > file_len {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "Not enough bytes for log entry header",
+        ));
+    }
+
+    file.seek(SeekFrom::Start(offset as u64))?;
+    let mut header = [0u8; FRAME_HEADER_SIZE];
+    file.read_exact(&mut header)?;
+    let (length, checksum) = decode_header(&header);
+
+    if offset + FRAME_HEADER_SIZE + length > file_len {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "Log entry extends beyond end of file",
+        ));
+    }
+
+    let mut payload = vec![0u8; length];
+    file.read_exact(&mut payload)?;
+
+    if crc32c::crc32c(&payload) != checksum {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Checksum mismatch for log entry at {}", position),
+        ));
+    }
+
+    match seal {
+        Some(seal) => LogEntry::from_bytes(&seal.open(&payload)?),
+        None => LogEntry::from_bytes(&payload),
+    }
+}
+
+/// Reads and verifies the frame at the reader's current position.
+///
+/// ## Arguments
+///
+/// * `reader` - The reader, positioned at the frame header.
+/// * `offset` - The offset of the frame header.
+/// * `file_len` - The length of the segment.
+/// * `seal` - The [`EntrySeal`] of the payload, or [`None`] if it is stored in the clear.
+///
+/// ## Returns
+///
+/// Returns [`Ok`]\([`Some`]\(([`LogEntry`], [`usize`]))) with the entry and the frame size,
+/// [`Ok`]\([`None`]) if the frame is incomplete or damaged,
+/// or [`Err`]\([`io::Error`]) if the read or the decryption failed.
+fn read_frame(
+    reader: &mut BufReader<File>,
+    offset: usize,
+    file_len: usize,
+    seal: Option<EntrySeal<'_>>,
+) -> io::Result<Option<(LogEntry, usize)>> {
+    if offset + FRAME_HEADER_SIZE > file_len {
+        return Ok(None);
+    }
+
+    let mut header = [0u8; FRAME_HEADER_SIZE];
+    reader.read_exact(&mut header)?;
+    let (length, checksum) = decode_header(&header);
+
+    if offset + FRAME_HEADER_SIZE + length > file_len {
+        return Ok(None);
+    }
+
+    let mut payload = vec![0u8; length];
+    reader.read_exact(&mut payload)?;
+
+    if crc32c::crc32c(&payload) != checksum {
+        return Ok(None);
+    }
+
+    Ok(decode_payload(&payload, seal)?.map(|entry| (entry, FRAME_HEADER_SIZE + length)))
+}
+
+/// Searches for the first valid frame at or after the given offset.
+///
+/// Only used after a damaged frame, so the remainder of the segment is read at once.
+///
+/// ## Arguments
+///
+/// * `reader` - The reader over the segment.
+/// * `start` - The offset to start searching from.
+/// * `file_len` - The length of the segment.
+/// * `seal` - The [`EntrySeal`] of the payloads, whose position is moved to each offset tried,
+///   or [`None`] if they are stored in the clear.
+///
+/// ## Returns
+///
+/// Returns [`Ok`]\([`Some`]\(([`LogEntry`], [`usize`], [`usize`]))) with the entry, its offset
+/// and its frame size, [`Ok`]\([`None`]) if no valid frame follows,
+/// or [`Err`]\([`io::Error`]) if the read or the decryption failed.
+fn find_next_frame(
+    reader: &mut BufReader<File>,
+    start: usize,
+    file_len: usize,
+    seal: Option<EntrySeal<'_>>,
+) -> io::Result<Option<(LogEntry, usize, usize)>> {
+    if start >= file_len {
+        return Ok(None);
+    }
+
+    reader.seek(SeekFrom::Start(start as u64))?;
+    let mut remainder = Vec::with_capacity(file_len - start);
+    reader
+        .by_ref()
+        .take((file_len - start) as u64)
+        .read_to_end(&mut remainder)?;
+
+    for position in 0..remainder.len() {
+        let seal = seal.map(|seal| EntrySeal {
+            position: LogPosition::new(seal.position.segment, start + position),
+            ..seal
+        });
+        if let Some((entry, frame_len)) = decode_frame(&remainder[position..], seal)? {
+            return Ok(Some((entry, start + position, frame_len)));
+        }
+    }
+
+    Ok(None)
+}
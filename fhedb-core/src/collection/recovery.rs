@@ -0,0 +1,88 @@
+//! # Log Recovery
+//!
+//! Provides the [`RecoveryReport`] describing the damage found while loading a collection.
+
+use crate::collection::segment::LogPosition;
+use std::fmt;
+
+/// A range of bytes in a log segment that did not contain a valid entry and was skipped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SkippedRegion {
+    /// The position of the first damaged byte.
+    pub position: LogPosition,
+    /// The number of bytes skipped before the next valid entry.
+    pub length: usize,
+}
+
+/// The outcome of checking a collection's files for damage when loading it.
+///
+/// Produced when a collection is loaded from disk. The logfile scan covers the part
+/// of the log that is not yet reflected in the primary index.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RecoveryReport {
+    /// The number of valid entries found by the scan.
+    pub entries_verified: usize,
+    /// The damaged regions that were skipped because a valid entry followed them.
+    pub skipped: Vec<SkippedRegion>,
+    /// The position at which a torn tail was cut off from the last segment, if any.
+    pub truncated_at: Option<LogPosition>,
+    /// The number of bytes removed from the end of the last segment.
+    pub bytes_truncated: usize,
+    /// Whether the metadata file was missing or damaged and restored from another generation.
+    pub metadata_restored: bool,
+    /// The positions of entries whose document ID is too long to be indexed, which were
+    /// left out of the indexes.
+    pub unindexed: Vec<LogPosition>,
+    /// Whether a damaged index file was discarded and rebuilt from the log.
+    pub index_rebuilt: bool,
+}
+
+impl RecoveryReport {
+    /// Checks whether the scan found no damage at all.
+    pub fn is_clean(&self) -> bool {
+        self.skipped.is_empty()
+            && self.truncated_at.is_none()
+            && !self.metadata_restored
+            && self.unindexed.is_empty()
+            && !self.index_rebuilt
+    }
+
+    /// Returns the total number of bytes that could not be recovered.
+    pub fn bytes_lost(&self) -> usize {
+        self.skipped
+            .iter()
+            .map(|region| region.length)
+            .sum::<usize>()
+            + self.bytes_truncated
+    }
+}
+
+impl fmt::Display for RecoveryReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} entries verified", self.entries_verified)?;
+        for region in &self.skipped {
+            write!(
+                f,
+                ", skipped {} bytes at {}",
+                region.length, region.position
+            )?;
+        }
+        if let Some(position) = self.truncated_at {
+            write!(
+                f,
+                ", truncated {} bytes at {}",
+                self.bytes_truncated, position
+            )?;
+        }
+        if self.metadata_restored {
+            write!(f, ", metadata restored from another generation")?;
+        }
+        for position in &self.unindexed {
+            write!(f, ", left unindexed entry at {}", position)?;
+        }
+        if self.index_rebuilt {
+            write!(f, ", damaged index rebuilt")?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,101 @@
+//! # Log Segments
+//!
+//! Provides the [`LogPosition`] type addressing entries across a collection's numbered
+//! log segments, along with the naming of segment files.
+//!
+//! A collection's log is split into segment files named `logfile.000000.log`,
+//! `logfile.000001.log` and so on. Writes always go to the segment with the highest
+//! number, and a new segment is started once the current one reaches the collection's
+//! maximum segment size, so older segments are never appended to again.
+
+use std::{fmt, fs, io, path::Path};
+
+/// The default maximum size of a log segment in bytes.
+pub const DEFAULT_MAX_SEGMENT_SIZE: usize = 64 * 1024 * 1024;
+
+/// The name of the single logfile used before logs were split into segments.
+pub(crate) const LEGACY_LOGFILE: &str = "logfile.log";
+
+/// The position of a log entry, made of its segment number and its byte offset in that segment.
+///
+/// Positions are ordered by segment first, which matches the order entries were written in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct LogPosition {
+    /// The number of the segment holding the entry.
+    pub segment: u64,
+    /// The byte offset in the segment where the entry's frame begins.
+    pub offset: usize,
+}
+
+impl LogPosition {
+    /// Creates a new [`LogPosition`].
+    ///
+    /// ## Arguments
+    ///
+    /// * `segment` - The number of the segment.
+    /// * `offset` - The byte offset in the segment.
+    pub fn new(segment: u64, offset: usize) -> Self {
+        Self { segment, offset }
+    }
+}
+
+impl fmt::Display for LogPosition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.segment, self.offset)
+    }
+}
+
+/// Returns the file name of the given log segment.
+///
+/// ## Arguments
+///
+/// * `segment` - The number of the segment.
+pub fn segment_file_name(segment: u64) -> String {
+    format!("logfile.{:06}.log", segment)
+}
+
+/// Parses the segment number out of a segment file name.
+///
+/// ## Arguments
+///
+/// * `name` - The file name to parse.
+///
+/// ## Returns
+///
+/// Returns [`Some`]\([`u64`]) with the segment number if the name is a segment file name,
+/// or [`None`] otherwise.
+pub fn parse_segment_file_name(name: &str) -> Option<u64> {
+    let number = name.strip_prefix("logfile.")?.strip_suffix(".log")?;
+    if number.is_empty() || !number.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+    number.parse().ok()
+}
+
+/// Lists the segment numbers present in a collection directory, in ascending order.
+///
+/// ## Arguments
+///
+/// * `dir` - The collection directory.
+///
+/// ## Returns
+///
+/// Returns [`Ok`]\([`Vec`]<[`u64`]>) with the segment numbers, which is empty if the directory
+/// does not exist, or [`Err`]\([`io::Error`]) if the directory could not be read.
+pub fn list_segments(dir: &Path) -> io::Result<Vec<u64>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+
+    let mut segments = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        if let Some(segment) = entry.file_name().to_str().and_then(parse_segment_file_name) {
+            segments.push(segment);
+        }
+    }
+    segments.sort_unstable();
+    Ok(segments)
+}
@@ -0,0 +1,456 @@
+//! # Database Backup
+//!
+//! Provides online backups of a database into a single portable archive, and their restore.
+//!
+//! A backup is taken in two steps. [`Database::snapshot`] captures the end of every
+//! collection's log and opens its segments, which only takes shared access to the database
+//! and is quick. [`DatabaseSnapshot::write_archive`] then copies the captured bytes, while
+//! writes carry on. Since segments are only appended to, and compaction replaces them
+//! rather than rewriting them in place, the open handles keep reading the captured state.
+//!
+//! The archive starts with a format header, followed by one entry per file. Each entry is
+//! made of the little-endian `u32` length of its path, the path, the little-endian `u64`
+//! length of its data, the data and a little-endian `u32` CRC32C checksum of the data.
+//! An entry with an empty path ends the archive. Indexes are not archived, as
+//! they are rebuilt from the logs on restore.
+
+use crate::{
+    collection::{
+        file::{METADATA_FILE, sync_dir},
+        segment::segment_file_name,
+    },
+    database::Database,
+    format::encryption::EncryptionKey,
+    format::{FORMAT_HEADER_SIZE, FileKind, check_format_header, encode_format_header},
+};
+use std::{
+    fmt,
+    fs::{self, File, OpenOptions},
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::{Component, Path, PathBuf},
+};
+
+/// The size of the buffer used to copy file contents into and out of archives.
+const COPY_BUFFER_SIZE: usize = 64 * 1024;
+
+/// The contents of a file captured by a [`DatabaseSnapshot`].
+#[derive(Debug)]
+enum SnapshotSource {
+    /// Contents encoded while the snapshot was taken.
+    Bytes(Vec<u8>),
+    /// The first `len` bytes of an open log segment.
+    Segment {
+        /// The handle of the segment, opened while the snapshot was taken.
+        file: File,
+        /// The length of the segment when the snapshot was taken.
+        len: u64,
+    },
+}
+
+/// A file captured by a [`DatabaseSnapshot`].
+#[derive(Debug)]
+struct SnapshotFile {
+    /// The path of the file within the archive, made of the collection and file names.
+    path: String,
+    /// The captured contents.
+    source: SnapshotSource,
+}
+
+/// A consistent view of every collection of a database, ready to be written to an archive.
+#[derive(Debug)]
+pub struct DatabaseSnapshot {
+    /// The number of collections captured.
+    collections: usize,
+    /// The files captured.
+    files: Vec<SnapshotFile>,
+}
+
+/// A summary of a written backup archive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct BackupReport {
+    /// The number of collections in the archive.
+    pub collections: usize,
+    /// The number of files in the archive.
+    pub files: usize,
+    /// The size of the archive in bytes.
+    pub bytes: u64,
+}
+
+impl fmt::Display for BackupReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} collections in {} files, {} bytes",
+            self.collections, self.files, self.bytes
+        )
+    }
+}
+
+impl DatabaseSnapshot {
+    /// Returns the number of collections captured.
+    pub fn collection_count(&self) -> usize {
+        self.collections
+    }
+
+    /// Writes the captured files to a backup archive.
+    ///
+    /// The archive is written to a temporary file next to the given path and flushed to
+    /// disk before it is moved into place, so an interrupted backup never leaves a partial
+    /// archive behind under the final name.
+    ///
+    /// ## Arguments
+    ///
+    /// * `path` - The path of the archive to write. An existing file is replaced.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`BackupReport`]) describing the archive,
+    /// or [`Err`]\([`io::Error`]) if a captured file could not be read or the write failed.
+    pub fn write_archive(self, path: impl AsRef<Path>) -> io::Result<BackupReport> {
+        let path = path.as_ref();
+        let mut temp_name = path.file_name().unwrap_or_default().to_os_string();
+        temp_name.push(".tmp");
+        let temp_path = path.with_file_name(temp_name);
+
+        let mut report = BackupReport {
+            collections: self.collections,
+            files: self.files.len(),
+            bytes: 0,
+        };
+        let result = self.write_entries(&temp_path).and_then(|bytes| {
+            report.bytes = bytes;
+            fs::rename(&temp_path, path)
+        });
+        if let Err(e) = result {
+            let _ = fs::remove_file(&temp_path);
+            return Err(e);
+        }
+
+        if let Some(parent) = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            sync_dir(parent)?;
+        }
+        Ok(report)
+    }
+
+    /// Writes the archive header and entries to the given file.
+    ///
+    /// ## Arguments
+    ///
+    /// * `path` - The path of the file to write.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`u64`]) with the number of bytes written,
+    /// or [`Err`]\([`io::Error`]) if the write failed.
+    fn write_entries(self, path: &Path) -> io::Result<u64> {
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(true)
+            .write(true)
+            .open(path)?;
+        let mut writer = BufWriter::new(file);
+        writer.write_all(&encode_format_header(FileKind::Backup, false))?;
+        let mut bytes = FORMAT_HEADER_SIZE as u64;
+
+        for snapshot_file in self.files {
+            let len = match &snapshot_file.source {
+                SnapshotSource::Bytes(contents) => contents.len() as u64,
+                SnapshotSource::Segment { len, .. } => *len,
+            };
+            writer.write_all(&(snapshot_file.path.len() as u32).to_le_bytes())?;
+            writer.write_all(snapshot_file.path.as_bytes())?;
+            writer.write_all(&len.to_le_bytes())?;
+
+            let checksum = match snapshot_file.source {
+                SnapshotSource::Bytes(contents) => {
+                    writer.write_all(&contents)?;
+                    crc32c::crc32c(&contents)
+                }
+                SnapshotSource::Segment { file, len } => {
+                    copy_with_checksum(&mut file.take(len), &mut writer, len)?
+                }
+            };
+            writer.write_all(&checksum.to_le_bytes())?;
+            bytes += 4 + snapshot_file.path.len() as u64 + 8 + len + 4;
+        }
+        writer.write_all(&0u32.to_le_bytes())?;
+        bytes += 4;
+
+        writer
+            .into_inner()
+            .map_err(|e| e.into_error())?
+            .sync_all()?;
+        Ok(bytes)
+    }
+}
+
+/// Backup and restore operations for databases.
+impl Database {
+    /// Captures a consistent view of every collection, to be written to a backup archive.
+    ///
+    /// For every collection, the end of its log is captured along with handles to its
+    /// segments, and its metadata is encoded to match that end. Only shared access is
+    /// needed, so writes are held back just for the duration of this call.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`DatabaseSnapshot`]) if successful,
+    /// or [`Err`]\([`io::Error`]) if a segment could not be opened or a metadata encoded.
+    pub fn snapshot(&self) -> io::Result<DatabaseSnapshot> {
+        let mut files = Vec::new();
+        let mut names: Vec<&String> = self.collections.keys().collect();
+        names.sort();
+
+        for name in &names {
+            let collection = &self.collections[*name];
+            let end = collection.log.end()?;
+            files.push(SnapshotFile {
+                path: format!("{}/{}", name, METADATA_FILE),
+                source: SnapshotSource::Bytes(collection.encode_metadata(end)?),
+            });
+
+            for segment in collection.log.segments()? {
+                if segment > end.segment {
+                    break;
+                }
+                let len = if segment == end.segment {
+                    end.offset as u64
+                } else {
+                    collection.log.segment_len(segment)? as u64
+                };
+                if len == 0 {
+                    continue;
+                }
+                files.push(SnapshotFile {
+                    path: format!("{}/{}", name, segment_file_name(segment)),
+                    source: SnapshotSource::Segment {
+                        file: File::open(collection.log.segment_path(segment))?,
+                        len,
+                    },
+                });
+            }
+        }
+
+        Ok(DatabaseSnapshot {
+            collections: names.len(),
+            files,
+        })
+    }
+
+    /// Backs up every collection into a single archive, while the database stays in use.
+    ///
+    /// ## Arguments
+    ///
+    /// * `path` - The path of the archive to write. An existing file is replaced.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`BackupReport`]) describing the archive,
+    /// or [`Err`]\([`io::Error`]) if the backup failed.
+    pub fn backup(&self, path: impl AsRef<Path>) -> io::Result<BackupReport> {
+        self.snapshot()?.write_archive(path)
+    }
+
+    /// Restores a database from a backup archive.
+    ///
+    /// ## Arguments
+    ///
+    /// * `archive_path` - The path of the archive written by [`Database::backup`].
+    /// * `name` - The name to restore the database under, which may differ from the
+    ///   name it was backed up from. An existing database of that name is replaced.
+    /// * `base_path` - The base path where databases are stored.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`Database`]) with the restored database,
+    /// or [`Err`]\([`io::Error`]) if the archive is invalid or the restore failed.
+    pub fn restore(
+        archive_path: impl AsRef<Path>,
+        name: impl Into<String>,
+        base_path: impl Into<PathBuf>,
+    ) -> io::Result<Self> {
+        Self::restore_with_key(archive_path, name, base_path, None)
+    }
+
+    /// Restores a database whose collections may be encrypted from a backup archive.
+    ///
+    /// The archive is extracted into a staging directory, checking every file against its
+    /// checksum, and the staged database is loaded through [`Database::from_files_with_key`]
+    /// before it replaces any existing database of the same name. The existing database is
+    /// moved aside until the restored one has been loaded from its final location, and
+    /// moved back if that load fails, so a failed restore leaves it in place.
+    ///
+    /// ## Arguments
+    ///
+    /// * `archive_path` - The path of the archive written by [`Database::backup`].
+    /// * `name` - The name to restore the database under, which may differ from the
+    ///   name it was backed up from. An existing database of that name is replaced.
+    /// * `base_path` - The base path where databases are stored.
+    /// * `key` - The [`EncryptionKey`] the collections were encrypted with, if any.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`Database`]) with the restored database,
+    /// or [`Err`]\([`io::Error`]) if the archive is invalid or the restore failed.
+    pub fn restore_with_key(
+        archive_path: impl AsRef<Path>,
+        name: impl Into<String>,
+        base_path: impl Into<PathBuf>,
+        key: Option<EncryptionKey>,
+    ) -> io::Result<Self> {
+        let name = name.into();
+        let base_path = base_path.into();
+        let target = base_path.join(&name);
+        let staging = base_path.join(format!(".{}.restore", name));
+        let replaced = base_path.join(format!(".{}.replaced", name));
+
+        if staging.exists() {
+            fs::remove_dir_all(&staging)?;
+        }
+        fs::create_dir_all(staging.join(&name))?;
+        let staged = extract_archive(archive_path.as_ref(), &staging.join(&name))
+            .and_then(|_| Self::from_files_with_key(&name, &staging, key.clone()));
+        if let Err(e) = staged {
+            let _ = fs::remove_dir_all(&staging);
+            return Err(e);
+        }
+
+        if replaced.exists() {
+            fs::remove_dir_all(&replaced)?;
+        }
+        if target.exists() {
+            fs::rename(&target, &replaced)?;
+        }
+        let restored = fs::rename(staging.join(&name), &target)
+            .and_then(|_| sync_dir(&base_path))
+            .and_then(|_| Self::from_files_with_key(&name, &base_path, key));
+        match restored {
+            Ok(database) => {
+                let _ = fs::remove_dir_all(&staging);
+                if replaced.exists() {
+                    fs::remove_dir_all(&replaced)?;
+                }
+                Ok(database)
+            }
+            Err(e) => {
+                if replaced.exists() {
+                    if target.exists() {
+                        fs::remove_dir_all(&target)?;
+                    }
+                    fs::rename(&replaced, &target)?;
+                }
+                let _ = fs::remove_dir_all(&staging);
+                sync_dir(&base_path)?;
+                Err(e)
+            }
+        }
+    }
+}
+
+/// Copies a given number of bytes, computing their CRC32C checksum along the way.
+///
+/// ## Arguments
+///
+/// * `reader` - The reader to copy from.
+/// * `writer` - The writer to copy to.
+/// * `len` - The number of bytes to copy.
+///
+/// ## Returns
+///
+/// Returns [`Ok`]\([`u32`]) with the checksum of the copied bytes,
+/// or [`Err`]\([`io::Error`]) if fewer bytes could be read or the write failed.
+fn copy_with_checksum(
+    reader: &mut impl Read,
+    writer: &mut impl Write,
+    len: u64,
+) -> io::Result<u32> {
+    let mut buffer = vec![0u8; COPY_BUFFER_SIZE];
+    let mut checksum = 0;
+    let mut remaining = len;
+    while remaining > 0 {
+        let chunk = remaining.min(COPY_BUFFER_SIZE as u64) as usize;
+        reader.read_exact(&mut buffer[..chunk])?;
+        checksum = crc32c::crc32c_append(checksum, &buffer[..chunk]);
+        writer.write_all(&buffer[..chunk])?;
+        remaining -= chunk as u64;
+    }
+    Ok(checksum)
+}
+
+/// Extracts and verifies every entry of a backup archive.
+///
+/// ## Arguments
+///
+/// * `archive_path` - The path of the archive.
+/// * `dir` - The directory to extract the collections into.
+///
+/// ## Returns
+///
+/// Returns [`Ok`]\(()) if every entry was extracted and its checksum matched,
+/// or [`Err`]\([`io::Error`]) if the archive is invalid, truncated or could not be read.
+fn extract_archive(archive_path: &Path, dir: &Path) -> io::Result<()> {
+    let mut reader = BufReader::new(File::open(archive_path)?);
+    let mut header = [0u8; FORMAT_HEADER_SIZE];
+    reader.read_exact(&mut header)?;
+    check_format_header(FileKind::Backup, &header)?;
+
+    loop {
+        let mut path_len = [0u8; 4];
+        reader.read_exact(&mut path_len)?;
+        let path_len = u32::from_le_bytes(path_len) as usize;
+        if path_len == 0 {
+            break;
+        }
+
+        let mut path = vec![0u8; path_len];
+        reader.read_exact(&mut path)?;
+        let path = String::from_utf8(path)
+            .ok()
+            .filter(|path| is_collection_file_path(path))
+            .ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidData, "Invalid file path in archive")
+            })?;
+
+        let mut len = [0u8; 8];
+        reader.read_exact(&mut len)?;
+        let len = u64::from_le_bytes(len);
+
+        let file_path = dir.join(&path);
+        if let Some(parent) = file_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut file = File::create(&file_path)?;
+        let actual = copy_with_checksum(&mut reader, &mut file, len)?;
+        let mut expected = [0u8; 4];
+        reader.read_exact(&mut expected)?;
+        if actual != u32::from_le_bytes(expected) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Checksum mismatch for '{}' in archive", path),
+            ));
+        }
+        file.sync_all()?;
+    }
+
+    for entry in fs::read_dir(dir)? {
+        sync_dir(&entry?.path())?;
+    }
+    sync_dir(dir)
+}
+
+/// Checks that an archive path names a file directly within a collection directory,
+/// so that extracting it cannot write outside the database directory.
+///
+/// ## Arguments
+///
+/// * `path` - The path of the entry within the archive.
+fn is_collection_file_path(path: &str) -> bool {
+    let components: Vec<Component> = Path::new(path).components().collect();
+    components.len() == 2
+        && components
+            .iter()
+            .all(|component| matches!(component, Component::Normal(_)))
+}
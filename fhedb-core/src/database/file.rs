@@ -2,13 +2,20 @@
 //!
 //! Provides file I/O operations for loading databases from disk.
 
-use crate::{collection::Collection, database::Database};
-use std::{fs, io, path::PathBuf};
+use crate::{
+    collection::{Collection, recovery::RecoveryReport},
+    database::Database,
+    format::encryption::EncryptionKey,
+};
+use std::{collections::HashMap, fs, io, path::PathBuf};
 
 /// File I/O operations for database persistence.
 impl Database {
     /// Loads a [`Database`] from existing files on disk.
     ///
+    /// Damage found in the collections' files while loading is available
+    /// through [`Database::recovery_reports`].
+    ///
     /// ## Arguments
     ///
     /// * `name` - The name of the database.
@@ -22,7 +29,31 @@ impl Database {
         name: impl Into<String>,
         base_path: impl Into<PathBuf>,
     ) -> Result<Self, std::io::Error> {
+        Self::from_files_with_key(name, base_path, None)
+    }
+
+    /// Loads a [`Database`] whose collections may be encrypted from existing files on disk.
+    ///
+    /// Every collection is loaded through [`Collection::from_files_with_key`], and the key
+    /// is kept for the collections created afterwards.
+    ///
+    /// ## Arguments
+    ///
+    /// * `name` - The name of the database.
+    /// * `base_path` - The base path where the database is stored.
+    /// * `key` - The [`EncryptionKey`] of the collections, or [`None`] if they are not encrypted.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`Database`]) if loaded successfully,
+    /// or [`Err`]\([`io::Error`]) if the load failed.
+    pub fn from_files_with_key(
+        name: impl Into<String>,
+        base_path: impl Into<PathBuf>,
+        key: Option<EncryptionKey>,
+    ) -> io::Result<Self> {
         let mut database = Self::new(name, base_path);
+        database.encryption_key = key;
 
         if !database.base_path.exists() {
             return Err(io::Error::new(
@@ -43,7 +74,12 @@ impl Database {
             if path.is_dir()
                 && let Some(collection_name) = path.file_name().and_then(|n| n.to_str())
             {
-                let collection = Collection::from_files(&database.base_path, collection_name)?;
+                let mut collection = Collection::from_files_with_key(
+                    &database.base_path,
+                    collection_name,
+                    database.encryption_key.clone(),
+                )?;
+                collection.set_change_feed(database.changes.clone());
                 database
                     .collections
                     .insert(collection_name.to_string(), collection);
@@ -52,4 +88,19 @@ impl Database {
 
         Ok(database)
     }
+
+    /// Returns the recovery reports of every collection whose files were found damaged
+    /// when the database was loaded from disk.
+    ///
+    /// ## Returns
+    ///
+    /// Returns a [`HashMap`] of collection names to their [`RecoveryReport`],
+    /// which is empty if every logfile was intact.
+    pub fn recovery_reports(&self) -> HashMap<String, RecoveryReport> {
+        self.collections
+            .iter()
+            .filter(|(_, collection)| !collection.recovery_report().is_clean())
+            .map(|(name, collection)| (name.clone(), collection.recovery_report().clone()))
+            .collect()
+    }
 }
@@ -2,10 +2,22 @@
 //!
 //! Provides the core [`Database`] type and its collection management operations.
 
+pub mod backup;
 pub mod file;
 
-use crate::{collection::Collection, schema::Schema};
-use std::{collections::HashMap, path::PathBuf};
+use crate::{
+    collection::{
+        Collection,
+        changes::{ChangeFeed, Subscription},
+        compaction::DEFAULT_COMPACTION_THRESHOLD,
+        compression::Compression,
+        durability::Durability,
+        segment::DEFAULT_MAX_SEGMENT_SIZE,
+    },
+    format::encryption::EncryptionKey,
+    schema::Schema,
+};
+use std::{collections::HashMap, io, path::PathBuf, time::Duration};
 
 /// A named group of [`Collection`]s stored under a shared base path.
 #[derive(Debug, Clone)]
@@ -16,6 +28,20 @@ pub struct Database {
     pub base_path: PathBuf,
     /// The collections stored in this database.
     pub(crate) collections: HashMap<String, Collection>,
+    /// The durability setting applied to every collection in this database.
+    pub(crate) durability: Durability,
+    /// The maximum log segment size applied to every collection in this database.
+    pub(crate) max_segment_size: usize,
+    /// The compaction threshold applied to every collection in this database.
+    pub(crate) compaction_threshold: f64,
+    /// The history retention window applied to every collection in this database.
+    pub(crate) history_retention: Option<Duration>,
+    /// The compression codec applied to collections created in this database.
+    pub(crate) compression: Compression,
+    /// The key encrypting the files of every collection in this database, if any.
+    pub(crate) encryption_key: Option<EncryptionKey>,
+    /// The feed the writes of every collection in this database are published to.
+    pub(crate) changes: ChangeFeed,
 }
 
 impl Database {
@@ -34,6 +60,13 @@ impl Database {
             name,
             base_path,
             collections: HashMap::new(),
+            durability: Durability::default(),
+            max_segment_size: DEFAULT_MAX_SEGMENT_SIZE,
+            compaction_threshold: DEFAULT_COMPACTION_THRESHOLD,
+            history_retention: None,
+            compression: Compression::default(),
+            encryption_key: None,
+            changes: ChangeFeed::default(),
         }
     }
 
@@ -64,7 +97,14 @@ impl Database {
             return Err(format!("Collection '{}' already exists", collection_name));
         }
 
-        let collection = Collection::new(collection_name.clone(), schema, &self.base_path)?;
+        let mut collection = Collection::new(collection_name.clone(), schema, &self.base_path)?;
+        collection.set_durability(self.durability);
+        collection.set_max_segment_size(self.max_segment_size);
+        collection.set_compaction_threshold(self.compaction_threshold);
+        collection.set_history_retention(self.history_retention);
+        collection.set_compression(self.compression);
+        collection.set_encryption_key(self.encryption_key.clone());
+        collection.set_change_feed(self.changes.clone());
 
         collection
             .write_metadata()
@@ -144,6 +184,165 @@ impl Database {
         self.collections.get_mut(collection_name)
     }
 
+    /// Returns the feed the writes of every collection in this database are published to.
+    pub fn change_feed(&self) -> &ChangeFeed {
+        &self.changes
+    }
+
+    /// Subscribes to the writes made to the database's collections.
+    ///
+    /// ## Arguments
+    ///
+    /// * `from` - The sequence number of the first event to receive,
+    ///   or [`None`] to only receive events published from now on.
+    /// * `collection_name` - The name of the collection to receive events of,
+    ///   or [`None`] for every collection.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`Subscription`]) positioned at the first event to receive,
+    /// or [`Err`]\([`String`]) if the collection was not found, or if that event is
+    /// no longer held or is yet to be published.
+    pub fn subscribe_changes(
+        &self,
+        from: Option<u64>,
+        collection_name: Option<&str>,
+    ) -> Result<Subscription, String> {
+        if let Some(collection_name) = collection_name
+            && !self.has_collection(collection_name)
+        {
+            return Err(format!("Collection '{}' not found", collection_name));
+        }
+        self.changes.subscribe(from, collection_name)
+    }
+
+    /// Returns the durability setting of this database.
+    pub fn durability(&self) -> Durability {
+        self.durability
+    }
+
+    /// Sets when writes are flushed to disk, for every current and future collection.
+    ///
+    /// ## Arguments
+    ///
+    /// * `durability` - The [`Durability`] to use for subsequent writes.
+    pub fn set_durability(&mut self, durability: Durability) {
+        self.durability = durability;
+        for collection in self.collections.values_mut() {
+            collection.set_durability(durability);
+        }
+    }
+
+    /// Returns the size in bytes after which collection logs roll over to a new segment.
+    pub fn max_segment_size(&self) -> usize {
+        self.max_segment_size
+    }
+
+    /// Sets the maximum log segment size, for every current and future collection.
+    ///
+    /// ## Arguments
+    ///
+    /// * `max_segment_size` - The maximum segment size in bytes to use for subsequent writes.
+    pub fn set_max_segment_size(&mut self, max_segment_size: usize) {
+        self.max_segment_size = max_segment_size;
+        for collection in self.collections.values_mut() {
+            collection.set_max_segment_size(max_segment_size);
+        }
+    }
+
+    /// Returns the garbage ratio above which collections need compaction.
+    pub fn compaction_threshold(&self) -> f64 {
+        self.compaction_threshold
+    }
+
+    /// Sets the compaction threshold, for every current and future collection.
+    ///
+    /// ## Arguments
+    ///
+    /// * `threshold` - The share of garbage entries above which collections need compaction.
+    pub fn set_compaction_threshold(&mut self, threshold: f64) {
+        self.compaction_threshold = threshold;
+        for collection in self.collections.values_mut() {
+            collection.set_compaction_threshold(threshold);
+        }
+    }
+
+    /// Returns how long compaction keeps the log entries of collections untouched.
+    pub fn history_retention(&self) -> Option<Duration> {
+        self.history_retention
+    }
+
+    /// Sets the history retention window, for every current and future collection.
+    ///
+    /// ## Arguments
+    ///
+    /// * `retention` - The retention window, or [`None`] to let compaction discard history.
+    pub fn set_history_retention(&mut self, retention: Option<Duration>) {
+        self.history_retention = retention;
+        for collection in self.collections.values_mut() {
+            collection.set_history_retention(retention);
+        }
+    }
+
+    /// Returns the compression codec applied to collections created in this database.
+    pub fn compression(&self) -> Compression {
+        self.compression
+    }
+
+    /// Sets the compression codec for every future collection.
+    ///
+    /// Existing collections keep the codec recorded in their metadata,
+    /// which can be changed through [`Collection::set_compression`].
+    ///
+    /// ## Arguments
+    ///
+    /// * `compression` - The [`Compression`] codec to use for collections created afterwards.
+    pub fn set_compression(&mut self, compression: Compression) {
+        self.compression = compression;
+    }
+
+    /// Returns the key encrypting the files of the collections in this database, if any.
+    pub fn encryption_key(&self) -> Option<&EncryptionKey> {
+        self.encryption_key.as_ref()
+    }
+
+    /// Sets the key encrypting the files of every current and future collection.
+    ///
+    /// Files already written by current collections stay as they are until the collections
+    /// are loaded again through [`Database::from_files_with_key`].
+    ///
+    /// ## Arguments
+    ///
+    /// * `key` - The [`EncryptionKey`] to use, or [`None`] to store new files in the clear.
+    pub fn set_encryption_key(&mut self, key: Option<EncryptionKey>) {
+        for collection in self.collections.values_mut() {
+            collection.set_encryption_key(key.clone());
+        }
+        self.encryption_key = key;
+    }
+
+    /// Returns the names of the collections whose garbage ratio crossed the compaction threshold.
+    pub fn collections_needing_compaction(&self) -> Vec<String> {
+        self.collections
+            .iter()
+            .filter(|(_, collection)| collection.needs_compaction())
+            .map(|(name, _)| name.clone())
+            .collect()
+    }
+
+    /// Flushes the writes of every collection still waiting for a group commit to disk.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\(()) if every collection was flushed,
+    /// or [`Err`]\([`io::Error`]) with the first flush failure.
+    pub fn flush(&self) -> io::Result<()> {
+        for collection in self.collections.values() {
+            collection.flush()?;
+        }
+        Ok(())
+    }
+
     /// Removes all collections from the in-memory database.
     pub fn clear_collections(&mut self) {
         self.collections.clear();
@@ -56,8 +56,53 @@ impl DocId {
             DocId::U64(value) => bson::Bson::Int64(*value as i64),
         }
     }
+
+    /// Encodes the document ID as an index key.
+    ///
+    /// Integer IDs are stored big-endian so that the byte order of keys matches the
+    /// numeric order of the IDs.
+    pub fn to_key_bytes(&self) -> Vec<u8> {
+        match self {
+            DocId::U64(value) => {
+                let mut bytes = Vec::with_capacity(9);
+                bytes.push(KEY_TAG_U64);
+                bytes.extend_from_slice(&value.to_be_bytes());
+                bytes
+            }
+            DocId::String(s) => {
+                let mut bytes = Vec::with_capacity(1 + s.len());
+                bytes.push(KEY_TAG_STRING);
+                bytes.extend_from_slice(s.as_bytes());
+                bytes
+            }
+        }
+    }
+
+    /// Decodes a document ID from an index key produced by [`DocId::to_key_bytes`].
+    ///
+    /// ## Arguments
+    ///
+    /// * `bytes` - The encoded key bytes.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Some`]\([`DocId`]) if the key is valid, or [`None`] otherwise.
+    pub fn from_key_bytes(bytes: &[u8]) -> Option<Self> {
+        let (tag, rest) = bytes.split_first()?;
+        match *tag {
+            KEY_TAG_U64 => Some(Self::U64(u64::from_be_bytes(rest.try_into().ok()?))),
+            KEY_TAG_STRING => Some(Self::String(String::from_utf8(rest.to_vec()).ok()?)),
+            _ => None,
+        }
+    }
 }
 
+/// The key prefix for [`DocId::U64`] index keys.
+const KEY_TAG_U64: u8 = 0x01;
+
+/// The key prefix for [`DocId::String`] index keys.
+const KEY_TAG_STRING: u8 = 0x02;
+
 impl Default for DocId {
     fn default() -> Self {
         Self::new()
@@ -0,0 +1,562 @@
+//! # Homomorphic Encryption
+//!
+//! Provides the values of encrypted fields, which the database can process without
+//! being able to read them.
+//!
+//! Values are encrypted client-side with an [`FheKey`] and stored as string tokens.
+//!
+//! An [`EncryptedInt`] carries the Paillier public modulus, the ciphertext and an equality
+//! tag. The database sums them by multiplying their ciphertexts, and compares them through
+//! their tags, which are keyed hashes of the plaintext. Sums carry no tag, since it cannot
+//! be derived without the key.
+//!
+//! An [`EncryptedString`] carries an XChaCha20-Poly1305 ciphertext and a blind index, a
+//! keyed hash of the plaintext the database finds equal values by.
+//!
+//! An [`FheKey`] is exported with [`FheKey::to_bytes`] and imported with
+//! [`FheKey::from_bytes`], so that values remain readable after the process that encrypted
+//! them exits. The encoding starts with the `FHEK` magic number and a little-endian `u16`
+//! version, followed by the string key, the tag key and the two Paillier primes, each prime
+//! preceded by its length as a little-endian `u16`.
+//!
+//! Tags and blind indexes are deterministic, which is what lets the database match them.
+//! Anyone reading the stored tokens, the server included, therefore learns which documents
+//! hold equal values and how often each value occurs. For fields with few possible values,
+//! such as flags or small counters, that is often enough to infer the values themselves.
+
+pub mod paillier;
+
+use crate::format::encryption::{ENCRYPTION_OVERHEAD, EncryptionKey, KEY_SIZE};
+use chacha20poly1305::aead::{OsRng, rand_core::RngCore};
+use hmac::{Hmac, Mac};
+use num_bigint::BigUint;
+use paillier::{PublicKey, SecretKey};
+use sha2::Sha256;
+use std::{fmt, str::FromStr};
+
+/// The prefix of every encrypted integer token.
+pub const ENCRYPTED_INT_PREFIX: &str = "fhe:int:";
+
+/// The prefix of every encrypted string token.
+pub const ENCRYPTED_STRING_PREFIX: &str = "fhe:str:";
+
+/// The size of the Paillier modulus generated by default, in bits.
+pub const DEFAULT_MODULUS_BITS: u64 = 2048;
+
+/// The smallest Paillier modulus accepted, in bits.
+pub const MIN_MODULUS_BITS: u64 = 256;
+
+/// The largest Paillier modulus accepted, in bits.
+///
+/// Every value written to or compared with an encrypted integer field is checked against
+/// the modulus it carries, so larger moduli are rejected before any arithmetic on them.
+pub const MAX_MODULUS_BITS: u64 = 8192;
+
+/// The size of an equality tag or blind index in bytes.
+pub const TAG_SIZE: usize = 16;
+
+/// The size of the key equality tags are computed with, in bytes.
+const TAG_KEY_SIZE: usize = 32;
+
+/// The magic number an exported [`FheKey`] starts with.
+const FHE_KEY_MAGIC: &[u8; 4] = b"FHEK";
+
+/// The version of the [`FheKey`] encoding written by this build.
+pub const FHE_KEY_VERSION: u16 = 1;
+
+/// An encrypted integer, as stored in an encrypted field.
+///
+/// Its string form is `fhe:int:<modulus>:<ciphertext>:<tag>`, with every part hex encoded
+/// and the tag left empty for values that have none.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EncryptedInt {
+    /// The public key the value is encrypted under.
+    key: PublicKey,
+    /// The Paillier ciphertext.
+    ciphertext: BigUint,
+    /// The equality tag, if the value was encrypted directly rather than computed.
+    tag: Option<[u8; TAG_SIZE]>,
+}
+
+impl EncryptedInt {
+    /// Returns the public key the value is encrypted under.
+    pub fn public_key(&self) -> &PublicKey {
+        &self.key
+    }
+
+    /// Returns whether the value carries an equality tag.
+    pub fn has_tag(&self) -> bool {
+        self.tag.is_some()
+    }
+
+    /// Adds another encrypted integer to this one, without decrypting either.
+    ///
+    /// ## Arguments
+    ///
+    /// * `other` - The [`EncryptedInt`] to add.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`EncryptedInt`]) with the encrypted sum, which carries no tag,
+    /// or [`Err`]\([`String`]) if the values are encrypted under different keys.
+    pub fn add(&self, other: &EncryptedInt) -> Result<EncryptedInt, String> {
+        self.check_same_key(other)?;
+        Ok(EncryptedInt {
+            key: self.key.clone(),
+            ciphertext: self.key.add(&self.ciphertext, &other.ciphertext),
+            tag: None,
+        })
+    }
+
+    /// Returns an encrypted zero under the given key, to start a sum from.
+    ///
+    /// ## Arguments
+    ///
+    /// * `key` - The [`PublicKey`] to encrypt under.
+    pub fn zero(key: &PublicKey) -> EncryptedInt {
+        EncryptedInt {
+            key: key.clone(),
+            ciphertext: key.zero(),
+            tag: None,
+        }
+    }
+
+    /// Checks whether this value encrypts the same integer as another, using their tags.
+    ///
+    /// ## Arguments
+    ///
+    /// * `other` - The [`EncryptedInt`] to compare with.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`bool`]) indicating whether the values are equal, which they never
+    /// are if encrypted under different keys, or [`Err`]\([`String`]) if `other` has no tag.
+    pub fn matches(&self, other: &EncryptedInt) -> Result<bool, String> {
+        match (&self.tag, &other.tag) {
+            (_, None) => Err("Encrypted value to compare with has no equality tag".to_string()),
+            (Some(a), Some(b)) => Ok(self.key == other.key && a == b),
+            (None, _) => Ok(false),
+        }
+    }
+
+    /// Checks that another value is encrypted under the same key.
+    ///
+    /// ## Arguments
+    ///
+    /// * `other` - The [`EncryptedInt`] to check.
+    fn check_same_key(&self, other: &EncryptedInt) -> Result<(), String> {
+        if self.key == other.key {
+            Ok(())
+        } else {
+            Err("Encrypted values use different keys".to_string())
+        }
+    }
+}
+
+impl fmt::Display for EncryptedInt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}{}:{}:{}",
+            ENCRYPTED_INT_PREFIX,
+            self.key.modulus().to_str_radix(16),
+            self.ciphertext.to_str_radix(16),
+            self.tag
+                .as_ref()
+                .map(|tag| encode_hex(tag))
+                .unwrap_or_default()
+        )
+    }
+}
+
+impl FromStr for EncryptedInt {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || "Invalid encrypted int".to_string();
+        let parts: Vec<&str> = s
+            .strip_prefix(ENCRYPTED_INT_PREFIX)
+            .ok_or_else(invalid)?
+            .split(':')
+            .collect();
+        let [modulus, ciphertext, tag] = parts[..] else {
+            return Err(invalid());
+        };
+        if modulus.len() as u64 > MAX_MODULUS_BITS / 4
+            || ciphertext.len() as u64 > MAX_MODULUS_BITS / 2
+        {
+            return Err(invalid());
+        }
+
+        let modulus = BigUint::parse_bytes(modulus.as_bytes(), 16).ok_or_else(invalid)?;
+        let ciphertext = BigUint::parse_bytes(ciphertext.as_bytes(), 16).ok_or_else(invalid)?;
+        let tag = match tag {
+            "" => None,
+            tag => Some(
+                decode_hex(tag)
+                    .and_then(|bytes| bytes.try_into().ok())
+                    .ok_or_else(invalid)?,
+            ),
+        };
+
+        if !(MIN_MODULUS_BITS..=MAX_MODULUS_BITS).contains(&modulus.bits()) {
+            return Err(invalid());
+        }
+        let key = PublicKey::new(modulus);
+        if !key.is_ciphertext(&ciphertext) {
+            return Err(invalid());
+        }
+        Ok(EncryptedInt {
+            key,
+            ciphertext,
+            tag,
+        })
+    }
+}
+
+/// An encrypted string, as stored in an encrypted field.
+///
+/// Its string form is `fhe:str:<ciphertext>:<blind index>`, with both parts hex encoded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EncryptedString {
+    /// The nonce followed by the ciphertext.
+    sealed: Vec<u8>,
+    /// The blind index of the plaintext.
+    index: [u8; TAG_SIZE],
+}
+
+impl EncryptedString {
+    /// Returns the blind index of the value.
+    pub fn blind_index(&self) -> &[u8; TAG_SIZE] {
+        &self.index
+    }
+
+    /// Checks whether this value encrypts the same string as another, using their blind
+    /// indexes. Values encrypted under different keys never match.
+    ///
+    /// ## Arguments
+    ///
+    /// * `other` - The [`EncryptedString`] to compare with.
+    pub fn matches(&self, other: &EncryptedString) -> bool {
+        self.index == other.index
+    }
+}
+
+impl fmt::Display for EncryptedString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}{}:{}",
+            ENCRYPTED_STRING_PREFIX,
+            encode_hex(&self.sealed),
+            encode_hex(&self.index)
+        )
+    }
+}
+
+impl FromStr for EncryptedString {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || "Invalid encrypted string".to_string();
+        let (sealed, index) = s
+            .strip_prefix(ENCRYPTED_STRING_PREFIX)
+            .and_then(|rest| rest.split_once(':'))
+            .ok_or_else(invalid)?;
+
+        let sealed = decode_hex(sealed)
+            .filter(|sealed| sealed.len() >= ENCRYPTION_OVERHEAD)
+            .ok_or_else(invalid)?;
+        let index = decode_hex(index)
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(invalid)?;
+        Ok(EncryptedString { sealed, index })
+    }
+}
+
+/// A client-side key for encrypted fields, which encrypts and decrypts their values.
+///
+/// It combines a Paillier key pair for integers, a symmetric key for strings and the key
+/// equality tags and blind indexes are computed with. The database only ever sees the
+/// public modulus, inside the integer values themselves.
+#[derive(Clone)]
+pub struct FheKey {
+    /// The Paillier key pair.
+    secret: SecretKey,
+    /// The key strings are encrypted with.
+    string_key: EncryptionKey,
+    /// The raw bytes of the string key, kept so that the key can be exported.
+    string_key_bytes: [u8; KEY_SIZE],
+    /// The key equality tags and blind indexes are computed with.
+    tag_key: [u8; TAG_KEY_SIZE],
+}
+
+impl FheKey {
+    /// Generates a new key with a [`DEFAULT_MODULUS_BITS`] modulus.
+    pub fn generate() -> Self {
+        Self::generate_with_bits(DEFAULT_MODULUS_BITS)
+            .expect("the default modulus size is supported")
+    }
+
+    /// Generates a new key with a modulus of the given size.
+    ///
+    /// ## Arguments
+    ///
+    /// * `bits` - The size of the modulus in bits.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`FheKey`]) if successful, or [`Err`]\([`String`]) if the size is
+    /// outside [`MIN_MODULUS_BITS`] to [`MAX_MODULUS_BITS`].
+    pub fn generate_with_bits(bits: u64) -> Result<Self, String> {
+        if !(MIN_MODULUS_BITS..=MAX_MODULUS_BITS).contains(&bits) {
+            return Err(format!(
+                "An encrypted field key needs a modulus of {} to {} bits",
+                MIN_MODULUS_BITS, MAX_MODULUS_BITS
+            ));
+        }
+
+        let mut tag_key = [0u8; TAG_KEY_SIZE];
+        OsRng.fill_bytes(&mut tag_key);
+        let (string_key, string_key_bytes) = EncryptionKey::generate();
+        Ok(Self {
+            secret: SecretKey::generate(bits),
+            string_key,
+            string_key_bytes,
+            tag_key,
+        })
+    }
+
+    /// Exports the key, including its secret parts, in the current encoding version.
+    ///
+    /// Anyone holding the exported bytes can decrypt every value encrypted under the key.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let (p, q) = self.secret.primes();
+        let (p, q) = (p.to_bytes_be(), q.to_bytes_be());
+
+        let mut bytes = Vec::with_capacity(6 + KEY_SIZE + TAG_KEY_SIZE + 4 + p.len() + q.len());
+        bytes.extend_from_slice(FHE_KEY_MAGIC);
+        bytes.extend_from_slice(&FHE_KEY_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&self.string_key_bytes);
+        bytes.extend_from_slice(&self.tag_key);
+        for prime in [p, q] {
+            bytes.extend_from_slice(&(prime.len() as u16).to_le_bytes());
+            bytes.extend_from_slice(&prime);
+        }
+        bytes
+    }
+
+    /// Imports a key exported by [`FheKey::to_bytes`].
+    ///
+    /// ## Arguments
+    ///
+    /// * `bytes` - The exported key.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`FheKey`]) if successful, or [`Err`]\([`String`]) if the bytes are not
+    /// an exported key, were exported in an unsupported version or hold an invalid key.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        let invalid = || "Invalid encrypted field key".to_string();
+        let rest = bytes.strip_prefix(FHE_KEY_MAGIC).ok_or_else(invalid)?;
+        let (version, rest) = split_bytes(rest, 2).ok_or_else(invalid)?;
+        let version = u16::from_le_bytes([version[0], version[1]]);
+        if version != FHE_KEY_VERSION {
+            return Err(format!(
+                "Unsupported encrypted field key version {}, expected {}",
+                version, FHE_KEY_VERSION
+            ));
+        }
+
+        let (string_key_bytes, rest) = split_bytes(rest, KEY_SIZE).ok_or_else(invalid)?;
+        let (tag_key, rest) = split_bytes(rest, TAG_KEY_SIZE).ok_or_else(invalid)?;
+        let (p, rest) = split_prime(rest).ok_or_else(invalid)?;
+        let (q, rest) = split_prime(rest).ok_or_else(invalid)?;
+        if !rest.is_empty() {
+            return Err(invalid());
+        }
+
+        // The size is checked first, as it bounds the cost of testing the factors for primality.
+        let bits = p.bits() + q.bits();
+        if !(MIN_MODULUS_BITS..=MAX_MODULUS_BITS + 1).contains(&bits) {
+            return Err(invalid());
+        }
+        let secret = SecretKey::from_primes(p, q)?;
+        if !(MIN_MODULUS_BITS..=MAX_MODULUS_BITS).contains(&secret.public_key().bits()) {
+            return Err(invalid());
+        }
+        let string_key_bytes: [u8; KEY_SIZE] =
+            string_key_bytes.try_into().map_err(|_| invalid())?;
+        Ok(Self {
+            secret,
+            string_key: EncryptionKey::from_bytes(&string_key_bytes),
+            string_key_bytes,
+            tag_key: tag_key.try_into().map_err(|_| invalid())?,
+        })
+    }
+
+    /// Returns the public key values are encrypted under.
+    pub fn public_key(&self) -> &PublicKey {
+        self.secret.public_key()
+    }
+
+    /// Encrypts an integer, along with its equality tag.
+    ///
+    /// ## Arguments
+    ///
+    /// * `value` - The integer to encrypt.
+    pub fn encrypt_int(&self, value: i64) -> EncryptedInt {
+        let key = self.public_key();
+        EncryptedInt {
+            key: key.clone(),
+            ciphertext: key.encrypt_i64(value),
+            tag: Some(self.keyed_hash(&[&key.modulus().to_bytes_be(), &value.to_le_bytes()])),
+        }
+    }
+
+    /// Decrypts an encrypted integer.
+    ///
+    /// ## Arguments
+    ///
+    /// * `value` - The [`EncryptedInt`] to decrypt.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`i64`]) with the integer, or [`Err`]\([`String`]) if the value is
+    /// encrypted under another key, is not a valid ciphertext or, for a sum, overflows an
+    /// [`i64`].
+    pub fn decrypt_int(&self, value: &EncryptedInt) -> Result<i64, String> {
+        if &value.key != self.public_key() {
+            return Err("Encrypted value uses a different key".to_string());
+        }
+        self.secret.decrypt_i64(&value.ciphertext)
+    }
+
+    /// Encrypts a string, along with its blind index.
+    ///
+    /// ## Arguments
+    ///
+    /// * `value` - The string to encrypt.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`EncryptedString`]) if successful,
+    /// or [`Err`]\([`String`]) if the string is too large to be encrypted.
+    pub fn encrypt_string(&self, value: &str) -> Result<EncryptedString, String> {
+        let index = self.blind_index(value);
+        let sealed = self
+            .string_key
+            .encrypt(&index, value.as_bytes())
+            .map_err(|e| e.to_string())?;
+        Ok(EncryptedString { sealed, index })
+    }
+
+    /// Decrypts an encrypted string.
+    ///
+    /// ## Arguments
+    ///
+    /// * `value` - The [`EncryptedString`] to decrypt.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`String`]) with the string, or [`Err`]\([`String`]) if the value is
+    /// encrypted under another key or was altered.
+    pub fn decrypt_string(&self, value: &EncryptedString) -> Result<String, String> {
+        let plaintext = self
+            .string_key
+            .decrypt(&value.index, &value.sealed)
+            .map_err(|e| e.to_string())?;
+        String::from_utf8(plaintext).map_err(|_| "Decrypted value is not UTF-8".to_string())
+    }
+
+    /// Computes the blind index of a string, which equality conditions are matched by.
+    ///
+    /// ## Arguments
+    ///
+    /// * `value` - The string to compute the blind index of.
+    pub fn blind_index(&self, value: &str) -> [u8; TAG_SIZE] {
+        self.keyed_hash(&[ENCRYPTED_STRING_PREFIX.as_bytes(), value.as_bytes()])
+    }
+
+    /// Computes a truncated HMAC-SHA256 of the given parts under the tag key.
+    ///
+    /// ## Arguments
+    ///
+    /// * `parts` - The byte strings to hash, in order.
+    fn keyed_hash(&self, parts: &[&[u8]]) -> [u8; TAG_SIZE] {
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(&self.tag_key).expect("HMAC accepts keys of any size");
+        for part in parts {
+            mac.update(part);
+        }
+        let digest = mac.finalize().into_bytes();
+
+        let mut tag = [0u8; TAG_SIZE];
+        tag.copy_from_slice(&digest[..TAG_SIZE]);
+        tag
+    }
+}
+
+impl fmt::Debug for FheKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "FheKey({} bits, ..)", self.public_key().bits())
+    }
+}
+
+/// Splits a number of bytes off the front of a slice.
+///
+/// ## Arguments
+///
+/// * `bytes` - The slice to split.
+/// * `len` - The number of bytes to split off.
+///
+/// ## Returns
+///
+/// Returns [`Some`] with the bytes split off and the rest, or [`None`] if the slice is
+/// shorter than `len`.
+fn split_bytes(bytes: &[u8], len: usize) -> Option<(&[u8], &[u8])> {
+    (bytes.len() >= len).then(|| bytes.split_at(len))
+}
+
+/// Splits a prime preceded by its length off the front of an exported key.
+///
+/// ## Arguments
+///
+/// * `bytes` - The rest of the exported key.
+///
+/// ## Returns
+///
+/// Returns [`Some`] with the prime and the rest, or [`None`] if the bytes are truncated.
+fn split_prime(bytes: &[u8]) -> Option<(BigUint, &[u8])> {
+    let (len, rest) = split_bytes(bytes, 2)?;
+    let (prime, rest) = split_bytes(rest, u16::from_le_bytes([len[0], len[1]]) as usize)?;
+    Some((BigUint::from_bytes_be(prime), rest))
+}
+
+/// Encodes bytes as lowercase hexadecimal digits.
+///
+/// ## Arguments
+///
+/// * `bytes` - The bytes to encode.
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Decodes hexadecimal digits into bytes.
+///
+/// ## Arguments
+///
+/// * `hex` - The digits to decode.
+///
+/// ## Returns
+///
+/// Returns [`Some`]\([`Vec`]<[`u8`]>) with the bytes, or [`None`] if the input is not hex.
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
@@ -0,0 +1,260 @@
+//! # Paillier
+//!
+//! Provides the Paillier cryptosystem backing encrypted integer fields.
+//!
+//! Paillier encryption is additively homomorphic: multiplying two ciphertexts modulo `n²`
+//! yields a ciphertext of the sum of their plaintexts. Anyone holding the [`PublicKey`]
+//! can therefore add encrypted values, while only the holder of the [`SecretKey`] can
+//! decrypt them. The generator is fixed to `n + 1`, which keeps encryption and
+//! decryption to a single modular exponentiation each.
+//!
+//! Primes are generated by `num-bigint-dig`, whose primality test also backs the keys of the
+//! RustCrypto `rsa` crate, and random values are drawn from the operating system's generator
+//! through `num-bigint`. No maintained Paillier crate fits the workspace, so only the key
+//! equations and the homomorphic operations are implemented here.
+
+use chacha20poly1305::aead::OsRng;
+use num_bigint::{BigUint, RandBigInt};
+use num_bigint_dig::{RandPrime, prime::probably_prime};
+use num_integer::Integer;
+use num_traits::{CheckedSub, One, ToPrimitive, Zero};
+
+/// The number of Miller-Rabin rounds run when checking that imported factors are prime.
+const PRIMALITY_ROUNDS: usize = 20;
+
+/// A Paillier public key, which encrypts values and adds ciphertexts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PublicKey {
+    /// The modulus `n`, the product of two secret primes.
+    n: BigUint,
+    /// The square of the modulus, which ciphertexts are reduced by.
+    n_squared: BigUint,
+}
+
+/// A Paillier secret key, which decrypts ciphertexts.
+#[derive(Debug, Clone)]
+pub struct SecretKey {
+    /// The matching public key.
+    public: PublicKey,
+    /// The first prime factor of the modulus.
+    p: BigUint,
+    /// The second prime factor of the modulus.
+    q: BigUint,
+    /// Carmichael's function of the modulus, `lcm(p - 1, q - 1)`.
+    lambda: BigUint,
+    /// The inverse of `lambda` modulo `n`.
+    mu: BigUint,
+}
+
+impl PublicKey {
+    /// Creates a public key from its modulus.
+    ///
+    /// ## Arguments
+    ///
+    /// * `n` - The modulus of the key.
+    pub fn new(n: BigUint) -> Self {
+        let n_squared = &n * &n;
+        Self { n, n_squared }
+    }
+
+    /// Returns the modulus of the key.
+    pub fn modulus(&self) -> &BigUint {
+        &self.n
+    }
+
+    /// Returns the size of the modulus in bits.
+    pub fn bits(&self) -> u64 {
+        self.n.bits()
+    }
+
+    /// Checks whether a value is a possible ciphertext under this key.
+    ///
+    /// Ciphertexts are the values below `n²` that share no factor with `n`. Any other value
+    /// could only have been crafted, and would reveal a factor of `n` when decrypted.
+    ///
+    /// ## Arguments
+    ///
+    /// * `ciphertext` - The value to check.
+    pub fn is_ciphertext(&self, ciphertext: &BigUint) -> bool {
+        !ciphertext.is_zero() && ciphertext < &self.n_squared && ciphertext.gcd(&self.n).is_one()
+    }
+
+    /// Encrypts a plaintext under a fresh random nonce.
+    ///
+    /// ## Arguments
+    ///
+    /// * `plaintext` - The plaintext, which is reduced modulo `n`.
+    pub fn encrypt(&self, plaintext: &BigUint) -> BigUint {
+        let r = loop {
+            let r = OsRng.gen_biguint_range(&BigUint::one(), &self.n);
+            if r.gcd(&self.n).is_one() {
+                break r;
+            }
+        };
+        let g_m = (BigUint::one() + (plaintext % &self.n) * &self.n) % &self.n_squared;
+        (g_m * r.modpow(&self.n, &self.n_squared)) % &self.n_squared
+    }
+
+    /// Encrypts a signed integer, which is encoded modulo `n`.
+    ///
+    /// ## Arguments
+    ///
+    /// * `value` - The integer to encrypt.
+    pub fn encrypt_i64(&self, value: i64) -> BigUint {
+        let magnitude = BigUint::from(value.unsigned_abs());
+        let plaintext = if value < 0 {
+            &self.n - magnitude
+        } else {
+            magnitude
+        };
+        self.encrypt(&plaintext)
+    }
+
+    /// Adds two ciphertexts, yielding a ciphertext of the sum of their plaintexts.
+    ///
+    /// ## Arguments
+    ///
+    /// * `a` - The first ciphertext.
+    /// * `b` - The second ciphertext.
+    pub fn add(&self, a: &BigUint, b: &BigUint) -> BigUint {
+        (a * b) % &self.n_squared
+    }
+
+    /// Returns a ciphertext of zero, the neutral element of [`PublicKey::add`].
+    ///
+    /// It is not randomized, so it must only be used as the start of a sum.
+    pub fn zero(&self) -> BigUint {
+        BigUint::one()
+    }
+}
+
+impl SecretKey {
+    /// Generates a new key pair.
+    ///
+    /// ## Arguments
+    ///
+    /// * `bits` - The size of the modulus in bits, split evenly between the two primes.
+    pub fn generate(bits: u64) -> Self {
+        loop {
+            let p = generate_prime(bits / 2);
+            let q = generate_prime(bits - bits / 2);
+            if let Ok(key) = Self::from_primes(p, q) {
+                return key;
+            }
+        }
+    }
+
+    /// Creates a key pair from the two prime factors of its modulus.
+    ///
+    /// ## Arguments
+    ///
+    /// * `p` - The first prime factor.
+    /// * `q` - The second prime factor.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`SecretKey`]) if successful,
+    /// or [`Err`]\([`String`]) if the factors are not two distinct primes forming a key.
+    pub fn from_primes(p: BigUint, q: BigUint) -> Result<Self, String> {
+        if p == q || !is_prime(&p) || !is_prime(&q) {
+            return Err("Invalid prime factors for a Paillier key".to_string());
+        }
+
+        let n = &p * &q;
+        let lambda = (&p - 1u32).lcm(&(&q - 1u32));
+        let mu = lambda
+            .modinv(&n)
+            .ok_or_else(|| "Invalid prime factors for a Paillier key".to_string())?;
+        Ok(Self {
+            public: PublicKey::new(n),
+            p,
+            q,
+            lambda,
+            mu,
+        })
+    }
+
+    /// Returns the two prime factors of the modulus.
+    pub fn primes(&self) -> (&BigUint, &BigUint) {
+        (&self.p, &self.q)
+    }
+
+    /// Returns the matching public key.
+    pub fn public_key(&self) -> &PublicKey {
+        &self.public
+    }
+
+    /// Decrypts a ciphertext.
+    ///
+    /// ## Arguments
+    ///
+    /// * `ciphertext` - The ciphertext to decrypt.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`BigUint`]) with the plaintext, in the range `0..n`,
+    /// or [`Err`]\([`String`]) if the value is not a ciphertext under this key.
+    pub fn decrypt(&self, ciphertext: &BigUint) -> Result<BigUint, String> {
+        let invalid = || "Value is not a ciphertext under this key".to_string();
+        if !self.public.is_ciphertext(ciphertext) {
+            return Err(invalid());
+        }
+
+        let n = &self.public.n;
+        let u = ciphertext.modpow(&self.lambda, &self.public.n_squared);
+        let (l, remainder) = u
+            .checked_sub(&BigUint::one())
+            .ok_or_else(invalid)?
+            .div_rem(n);
+        if !remainder.is_zero() {
+            return Err(invalid());
+        }
+        Ok((l * &self.mu) % n)
+    }
+
+    /// Decrypts a ciphertext of a signed integer.
+    ///
+    /// ## Arguments
+    ///
+    /// * `ciphertext` - The ciphertext to decrypt.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`i64`]) with the integer, or [`Err`]\([`String`]) if the value is not
+    /// a ciphertext under this key or does not fit in an [`i64`], which happens when a sum
+    /// overflows.
+    pub fn decrypt_i64(&self, ciphertext: &BigUint) -> Result<i64, String> {
+        let n = &self.public.n;
+        let plaintext = self.decrypt(ciphertext)?;
+        let value = if plaintext > n >> 1 {
+            (n - plaintext)
+                .to_u64()
+                .and_then(|magnitude| 0i64.checked_sub_unsigned(magnitude))
+        } else {
+            plaintext.to_i64()
+        };
+        value.ok_or_else(|| "Decrypted value does not fit in a 64-bit integer".to_string())
+    }
+}
+
+/// Generates a random prime of exactly the given size.
+///
+/// ## Arguments
+///
+/// * `bits` - The size of the prime in bits. Its top two bits are set, so the product
+///   of two such primes has exactly the sum of their sizes.
+fn generate_prime(bits: u64) -> BigUint {
+    BigUint::from_bytes_be(&OsRng.gen_prime(bits as usize).to_bytes_be())
+}
+
+/// Checks whether a value is prime, with the same test `num-bigint-dig` generates primes with.
+///
+/// ## Arguments
+///
+/// * `value` - The value to check.
+fn is_prime(value: &BigUint) -> bool {
+    probably_prime(
+        &num_bigint_dig::BigUint::from_bytes_be(&value.to_bytes_be()),
+        PRIMALITY_ROUNDS,
+    )
+}
@@ -0,0 +1,173 @@
+//! # Encryption
+//!
+//! Provides the [`EncryptionKey`] used to encrypt storage files at rest.
+//!
+//! Data is sealed with XChaCha20-Poly1305 under a random nonce, which is stored in front
+//! of the ciphertext. The associated data binds every sealed block to where it is stored:
+//! log entries to their collection, segment and offset, metadata files to their collection,
+//! and index pages to the name of their index file and their page number.
+
+use chacha20poly1305::{
+    Key, KeyInit, XChaCha20Poly1305, XNonce,
+    aead::{Aead, AeadCore, OsRng, Payload},
+};
+use std::{fmt, fs, io, path::Path, sync::Arc};
+
+/// The size of an encryption key in bytes.
+pub const KEY_SIZE: usize = 32;
+
+/// The size of the nonce stored in front of every sealed block.
+pub const NONCE_SIZE: usize = 24;
+
+/// The number of bytes sealing adds to a block: its nonce and authentication tag.
+pub const ENCRYPTION_OVERHEAD: usize = NONCE_SIZE + 16;
+
+/// A key for the authenticated encryption of storage files.
+///
+/// Cloning the key shares the underlying cipher. The key material is never printed.
+#[derive(Clone)]
+pub struct EncryptionKey {
+    /// The cipher initialized with the key.
+    cipher: Arc<XChaCha20Poly1305>,
+}
+
+impl EncryptionKey {
+    /// Creates a key from its raw bytes.
+    ///
+    /// ## Arguments
+    ///
+    /// * `bytes` - The [`KEY_SIZE`] bytes of the key.
+    pub fn from_bytes(bytes: &[u8; KEY_SIZE]) -> Self {
+        Self {
+            cipher: Arc::new(XChaCha20Poly1305::new(Key::from_slice(bytes))),
+        }
+    }
+
+    /// Parses a key from its hexadecimal representation.
+    ///
+    /// ## Arguments
+    ///
+    /// * `hex` - The key as 64 hexadecimal digits. Surrounding whitespace is ignored.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`EncryptionKey`]) if successful,
+    /// or [`Err`]\([`io::Error`]) if the string is not a hex encoded key of the right size.
+    pub fn from_hex(hex: &str) -> io::Result<Self> {
+        let digits = hex.trim().as_bytes();
+        let invalid = || {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "An encryption key must be {} hexadecimal digits",
+                    KEY_SIZE * 2
+                ),
+            )
+        };
+        if digits.len() != KEY_SIZE * 2 {
+            return Err(invalid());
+        }
+
+        let mut bytes = [0u8; KEY_SIZE];
+        for (byte, pair) in bytes.iter_mut().zip(digits.chunks(2)) {
+            let pair = std::str::from_utf8(pair).map_err(|_| invalid())?;
+            *byte = u8::from_str_radix(pair, 16).map_err(|_| invalid())?;
+        }
+        Ok(Self::from_bytes(&bytes))
+    }
+
+    /// Reads a hex encoded key from a key file.
+    ///
+    /// ## Arguments
+    ///
+    /// * `path` - The path to the key file.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`EncryptionKey`]) if successful,
+    /// or [`Err`]\([`io::Error`]) if the file could not be read or holds no valid key.
+    pub fn from_file(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path)?;
+        Self::from_hex(&contents)
+            .map_err(|e| io::Error::new(e.kind(), format!("Key file {}: {}", path.display(), e)))
+    }
+
+    /// Generates a new random key, returning it along with its raw bytes.
+    pub fn generate() -> (Self, [u8; KEY_SIZE]) {
+        let key = XChaCha20Poly1305::generate_key(&mut OsRng);
+        let bytes: [u8; KEY_SIZE] = key.into();
+        (Self::from_bytes(&bytes), bytes)
+    }
+
+    /// Seals a block of data.
+    ///
+    /// ## Arguments
+    ///
+    /// * `aad` - The associated data the block is bound to.
+    /// * `plaintext` - The data to seal.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`Vec`]<[`u8`]>) with the nonce followed by the ciphertext,
+    /// which is [`ENCRYPTION_OVERHEAD`] bytes longer than the plaintext,
+    /// or [`Err`]\([`io::Error`]) if the data is too large to be sealed.
+    pub(crate) fn encrypt(&self, aad: &[u8], plaintext: &[u8]) -> io::Result<Vec<u8>> {
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: plaintext,
+                    aad,
+                },
+            )
+            .map_err(|_| io::Error::other("Failed to encrypt data"))?;
+
+        let mut sealed = Vec::with_capacity(NONCE_SIZE + ciphertext.len());
+        sealed.extend_from_slice(&nonce);
+        sealed.extend_from_slice(&ciphertext);
+        Ok(sealed)
+    }
+
+    /// Opens a block of data sealed by [`EncryptionKey::encrypt`].
+    ///
+    /// ## Arguments
+    ///
+    /// * `aad` - The associated data the block was bound to.
+    /// * `sealed` - The nonce followed by the ciphertext.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`Vec`]<[`u8`]>) with the plaintext, or [`Err`]\([`io::Error`]) of kind
+    /// [`io::ErrorKind::InvalidData`] if the block was sealed with another key or altered.
+    pub(crate) fn decrypt(&self, aad: &[u8], sealed: &[u8]) -> io::Result<Vec<u8>> {
+        let failed = || {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Failed to decrypt data, the encryption key is wrong or the data was altered",
+            )
+        };
+        if sealed.len() < ENCRYPTION_OVERHEAD {
+            return Err(failed());
+        }
+
+        let (nonce, ciphertext) = sealed.split_at(NONCE_SIZE);
+        self.cipher
+            .decrypt(
+                XNonce::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad,
+                },
+            )
+            .map_err(|_| failed())
+    }
+}
+
+impl fmt::Debug for EncryptionKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("EncryptionKey(..)")
+    }
+}
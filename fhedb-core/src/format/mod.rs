@@ -0,0 +1,156 @@
+//! # Storage Format
+//!
+//! Provides the header that every storage file starts with, identifying the kind of
+//! file and the version of the format it was written in.
+//!
+//! The header is made of a four byte magic number, a little-endian `u16` format version
+//! and little-endian `u16` flags. Files written before the header was introduced carry none,
+//! and are treated as version zero.
+
+/// The encryption module - contains the keys used to encrypt storage files at rest.
+pub mod encryption;
+
+/// The upgrade module - contains the migration of collection directories between format versions.
+pub mod upgrade;
+
+use std::{fmt, io};
+
+/// The version of the storage format written by this build.
+pub const FORMAT_VERSION: u16 = 1;
+
+/// The size of the header at the start of every storage file.
+pub const FORMAT_HEADER_SIZE: usize = 8;
+
+/// The header flag marking a file whose contents are encrypted.
+pub const ENCRYPTED_FLAG: u16 = 1;
+
+/// The header flag marking an index file whose plaintext pages carry a CRC32C checksum.
+pub const CHECKSUM_FLAG: u16 = 2;
+
+/// The kinds of storage files carrying a format header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileKind {
+    /// A log segment of a collection.
+    LogSegment,
+    /// A metadata file of a collection.
+    Metadata,
+    /// A page file of an index, where the header starts the metadata page.
+    Index,
+    /// A backup archive of a database.
+    Backup,
+}
+
+impl FileKind {
+    /// Returns the magic number identifying files of this kind.
+    pub fn magic(&self) -> &'static [u8; 4] {
+        match self {
+            FileKind::LogSegment => b"FHDL",
+            FileKind::Metadata => b"FHDM",
+            FileKind::Index => b"FHDI",
+            FileKind::Backup => b"FHDB",
+        }
+    }
+}
+
+impl fmt::Display for FileKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FileKind::LogSegment => write!(f, "log segment"),
+            FileKind::Metadata => write!(f, "metadata file"),
+            FileKind::Index => write!(f, "index file"),
+            FileKind::Backup => write!(f, "backup archive"),
+        }
+    }
+}
+
+/// Encodes the header of a file of the given kind in the current format version.
+///
+/// ## Arguments
+///
+/// * `kind` - The [`FileKind`] of the file.
+/// * `encrypted` - Whether the contents following the header are encrypted.
+pub fn encode_format_header(kind: FileKind, encrypted: bool) -> [u8; FORMAT_HEADER_SIZE] {
+    let mut header = [0u8; FORMAT_HEADER_SIZE];
+    header[0..4].copy_from_slice(kind.magic());
+    header[4..6].copy_from_slice(&FORMAT_VERSION.to_le_bytes());
+    if encrypted {
+        header[6..8].copy_from_slice(&ENCRYPTED_FLAG.to_le_bytes());
+    }
+    header
+}
+
+/// Reads the format version from the header at the start of the given bytes.
+///
+/// ## Arguments
+///
+/// * `kind` - The [`FileKind`] the bytes are expected to belong to.
+/// * `bytes` - The bytes at the start of the file.
+///
+/// ## Returns
+///
+/// Returns [`Some`]\([`u16`]) with the version if the bytes start with the header of
+/// the given kind, or [`None`] if they do not, as in files written before version one.
+pub fn format_version(kind: FileKind, bytes: &[u8]) -> Option<u16> {
+    let header = bytes.get(..FORMAT_HEADER_SIZE)?;
+    if &header[0..4] != kind.magic() {
+        return None;
+    }
+    Some(u16::from_le_bytes([header[4], header[5]]))
+}
+
+/// Reads the flags from the header at the start of the given bytes.
+///
+/// ## Arguments
+///
+/// * `bytes` - The bytes at the start of the file, which must hold a whole header.
+pub fn format_flags(bytes: &[u8]) -> u16 {
+    u16::from_le_bytes([bytes[6], bytes[7]])
+}
+
+/// Checks that the given bytes start with a header of the current format version.
+///
+/// ## Arguments
+///
+/// * `kind` - The [`FileKind`] the bytes are expected to belong to.
+/// * `bytes` - The bytes at the start of the file.
+///
+/// ## Returns
+///
+/// Returns [`Ok`]\([`bool`]) with whether the contents are encrypted if the header is
+/// current, or [`Err`]\([`io::Error`]) of kind [`io::ErrorKind::Unsupported`] if the file
+/// was written by a newer version or carries unknown flags, and of kind
+/// [`io::ErrorKind::InvalidData`] if it has no header or must be upgraded first.
+pub fn check_format_header(kind: FileKind, bytes: &[u8]) -> io::Result<bool> {
+    match format_version(kind, bytes) {
+        Some(FORMAT_VERSION) => {
+            let flags = format_flags(bytes);
+            let known = match kind {
+                FileKind::Index => ENCRYPTED_FLAG | CHECKSUM_FLAG,
+                _ => ENCRYPTED_FLAG,
+            };
+            if flags & !known != 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    format!("The {} carries unknown flags {:#06x}", kind, flags),
+                ));
+            }
+            Ok(flags & ENCRYPTED_FLAG != 0)
+        }
+        Some(version) if version > FORMAT_VERSION => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!(
+                "The {} uses format version {}, but only versions up to {} are supported",
+                kind, version, FORMAT_VERSION
+            ),
+        )),
+        version => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "The {} uses format version {} and must be upgraded to version {}",
+                kind,
+                version.unwrap_or(0),
+                FORMAT_VERSION
+            ),
+        )),
+    }
+}
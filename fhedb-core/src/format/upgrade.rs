@@ -0,0 +1,365 @@
+//! # Format Upgrades
+//!
+//! Provides the migration of collection directories from older storage format versions
+//! to the current one, one version at a time.
+//!
+//! Every step only rewrites files that are still in the older version, each through a
+//! temporary file that replaces the original once complete. An upgrade interrupted by a
+//! crash therefore picks up where it left off when it is run again.
+
+use crate::{
+    collection::{
+        compaction::remove_stale_compaction_files,
+        file::{
+            INDEX_FILE, METADATA_FILE, METADATA_PREVIOUS_FILE, METADATA_TEMP_FILE,
+            migrate_legacy_logfile, sync_dir,
+        },
+        reader::{FRAME_HEADER_SIZE, decode_frame, encode_frame},
+        segment::{list_segments, segment_file_name},
+    },
+    format::{FORMAT_HEADER_SIZE, FORMAT_VERSION, FileKind, encode_format_header, format_version},
+};
+use bson::{Bson, Document as BsonDocument};
+use std::{
+    fmt,
+    fs::{self, File, OpenOptions},
+    io::{self, Read, Write},
+    path::Path,
+};
+
+/// The extension of the temporary files upgraded files are written to.
+const UPGRADE_FILE_EXTENSION: &str = "upgrade";
+
+/// The metadata generations of a collection, in the order they are upgraded.
+/// The current generation comes last, so it is only upgraded once everything else is.
+const METADATA_FILES: [&str; 3] = [METADATA_PREVIOUS_FILE, METADATA_TEMP_FILE, METADATA_FILE];
+
+/// The outcome of upgrading a data directory.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UpgradeReport {
+    /// The number of databases found in the data directory.
+    pub databases: usize,
+    /// The number of collections checked.
+    pub collections: usize,
+    /// The collections that were upgraded, as `database/collection`.
+    pub upgraded: Vec<String>,
+}
+
+impl fmt::Display for UpgradeReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} databases and {} collections checked, {} upgraded to format version {}",
+            self.databases,
+            self.collections,
+            self.upgraded.len(),
+            FORMAT_VERSION
+        )
+    }
+}
+
+/// Returns the format version of the collection stored in the given directory.
+///
+/// The version is the lowest one among the collection's metadata generations, which are
+/// always upgraded after its other files. Damaged generations are left out.
+///
+/// ## Arguments
+///
+/// * `collection_dir` - The directory of the collection.
+///
+/// ## Returns
+///
+/// Returns [`Ok`]\([`Some`]\([`u16`])) with the version, [`Ok`]\([`None`]) if the directory
+/// holds no readable metadata, or [`Err`]\([`io::Error`]) if a metadata file could not be read.
+pub fn collection_format_version(collection_dir: &Path) -> io::Result<Option<u16>> {
+    Ok(metadata_versions(collection_dir)?.into_iter().min())
+}
+
+/// Upgrades the collection stored in the given directory to the current format version.
+///
+/// ## Arguments
+///
+/// * `collection_dir` - The directory of the collection.
+///
+/// ## Returns
+///
+/// Returns [`Ok`]\(`true`) if the collection was upgraded, [`Ok`]\(`false`) if it already
+/// was in the current version or holds no readable metadata, or [`Err`]\([`io::Error`]) if
+/// it was written by a newer version or could not be upgraded.
+pub fn upgrade_collection(collection_dir: &Path) -> io::Result<bool> {
+    let versions = metadata_versions(collection_dir)?;
+    let (Some(&version), Some(&newest)) = (versions.iter().min(), versions.iter().max()) else {
+        return Ok(false);
+    };
+    if newest > FORMAT_VERSION {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!(
+                "Collection at {} uses format version {}, but only versions up to {} are supported",
+                collection_dir.display(),
+                newest,
+                FORMAT_VERSION
+            ),
+        ));
+    }
+
+    for from in version..FORMAT_VERSION {
+        match from {
+            0 => upgrade_to_v1(collection_dir)?,
+            _ => unreachable!("No upgrade step from format version {}", from),
+        }
+    }
+    Ok(version < FORMAT_VERSION)
+}
+
+/// Upgrades every collection of the database stored in the given directory.
+///
+/// ## Arguments
+///
+/// * `database_dir` - The directory of the database.
+///
+/// ## Returns
+///
+/// Returns [`Ok`]\([`Vec`]<[`String`]>) with the names of the upgraded collections,
+/// or [`Err`]\([`io::Error`]) if a collection could not be upgraded.
+pub fn upgrade_database(database_dir: &Path) -> io::Result<Vec<String>> {
+    let mut upgraded = Vec::new();
+    for name in subdirectories(database_dir)? {
+        if upgrade_collection(&database_dir.join(&name))? {
+            upgraded.push(name);
+        }
+    }
+    Ok(upgraded)
+}
+
+/// Upgrades every collection of every database stored in the given data directory.
+///
+/// ## Arguments
+///
+/// * `data_dir` - The directory holding one subdirectory per database.
+///
+/// ## Returns
+///
+/// Returns [`Ok`]\([`UpgradeReport`]) describing the upgraded collections,
+/// or [`Err`]\([`io::Error`]) if the directory could not be read or a collection
+/// could not be upgraded.
+pub fn upgrade_data_dir(data_dir: &Path) -> io::Result<UpgradeReport> {
+    if !data_dir.is_dir() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("Data directory not found: {}", data_dir.display()),
+        ));
+    }
+
+    let mut report = UpgradeReport::default();
+    for database in subdirectories(data_dir)? {
+        let database_dir = data_dir.join(&database);
+        report.databases += 1;
+        report.collections += subdirectories(&database_dir)?.len();
+        for collection in upgrade_database(&database_dir)? {
+            report.upgraded.push(format!("{}/{}", database, collection));
+        }
+    }
+    Ok(report)
+}
+
+/// Upgrades a collection from the headerless files of version zero to version one.
+///
+/// Every log segment and metadata generation gets a format header. As this moves every
+/// log entry back by the size of the header, the positions that delta-encoded updates
+/// refer to are moved along, and the primary index is removed to be rebuilt on load.
+///
+/// ## Arguments
+///
+/// * `collection_dir` - The directory of the collection.
+fn upgrade_to_v1(collection_dir: &Path) -> io::Result<()> {
+    migrate_legacy_logfile(collection_dir)?;
+    remove_stale_compaction_files(collection_dir)?;
+
+    match fs::remove_file(collection_dir.join(INDEX_FILE)) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e),
+    }
+
+    for segment in list_segments(collection_dir)? {
+        let path = collection_dir.join(segment_file_name(segment));
+        let contents = fs::read(&path)?;
+        if format_version(FileKind::LogSegment, &contents).is_some() {
+            continue;
+        }
+        let mut upgraded = encode_format_header(FileKind::LogSegment, false).to_vec();
+        upgraded.extend(shift_delta_bases(contents)?);
+        replace_file(&path, &upgraded)?;
+    }
+
+    for name in METADATA_FILES {
+        let path = collection_dir.join(name);
+        let Some(mut metadata) = read_headerless_metadata(&path)? else {
+            continue;
+        };
+        if let Ok(log_length) = metadata.get_i64("log_length") {
+            let log_segment = metadata.get_i64("log_segment").unwrap_or(0) as u64;
+            if collection_dir.join(segment_file_name(log_segment)).exists() {
+                let log_length = log_length + FORMAT_HEADER_SIZE as i64;
+                metadata.insert("log_length", Bson::Int64(log_length));
+            }
+        }
+
+        let mut upgraded = encode_format_header(FileKind::Metadata, false).to_vec();
+        metadata
+            .to_writer(&mut upgraded)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        replace_file(&path, &upgraded)?;
+    }
+
+    sync_dir(collection_dir)
+}
+
+/// Moves the full image positions of the delta-encoded updates in a headerless segment
+/// back by the size of the format header.
+///
+/// Positions are stored as 64-bit integers, so every frame keeps its size and the
+/// offsets of all entries only move by the header. Damaged bytes are copied unchanged.
+///
+/// ## Arguments
+///
+/// * `contents` - The contents of the segment.
+///
+/// ## Returns
+///
+/// Returns [`Ok`]\([`Vec`]<[`u8`]>) with the updated contents,
+/// or [`Err`]\([`io::Error`]) if an entry could not be re-encoded in place.
+fn shift_delta_bases(mut contents: Vec<u8>) -> io::Result<Vec<u8>> {
+    let mut offset = 0;
+    while offset < contents.len() {
+        let Some((log_entry, frame_len)) = decode_frame(&contents[offset..], None)? else {
+            offset += 1;
+            continue;
+        };
+        if log_entry.base.is_some() {
+            let payload = &contents[offset + FRAME_HEADER_SIZE..offset + frame_len];
+            let mut log_document = BsonDocument::from_reader(payload)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            if let Ok(base) = log_document.get_document_mut("base")
+                && let Ok(base_offset) = base.get_i64("offset")
+            {
+                let base_offset = base_offset + FORMAT_HEADER_SIZE as i64;
+                base.insert("offset", Bson::Int64(base_offset));
+            }
+
+            let shifted = log_document
+                .to_vec()
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            let frame = encode_frame(&shifted);
+            if frame.len() != frame_len {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Log entry at offset {} changed size when upgraded", offset),
+                ));
+            }
+            contents[offset..offset + frame_len].copy_from_slice(&frame);
+        }
+        offset += frame_len;
+    }
+    Ok(contents)
+}
+
+/// Returns the format versions of the readable metadata generations of a collection.
+///
+/// ## Arguments
+///
+/// * `collection_dir` - The directory of the collection.
+fn metadata_versions(collection_dir: &Path) -> io::Result<Vec<u16>> {
+    let mut versions = Vec::new();
+    for name in METADATA_FILES {
+        versions.extend(metadata_file_version(&collection_dir.join(name))?);
+    }
+    Ok(versions)
+}
+
+/// Returns the format version of a single metadata file.
+///
+/// ## Arguments
+///
+/// * `path` - The path to the metadata file.
+///
+/// ## Returns
+///
+/// Returns [`Ok`]\([`Some`]\([`u16`])) with the version of the file, [`Ok`]\([`None`]) if it
+/// does not exist or is damaged, or [`Err`]\([`io::Error`]) if it could not be read.
+fn metadata_file_version(path: &Path) -> io::Result<Option<u16>> {
+    let mut header = Vec::with_capacity(FORMAT_HEADER_SIZE);
+    match File::open(path) {
+        Ok(file) => file
+            .take(FORMAT_HEADER_SIZE as u64)
+            .read_to_end(&mut header)?,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+
+    if let Some(version) = format_version(FileKind::Metadata, &header) {
+        return Ok(Some(version));
+    }
+    Ok(read_headerless_metadata(path)?.map(|_| 0))
+}
+
+/// Reads a metadata file written before format headers were introduced.
+///
+/// ## Arguments
+///
+/// * `path` - The path to the metadata file.
+///
+/// ## Returns
+///
+/// Returns [`Ok`]\([`Some`]\([`BsonDocument`])) with the metadata, [`Ok`]\([`None`]) if the
+/// file does not exist, already has a header or is damaged,
+/// or [`Err`]\([`io::Error`]) if it could not be read.
+fn read_headerless_metadata(path: &Path) -> io::Result<Option<BsonDocument>> {
+    let contents = match fs::read(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    if format_version(FileKind::Metadata, &contents).is_some() {
+        return Ok(None);
+    }
+    Ok(BsonDocument::from_reader(contents.as_slice()).ok())
+}
+
+/// Replaces a file with the given contents through a temporary file.
+///
+/// ## Arguments
+///
+/// * `path` - The path to the file to replace.
+/// * `contents` - The new contents of the file.
+fn replace_file(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let temp_path = path.with_extension(UPGRADE_FILE_EXTENSION);
+    let mut temp_file = OpenOptions::new()
+        .create(true)
+        .truncate(true)
+        .write(true)
+        .open(&temp_path)?;
+    temp_file.write_all(contents)?;
+    temp_file.sync_all()?;
+    fs::rename(temp_path, path)
+}
+
+/// Lists the names of the subdirectories of a directory, in ascending order.
+///
+/// ## Arguments
+///
+/// * `dir` - The directory to list.
+fn subdirectories(dir: &Path) -> io::Result<Vec<String>> {
+    let mut names = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir()
+            && let Some(name) = entry.file_name().to_str()
+        {
+            names.push(name.to_string());
+        }
+    }
+    names.sort();
+    Ok(names)
+}
@@ -0,0 +1,492 @@
+//! # Key Codec
+//!
+//! Provides the order-preserving binary encoding of the values making up index keys.
+//!
+//! [`BPlusTree`](crate::index::tree::BPlusTree) compares keys as raw bytes, so the encoding
+//! of a value is chosen for its bytes to compare like the value itself: whenever
+//! [`BsonComparable`](crate::query::BsonComparable) orders two values, their encodings are
+//! ordered the same way. Every encoding starts with a tag identifying the kind of value,
+//! null sorting before booleans, numbers and strings, and no encoding is a prefix of
+//! another, so that a sequence of encodings forms a tuple ordered field by field.
+//!
+//! Integers and floating point numbers share a tag and are ordered by their exact values,
+//! an integer sorting before a floating point number of the same value. Strings are
+//! terminated with two zero bytes, escaping the zero bytes they contain as a zero byte
+//! followed by `0xFF`.
+
+use crate::document::DocId;
+use bson::Bson;
+use std::io;
+
+/// The tag of null values.
+const TAG_NULL: u8 = 0x01;
+
+/// The tag of boolean values.
+const TAG_BOOLEAN: u8 = 0x02;
+
+/// The tag of integers and floating point numbers.
+const TAG_NUMBER: u8 = 0x03;
+
+/// The tag of string values.
+const TAG_STRING: u8 = 0x04;
+
+/// The class of negative infinity.
+const CLASS_NEGATIVE_INFINITY: u8 = 0x01;
+
+/// The class of negative finite numbers.
+const CLASS_NEGATIVE: u8 = 0x02;
+
+/// The class of zero.
+const CLASS_ZERO: u8 = 0x03;
+
+/// The class of positive finite numbers.
+const CLASS_POSITIVE: u8 = 0x04;
+
+/// The class of positive infinity.
+const CLASS_POSITIVE_INFINITY: u8 = 0x05;
+
+/// The class of NaN, which compares with no number.
+const CLASS_NAN: u8 = 0x06;
+
+/// The type suffix of 32-bit integers.
+const NUMBER_INT32: u8 = 0x01;
+
+/// The type suffix of 64-bit integers.
+const NUMBER_INT64: u8 = 0x02;
+
+/// The type suffix of floating point numbers.
+const NUMBER_DOUBLE: u8 = 0x03;
+
+/// The tag of [`DocId::U64`] identifiers.
+const TAG_ID_U64: u8 = 0x01;
+
+/// The tag of [`DocId::String`] identifiers.
+const TAG_ID_STRING: u8 = 0x02;
+
+/// Encodes a value.
+///
+/// ## Arguments
+///
+/// * `value` - The value to encode.
+///
+/// ## Returns
+///
+/// Returns [`Some`]\([`Vec<u8>`]) with the encoded value,
+/// or [`None`] if it is not a null, boolean, number or string.
+pub fn encode_value(value: &Bson) -> Option<Vec<u8>> {
+    let mut bytes = Vec::new();
+    encode_value_into(value, &mut bytes)?;
+    Some(bytes)
+}
+
+/// Encodes a value, appending it to a buffer.
+///
+/// ## Arguments
+///
+/// * `value` - The value to encode.
+/// * `bytes` - The buffer to append the encoding to.
+///
+/// ## Returns
+///
+/// Returns [`Some`]\(()) if the value was appended,
+/// or [`None`] if it is not a null, boolean, number or string.
+pub fn encode_value_into(value: &Bson, bytes: &mut Vec<u8>) -> Option<()> {
+    match value {
+        Bson::Null => bytes.push(TAG_NULL),
+        Bson::Boolean(value) => bytes.extend([TAG_BOOLEAN, *value as u8]),
+        Bson::Int32(value) => encode_integer(*value as i64, NUMBER_INT32, bytes),
+        Bson::Int64(value) => encode_integer(*value, NUMBER_INT64, bytes),
+        Bson::Double(value) => encode_double(*value, bytes),
+        Bson::String(value) => {
+            bytes.push(TAG_STRING);
+            encode_string(value, bytes);
+        }
+        _ => return None,
+    }
+    Some(())
+}
+
+/// Encodes a value as the prefix shared by the encodings of every value equal to it under
+/// [`BsonComparable`](crate::query::BsonComparable), by leaving out the type suffix of
+/// numbers. Integers and floating point numbers of the same value then share the prefix.
+///
+/// ## Arguments
+///
+/// * `value` - The value to encode.
+///
+/// ## Returns
+///
+/// Returns [`Some`]\([`Vec<u8>`]) with the encoded prefix,
+/// or [`None`] if it is not a null, boolean, number or string.
+pub fn encode_comparable(value: &Bson) -> Option<Vec<u8>> {
+    let mut bytes = encode_value(value)?;
+    if bytes[0] == TAG_NUMBER {
+        bytes.pop();
+    }
+    Some(bytes)
+}
+
+/// Decodes a value from the start of a buffer.
+///
+/// A negative zero decodes as zero, to which it is equal.
+///
+/// ## Arguments
+///
+/// * `bytes` - The buffer starting with an encoded value.
+///
+/// ## Returns
+///
+/// Returns [`Ok`]\(`(value, length)`) with the value and the length of its encoding,
+/// or [`Err`]\([`io::Error`]) if the buffer does not start with a valid encoding.
+pub fn decode_value(bytes: &[u8]) -> io::Result<(Bson, usize)> {
+    let (tag, rest) = bytes.split_first().ok_or_else(truncated)?;
+    match *tag {
+        TAG_NULL => Ok((Bson::Null, 1)),
+        TAG_BOOLEAN => match rest.first() {
+            Some(0) => Ok((Bson::Boolean(false), 2)),
+            Some(1) => Ok((Bson::Boolean(true), 2)),
+            Some(_) => Err(invalid("Invalid boolean")),
+            None => Err(truncated()),
+        },
+        TAG_NUMBER => decode_number(rest).map(|(value, length)| (value, length + 1)),
+        TAG_STRING => {
+            let (value, length) = decode_string(rest)?;
+            Ok((Bson::String(value), length + 1))
+        }
+        _ => Err(invalid("Unknown value tag")),
+    }
+}
+
+/// Encodes a tuple of values, ordered by its first value, then its second and so on.
+///
+/// ## Arguments
+///
+/// * `values` - The values to encode.
+///
+/// ## Returns
+///
+/// Returns [`Some`]\([`Vec<u8>`]) with the encoded tuple,
+/// or [`None`] if one of the values cannot be encoded.
+pub fn encode_tuple(values: &[Bson]) -> Option<Vec<u8>> {
+    let mut bytes = Vec::new();
+    for value in values {
+        encode_value_into(value, &mut bytes)?;
+    }
+    Some(bytes)
+}
+
+/// Decodes a tuple of values spanning a whole buffer.
+///
+/// ## Arguments
+///
+/// * `bytes` - The encoded tuple.
+///
+/// ## Returns
+///
+/// Returns [`Ok`]\([`Vec<Bson>`]) with the values,
+/// or [`Err`]\([`io::Error`]) if the buffer is not a sequence of valid encodings.
+pub fn decode_tuple(mut bytes: &[u8]) -> io::Result<Vec<Bson>> {
+    let mut values = Vec::new();
+    while !bytes.is_empty() {
+        let (value, length) = decode_value(bytes)?;
+        values.push(value);
+        bytes = &bytes[length..];
+    }
+    Ok(values)
+}
+
+/// Encodes a document ID, integer IDs sorting before string IDs.
+///
+/// ## Arguments
+///
+/// * `id` - The [`DocId`] to encode.
+pub fn encode_doc_id(id: &DocId) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    encode_doc_id_into(id, &mut bytes);
+    bytes
+}
+
+/// Encodes a document ID, appending it to a buffer.
+///
+/// ## Arguments
+///
+/// * `id` - The [`DocId`] to encode.
+/// * `bytes` - The buffer to append the encoding to.
+pub fn encode_doc_id_into(id: &DocId, bytes: &mut Vec<u8>) {
+    match id {
+        DocId::U64(value) => {
+            bytes.push(TAG_ID_U64);
+            bytes.extend(value.to_be_bytes());
+        }
+        DocId::String(value) => {
+            bytes.push(TAG_ID_STRING);
+            encode_string(value, bytes);
+        }
+    }
+}
+
+/// Decodes a document ID from the start of a buffer.
+///
+/// ## Arguments
+///
+/// * `bytes` - The buffer starting with an encoded document ID.
+///
+/// ## Returns
+///
+/// Returns [`Ok`]\(`(id, length)`) with the [`DocId`] and the length of its encoding,
+/// or [`Err`]\([`io::Error`]) if the buffer does not start with a valid encoding.
+pub fn decode_doc_id(bytes: &[u8]) -> io::Result<(DocId, usize)> {
+    let (tag, rest) = bytes.split_first().ok_or_else(truncated)?;
+    match *tag {
+        TAG_ID_U64 => {
+            let value = rest.get(..8).ok_or_else(truncated)?;
+            let value = u64::from_be_bytes(value.try_into().map_err(|_| truncated())?);
+            Ok((DocId::U64(value), 9))
+        }
+        TAG_ID_STRING => {
+            let (value, length) = decode_string(rest)?;
+            Ok((DocId::String(value), length + 1))
+        }
+        _ => Err(invalid("Unknown document ID tag")),
+    }
+}
+
+/// Appends the escaped bytes of a string followed by its terminator.
+///
+/// ## Arguments
+///
+/// * `value` - The string to encode.
+/// * `bytes` - The buffer to append the encoding to.
+fn encode_string(value: &str, bytes: &mut Vec<u8>) {
+    for &byte in value.as_bytes() {
+        if byte == 0 {
+            bytes.extend([0, 0xFF]);
+        } else {
+            bytes.push(byte);
+        }
+    }
+    bytes.extend([0, 0]);
+}
+
+/// Decodes a string encoded by [`encode_string`] from the start of a buffer.
+///
+/// ## Arguments
+///
+/// * `bytes` - The buffer starting with an encoded string.
+fn decode_string(bytes: &[u8]) -> io::Result<(String, usize)> {
+    let mut value = Vec::new();
+    let mut position = 0;
+    loop {
+        match bytes.get(position) {
+            Some(0) => match bytes.get(position + 1) {
+                Some(0) => break,
+                Some(0xFF) => value.push(0),
+                Some(_) => return Err(invalid("Invalid escape in string")),
+                None => return Err(truncated()),
+            },
+            Some(&byte) => {
+                value.push(byte);
+                position += 1;
+                continue;
+            }
+            None => return Err(truncated()),
+        }
+        position += 2;
+    }
+    let value = String::from_utf8(value).map_err(|_| invalid("String is not valid UTF-8"))?;
+    Ok((value, position + 2))
+}
+
+/// Appends the encoding of an integer.
+///
+/// ## Arguments
+///
+/// * `value` - The integer to encode.
+/// * `number_type` - The type suffix of the integer.
+/// * `bytes` - The buffer to append the encoding to.
+fn encode_integer(value: i64, number_type: u8, bytes: &mut Vec<u8>) {
+    bytes.push(TAG_NUMBER);
+    if value == 0 {
+        bytes.extend([CLASS_ZERO, number_type]);
+    } else {
+        encode_magnitude(value < 0, value.unsigned_abs(), 0, bytes);
+        bytes.push(number_type);
+    }
+}
+
+/// Appends the encoding of a floating point number.
+///
+/// ## Arguments
+///
+/// * `value` - The floating point number to encode.
+/// * `bytes` - The buffer to append the encoding to.
+fn encode_double(value: f64, bytes: &mut Vec<u8>) {
+    bytes.push(TAG_NUMBER);
+    if value.is_nan() {
+        bytes.push(CLASS_NAN);
+    } else if value == 0.0 {
+        bytes.push(CLASS_ZERO);
+    } else if value.is_infinite() {
+        bytes.push(if value < 0.0 {
+            CLASS_NEGATIVE_INFINITY
+        } else {
+            CLASS_POSITIVE_INFINITY
+        });
+    } else {
+        let bits = value.to_bits();
+        let biased_exponent = ((bits >> 52) & 0x7FF) as i32;
+        let fraction = bits & ((1 << 52) - 1);
+        let (mantissa, exponent) = if biased_exponent == 0 {
+            (fraction, -1074)
+        } else {
+            (fraction | (1 << 52), biased_exponent - 1075)
+        };
+        encode_magnitude(value < 0.0, mantissa, exponent, bytes);
+    }
+    bytes.push(NUMBER_DOUBLE);
+}
+
+/// Appends the class and magnitude of a finite non-zero number `mantissa * 2^exponent`.
+///
+/// The mantissa is shifted left until its top bit is set, and the exponent adjusted so
+/// that it is the position of that bit. The exponent is then written before the mantissa,
+/// both big-endian, and both inverted for negative numbers, so that larger magnitudes sort
+/// after smaller ones among positive numbers and before them among negative ones.
+///
+/// ## Arguments
+///
+/// * `negative` - Whether the number is negative.
+/// * `mantissa` - The non-zero mantissa of the number's magnitude.
+/// * `exponent` - The power of two the mantissa is multiplied by.
+/// * `bytes` - The buffer to append the encoding to.
+fn encode_magnitude(negative: bool, mantissa: u64, exponent: i32, bytes: &mut Vec<u8>) {
+    let shift = mantissa.leading_zeros();
+    let mantissa = mantissa << shift;
+    let exponent = ((exponent + 63 - shift as i32) as i16 as u16) ^ 0x8000;
+    if negative {
+        bytes.push(CLASS_NEGATIVE);
+        bytes.extend((!exponent).to_be_bytes());
+        bytes.extend((!mantissa).to_be_bytes());
+    } else {
+        bytes.push(CLASS_POSITIVE);
+        bytes.extend(exponent.to_be_bytes());
+        bytes.extend(mantissa.to_be_bytes());
+    }
+}
+
+/// Decodes a number from the bytes following its tag.
+///
+/// ## Arguments
+///
+/// * `bytes` - The buffer starting with the class of the number.
+fn decode_number(bytes: &[u8]) -> io::Result<(Bson, usize)> {
+    let (class, rest) = bytes.split_first().ok_or_else(truncated)?;
+    let (magnitude, rest) = match *class {
+        CLASS_NEGATIVE | CLASS_POSITIVE => {
+            let encoded = rest.get(..10).ok_or_else(truncated)?;
+            let mut exponent = u16::from_be_bytes([encoded[0], encoded[1]]);
+            let mut mantissa = u64::from_be_bytes(encoded[2..].try_into().unwrap());
+            if *class == CLASS_NEGATIVE {
+                exponent = !exponent;
+                mantissa = !mantissa;
+            }
+            if mantissa >> 63 == 0 {
+                return Err(invalid("Number is not normalized"));
+            }
+            (Some(((exponent ^ 0x8000) as i16, mantissa)), &rest[10..])
+        }
+        CLASS_ZERO | CLASS_NEGATIVE_INFINITY | CLASS_POSITIVE_INFINITY | CLASS_NAN => (None, rest),
+        _ => return Err(invalid("Unknown number class")),
+    };
+    let length = bytes.len() - rest.len() + 1;
+    let number_type = *rest.first().ok_or_else(truncated)?;
+    let negative = *class == CLASS_NEGATIVE;
+
+    let value = match (number_type, *class, magnitude) {
+        (NUMBER_INT32 | NUMBER_INT64, CLASS_ZERO, _) => integer(number_type, 0)?,
+        (NUMBER_INT32 | NUMBER_INT64, _, Some((exponent, mantissa))) => {
+            if !(0..=63).contains(&exponent) || mantissa.trailing_zeros() < (63 - exponent) as u32 {
+                return Err(invalid("Integer is out of range"));
+            }
+            let magnitude = mantissa >> (63 - exponent);
+            let value = if negative {
+                0i64.checked_sub_unsigned(magnitude)
+            } else {
+                i64::try_from(magnitude).ok()
+            };
+            integer(
+                number_type,
+                value.ok_or_else(|| invalid("Integer is out of range"))?,
+            )?
+        }
+        (NUMBER_DOUBLE, CLASS_ZERO, _) => Bson::Double(0.0),
+        (NUMBER_DOUBLE, CLASS_NEGATIVE_INFINITY, _) => Bson::Double(f64::NEG_INFINITY),
+        (NUMBER_DOUBLE, CLASS_POSITIVE_INFINITY, _) => Bson::Double(f64::INFINITY),
+        (NUMBER_DOUBLE, CLASS_NAN, _) => Bson::Double(f64::NAN),
+        (NUMBER_DOUBLE, _, Some((exponent, mantissa))) => {
+            Bson::Double(double(negative, exponent as i32, mantissa)?)
+        }
+        _ => return Err(invalid("Invalid number type")),
+    };
+    Ok((value, length))
+}
+
+/// Builds an integer of the given type suffix.
+///
+/// ## Arguments
+///
+/// * `number_type` - The type suffix of the integer.
+/// * `value` - The value of the integer.
+fn integer(number_type: u8, value: i64) -> io::Result<Bson> {
+    if number_type == NUMBER_INT32 {
+        let value = i32::try_from(value).map_err(|_| invalid("Integer is out of range"))?;
+        Ok(Bson::Int32(value))
+    } else {
+        Ok(Bson::Int64(value))
+    }
+}
+
+/// Rebuilds a finite non-zero floating point number from its normalized magnitude.
+///
+/// ## Arguments
+///
+/// * `negative` - Whether the number is negative.
+/// * `exponent` - The position of the top bit of the magnitude.
+/// * `mantissa` - The normalized mantissa, whose top bit is set.
+fn double(negative: bool, exponent: i32, mantissa: u64) -> io::Result<f64> {
+    let (biased_exponent, fraction, dropped) = if exponent >= -1022 {
+        (
+            exponent + 1023,
+            (mantissa >> 11) & ((1 << 52) - 1),
+            mantissa & 0x7FF,
+        )
+    } else {
+        let shift = (-1011 - exponent) as u32;
+        if shift > 63 {
+            return Err(invalid("Floating point number is out of range"));
+        }
+        (0, mantissa >> shift, mantissa & ((1 << shift) - 1))
+    };
+    if biased_exponent >= 0x7FF || dropped != 0 {
+        return Err(invalid("Floating point number is out of range"));
+    }
+    let bits = ((negative as u64) << 63) | ((biased_exponent as u64) << 52) | fraction;
+    Ok(f64::from_bits(bits))
+}
+
+/// Creates the error of an encoding cut short.
+fn truncated() -> io::Error {
+    io::Error::new(io::ErrorKind::UnexpectedEof, "Encoded key is truncated")
+}
+
+/// Creates the error of an invalid encoding.
+///
+/// ## Arguments
+///
+/// * `message` - The description of the problem.
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("Invalid encoded key: {}", message),
+    )
+}
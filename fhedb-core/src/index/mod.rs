@@ -10,3 +10,12 @@ pub mod node;
 
 /// The tree module - contains the B+ tree structure and operations for managing the index.
 pub mod tree;
+
+/// The codec module - contains the order-preserving binary encoding of index keys.
+pub mod codec;
+
+/// The primary module - contains the persistent document ID to log offset index.
+pub mod primary;
+
+/// The secondary module - contains the persistent field value to document ID indexes.
+pub mod secondary;
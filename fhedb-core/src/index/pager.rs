@@ -1,7 +1,22 @@
 //! # Index Pager
 //!
 //! Manages page-level file I/O for B+ tree indices.
-
+//!
+//! Page 0 holds the pager metadata: the format header, followed by the root and free
+//! page numbers as little-endian `u32`s and the 16 byte checkpoint.
+//!
+//! In an encrypted page file, every page is sealed on its own, bound to its page number,
+//! which adds [`ENCRYPTION_OVERHEAD`] bytes to each page on disk. Only the format header
+//! at the start of page 0 is stored in the clear. In a plaintext page file, every page is
+//! followed by the little-endian CRC32C of its contents, so torn pages are detected when read.
+//! Files written before pages carried checksums keep being read and written without them.
+
+use crate::format::{
+    CHECKSUM_FLAG, FORMAT_HEADER_SIZE, FORMAT_VERSION, FileKind, check_format_header,
+    encode_format_header,
+    encryption::{ENCRYPTION_OVERHEAD, EncryptionKey},
+    format_flags, format_version,
+};
 use std::{
     fs::{File, OpenOptions},
     io::{self, Read, Seek, SeekFrom, Write},
@@ -11,6 +26,9 @@ use std::{
 /// The size of a page in bytes.
 pub const PAGE_SIZE: usize = 4096;
 
+/// The size of the checksum following each page of a plaintext page file.
+pub const PAGE_CHECKSUM_SIZE: usize = 4;
+
 /// A fixed-size block of data representing a page on the disk.
 pub type Page = [u8; PAGE_SIZE];
 
@@ -25,6 +43,17 @@ pub struct Pager {
     root_page_num: u32,
     /// The page number of the first free page in the file.
     free_page_num: u32,
+    /// An application-defined value persisted alongside the pager metadata.
+    checkpoint: [u8; 16],
+    /// The checkpoint value written to the metadata page, which lags behind
+    /// `checkpoint` while a staged value waits to be committed.
+    saved_checkpoint: [u8; 16],
+    /// The key sealing the pages, or [`None`] if the file is stored in the clear.
+    key: Option<EncryptionKey>,
+    /// The name of the page file, which sealed pages are bound to.
+    file_name: String,
+    /// Whether the pages of a plaintext file are followed by a checksum.
+    checksummed: bool,
 }
 
 impl Pager {
@@ -37,9 +66,34 @@ impl Pager {
     /// ## Returns
     ///
     /// Returns [`Ok`]\([`Pager`]) if successful,
-    /// or [`Err`]\([`io::Error`]) if the file could not be opened or is corrupted.
+    /// or [`Err`]\([`io::Error`]) if the file could not be opened, is corrupted,
+    /// is encrypted or was written in a newer format version.
     pub fn new(path: impl AsRef<Path>) -> io::Result<Self> {
-        let file = OpenOptions::new()
+        Self::open(path, None)
+    }
+
+    /// Opens a pager for the specified file path, encrypting the pages of a new file.
+    ///
+    /// An existing file keeps the mode it was created in: encrypted files require the key,
+    /// while plaintext files are read and written in the clear even if a key is given.
+    ///
+    /// ## Arguments
+    ///
+    /// * `path` - The path to the page file. If the file does not exist, it will be created.
+    /// * `key` - The [`EncryptionKey`] sealing the pages, or [`None`] to store them in the clear.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`Pager`]) if successful,
+    /// or [`Err`]\([`io::Error`]) if the file could not be opened, is corrupted, is encrypted
+    /// while no key was given, or was written in a newer format version.
+    pub fn open(path: impl AsRef<Path>, key: Option<EncryptionKey>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let file_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let mut file = OpenOptions::new()
             .read(true)
             .write(true)
             .create(true)
@@ -48,38 +102,70 @@ impl Pager {
 
         let len = file.metadata()?.len();
 
-        if len % PAGE_SIZE as u64 != 0 {
+        let (encrypted, checksummed) = if len == 0 {
+            (key.is_some(), key.is_none())
+        } else {
+            let mut header = [0u8; FORMAT_HEADER_SIZE];
+            file.read_exact(&mut header)?;
+            match format_version(FileKind::Index, &header) {
+                Some(_) => (
+                    check_format_header(FileKind::Index, &header)?,
+                    format_flags(&header) & CHECKSUM_FLAG != 0,
+                ),
+                None => (false, false),
+            }
+        };
+        if encrypted && key.is_none() {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "The index file is encrypted, but no encryption key was given",
+            ));
+        }
+
+        let mut pager = Self {
+            file,
+            total_pages: 0,
+            root_page_num: 0,
+            free_page_num: 0,
+            checkpoint: [0; 16],
+            saved_checkpoint: [0; 16],
+            key: key.filter(|_| encrypted),
+            file_name,
+            checksummed: checksummed && !encrypted,
+        };
+
+        if len % pager.page_stride() != 0 {
             return Err(io::Error::new(
                 io::ErrorKind::InvalidData,
                 "Page file data is not a multiple of page size.",
             ));
         }
 
-        let total_pages = (len / PAGE_SIZE as u64) as u32;
-
-        let pager = if total_pages == 0 {
-            let mut temp = Self {
-                file,
-                total_pages: 1,
-                root_page_num: 0,
-                free_page_num: 0,
-            };
-            temp.save_metadata()?;
-            temp
+        pager.total_pages = (len / pager.page_stride()) as u32;
+        if pager.total_pages == 0 {
+            pager.total_pages = 1;
+            pager.save_metadata()?;
         } else {
-            let mut temp = Self {
-                file,
-                total_pages,
-                root_page_num: 0,
-                free_page_num: 0,
-            };
-            temp.load_metadata()?;
-            temp
-        };
+            pager.load_metadata()?;
+        }
 
         Ok(pager)
     }
 
+    /// Checks whether the pages are encrypted on disk.
+    pub fn is_encrypted(&self) -> bool {
+        self.key.is_some()
+    }
+
+    /// Returns the number of bytes a page takes up on disk.
+    fn page_stride(&self) -> u64 {
+        match self.key {
+            Some(_) => (PAGE_SIZE + ENCRYPTION_OVERHEAD) as u64,
+            None if self.checksummed => (PAGE_SIZE + PAGE_CHECKSUM_SIZE) as u64,
+            None => PAGE_SIZE as u64,
+        }
+    }
+
     /// Creates a new empty page filled with zeroes.
     pub fn new_page(&self) -> Page {
         [0u8; PAGE_SIZE]
@@ -95,6 +181,65 @@ impl Pager {
         self.free_page_num
     }
 
+    /// Returns the checkpoint value stored in the metadata page.
+    pub fn checkpoint(&self) -> [u8; 16] {
+        self.checkpoint
+    }
+
+    /// Sets the checkpoint value and persists the metadata.
+    ///
+    /// The checkpoint is not interpreted by the pager, owners of the page file can use it
+    /// to record how far the file has been synchronized with some external source.
+    ///
+    /// ## Arguments
+    ///
+    /// * `checkpoint` - The value to store.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\(()) if successful,
+    /// or [`Err`]\([`io::Error`]) if the metadata could not be written.
+    pub fn set_checkpoint(&mut self, checkpoint: [u8; 16]) -> io::Result<()> {
+        self.checkpoint = checkpoint;
+        self.saved_checkpoint = checkpoint;
+        self.save_metadata()
+    }
+
+    /// Sets the checkpoint value without persisting it.
+    ///
+    /// The metadata page keeps the previously persisted value until
+    /// [`Pager::commit_checkpoint`] is called, so a crash in between never leaves a
+    /// checkpoint on disk that covers pages which did not reach the disk themselves.
+    ///
+    /// ## Arguments
+    ///
+    /// * `checkpoint` - The value to stage.
+    pub fn stage_checkpoint(&mut self, checkpoint: [u8; 16]) {
+        self.checkpoint = checkpoint;
+    }
+
+    /// Flushes every page written so far to disk, then persists and flushes the checkpoint.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\(()) if successful,
+    /// or [`Err`]\([`io::Error`]) if the pages or the metadata could not be flushed.
+    pub fn commit_checkpoint(&mut self) -> io::Result<()> {
+        self.sync()?;
+        self.saved_checkpoint = self.checkpoint;
+        self.save_metadata()?;
+        self.sync()
+    }
+
+    /// Flushes every page written so far to disk.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\(()) if successful, or [`Err`]\([`io::Error`]) if the flush failed.
+    pub fn sync(&self) -> io::Result<()> {
+        self.file.sync_data()
+    }
+
     /// Sets the root page number and persists the metadata.
     ///
     /// ## Arguments
@@ -110,23 +255,40 @@ impl Pager {
         self.save_metadata()
     }
 
-    /// Loads the pager metadata (root and free page numbers) from page 0.
+    /// Loads the pager metadata (root and free page numbers, checkpoint) from page 0.
+    ///
+    /// A metadata page written before format headers were introduced, which starts
+    /// with the root page number instead, is rewritten in the current format.
     ///
     /// ## Returns
     ///
     /// Returns [`Ok`]\((`root_page_num`, `free_page_num`)) if successful,
-    /// or [`Err`]\([`io::Error`]) if the metadata page could not be read.
+    /// or [`Err`]\([`io::Error`]) if the metadata page could not be read
+    /// or is in an unsupported format version.
     pub fn load_metadata(&mut self) -> io::Result<(u32, u32)> {
         let metadata_page = self.read_page(0)?;
-        let root_page_num = u32::from_le_bytes(metadata_page[0..4].try_into().unwrap());
-        let free_page_num = u32::from_le_bytes(metadata_page[4..8].try_into().unwrap());
-        self.root_page_num = root_page_num;
-        self.free_page_num = free_page_num;
+        let Some(version) = format_version(FileKind::Index, &metadata_page) else {
+            self.root_page_num = u32::from_le_bytes(metadata_page[0..4].try_into().unwrap());
+            self.free_page_num = u32::from_le_bytes(metadata_page[4..8].try_into().unwrap());
+            self.checkpoint = metadata_page[8..24].try_into().unwrap();
+            self.saved_checkpoint = self.checkpoint;
+            self.save_metadata()?;
+            return Ok((self.root_page_num, self.free_page_num));
+        };
+        if version != FORMAT_VERSION {
+            check_format_header(FileKind::Index, &metadata_page)?;
+        }
 
-        Ok((root_page_num, free_page_num))
+        let fields = &metadata_page[FORMAT_HEADER_SIZE..];
+        self.root_page_num = u32::from_le_bytes(fields[0..4].try_into().unwrap());
+        self.free_page_num = u32::from_le_bytes(fields[4..8].try_into().unwrap());
+        self.checkpoint = fields[8..24].try_into().unwrap();
+        self.saved_checkpoint = self.checkpoint;
+
+        Ok((self.root_page_num, self.free_page_num))
     }
 
-    /// Writes the current pager metadata (root and free page numbers) to page 0.
+    /// Writes the current pager metadata (root and free page numbers, checkpoint) to page 0.
     ///
     /// ## Returns
     ///
@@ -134,8 +296,15 @@ impl Pager {
     /// or [`Err`]\([`io::Error`]) if the metadata page could not be written.
     pub fn save_metadata(&mut self) -> io::Result<()> {
         let mut metadata_page = self.new_page();
-        metadata_page[0..4].copy_from_slice(&self.root_page_num.to_le_bytes());
-        metadata_page[4..8].copy_from_slice(&self.free_page_num.to_le_bytes());
+        let mut header = encode_format_header(FileKind::Index, self.key.is_some());
+        if self.checksummed {
+            header[6..8].copy_from_slice(&CHECKSUM_FLAG.to_le_bytes());
+        }
+        metadata_page[..FORMAT_HEADER_SIZE].copy_from_slice(&header);
+        let fields = &mut metadata_page[FORMAT_HEADER_SIZE..];
+        fields[0..4].copy_from_slice(&self.root_page_num.to_le_bytes());
+        fields[4..8].copy_from_slice(&self.free_page_num.to_le_bytes());
+        fields[8..24].copy_from_slice(&self.saved_checkpoint);
 
         self.write_page(0, &metadata_page)
     }
@@ -148,8 +317,8 @@ impl Pager {
     ///
     /// ## Returns
     ///
-    /// Returns [`Ok`]\([`Page`]) if successful,
-    /// or [`Err`]\([`io::Error`]) if the page number is out of bounds or the read failed.
+    /// Returns [`Ok`]\([`Page`]) if successful, or [`Err`]\([`io::Error`]) if the page
+    /// number is out of bounds, the read failed or the page fails its checksum.
     pub fn read_page(&mut self, page_num: u32) -> io::Result<Page> {
         if page_num >= self.total_pages {
             return Err(io::Error::new(
@@ -161,10 +330,38 @@ impl Pager {
             ));
         }
 
-        let mut page = self.new_page();
         self.file
-            .seek(SeekFrom::Start(page_num as u64 * PAGE_SIZE as u64))?;
-        self.file.read_exact(&mut page)?;
+            .seek(SeekFrom::Start(page_num as u64 * self.page_stride()))?;
+        let Some(key) = &self.key else {
+            let mut page = self.new_page();
+            self.file.read_exact(&mut page)?;
+            if self.checksummed {
+                let mut checksum = [0u8; PAGE_CHECKSUM_SIZE];
+                self.file.read_exact(&mut checksum)?;
+                if crc32c::crc32c(&page) != u32::from_le_bytes(checksum) {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!(
+                            "Page {} of index file '{}' fails its checksum",
+                            page_num, self.file_name
+                        ),
+                    ));
+                }
+            }
+            return Ok(page);
+        };
+
+        let mut sealed = vec![0u8; self.page_stride() as usize];
+        self.file.read_exact(&mut sealed)?;
+        let aad = page_aad(&self.file_name, page_num);
+        let mut page = self.new_page();
+        if page_num == 0 {
+            page[..FORMAT_HEADER_SIZE].copy_from_slice(&sealed[..FORMAT_HEADER_SIZE]);
+            page[FORMAT_HEADER_SIZE..]
+                .copy_from_slice(&key.decrypt(&aad, &sealed[FORMAT_HEADER_SIZE..])?);
+        } else {
+            page.copy_from_slice(&key.decrypt(&aad, &sealed)?);
+        }
 
         Ok(page)
     }
@@ -191,11 +388,42 @@ impl Pager {
             ));
         }
 
+        self.store_page(page_num, page)
+    }
+
+    /// Writes a page to its place in the file, sealing it first if the file is encrypted
+    /// or appending its checksum if it is not.
+    ///
+    /// ## Arguments
+    ///
+    /// * `page_num` - The page number to write to, which may lie just past the last page.
+    /// * `page` - The page data to write.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\(()) if successful,
+    /// or [`Err`]\([`io::Error`]) if the page could not be sealed or the write failed.
+    fn store_page(&mut self, page_num: u32, page: &Page) -> io::Result<()> {
         self.file
-            .seek(SeekFrom::Start(page_num as u64 * PAGE_SIZE as u64))?;
-        self.file.write_all(page)?;
+            .seek(SeekFrom::Start(page_num as u64 * self.page_stride()))?;
+        let Some(key) = &self.key else {
+            if !self.checksummed {
+                return self.file.write_all(page);
+            }
+            let mut stored = page.to_vec();
+            stored.extend_from_slice(&crc32c::crc32c(page).to_le_bytes());
+            return self.file.write_all(&stored);
+        };
 
-        Ok(())
+        let aad = page_aad(&self.file_name, page_num);
+        let sealed = if page_num == 0 {
+            let mut sealed = page[..FORMAT_HEADER_SIZE].to_vec();
+            sealed.extend(key.encrypt(&aad, &page[FORMAT_HEADER_SIZE..])?);
+            sealed
+        } else {
+            key.encrypt(&aad, page)?
+        };
+        self.file.write_all(&sealed)
     }
 
     /// Allocates a new page at the end of the file.
@@ -218,9 +446,7 @@ impl Pager {
         let page_num = self.total_pages;
         let empty_page = self.new_page();
 
-        self.file
-            .seek(SeekFrom::Start(page_num as u64 * PAGE_SIZE as u64))?;
-        self.file.write_all(&empty_page)?;
+        self.store_page(page_num, &empty_page)?;
         self.total_pages += 1;
 
         Ok(page_num)
@@ -256,3 +482,17 @@ impl Pager {
         self.total_pages
     }
 }
+
+/// Returns the associated data binding a sealed page to the index file and its page number.
+///
+/// ## Arguments
+///
+/// * `file_name` - The name of the index file.
+/// * `page_num` - The page number.
+fn page_aad(file_name: &str, page_num: u32) -> Vec<u8> {
+    let mut aad = Vec::with_capacity(8 + file_name.len());
+    aad.extend_from_slice(FileKind::Index.magic());
+    aad.extend_from_slice(&page_num.to_le_bytes());
+    aad.extend_from_slice(file_name.as_bytes());
+    aad
+}
@@ -0,0 +1,322 @@
+//! # Primary Index
+//!
+//! Provides the [`PrimaryIndex`] type mapping document IDs to log positions.
+
+use crate::{
+    collection::segment::LogPosition,
+    document::DocId,
+    format::encryption::EncryptionKey,
+    index::{
+        pager::Pager,
+        tree::{BPlusTree, MAX_KEY_SIZE},
+    },
+};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+/// A persistent index mapping [`DocId`]s to the log positions of their latest entries.
+///
+/// The underlying [`BPlusTree`] is opened lazily, so an index whose file does not exist
+/// yet behaves as an empty index until the first write creates it.
+#[derive(Debug, Clone)]
+pub struct PrimaryIndex {
+    /// The path to the index file.
+    path: PathBuf,
+    /// The lazily opened tree, shared between clones of the owning collection.
+    tree: Arc<Mutex<Option<BPlusTree>>>,
+    /// The key sealing the pages of the index file, if it is encrypted.
+    key: Option<EncryptionKey>,
+}
+
+impl PrimaryIndex {
+    /// Creates a new [`PrimaryIndex`] backed by the file at the given path.
+    ///
+    /// ## Arguments
+    ///
+    /// * `path` - The path to the index file. The file is only created on the first write.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            tree: Arc::new(Mutex::new(None)),
+            key: None,
+        }
+    }
+
+    /// Sets the key used to open the index file.
+    ///
+    /// An index file created afterwards is encrypted with the key, while an existing
+    /// plaintext one stays in the clear until it is cleared and rebuilt.
+    ///
+    /// ## Arguments
+    ///
+    /// * `key` - The [`EncryptionKey`] to use, or [`None`] to store the index in the clear.
+    pub fn set_encryption_key(&mut self, key: Option<EncryptionKey>) {
+        self.key = key;
+    }
+
+    /// Checks whether the index file exists and is encrypted.
+    pub fn is_encrypted(&self) -> io::Result<bool> {
+        let encrypted = self.with_tree(false, |tree| Ok(tree.pager().is_encrypted()))?;
+        Ok(encrypted.unwrap_or(false))
+    }
+
+    /// Returns the path to the index file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Checks whether the index file exists on disk.
+    pub fn exists(&self) -> bool {
+        self.path.exists()
+    }
+
+    /// Runs an operation against the underlying tree, opening it first if needed.
+    ///
+    /// ## Arguments
+    ///
+    /// * `create` - Whether to create the index file if it does not exist.
+    /// * `operation` - The operation to run against the tree.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`None`]) if the index file does not exist and `create` is false,
+    /// [`Ok`]\([`Some`]) with the operation's result otherwise,
+    /// or [`Err`]\([`io::Error`]) if the tree could not be opened or the operation failed.
+    fn with_tree<T>(
+        &self,
+        create: bool,
+        operation: impl FnOnce(&mut BPlusTree) -> io::Result<T>,
+    ) -> io::Result<Option<T>> {
+        let mut guard = self
+            .tree
+            .lock()
+            .map_err(|_| io::Error::other("Primary index lock is poisoned"))?;
+
+        if guard.is_none() {
+            if !self.path.exists() {
+                if !create {
+                    return Ok(None);
+                }
+                if let Some(parent) = self.path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+            }
+            *guard = Some(BPlusTree::open(Pager::open(&self.path, self.key.clone())?)?);
+        }
+
+        match guard.as_mut() {
+            Some(tree) => operation(tree).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Looks up the log position of a document.
+    ///
+    /// ## Arguments
+    ///
+    /// * `id` - The [`DocId`] to look up.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`Some`]\([`LogPosition`])) with the position if indexed,
+    /// [`Ok`]\([`None`]) if the document is not indexed,
+    /// or [`Err`]\([`io::Error`]) on I/O failure.
+    pub fn get(&self, id: &DocId) -> io::Result<Option<LogPosition>> {
+        let value = self.with_tree(false, |tree| tree.get(&id.to_key_bytes()))?;
+        Ok(value.flatten().map(|value| decode_position(&value)))
+    }
+
+    /// Checks whether the key of a document ID fits in the index.
+    ///
+    /// ## Arguments
+    ///
+    /// * `id` - The [`DocId`] to check.
+    pub fn fits(id: &DocId) -> bool {
+        id.to_key_bytes().len() <= MAX_KEY_SIZE
+    }
+
+    /// Checks whether a document is present in the index.
+    ///
+    /// ## Arguments
+    ///
+    /// * `id` - The [`DocId`] to check.
+    pub fn contains(&self, id: &DocId) -> io::Result<bool> {
+        Ok(self.get(id)?.is_some())
+    }
+
+    /// Points a document at a new log position, inserting it if it is not indexed yet.
+    ///
+    /// ## Arguments
+    ///
+    /// * `id` - The [`DocId`] to index.
+    /// * `position` - The [`LogPosition`] of the document's latest entry.
+    pub fn insert(&self, id: &DocId, position: LogPosition) -> io::Result<()> {
+        let key = id.to_key_bytes();
+        let value = encode_position(position);
+        self.with_tree(true, |tree| {
+            if tree.get(&key)?.is_some() {
+                tree.update(&key, &value)
+            } else {
+                tree.insert(&key, &value)
+            }
+        })?;
+        Ok(())
+    }
+
+    /// Removes a document from the index. Does nothing if the document is not indexed.
+    ///
+    /// ## Arguments
+    ///
+    /// * `id` - The [`DocId`] to remove.
+    pub fn remove(&self, id: &DocId) -> io::Result<()> {
+        self.with_tree(false, |tree| tree.delete(&id.to_key_bytes()))?;
+        Ok(())
+    }
+
+    /// Returns every indexed document with its log position, ordered by key.
+    pub fn entries(&self) -> io::Result<Vec<(DocId, LogPosition)>> {
+        let entries = self.with_tree(false, |tree| {
+            let mut entries = Vec::new();
+            for item in tree.scan(None, None)? {
+                let (key, value) = item?;
+                let id = DocId::from_key_bytes(&key).ok_or_else(|| {
+                    io::Error::new(io::ErrorKind::InvalidData, "Invalid key in primary index")
+                })?;
+                entries.push((id, decode_position(&value)));
+            }
+            Ok(entries)
+        })?;
+        Ok(entries.unwrap_or_default())
+    }
+
+    /// Returns the number of indexed documents.
+    pub fn len(&self) -> io::Result<usize> {
+        let count = self.with_tree(false, |tree| {
+            let mut count = 0;
+            for item in tree.scan(None, None)? {
+                item?;
+                count += 1;
+            }
+            Ok(count)
+        })?;
+        Ok(count.unwrap_or(0))
+    }
+
+    /// Returns the number of indexed documents, counting no further than a limit.
+    ///
+    /// ## Arguments
+    ///
+    /// * `limit` - The count at which to stop reading the index.
+    pub fn len_up_to(&self, limit: usize) -> io::Result<usize> {
+        let count = self.with_tree(false, |tree| {
+            let mut count = 0;
+            for item in tree.scan(None, None)?.take(limit) {
+                item?;
+                count += 1;
+            }
+            Ok(count)
+        })?;
+        Ok(count.unwrap_or(0))
+    }
+
+    /// Checks whether the index contains no documents.
+    pub fn is_empty(&self) -> io::Result<bool> {
+        let empty = self.with_tree(false, |tree| Ok(tree.scan(None, None)?.next().is_none()))?;
+        Ok(empty.unwrap_or(true))
+    }
+
+    /// Returns the log position up to which the index is known to be in sync.
+    pub fn checkpoint(&self) -> io::Result<LogPosition> {
+        let checkpoint = self.with_tree(false, |tree| Ok(tree.pager().checkpoint()))?;
+        Ok(checkpoint
+            .map(|value| decode_position(&value))
+            .unwrap_or_default())
+    }
+
+    /// Records the log position up to which the index is in sync.
+    ///
+    /// ## Arguments
+    ///
+    /// * `checkpoint` - The [`LogPosition`] just past the last entry covered by the index.
+    pub fn set_checkpoint(&self, checkpoint: LogPosition) -> io::Result<()> {
+        let value = encode_position(checkpoint);
+        self.with_tree(true, |tree| tree.pager().set_checkpoint(value))?;
+        Ok(())
+    }
+
+    /// Records the log position up to which the index is in sync, without persisting it
+    /// until [`PrimaryIndex::commit_checkpoint`] is called.
+    ///
+    /// ## Arguments
+    ///
+    /// * `checkpoint` - The [`LogPosition`] just past the last entry covered by the index.
+    pub fn stage_checkpoint(&self, checkpoint: LogPosition) -> io::Result<()> {
+        let value = encode_position(checkpoint);
+        self.with_tree(true, |tree| {
+            tree.pager().stage_checkpoint(value);
+            Ok(())
+        })?;
+        Ok(())
+    }
+
+    /// Flushes the index pages to disk, then persists the staged checkpoint.
+    /// Does nothing if the index file does not exist.
+    pub fn commit_checkpoint(&self) -> io::Result<()> {
+        self.with_tree(false, |tree| tree.pager().commit_checkpoint())?;
+        Ok(())
+    }
+
+    /// Reads every page of the index file and walks its entries, checking that none is damaged.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\(()) if the index is intact or does not exist,
+    /// or [`Err`]\([`io::Error`]) if a page could not be read or decoded.
+    pub fn verify(&self) -> io::Result<()> {
+        self.with_tree(false, |tree| tree.verify())?;
+        Ok(())
+    }
+
+    /// Removes every entry by deleting the index file.
+    pub fn clear(&self) -> io::Result<()> {
+        let mut guard = self
+            .tree
+            .lock()
+            .map_err(|_| io::Error::other("Primary index lock is poisoned"))?;
+        *guard = None;
+
+        if self.path.exists() {
+            fs::remove_file(&self.path)?;
+        }
+        Ok(())
+    }
+}
+
+/// Encodes a log position as a 16-byte tree value.
+///
+/// The offset is stored first, so values written before logs were split into segments
+/// decode as positions in segment zero.
+///
+/// ## Arguments
+///
+/// * `position` - The [`LogPosition`] to encode.
+fn encode_position(position: LogPosition) -> [u8; 16] {
+    let mut value = [0u8; 16];
+    value[0..8].copy_from_slice(&(position.offset as u64).to_le_bytes());
+    value[8..16].copy_from_slice(&position.segment.to_le_bytes());
+    value
+}
+
+/// Decodes a log position from a 16-byte tree value.
+///
+/// ## Arguments
+///
+/// * `value` - The tree value to decode.
+fn decode_position(value: &[u8; 16]) -> LogPosition {
+    let offset = u64::from_le_bytes(value[0..8].try_into().unwrap()) as usize;
+    let segment = u64::from_le_bytes(value[8..16].try_into().unwrap());
+    LogPosition::new(segment, offset)
+}
@@ -0,0 +1,574 @@
+//! # Secondary Index
+//!
+//! Provides the [`SecondaryIndex`] type mapping the values of a field, or of an ordered list
+//! of fields, to the documents holding them.
+//!
+//! Every entry is keyed by the encoded values followed by the encoded ID of the document
+//! and the length of that ID, so that documents sharing values get distinct keys. Values
+//! and IDs are encoded by the [`codec`](crate::index::codec), whose encodings are ordered
+//! like the values, the values of several fields being encoded as a tuple. The documents
+//! holding values are found by scanning the keys starting with their encoding, and a range
+//! of values by scanning the keys between the encodings of its bounds, after the values of
+//! the preceding fields.
+
+use crate::{
+    document::DocId,
+    format::encryption::EncryptionKey,
+    index::{
+        codec::{decode_doc_id, encode_comparable, encode_doc_id, encode_tuple},
+        pager::Pager,
+        tree::{BPlusTree, MAX_KEY_SIZE},
+    },
+};
+use bson::Bson;
+use std::{
+    fs, io,
+    ops::Bound,
+    path::{Path, PathBuf},
+    slice,
+    sync::{Arc, Mutex},
+};
+
+/// The maximum size in bytes of the encoded values of an entry. Longer strings are
+/// truncated, so that the documents holding them are found along with those sharing the
+/// truncated prefix.
+pub const MAX_INDEXED_VALUE_SIZE: usize = 1024;
+
+/// The value stored with every entry, whose key already holds all of its information.
+const ENTRY_VALUE: [u8; 16] = [0u8; 16];
+
+/// A persistent index mapping the values of a field, or of an ordered list of fields, to
+/// the [`DocId`]s of the documents holding them.
+///
+/// Like the [`PrimaryIndex`](crate::index::primary::PrimaryIndex), the underlying
+/// [`BPlusTree`] is opened lazily and only created on the first write.
+#[derive(Debug, Clone)]
+pub struct SecondaryIndex {
+    /// The names of the indexed fields, in the order their values are keyed by.
+    fields: Vec<String>,
+    /// The path to the index file.
+    path: PathBuf,
+    /// The lazily opened tree, shared between clones of the owning collection.
+    tree: Arc<Mutex<Option<BPlusTree>>>,
+    /// The key sealing the pages of the index file, if it is encrypted.
+    key: Option<EncryptionKey>,
+}
+
+impl SecondaryIndex {
+    /// Creates a new [`SecondaryIndex`] backed by the file at the given path.
+    ///
+    /// ## Arguments
+    ///
+    /// * `field` - The name of the indexed field.
+    /// * `path` - The path to the index file. The file is only created on the first write.
+    pub fn new(field: impl Into<String>, path: impl Into<PathBuf>) -> Self {
+        Self::compound(vec![field.into()], path)
+    }
+
+    /// Creates a new [`SecondaryIndex`] over several fields backed by the file at the given
+    /// path, whose entries are ordered by the value of the first field, then the second and
+    /// so on.
+    ///
+    /// ## Arguments
+    ///
+    /// * `fields` - The names of the indexed fields, which must not be empty.
+    /// * `path` - The path to the index file. The file is only created on the first write.
+    pub fn compound(fields: Vec<String>, path: impl Into<PathBuf>) -> Self {
+        Self {
+            fields,
+            path: path.into(),
+            tree: Arc::new(Mutex::new(None)),
+            key: None,
+        }
+    }
+
+    /// Checks whether the ID of a document fits in an entry alongside values of the
+    /// longest indexed size.
+    ///
+    /// ## Arguments
+    ///
+    /// * `id` - The [`DocId`] to check.
+    pub fn fits(id: &DocId) -> bool {
+        MAX_INDEXED_VALUE_SIZE + encode_doc_id(id).len() + 2 <= MAX_KEY_SIZE
+    }
+
+    /// Returns the name of the first indexed field, the only one unless the index is compound.
+    pub fn field(&self) -> &str {
+        &self.fields[0]
+    }
+
+    /// Returns the names of the indexed fields, in the order their values are keyed by.
+    pub fn fields(&self) -> &[String] {
+        &self.fields
+    }
+
+    /// Returns the path to the index file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Checks whether the index file exists on disk.
+    pub fn exists(&self) -> bool {
+        self.path.exists()
+    }
+
+    /// Sets the key used to open the index file.
+    ///
+    /// ## Arguments
+    ///
+    /// * `key` - The [`EncryptionKey`] to use, or [`None`] to store the index in the clear.
+    pub fn set_encryption_key(&mut self, key: Option<EncryptionKey>) {
+        self.key = key;
+    }
+
+    /// Checks whether the index file exists and is encrypted.
+    pub fn is_encrypted(&self) -> io::Result<bool> {
+        let encrypted = self.with_tree(false, |tree| Ok(tree.pager().is_encrypted()))?;
+        Ok(encrypted.unwrap_or(false))
+    }
+
+    /// Runs an operation against the underlying tree, opening it first if needed.
+    ///
+    /// ## Arguments
+    ///
+    /// * `create` - Whether to create the index file if it does not exist.
+    /// * `operation` - The operation to run against the tree.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`None`]) if the index file does not exist and `create` is false,
+    /// [`Ok`]\([`Some`]) with the operation's result otherwise,
+    /// or [`Err`]\([`io::Error`]) if the tree could not be opened or the operation failed.
+    fn with_tree<T>(
+        &self,
+        create: bool,
+        operation: impl FnOnce(&mut BPlusTree) -> io::Result<T>,
+    ) -> io::Result<Option<T>> {
+        let mut guard = self
+            .tree
+            .lock()
+            .map_err(|_| io::Error::other("Secondary index lock is poisoned"))?;
+
+        if guard.is_none() {
+            if !self.path.exists() {
+                if !create {
+                    return Ok(None);
+                }
+                if let Some(parent) = self.path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+            }
+            *guard = Some(BPlusTree::open(Pager::open(&self.path, self.key.clone())?)?);
+        }
+
+        match guard.as_mut() {
+            Some(tree) => operation(tree).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Records that a document holds a value. Does nothing if the entry already exists,
+    /// or if the value cannot be indexed.
+    ///
+    /// ## Arguments
+    ///
+    /// * `value` - The value of the indexed field.
+    /// * `id` - The [`DocId`] of the document holding it.
+    pub fn insert(&self, value: &Bson, id: &DocId) -> io::Result<()> {
+        self.insert_values(slice::from_ref(value), id)
+    }
+
+    /// Records that a document holds the values of the indexed fields. Does nothing if the
+    /// entry already exists, or if one of the values cannot be indexed.
+    ///
+    /// ## Arguments
+    ///
+    /// * `values` - The values of the indexed fields, in order.
+    /// * `id` - The [`DocId`] of the document holding them.
+    pub fn insert_values(&self, values: &[Bson], id: &DocId) -> io::Result<()> {
+        let Some(key) = entry_key(values, id) else {
+            return Ok(());
+        };
+        self.with_tree(true, |tree| {
+            if tree.get(&key)?.is_none() {
+                tree.insert(&key, &ENTRY_VALUE)?;
+            }
+            Ok(())
+        })?;
+        Ok(())
+    }
+
+    /// Removes the record of a document holding a value. Does nothing if there is none.
+    ///
+    /// ## Arguments
+    ///
+    /// * `value` - The value of the indexed field.
+    /// * `id` - The [`DocId`] of the document that held it.
+    pub fn remove(&self, value: &Bson, id: &DocId) -> io::Result<()> {
+        self.remove_values(slice::from_ref(value), id)
+    }
+
+    /// Removes the record of a document holding the values of the indexed fields.
+    /// Does nothing if there is none.
+    ///
+    /// ## Arguments
+    ///
+    /// * `values` - The values of the indexed fields, in order.
+    /// * `id` - The [`DocId`] of the document that held them.
+    pub fn remove_values(&self, values: &[Bson], id: &DocId) -> io::Result<()> {
+        let Some(key) = entry_key(values, id) else {
+            return Ok(());
+        };
+        self.with_tree(false, |tree| tree.delete(&key))?;
+        Ok(())
+    }
+
+    /// Looks up the documents holding a value.
+    ///
+    /// Documents holding a different string sharing the first
+    /// [`MAX_INDEXED_VALUE_SIZE`] bytes of its encoding are returned as well.
+    ///
+    /// ## Arguments
+    ///
+    /// * `value` - The value to look up.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`Vec<DocId>`]) with the IDs of the documents ordered by value then ID,
+    /// or [`Err`]\([`io::Error`]) if the value cannot be indexed or on I/O failure.
+    pub fn lookup(&self, value: &Bson) -> io::Result<Vec<DocId>> {
+        self.lookup_values(slice::from_ref(value))
+    }
+
+    /// Looks up the documents holding values of the first indexed fields.
+    ///
+    /// Documents holding different strings sharing the first [`MAX_INDEXED_VALUE_SIZE`]
+    /// bytes of the encoding of the values are returned as well.
+    ///
+    /// ## Arguments
+    ///
+    /// * `values` - The values of the first indexed fields, in order.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`Vec<DocId>`]) with the IDs of the documents ordered by values then
+    /// ID, or [`Err`]\([`io::Error`]) if a value cannot be indexed or on I/O failure.
+    pub fn lookup_values(&self, values: &[Bson]) -> io::Result<Vec<DocId>> {
+        let prefix = self.index_values(values)?;
+
+        let ids = self.with_tree(false, |tree| {
+            let mut ids = Vec::new();
+            for item in tree.scan(Some(&prefix), None)? {
+                let (key, _) = item?;
+                if !key.starts_with(&prefix) {
+                    break;
+                }
+                ids.push(entry_doc_id(&key)?);
+            }
+            Ok(ids)
+        })?;
+        Ok(ids.unwrap_or_default())
+    }
+
+    /// Looks up the documents holding a value within a range.
+    ///
+    /// Only values of the bounds' type are in range, and a range without bounds holds
+    /// every value. Documents holding a string sharing the first [`MAX_INDEXED_VALUE_SIZE`]
+    /// bytes of its encoding with a bound may be returned even if they are out of range.
+    ///
+    /// ## Arguments
+    ///
+    /// * `lower` - The lower [`Bound`] of the range.
+    /// * `upper` - The upper [`Bound`] of the range.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`Vec<DocId>`]) with the IDs of the documents ordered by value then ID,
+    /// or [`Err`]\([`io::Error`]) if a bound cannot be indexed, the bounds are of different
+    /// types or on I/O failure.
+    pub fn range(&self, lower: Bound<&Bson>, upper: Bound<&Bson>) -> io::Result<Vec<DocId>> {
+        self.range_after(&[], lower, upper)
+    }
+
+    /// Looks up the documents holding values of the first indexed fields, and a value of
+    /// the next field within a range.
+    ///
+    /// Only values of the bounds' type are in range, and a range without bounds holds
+    /// every value. Documents holding strings sharing the first [`MAX_INDEXED_VALUE_SIZE`]
+    /// bytes of the encoding of the values and a bound may be returned even if they are
+    /// out of range.
+    ///
+    /// ## Arguments
+    ///
+    /// * `values` - The values of the first indexed fields, in order.
+    /// * `lower` - The lower [`Bound`] of the range of the next field.
+    /// * `upper` - The upper [`Bound`] of the range of the next field.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`Vec<DocId>`]) with the IDs of the documents ordered by values then
+    /// ID, or [`Err`]\([`io::Error`]) if there is no next field, a value or bound cannot be
+    /// indexed, the bounds are of different types or on I/O failure.
+    pub fn range_after(
+        &self,
+        values: &[Bson],
+        lower: Bound<&Bson>,
+        upper: Bound<&Bson>,
+    ) -> io::Result<Vec<DocId>> {
+        let Some(field) = self.fields.get(values.len()) else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "Index on {} has no field after {} values",
+                    self.describe(),
+                    values.len()
+                ),
+            ));
+        };
+        let prefix = self.index_values(values)?;
+        let lower = range_bound(&prefix, field, lower)?;
+        let upper = range_bound(&prefix, field, upper)?;
+        let tag = match (bound_bytes(&lower), bound_bytes(&upper)) {
+            (Some(lower), Some(upper)) if lower.get(prefix.len()) != upper.get(prefix.len()) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Range bounds of field '{}' have different types", field),
+                ));
+            }
+            (Some(bound), _) | (None, Some(bound)) => bound.get(prefix.len()).copied(),
+            (None, None) => None,
+        };
+        // The keys in range all start with the values and the type tag of the bounds.
+        let mut scope = prefix;
+        scope.extend(tag);
+        scope.truncate(MAX_INDEXED_VALUE_SIZE);
+        let start = match bound_bytes(&lower) {
+            Some(bound) => bound.to_vec(),
+            None => scope.clone(),
+        };
+
+        let ids = self.with_tree(false, |tree| {
+            let mut ids = Vec::new();
+            for item in tree.scan(Some(&start), None)? {
+                let (key, _) = item?;
+                if !key.starts_with(&scope) {
+                    break;
+                }
+                if let Bound::Excluded(bound) = &lower
+                    && key.starts_with(bound)
+                {
+                    continue;
+                }
+                match &upper {
+                    Bound::Included(bound)
+                        if &key[..key.len().min(bound.len())] > bound.as_slice() =>
+                    {
+                        break;
+                    }
+                    Bound::Excluded(bound)
+                        if &key[..key.len().min(bound.len())] >= bound.as_slice() =>
+                    {
+                        break;
+                    }
+                    _ => {}
+                }
+                ids.push(entry_doc_id(&key)?);
+            }
+            Ok(ids)
+        })?;
+        Ok(ids.unwrap_or_default())
+    }
+
+    /// Encodes values of the first indexed fields as the start of entry keys, truncated to
+    /// [`MAX_INDEXED_VALUE_SIZE`] bytes.
+    ///
+    /// ## Arguments
+    ///
+    /// * `values` - The values of the first indexed fields, in order.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`Vec<u8>`]) with the encoded values, or [`Err`]\([`io::Error`]) if
+    /// there are more values than fields or one of them cannot be indexed.
+    fn index_values(&self, values: &[Bson]) -> io::Result<Vec<u8>> {
+        if values.len() > self.fields.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "Index on {} has fewer fields than {} values",
+                    self.describe(),
+                    values.len()
+                ),
+            ));
+        }
+        index_values(values).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Values of {} cannot be indexed", self.describe()),
+            )
+        })
+    }
+
+    /// Describes the indexed fields for error messages.
+    fn describe(&self) -> String {
+        match self.fields.as_slice() {
+            [field] => format!("field '{}'", field),
+            fields => format!("fields '{}'", fields.join("', '")),
+        }
+    }
+
+    /// Returns the number of entries in the index.
+    pub fn len(&self) -> io::Result<usize> {
+        let count = self.with_tree(false, |tree| {
+            let mut count = 0;
+            for item in tree.scan(None, None)? {
+                item?;
+                count += 1;
+            }
+            Ok(count)
+        })?;
+        Ok(count.unwrap_or(0))
+    }
+
+    /// Checks whether the index contains no entries.
+    pub fn is_empty(&self) -> io::Result<bool> {
+        Ok(self.len()? == 0)
+    }
+
+    /// Flushes the index pages to disk. Does nothing if the index file does not exist.
+    pub fn sync(&self) -> io::Result<()> {
+        self.with_tree(false, |tree| tree.pager().sync())?;
+        Ok(())
+    }
+
+    /// Reads every page of the index file and walks its entries, checking that none is damaged.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\(()) if the index is intact or does not exist,
+    /// or [`Err`]\([`io::Error`]) if a page could not be read or decoded.
+    pub fn verify(&self) -> io::Result<()> {
+        self.with_tree(false, |tree| tree.verify())?;
+        Ok(())
+    }
+
+    /// Removes every entry by deleting the index file.
+    pub fn clear(&self) -> io::Result<()> {
+        let mut guard = self
+            .tree
+            .lock()
+            .map_err(|_| io::Error::other("Secondary index lock is poisoned"))?;
+        *guard = None;
+
+        if self.path.exists() {
+            fs::remove_file(&self.path)?;
+        }
+        Ok(())
+    }
+}
+
+/// Encodes values as the first part of an entry key, truncated to
+/// [`MAX_INDEXED_VALUE_SIZE`] bytes.
+///
+/// ## Arguments
+///
+/// * `values` - The values to encode.
+///
+/// ## Returns
+///
+/// Returns [`Some`]\([`Vec<u8>`]) with the encoded values,
+/// or [`None`] if values of one of their types cannot be indexed.
+fn index_values(values: &[Bson]) -> Option<Vec<u8>> {
+    let mut bytes = encode_tuple(values)?;
+    bytes.truncate(MAX_INDEXED_VALUE_SIZE);
+    Some(bytes)
+}
+
+/// Encodes a range bound following encoded values as the key prefix it is compared against,
+/// which integers and floating point numbers of the same value share.
+///
+/// Encodings longer than [`MAX_INDEXED_VALUE_SIZE`] are truncated, which makes an excluded
+/// bound included, as the truncated encoding is shared by values on both sides of it.
+///
+/// ## Arguments
+///
+/// * `prefix` - The encoded values of the preceding fields.
+/// * `field` - The name of the field the bound applies to.
+/// * `bound` - The [`Bound`] to encode.
+fn range_bound(prefix: &[u8], field: &str, bound: Bound<&Bson>) -> io::Result<Bound<Vec<u8>>> {
+    let encode = |value: &Bson| {
+        let encoded = encode_comparable(value).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Range bound of field '{}' cannot be indexed", field),
+            )
+        })?;
+        Ok::<_, io::Error>([prefix, &encoded].concat())
+    };
+    Ok(match bound {
+        Bound::Included(value) => {
+            let mut bytes = encode(value)?;
+            bytes.truncate(MAX_INDEXED_VALUE_SIZE);
+            Bound::Included(bytes)
+        }
+        Bound::Excluded(value) => {
+            let mut bytes = encode(value)?;
+            if bytes.len() > MAX_INDEXED_VALUE_SIZE {
+                bytes.truncate(MAX_INDEXED_VALUE_SIZE);
+                Bound::Included(bytes)
+            } else {
+                Bound::Excluded(bytes)
+            }
+        }
+        Bound::Unbounded => Bound::Unbounded,
+    })
+}
+
+/// Returns the encoded value of a bound, if it is bounded.
+///
+/// ## Arguments
+///
+/// * `bound` - The encoded [`Bound`].
+fn bound_bytes(bound: &Bound<Vec<u8>>) -> Option<&[u8]> {
+    match bound {
+        Bound::Included(bytes) | Bound::Excluded(bytes) => Some(bytes),
+        Bound::Unbounded => None,
+    }
+}
+
+/// Builds the key of the entry recording that a document holds values.
+///
+/// ## Arguments
+///
+/// * `values` - The values of the indexed fields.
+/// * `id` - The [`DocId`] of the document holding them.
+///
+/// ## Returns
+///
+/// Returns [`Some`]\([`Vec<u8>`]) with the key, or [`None`] if a value cannot be indexed.
+fn entry_key(values: &[Bson], id: &DocId) -> Option<Vec<u8>> {
+    let mut key = index_values(values)?;
+    let id_key = encode_doc_id(id);
+    key.extend_from_slice(&id_key);
+    key.extend((id_key.len() as u16).to_be_bytes());
+    Some(key)
+}
+
+/// Extracts the document ID from an entry key built by [`entry_key`].
+///
+/// ## Arguments
+///
+/// * `key` - The entry key.
+fn entry_doc_id(key: &[u8]) -> io::Result<DocId> {
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData, "Invalid key in secondary index");
+    if key.len() < 2 {
+        return Err(invalid());
+    }
+    let (rest, length) = key.split_at(key.len() - 2);
+    let length = u16::from_be_bytes([length[0], length[1]]) as usize;
+    let start = rest.len().checked_sub(length).ok_or_else(invalid)?;
+    match decode_doc_id(&rest[start..]) {
+        Ok((id, decoded)) if decoded == length => Ok(id),
+        _ => Err(invalid()),
+    }
+}
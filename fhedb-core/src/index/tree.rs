@@ -8,7 +8,11 @@ use crate::index::{
 };
 use std::io;
 
+/// The maximum size in bytes of a key, so that its leaf cell fits in a single page.
+pub const MAX_KEY_SIZE: usize = PAGE_SIZE - NodeHeader::SIZE - SLOT_SIZE - 2 - 16;
+
 /// A disk-backed B+ tree index structure.
+#[derive(Debug)]
 pub struct BPlusTree {
     /// The pager responsible for page-level file I/O.
     pager: Pager,
@@ -98,16 +102,16 @@ impl BPlusTree {
     /// Returns [`Ok`]\(()) if successful,
     /// or [`Err`]\([`io::Error`]) if the key already exists or the payload is too large.
     pub fn insert(&mut self, key: &[u8], value: &[u8; 16]) -> io::Result<()> {
-        let cell = LeafCell { key, value };
-        let cell_bytes = cell.to_bytes();
-
-        if cell_bytes.len() > PAGE_SIZE - NodeHeader::SIZE - SLOT_SIZE {
+        if key.len() > MAX_KEY_SIZE {
             return Err(io::Error::new(
                 io::ErrorKind::InvalidInput,
                 "Key payload too large for a single page",
             ));
         }
 
+        let cell = LeafCell { key, value };
+        let cell_bytes = cell.to_bytes();
+
         let mut current_page_num = self.find_leaf(key)?;
         let mut page = self.pager.read_page(current_page_num)?;
 
@@ -728,6 +732,22 @@ impl BPlusTree {
         Ok(())
     }
 
+    /// Reads every page of the tree and walks all of its entries.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\(()) if every page could be read and decoded,
+    /// or [`Err`]\([`io::Error`]) if a page is damaged.
+    pub fn verify(&mut self) -> io::Result<()> {
+        for page_num in 1..self.pager.page_count() {
+            self.pager.read_page(page_num)?;
+        }
+        for item in self.scan(None, None)? {
+            item?;
+        }
+        Ok(())
+    }
+
     /// Returns a reference to the underlying pager.
     pub fn pager(&mut self) -> &mut Pager {
         &mut self.pager
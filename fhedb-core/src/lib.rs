@@ -23,17 +23,50 @@ pub mod query;
 /// The index module - contains B+ tree index structures and operations.
 pub mod index;
 
+/// The format module - contains the storage file headers, encryption and format upgrades.
+pub mod format;
+
+/// The fhe module - contains the homomorphically encrypted field values.
+pub mod fhe;
+
 /// Commonly used types re-exported for easy access.
 pub mod prelude {
     pub use crate::collection::{
-        Collection,
+        Collection, METADATA_INSERT_BATCH,
+        changes::{ChangeEvent, ChangeFeed, DEFAULT_CHANGE_FEED_CAPACITY, Subscription},
+        compaction::{
+            CompactionPlan, CompactionReport, DEFAULT_COMPACTION_THRESHOLD, LogStats,
+            PreparedCompaction,
+        },
+        compression::Compression,
+        durability::{DEFAULT_GROUP_COMMIT_INTERVAL, Durability},
         file::{LogEntry, Operation},
+        history::parse_timestamp,
+        indexes::is_indexable,
+        reader::{FRAME_HEADER_SIZE, LogEntries, LogReader},
+        recovery::{RecoveryReport, SkippedRegion},
+        segment::{DEFAULT_MAX_SEGMENT_SIZE, LogPosition},
+    };
+    pub use crate::database::{
+        Database,
+        backup::{BackupReport, DatabaseSnapshot},
     };
-    pub use crate::database::Database;
     pub use crate::document::{DocId, Document};
+    pub use crate::fhe::{EncryptedInt, EncryptedString, FheKey};
+    pub use crate::format::{
+        FORMAT_HEADER_SIZE, FORMAT_VERSION, FileKind,
+        encryption::{ENCRYPTION_OVERHEAD, EncryptionKey, KEY_SIZE},
+        upgrade::{UpgradeReport, upgrade_collection, upgrade_data_dir, upgrade_database},
+    };
     pub use crate::index::{
+        codec::{
+            decode_doc_id, decode_tuple, decode_value, encode_comparable, encode_doc_id,
+            encode_tuple, encode_value,
+        },
         node::{InternalCell, LeafCell, Node, NodeHeader, NodeType, SLOT_SIZE},
-        pager::{PAGE_SIZE, Page, Pager},
+        pager::{PAGE_CHECKSUM_SIZE, PAGE_SIZE, Page, Pager},
+        primary::PrimaryIndex,
+        secondary::{MAX_INDEXED_VALUE_SIZE, SecondaryIndex},
         tree::BPlusTree,
     };
     pub use crate::query::{BsonComparable, Unescapable, ValueParseable};
@@ -0,0 +1,87 @@
+//! # Aggregation
+//!
+//! Provides aggregation utilities for query operations.
+
+use crate::{collection::Collection, fhe::EncryptedInt};
+use bson::Bson;
+use fhedb_types::{FieldCondition, FieldType};
+
+/// Document aggregation operations for query execution.
+impl Collection {
+    /// Sums a numeric field over the documents matching the conditions.
+    ///
+    /// Encrypted fields are summed without being decrypted, yielding an encrypted sum.
+    /// Null values of nullable fields are skipped.
+    ///
+    /// ## Arguments
+    ///
+    /// * `field_name` - The name of the field to sum.
+    /// * `conditions` - The conditions documents must match (AND logic).
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\(([`Bson`], [`usize`])) with the sum and the number of values summed,
+    /// or [`Err`]\([`String`]) if the field is unknown or not numeric, or the sum overflows.
+    /// The sum of an encrypted field over no values is [`Bson::Null`], as there is no key
+    /// to encrypt zero under.
+    pub fn sum(
+        &self,
+        field_name: &str,
+        conditions: &[FieldCondition],
+    ) -> Result<(Bson, usize), String> {
+        let field_def = self
+            .schema()
+            .fields
+            .get(field_name)
+            .ok_or_else(|| format!("Unknown field '{}'.", field_name))?;
+        let field_type = match &field_def.field_type {
+            FieldType::Nullable(inner) => inner.as_ref(),
+            field_type => field_type,
+        };
+
+        let values: Vec<Bson> = self
+            .filter(conditions)?
+            .into_iter()
+            .filter_map(|doc| doc.data.get(field_name).cloned())
+            .filter(|value| *value != Bson::Null)
+            .collect();
+        let count = values.len();
+
+        let sum = match field_type {
+            FieldType::Int => {
+                let mut sum = 0i64;
+                for value in &values {
+                    let value = match value {
+                        Bson::Int32(n) => *n as i64,
+                        Bson::Int64(n) => *n,
+                        _ => continue,
+                    };
+                    sum = sum
+                        .checked_add(value)
+                        .ok_or_else(|| format!("Sum of field '{}' overflows.", field_name))?;
+                }
+                Bson::Int64(sum)
+            }
+            FieldType::Float => Bson::Double(values.iter().filter_map(Bson::as_f64).sum()),
+            FieldType::EncryptedInt => {
+                let mut sum: Option<EncryptedInt> = None;
+                for value in &values {
+                    let Bson::String(value) = value else {
+                        continue;
+                    };
+                    let value: EncryptedInt = value.parse()?;
+                    let start = sum.unwrap_or_else(|| EncryptedInt::zero(value.public_key()));
+                    sum = Some(start.add(&value)?);
+                }
+                sum.map_or(Bson::Null, |sum| Bson::String(sum.to_string()))
+            }
+            _ => {
+                return Err(format!(
+                    "Cannot sum field '{}', which is not numeric.",
+                    field_name
+                ));
+            }
+        };
+        Ok((sum, count))
+    }
+}
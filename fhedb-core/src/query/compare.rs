@@ -1,7 +1,12 @@
 //! # BSON Comparison
 //!
 //! Provides comparison operations for BSON values.
+//!
+//! Numbers of different types are compared by their exact values, in the order of their
+//! index key encodings, so that a query scanning an index finds the same documents as one
+//! filtering them.
 
+use crate::index::codec::encode_comparable;
 use bson::Bson;
 use fhedb_types::QueryOperator;
 
@@ -26,8 +31,10 @@ impl BsonComparable for Bson {
         let result = match (self, other) {
             (Bson::Int64(x), Bson::Int64(y)) => compare_ord(x, y, op),
             (Bson::Double(x), Bson::Double(y)) => compare_ord(x, y, op),
-            (Bson::Int64(x), Bson::Double(y)) => compare_ord(&(*x as f64), y, op),
-            (Bson::Double(x), Bson::Int64(y)) => compare_ord(x, &(*y as f64), op),
+            (
+                Bson::Int32(_) | Bson::Int64(_) | Bson::Double(_),
+                Bson::Int32(_) | Bson::Int64(_) | Bson::Double(_),
+            ) => compare_numbers(self, other, op),
             (Bson::String(x), Bson::String(y)) => compare_ord(x, y, op),
             (Bson::Array(_), _) | (_, Bson::Array(_)) => {
                 return Err("Comparison operators not supported for arrays.".to_string());
@@ -39,6 +46,22 @@ impl BsonComparable for Bson {
     }
 }
 
+/// Compares two numbers of any type by their exact values, without rounding integers to
+/// floating point numbers. NaN compares with no number.
+///
+/// ## Arguments
+///
+/// * `a` - First number.
+/// * `b` - Second number.
+/// * `op` - The comparison operator.
+fn compare_numbers(a: &Bson, b: &Bson, op: &QueryOperator) -> bool {
+    let is_nan = |value: &Bson| matches!(value, Bson::Double(value) if value.is_nan());
+    if is_nan(a) || is_nan(b) {
+        return false;
+    }
+    compare_ord(&encode_comparable(a), &encode_comparable(b), op)
+}
+
 /// Compares two values implementing [`PartialOrd`] using the given operator.
 /// Non-comparison operators return false.
 ///
@@ -2,28 +2,119 @@
 //!
 //! Provides document filtering utilities for query operations.
 
-use crate::{collection::Collection, document::Document, schema::SchemaOps};
-use fhedb_types::FieldCondition;
+use crate::{
+    collection::{Collection, file::LogEntry},
+    document::Document,
+    query::ValueParseable,
+    schema::SchemaOps,
+};
+use bson::doc;
+use chrono::{DateTime, Utc};
+use fhedb_types::{FieldCondition, QueryOperator};
 
 /// Document filtering operations for query execution.
 impl Collection {
     /// Filters documents based on conditions.
     ///
+    /// If a condition matches an indexed field with `=`, only the documents the index
+    /// holds for its value are checked against the conditions. Otherwise, if conditions
+    /// compare an indexed field with `<`, `<=`, `>` or `>=`, only the documents the index
+    /// holds within the range they allow are.
+    ///
     /// ## Arguments
     ///
     /// * `conditions` - The conditions to apply (AND logic).
     ///
     /// ## Returns
     ///
-    /// Returns matching documents. Empty conditions returns all documents.
+    /// Returns matching documents, or [`Err`]\([`String`]) if the conditions are invalid
+    /// or the documents could not be read. Empty conditions returns all documents.
     pub fn filter(&self, conditions: &[FieldCondition]) -> Result<Vec<Document>, String> {
-        let all_docs = self.get_documents();
+        let documents = match self.indexed_candidates(conditions)? {
+            Some(documents) => documents,
+            None => self.get_documents().map_err(|e| e.to_string())?,
+        };
+        self.filter_documents(documents, conditions)
+    }
+
+    /// Filters the documents of the collection as they were at a past instant.
+    ///
+    /// Conditions are evaluated against the current schema.
+    ///
+    /// ## Arguments
+    ///
+    /// * `conditions` - The conditions to apply (AND logic).
+    /// * `at` - The instant to read the collection at.
+    ///
+    /// ## Returns
+    ///
+    /// Returns matching documents, or [`Err`]\([`String`]) if the collection's state at
+    /// that instant could not be reconstructed. Empty conditions returns all documents.
+    pub fn filter_as_of(
+        &self,
+        conditions: &[FieldCondition],
+        at: DateTime<Utc>,
+    ) -> Result<Vec<Document>, String> {
+        let documents = self.documents_as_of(at).map_err(|e| e.to_string())?;
+        self.filter_documents(documents, conditions)
+    }
+
+    /// Lists the log entries of the document selected by an equality condition on its ID.
+    ///
+    /// ## Arguments
+    ///
+    /// * `conditions` - A single condition matching the ID field with `=`.
+    ///
+    /// ## Returns
+    ///
+    /// Returns the document's log entries oldest first, as returned by
+    /// [`Collection::document_history`], or [`Err`]\([`String`]) if the conditions
+    /// do not select a single document ID or the log could not be read.
+    pub fn filter_history(&self, conditions: &[FieldCondition]) -> Result<Vec<LogEntry>, String> {
+        let [condition] = conditions else {
+            return Err(format!(
+                "History must be queried with a single condition on the ID field '{}'",
+                self.id_field
+            ));
+        };
+        if condition.field_name != self.id_field || condition.operator != QueryOperator::Equal {
+            return Err(format!(
+                "History must be queried with an equality condition on the ID field '{}'",
+                self.id_field
+            ));
+        }
+
+        let id_type = self
+            .schema()
+            .fields
+            .get(&self.id_field)
+            .map(|definition| definition.field_type.clone())
+            .ok_or_else(|| format!("ID field '{}' not found in schema", self.id_field))?;
+        let id_value = condition.value.parse_as_bson(&id_type)?;
+        let id = self
+            .get_doc_id_from_bson(&doc! { self.id_field.as_str(): id_value })
+            .ok_or_else(|| format!("Invalid value for ID field '{}'", self.id_field))?;
+
+        self.document_history(&id).map_err(|e| e.to_string())
+    }
+
+    /// Keeps the documents matching every condition.
+    ///
+    /// ## Arguments
+    ///
+    /// * `documents` - The documents to filter.
+    /// * `conditions` - The conditions to apply (AND logic).
+    fn filter_documents(
+        &self,
+        documents: Vec<Document>,
+        conditions: &[FieldCondition],
+    ) -> Result<Vec<Document>, String> {
         if conditions.is_empty() {
-            return Ok(all_docs);
+            return Ok(documents);
         }
 
         let mut filtered = Vec::new();
-        for doc in all_docs {
+        for doc in documents {
             let matches = conditions.iter().try_fold(true, |acc, c| {
                 self.schema()
                     .evaluate_condition(&doc.data, c)
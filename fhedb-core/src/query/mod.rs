@@ -2,6 +2,7 @@
 //!
 //! Provides query execution utilities for document operations.
 
+mod aggregate;
 mod compare;
 mod filter;
 mod reference;
@@ -119,10 +119,8 @@ fn split_array_elements(inner: &str) -> Result<Vec<&str>, String> {
                 in_string = true;
                 string_char = c;
             }
-            c if in_string && c == string_char => {
-                if i == 0 || chars[i - 1] != '\\' {
-                    in_string = false;
-                }
+            c if in_string && c == string_char && (i == 0 || chars[i - 1] != '\\') => {
+                in_string = false;
             }
             '[' if !in_string => depth += 1,
             ']' if !in_string => depth -= 1,
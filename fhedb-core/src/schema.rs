@@ -2,7 +2,10 @@
 //!
 //! Schema definitions and validation logic for FHEDB collections.
 
-use crate::query::{BsonComparable, ValueParseable};
+use crate::{
+    fhe::{EncryptedInt, EncryptedString},
+    query::{BsonComparable, ValueParseable},
+};
 use bson::{Bson, Document};
 use std::collections::HashMap;
 
@@ -122,13 +125,8 @@ impl SchemaOps for Schema {
 
         match id_fields.len() {
             0 => {
-                self.fields.insert(
-                    "id".to_string(),
-                    FieldDefinition {
-                        field_type: FieldType::IdInt,
-                        default_value: None,
-                    },
-                );
+                self.fields
+                    .insert("id".to_string(), FieldDefinition::new(FieldType::IdInt));
                 Ok(("id".to_string(), IdType::Int))
             }
             1 => Ok(id_fields[0].clone()),
@@ -223,6 +221,19 @@ impl SchemaOps for Schema {
             .get(&condition.field_name)
             .ok_or_else(|| format!("Unknown field '{}'.", condition.field_name))?;
 
+        let encrypted = is_encrypted(&field_def.field_type);
+        if encrypted
+            && !matches!(
+                condition.operator,
+                QueryOperator::Equal | QueryOperator::NotEqual
+            )
+        {
+            return Err(format!(
+                "Operator '{}' is not supported on encrypted field '{}', only '=' and '!=' are.",
+                condition.operator, condition.field_name
+            ));
+        }
+
         let parse_type = get_parse_type(&field_def.field_type, &condition.operator);
         let condition_value = condition.value.parse_as_bson(parse_type)?;
 
@@ -233,6 +244,10 @@ impl SchemaOps for Schema {
                 QueryOperator::NotEqual => condition_value != Bson::Null,
                 _ => false,
             }),
+            Some(doc_val) if encrypted => {
+                let matches = encrypted_equals(&field_def.field_type, doc_val, &condition_value)?;
+                Ok(matches == (condition.operator == QueryOperator::Equal))
+            }
             Some(doc_val) => match &condition.operator {
                 QueryOperator::Equal => Ok(doc_val == &condition_value),
                 QueryOperator::NotEqual => Ok(doc_val != &condition_value),
@@ -305,6 +320,56 @@ fn get_parse_type<'a>(field_type: &'a FieldType, operator: &QueryOperator) -> &'
     field_type
 }
 
+/// Checks whether a field type holds encrypted values, which only support equality.
+///
+/// ## Arguments
+///
+/// * `field_type` - The field's declared type.
+pub fn is_encrypted(field_type: &FieldType) -> bool {
+    match field_type {
+        FieldType::EncryptedInt | FieldType::EncryptedString => true,
+        FieldType::Nullable(inner) => is_encrypted(inner),
+        _ => false,
+    }
+}
+
+/// Compares an encrypted value against an encrypted condition value through their
+/// equality tags or blind indexes.
+///
+/// ## Arguments
+///
+/// * `field_type` - The field's declared type.
+/// * `value` - The stored [`Bson`] value.
+/// * `condition_value` - The [`Bson`] value from the condition.
+///
+/// ## Returns
+///
+/// Returns [`Ok`]\([`bool`]) indicating whether both encrypt the same value,
+/// or [`Err`]\([`String`]) if they cannot be compared.
+fn encrypted_equals(
+    field_type: &FieldType,
+    value: &Bson,
+    condition_value: &Bson,
+) -> Result<bool, String> {
+    let (Bson::String(value), Bson::String(condition_value)) = (value, condition_value) else {
+        return Ok(false);
+    };
+    let field_type = match field_type {
+        FieldType::Nullable(inner) => inner.as_ref(),
+        field_type => field_type,
+    };
+    match field_type {
+        FieldType::EncryptedString => {
+            let value: EncryptedString = value.parse()?;
+            Ok(value.matches(&condition_value.parse()?))
+        }
+        _ => {
+            let value: EncryptedInt = value.parse()?;
+            value.matches(&condition_value.parse()?)
+        }
+    }
+}
+
 /// Converts a [`Document`] to a [`Schema`].
 ///
 /// ## Arguments
@@ -348,23 +413,18 @@ pub fn schema_to_document(schema: &Schema) -> Document {
 /// Returns [`Some`]\([`FieldDefinition`]) if valid, or [`None`] if not recognized.
 fn parse_field_definition(value: &Bson) -> Option<FieldDefinition> {
     match value {
-        Bson::String(_) => parse_field_type(value).map(|field_type| FieldDefinition {
-            field_type,
-            default_value: None,
-        }),
+        Bson::String(_) => parse_field_type(value).map(FieldDefinition::new),
         Bson::Document(doc) => {
             if doc.contains_key("type") {
                 let field_type = parse_field_type(doc.get("type")?)?;
                 let default_value = doc.get("default").cloned();
-                Some(FieldDefinition {
-                    field_type,
-                    default_value,
-                })
+                let unique = doc.get_bool("unique").unwrap_or(false);
+                Some(
+                    FieldDefinition::with_optional_default(field_type, default_value)
+                        .with_unique(unique),
+                )
             } else {
-                parse_field_type(value).map(|field_type| FieldDefinition {
-                    field_type,
-                    default_value: None,
-                })
+                parse_field_type(value).map(FieldDefinition::new)
             }
         }
         _ => None,
@@ -389,6 +449,8 @@ fn parse_field_type(value: &Bson) -> Option<FieldType> {
             "string" => Some(FieldType::String),
             "id_string" => Some(FieldType::IdString),
             "id_int" => Some(FieldType::IdInt),
+            "encrypted_int" => Some(FieldType::EncryptedInt),
+            "encrypted_string" => Some(FieldType::EncryptedString),
             _ => None,
         },
         Bson::Document(doc) => {
@@ -419,6 +481,8 @@ fn field_type_to_bson(field_type: &FieldType) -> Bson {
         FieldType::String => Bson::String("string".to_string()),
         FieldType::IdString => Bson::String("id_string".to_string()),
         FieldType::IdInt => Bson::String("id_int".to_string()),
+        FieldType::EncryptedInt => Bson::String("encrypted_int".to_string()),
+        FieldType::EncryptedString => Bson::String("encrypted_string".to_string()),
         FieldType::Array(inner_type) => {
             let mut doc = Document::new();
             doc.insert("array", field_type_to_bson(inner_type));
@@ -443,15 +507,19 @@ fn field_type_to_bson(field_type: &FieldType) -> Bson {
 ///
 /// * `field_def` - The [`FieldDefinition`] to convert.
 fn field_definition_to_bson(field_def: &FieldDefinition) -> Bson {
-    match &field_def.default_value {
-        None => field_type_to_bson(&field_def.field_type),
-        Some(default) => {
-            let mut doc = Document::new();
-            doc.insert("type", field_type_to_bson(&field_def.field_type));
-            doc.insert("default", default);
-            Bson::Document(doc)
-        }
+    if field_def.default_value.is_none() && !field_def.unique {
+        return field_type_to_bson(&field_def.field_type);
+    }
+
+    let mut doc = Document::new();
+    doc.insert("type", field_type_to_bson(&field_def.field_type));
+    if let Some(default) = &field_def.default_value {
+        doc.insert("default", default);
+    }
+    if field_def.unique {
+        doc.insert("unique", true);
     }
+    Bson::Document(doc)
 }
 
 /// Checks whether a [`Bson`] value matches the expected [`FieldType`].
@@ -509,5 +577,13 @@ pub fn validate_bson_type(value: &Bson, field_type: &FieldType) -> Result<(), St
             Bson::Int32(_) | Bson::Int64(_) => Ok(()),
             _ => Err("Expected ID as integer".to_string()),
         },
+        FieldType::EncryptedInt => match value {
+            Bson::String(s) if s.parse::<EncryptedInt>().is_ok() => Ok(()),
+            _ => Err("Expected encrypted int".to_string()),
+        },
+        FieldType::EncryptedString => match value {
+            Bson::String(s) if s.parse::<EncryptedString>().is_ok() => Ok(()),
+            _ => Err("Expected encrypted string".to_string()),
+        },
     }
 }
@@ -0,0 +1,209 @@
+use bson::doc;
+use fhedb_core::prelude::*;
+use std::{thread, time::Duration};
+use tempfile::tempdir;
+
+use super::super::common::{make_int_schema, make_string_schema};
+
+#[test]
+fn publishes_writes_with_images() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection = Collection::new("users", make_int_schema(), temp_dir.path()).unwrap();
+    let mut subscription = collection.subscribe_changes(None).unwrap();
+    assert!(subscription.try_next().unwrap().is_none());
+
+    let alice = collection
+        .add_document(doc! { "name": "Alice", "age": 30i64 })
+        .unwrap();
+    collection
+        .update_document(alice.clone(), doc! { "age": 31i64 })
+        .unwrap();
+    collection.remove_document(alice.clone()).unwrap().unwrap();
+
+    let inserted = subscription.try_next().unwrap().unwrap();
+    assert_eq!(inserted.sequence, 1);
+    assert_eq!(inserted.collection, "users");
+    assert_eq!(inserted.operation, Operation::Insert);
+    assert_eq!(inserted.id, alice);
+    assert_eq!(inserted.document.get_str("name").unwrap(), "Alice");
+    assert_eq!(inserted.document.get_i64("age").unwrap(), 30);
+
+    let updated = subscription.try_next().unwrap().unwrap();
+    assert_eq!(updated.sequence, 2);
+    assert_eq!(updated.operation, Operation::Update);
+    assert_eq!(updated.document.get_str("name").unwrap(), "Alice");
+    assert_eq!(updated.document.get_i64("age").unwrap(), 31);
+
+    let removed = subscription.try_next().unwrap().unwrap();
+    assert_eq!(removed.sequence, 3);
+    assert_eq!(removed.operation, Operation::Delete);
+    assert_eq!(removed.id, alice);
+    assert_eq!(removed.document.get_i64("age").unwrap(), 31);
+    assert!(
+        parse_timestamp(&removed.timestamp).unwrap()
+            >= parse_timestamp(&inserted.timestamp).unwrap()
+    );
+
+    assert!(subscription.try_next().unwrap().is_none());
+    assert_eq!(subscription.next_sequence(), 4);
+}
+
+#[test]
+fn failed_writes_are_not_published() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection = Collection::new("users", make_int_schema(), temp_dir.path()).unwrap();
+    let mut subscription = collection.subscribe_changes(None).unwrap();
+
+    assert!(collection.add_document(doc! { "name": 1i64 }).is_err());
+    assert!(
+        collection
+            .remove_document(DocId::from(42u64))
+            .unwrap()
+            .is_none()
+    );
+
+    assert!(subscription.try_next().unwrap().is_none());
+    assert_eq!(collection.change_feed().next_sequence(), 1);
+}
+
+#[test]
+fn resumes_from_sequence() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection = Collection::new("users", make_int_schema(), temp_dir.path()).unwrap();
+    for name in ["Alice", "Bob", "Carol"] {
+        collection
+            .add_document(doc! { "name": name, "age": 20i64 })
+            .unwrap();
+    }
+
+    let mut subscription = collection.subscribe_changes(Some(2)).unwrap();
+    let bob = subscription.try_next().unwrap().unwrap();
+    assert_eq!(bob.sequence, 2);
+    assert_eq!(bob.document.get_str("name").unwrap(), "Bob");
+    let carol = subscription.try_next().unwrap().unwrap();
+    assert_eq!(carol.document.get_str("name").unwrap(), "Carol");
+    assert!(subscription.try_next().unwrap().is_none());
+
+    let mut from_start = collection.subscribe_changes(Some(1)).unwrap();
+    assert_eq!(from_start.try_next().unwrap().unwrap().sequence, 1);
+
+    let mut caught_up = collection.subscribe_changes(Some(4)).unwrap();
+    assert!(caught_up.try_next().unwrap().is_none());
+
+    let ahead = collection.subscribe_changes(Some(5)).unwrap_err();
+    assert!(ahead.contains("ahead of the change feed"));
+}
+
+#[test]
+fn bounded_feed_drops_oldest_changes() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection = Collection::new("users", make_int_schema(), temp_dir.path()).unwrap();
+    collection.set_change_feed(ChangeFeed::new(2));
+    let mut lagging = collection.subscribe_changes(None).unwrap();
+
+    for name in ["Alice", "Bob", "Carol"] {
+        collection
+            .add_document(doc! { "name": name, "age": 20i64 })
+            .unwrap();
+    }
+
+    assert_eq!(collection.change_feed().oldest_sequence(), 2);
+    let stale = collection.subscribe_changes(Some(1)).unwrap_err();
+    assert!(stale.contains("no longer available"));
+    let lagged = lagging.try_next().unwrap_err();
+    assert!(lagged.contains("fell behind"));
+
+    let mut subscription = collection.subscribe_changes(Some(2)).unwrap();
+    assert_eq!(subscription.try_next().unwrap().unwrap().sequence, 2);
+}
+
+#[test]
+fn waits_for_next_change() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection = Collection::new("users", make_int_schema(), temp_dir.path()).unwrap();
+    let mut subscription = collection.subscribe_changes(None).unwrap();
+
+    assert!(
+        subscription
+            .next_timeout(Duration::from_millis(10))
+            .unwrap()
+            .is_none()
+    );
+
+    let reader = thread::spawn(move || subscription.next_timeout(Duration::from_secs(10)));
+    thread::sleep(Duration::from_millis(20));
+    collection
+        .add_document(doc! { "name": "Alice", "age": 30i64 })
+        .unwrap();
+
+    let change = reader.join().unwrap().unwrap().unwrap();
+    assert_eq!(change.sequence, 1);
+    assert_eq!(change.document.get_str("name").unwrap(), "Alice");
+}
+
+#[tokio::test]
+async fn waits_for_next_change_asynchronously() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection = Collection::new("users", make_int_schema(), temp_dir.path()).unwrap();
+    let mut subscription = collection.subscribe_changes(None).unwrap();
+
+    let reader = tokio::spawn(async move { subscription.next_async().await });
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    assert!(!reader.is_finished());
+    collection
+        .add_document(doc! { "name": "Alice", "age": 30i64 })
+        .unwrap();
+
+    let change = tokio::time::timeout(Duration::from_secs(10), reader)
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap();
+    assert_eq!(change.sequence, 1);
+    assert_eq!(change.document.get_str("name").unwrap(), "Alice");
+}
+
+#[test]
+fn database_shares_feed_between_collections() {
+    let temp_dir = tempdir().unwrap();
+    let mut db = Database::new("test_db", temp_dir.path());
+    db.create_collection("users", make_int_schema()).unwrap();
+    db.create_collection("tags", make_string_schema()).unwrap();
+    let mut all = db.subscribe_changes(None, None).unwrap();
+    let mut users = db.subscribe_changes(None, Some("users")).unwrap();
+
+    db.get_collection_mut("tags")
+        .unwrap()
+        .add_document(doc! { "name": "rust", "age": 1i64 })
+        .unwrap();
+    db.get_collection_mut("users")
+        .unwrap()
+        .add_document(doc! { "name": "Alice", "age": 30i64 })
+        .unwrap();
+
+    let first = all.try_next().unwrap().unwrap();
+    assert_eq!((first.sequence, first.collection.as_str()), (1, "tags"));
+    let second = all.try_next().unwrap().unwrap();
+    assert_eq!((second.sequence, second.collection.as_str()), (2, "users"));
+
+    let user = users.try_next().unwrap().unwrap();
+    assert_eq!((user.sequence, user.collection.as_str()), (2, "users"));
+    assert!(users.try_next().unwrap().is_none());
+    assert_eq!(users.next_sequence(), 3);
+
+    let missing = db.subscribe_changes(None, Some("missing")).unwrap_err();
+    assert!(missing.contains("Collection 'missing' not found"));
+}
+
+#[test]
+fn reloaded_database_starts_new_epoch() {
+    let temp_dir = tempdir().unwrap();
+    let mut db = Database::new("test_db", temp_dir.path());
+    db.create_collection("users", make_int_schema()).unwrap();
+    let epoch = db.change_feed().epoch();
+    assert_eq!(db.subscribe_changes(None, None).unwrap().epoch(), epoch);
+
+    let reloaded = Database::from_files("test_db", temp_dir.path()).unwrap();
+    assert_ne!(reloaded.change_feed().epoch(), epoch);
+    assert_eq!(reloaded.change_feed().next_sequence(), 1);
+}
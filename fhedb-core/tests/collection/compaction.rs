@@ -176,3 +176,71 @@ fn logfile_complex_sequence() {
     );
     assert_eq!(doc1_entry.0.document.get_i64("age").unwrap(), 31);
 }
+
+#[test]
+fn logfile_keeps_insertion_order() {
+    let schema = make_int_schema();
+    let temp_dir = tempdir().unwrap();
+    let collection = Collection::new("users", schema, temp_dir.path()).unwrap();
+
+    for id in [3i64, 1, 2] {
+        let doc = doc! { "id": id, "name": format!("User {}", id), "age": 20i64 };
+        collection.append_to_log(&Operation::Insert, &doc).unwrap();
+    }
+    let doc3_v2 = doc! { "id": 3i64, "name": "User 3", "age": 40i64 };
+    collection
+        .append_to_log(&Operation::Update, &doc3_v2)
+        .unwrap();
+
+    assert!(collection.compact_logfile().is_ok());
+    let doc_ids: Vec<_> = collection
+        .read_log_entries()
+        .unwrap()
+        .iter()
+        .map(|e| e.0.document.get_i64("id").unwrap())
+        .collect();
+    assert_eq!(doc_ids, vec![3, 1, 2]);
+}
+
+#[test]
+fn logfile_preserves_timestamps() {
+    let schema = make_int_schema();
+    let temp_dir = tempdir().unwrap();
+    let collection = Collection::new("users", schema, temp_dir.path()).unwrap();
+
+    let doc1_v1 = doc! { "id": 1i64, "name": "Alice", "age": 30i64 };
+    let doc1_v2 = doc! { "id": 1i64, "name": "Alice", "age": 31i64 };
+    let doc2 = doc! { "id": 2i64, "name": "Bob", "age": 25i64 };
+    collection
+        .append_to_log(&Operation::Insert, &doc1_v1)
+        .unwrap();
+    collection.append_to_log(&Operation::Insert, &doc2).unwrap();
+    collection
+        .append_to_log(&Operation::Update, &doc1_v2)
+        .unwrap();
+    let original: Vec<_> = collection
+        .read_log_entries()
+        .unwrap()
+        .into_iter()
+        .map(|e| e.0.timestamp)
+        .collect();
+
+    assert!(collection.compact_logfile().is_ok());
+    let entries = collection.read_log_entries().unwrap();
+    assert_eq!(entries.len(), 2);
+
+    let doc1_entry = &entries[0].0;
+    assert_eq!(doc1_entry.document.get_i64("age").unwrap(), 31);
+    assert_eq!(doc1_entry.timestamp, original[2]);
+    assert_eq!(doc1_entry.inserted_at(), original[0]);
+
+    let doc2_entry = &entries[1].0;
+    assert_eq!(doc2_entry.timestamp, original[1]);
+    assert_eq!(doc2_entry.inserted_at, None);
+    assert_eq!(doc2_entry.inserted_at(), original[1]);
+
+    collection.compact_logfile().unwrap();
+    let recompacted = collection.read_log_entries().unwrap();
+    assert_eq!(recompacted[0].0.timestamp, original[2]);
+    assert_eq!(recompacted[0].0.inserted_at(), original[0]);
+}
@@ -0,0 +1,125 @@
+use bson::doc;
+use fhedb_core::prelude::*;
+use std::fs;
+use tempfile::tempdir;
+
+use super::super::common::{add_named_users, bio, make_int_schema};
+
+fn log_size(collection: &Collection) -> u64 {
+    collection
+        .segment_paths()
+        .unwrap()
+        .iter()
+        .map(|path| fs::metadata(path).unwrap().len())
+        .sum()
+}
+
+#[test]
+fn compression_defaults_to_none() {
+    let temp_dir = tempdir().unwrap();
+    let collection = Collection::new("users", make_int_schema(), temp_dir.path()).unwrap();
+
+    assert_eq!(collection.compression(), Compression::None);
+    assert_eq!("lz4".parse::<Compression>().unwrap(), Compression::Lz4);
+    assert!("gzip".parse::<Compression>().is_err());
+}
+
+#[test]
+fn compressed_log_is_smaller_and_readable() {
+    let temp_dir = tempdir().unwrap();
+    let mut plain = Collection::new("plain", make_int_schema(), temp_dir.path()).unwrap();
+    let mut compressed = Collection::new("compressed", make_int_schema(), temp_dir.path()).unwrap();
+    compressed.set_compression(Compression::Lz4);
+    add_named_users(&mut plain, 10, bio);
+    add_named_users(&mut compressed, 10, bio);
+
+    assert!(log_size(&compressed) * 2 < log_size(&plain));
+
+    let plain_entries = plain.read_log_entries().unwrap();
+    let compressed_entries = compressed.read_log_entries().unwrap();
+    assert_eq!(compressed_entries.len(), 10);
+    for (expected, (entry, position)) in plain_entries.iter().zip(&compressed_entries) {
+        assert_eq!(entry.document, expected.0.document);
+        let read = compressed.read_log_entry_at_offset(*position).unwrap();
+        assert_eq!(read.document, expected.0.document);
+    }
+    assert_eq!(
+        compressed.get_document(DocId::from_u64(3)).unwrap().data,
+        plain.get_document(DocId::from_u64(3)).unwrap().data
+    );
+}
+
+#[test]
+fn compression_recorded_in_metadata() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection = Collection::new("users", make_int_schema(), temp_dir.path()).unwrap();
+    collection.set_compression(Compression::Lz4);
+    add_named_users(&mut collection, 5, bio);
+    collection.write_metadata().unwrap();
+
+    let mut loaded = Collection::from_files(temp_dir.path(), "users").unwrap();
+
+    assert_eq!(loaded.compression(), Compression::Lz4);
+    assert_eq!(loaded.document_count().unwrap(), 5);
+    loaded
+        .update_document(DocId::from_u64(2), doc! { "age": 50i64 })
+        .unwrap();
+    let updated = loaded.get_document(DocId::from_u64(2)).unwrap();
+    assert_eq!(updated.data.get_i64("age").unwrap(), 50);
+    assert!(updated.data.get_str("name").unwrap().starts_with("User 2"));
+}
+
+#[test]
+fn changing_codec_keeps_entries_readable() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection = Collection::new("users", make_int_schema(), temp_dir.path()).unwrap();
+    collection.set_max_segment_size(2048);
+    add_named_users(&mut collection, 10, bio);
+    collection.set_compression(Compression::Lz4);
+    for i in 0..10u64 {
+        collection
+            .update_document(DocId::from_u64(i), doc! { "age": 100i64 })
+            .unwrap();
+    }
+    let size_before = log_size(&collection);
+
+    collection.compact().unwrap();
+
+    assert!(log_size(&collection) < size_before);
+    assert_eq!(collection.document_count().unwrap(), 10);
+    for i in 0..10u64 {
+        let document = collection.get_document(DocId::from_u64(i)).unwrap();
+        assert_eq!(document.data.get_i64("age").unwrap(), 100);
+    }
+
+    collection.set_compression(Compression::None);
+    collection
+        .add_document(doc! { "id": 10i64, "name": "User 10", "age": 30i64 })
+        .unwrap();
+    assert_eq!(collection.read_log_entries().unwrap().len(), 11);
+}
+
+#[test]
+fn database_applies_compression_to_new_collections() {
+    let temp_dir = tempdir().unwrap();
+    let mut db = Database::new("test_db", temp_dir.path());
+    db.create_collection("users", make_int_schema()).unwrap();
+    db.set_compression(Compression::Lz4);
+    db.create_collection("products", make_int_schema()).unwrap();
+
+    assert_eq!(db.compression(), Compression::Lz4);
+    assert_eq!(
+        db.get_collection("users").unwrap().compression(),
+        Compression::None
+    );
+    assert_eq!(
+        db.get_collection("products").unwrap().compression(),
+        Compression::Lz4
+    );
+
+    let loaded = Database::from_files("test_db", temp_dir.path()).unwrap();
+    assert_eq!(
+        loaded.get_collection("products").unwrap().compression(),
+        Compression::Lz4
+    );
+}
@@ -21,7 +21,7 @@ fn get_documents_empty() {
     let schema = make_string_schema();
     let temp_dir = tempdir().unwrap();
     let collection = Collection::new("users", schema, temp_dir.path()).unwrap();
-    let documents = collection.get_documents();
+    let documents = collection.get_documents().unwrap();
     assert_eq!(documents.len(), 0);
 }
 
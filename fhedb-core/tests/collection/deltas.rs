@@ -0,0 +1,171 @@
+use bson::doc;
+use fhedb_core::prelude::*;
+use tempfile::tempdir;
+
+use super::super::common::make_int_schema;
+
+fn add_user(collection: &mut Collection, id: i64) -> DocId {
+    let bio = "x".repeat(512);
+    collection
+        .add_document(doc! { "id": id, "name": bio.as_str(), "age": 20i64 })
+        .unwrap()
+}
+
+fn last_entry(collection: &Collection) -> LogEntry {
+    let end = collection.read_log_entries().unwrap().last().unwrap().1;
+    collection.read_log_entry_at_offset(end).unwrap()
+}
+
+#[test]
+fn delete_logs_tombstone() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection = Collection::new("users", make_int_schema(), temp_dir.path()).unwrap();
+    let id = add_user(&mut collection, 1);
+
+    let removed = collection.remove_document(id).unwrap().unwrap();
+
+    assert_eq!(removed.data.get_i64("age").unwrap(), 20);
+    let tombstone = last_entry(&collection);
+    assert_eq!(tombstone.operation, Operation::Delete);
+    assert_eq!(tombstone.document, doc! { "id": 1i64 });
+    assert_eq!(collection.document_count().unwrap(), 0);
+}
+
+#[test]
+fn small_update_logs_changed_fields() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection = Collection::new("users", make_int_schema(), temp_dir.path()).unwrap();
+    let id = add_user(&mut collection, 1);
+    let image = collection.primary_index().get(&id).unwrap().unwrap();
+    let logged = collection.log_stats().entries;
+
+    collection
+        .update_document(id.clone(), doc! { "age": 21i64 })
+        .unwrap();
+
+    let delta = last_entry(&collection);
+    assert_eq!(delta.operation, Operation::Update);
+    assert_eq!(delta.base, Some(image));
+    assert_eq!(delta.document, doc! { "id": 1i64, "age": 21i64 });
+    assert_eq!(collection.log_stats().entries, logged + 1);
+
+    let document = collection.get_document(id).unwrap();
+    assert_eq!(document.data.get_i64("age").unwrap(), 21);
+    assert_eq!(document.data.get_str("name").unwrap().len(), 512);
+}
+
+#[test]
+fn deltas_accumulate_against_full_image() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection = Collection::new("users", make_int_schema(), temp_dir.path()).unwrap();
+    let id = add_user(&mut collection, 1);
+    let image = collection.primary_index().get(&id).unwrap().unwrap();
+
+    collection
+        .update_document(id.clone(), doc! { "age": 21i64 })
+        .unwrap();
+    collection
+        .update_document(id.clone(), doc! { "age": 22i64 })
+        .unwrap();
+
+    let delta = last_entry(&collection);
+    assert_eq!(delta.base, Some(image));
+    assert_eq!(delta.document, doc! { "id": 1i64, "age": 22i64 });
+}
+
+#[test]
+fn large_update_logs_full_image() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection = Collection::new("users", make_int_schema(), temp_dir.path()).unwrap();
+    let id = add_user(&mut collection, 1);
+
+    collection
+        .update_document(id.clone(), doc! { "name": "y".repeat(512) })
+        .unwrap();
+
+    let entry = last_entry(&collection);
+    assert_eq!(entry.base, None);
+    assert_eq!(entry.document.get_i64("age").unwrap(), 20);
+
+    collection
+        .update_document(id.clone(), doc! { "age": 30i64 })
+        .unwrap();
+    let image = collection.read_log_entries().unwrap()[1].1;
+    assert_eq!(last_entry(&collection).base, Some(image));
+}
+
+#[test]
+fn update_in_new_segment_logs_full_image() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection = Collection::new("users", make_int_schema(), temp_dir.path()).unwrap();
+    collection.set_max_segment_size(1024);
+    let id = add_user(&mut collection, 1);
+    add_user(&mut collection, 2);
+    add_user(&mut collection, 3);
+
+    collection
+        .update_document(id.clone(), doc! { "age": 21i64 })
+        .unwrap();
+
+    let position = collection.primary_index().get(&id).unwrap().unwrap();
+    assert!(position.segment > 0);
+    assert_eq!(last_entry(&collection).base, None);
+}
+
+#[test]
+fn read_log_entries_replays_full_documents() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection = Collection::new("users", make_int_schema(), temp_dir.path()).unwrap();
+    let id = add_user(&mut collection, 1);
+    collection
+        .update_document(id.clone(), doc! { "age": 21i64 })
+        .unwrap();
+    collection.remove_document(id).unwrap();
+
+    let entries = collection.read_log_entries().unwrap();
+    assert_eq!(entries.len(), 3);
+    assert_eq!(entries[1].0.operation, Operation::Update);
+    assert_eq!(entries[1].0.document.get_i64("age").unwrap(), 21);
+    assert_eq!(entries[1].0.document.get_str("name").unwrap().len(), 512);
+    assert_eq!(entries[2].0.operation, Operation::Delete);
+    assert_eq!(entries[2].0.document, doc! { "id": 1i64 });
+
+    let later = collection.read_log_entries_from(entries[1].1).unwrap();
+    assert_eq!(later[0].0.document, entries[1].0.document);
+}
+
+#[test]
+fn deltas_survive_reload_and_compaction() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection = Collection::new("users", make_int_schema(), temp_dir.path()).unwrap();
+    for i in 0..5 {
+        add_user(&mut collection, i);
+    }
+    for i in 0..5u64 {
+        collection
+            .update_document(DocId::from_u64(i), doc! { "age": 30i64 + i as i64 })
+            .unwrap();
+    }
+    collection.remove_document(DocId::from_u64(4)).unwrap();
+    collection.write_metadata().unwrap();
+
+    let mut loaded = Collection::from_files(temp_dir.path(), "users").unwrap();
+    assert_eq!(loaded.document_count().unwrap(), 4);
+    assert_eq!(
+        loaded.get_documents().unwrap()[3]
+            .data
+            .get_i64("age")
+            .unwrap(),
+        33
+    );
+
+    loaded.compact().unwrap();
+    let entries = loaded.read_log_entries().unwrap();
+    assert_eq!(entries.len(), 4);
+    assert!(entries.iter().all(|(entry, _)| entry.base.is_none()));
+    for i in 0..4u64 {
+        let document = loaded.get_document(DocId::from_u64(i)).unwrap();
+        assert_eq!(document.data.get_i64("age").unwrap(), 30 + i as i64);
+        assert_eq!(document.data.get_str("name").unwrap().len(), 512);
+    }
+}
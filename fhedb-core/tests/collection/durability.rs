@@ -0,0 +1,156 @@
+use bson::doc;
+use fhedb_core::prelude::*;
+use std::time::Duration;
+use tempfile::tempdir;
+
+use super::super::common::make_int_schema;
+
+#[test]
+fn default_durability_is_group_commit() {
+    let temp_dir = tempdir().unwrap();
+    let collection = Collection::new("users", make_int_schema(), temp_dir.path()).unwrap();
+
+    assert_eq!(
+        collection.durability(),
+        Durability::GroupCommit(DEFAULT_GROUP_COMMIT_INTERVAL)
+    );
+}
+
+#[test]
+fn always_flushes_every_write() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection = Collection::new("users", make_int_schema(), temp_dir.path()).unwrap();
+    collection.set_durability(Durability::Always);
+
+    collection
+        .add_document(doc! { "id": 1i64, "name": "Alice", "age": 30i64 })
+        .unwrap();
+
+    assert!(!collection.has_unflushed_writes());
+}
+
+#[test]
+fn group_commit_defers_flush_until_interval() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection = Collection::new("users", make_int_schema(), temp_dir.path()).unwrap();
+    collection.set_durability(Durability::GroupCommit(Duration::from_secs(3600)));
+
+    collection
+        .add_document(doc! { "id": 1i64, "name": "Alice", "age": 30i64 })
+        .unwrap();
+    collection
+        .add_document(doc! { "id": 2i64, "name": "Bob", "age": 25i64 })
+        .unwrap();
+    assert!(collection.has_unflushed_writes());
+
+    collection.flush().unwrap();
+    assert!(!collection.has_unflushed_writes());
+}
+
+#[test]
+fn group_commit_persists_index_checkpoint_on_flush() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection = Collection::new("users", make_int_schema(), temp_dir.path()).unwrap();
+    collection.set_durability(Durability::GroupCommit(Duration::from_secs(3600)));
+
+    collection
+        .add_document(doc! { "id": 1i64, "name": "Alice", "age": 30i64 })
+        .unwrap();
+    let log_end = || {
+        let log_len = std::fs::metadata(collection.logfile_path()).unwrap().len();
+        LogPosition::new(0, log_len as usize)
+    };
+    let saved_checkpoint = || {
+        PrimaryIndex::new(collection.index_path())
+            .checkpoint()
+            .unwrap()
+    };
+    assert_eq!(collection.primary_index().checkpoint().unwrap(), log_end());
+    assert_eq!(saved_checkpoint(), LogPosition::default());
+
+    collection.flush().unwrap();
+    assert_eq!(saved_checkpoint(), log_end());
+}
+
+#[test]
+fn always_persists_index_checkpoint_with_every_write() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection = Collection::new("users", make_int_schema(), temp_dir.path()).unwrap();
+    collection.set_durability(Durability::Always);
+
+    collection
+        .add_document(doc! { "id": 1i64, "name": "Alice", "age": 30i64 })
+        .unwrap();
+
+    let log_len = std::fs::metadata(collection.logfile_path()).unwrap().len();
+    assert_eq!(
+        PrimaryIndex::new(collection.index_path())
+            .checkpoint()
+            .unwrap(),
+        LogPosition::new(0, log_len as usize)
+    );
+}
+
+#[test]
+fn group_commit_flushes_once_interval_elapsed() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection = Collection::new("users", make_int_schema(), temp_dir.path()).unwrap();
+    collection.set_durability(Durability::GroupCommit(Duration::ZERO));
+
+    collection
+        .add_document(doc! { "id": 1i64, "name": "Alice", "age": 30i64 })
+        .unwrap();
+
+    assert!(!collection.has_unflushed_writes());
+}
+
+#[test]
+fn never_does_not_track_writes() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection = Collection::new("users", make_int_schema(), temp_dir.path()).unwrap();
+    collection.set_durability(Durability::Never);
+
+    collection
+        .add_document(doc! { "id": 1i64, "name": "Alice", "age": 30i64 })
+        .unwrap();
+
+    assert!(!collection.has_unflushed_writes());
+    assert!(collection.flush().is_ok());
+}
+
+#[test]
+fn database_applies_durability_to_collections() {
+    let temp_dir = tempdir().unwrap();
+    let mut db = Database::new("test_db", temp_dir.path());
+    db.create_collection("users", make_int_schema()).unwrap();
+
+    db.set_durability(Durability::Always);
+    db.create_collection("products", make_int_schema()).unwrap();
+
+    assert_eq!(db.durability(), Durability::Always);
+    assert_eq!(
+        db.get_collection("users").unwrap().durability(),
+        Durability::Always
+    );
+    assert_eq!(
+        db.get_collection("products").unwrap().durability(),
+        Durability::Always
+    );
+}
+
+#[test]
+fn database_flush_clears_pending_writes() {
+    let temp_dir = tempdir().unwrap();
+    let mut db = Database::new("test_db", temp_dir.path());
+    db.set_durability(Durability::GroupCommit(Duration::from_secs(3600)));
+    db.create_collection("users", make_int_schema()).unwrap();
+
+    db.get_collection_mut("users")
+        .unwrap()
+        .add_document(doc! { "id": 1i64, "name": "Alice", "age": 30i64 })
+        .unwrap();
+    assert!(db.get_collection("users").unwrap().has_unflushed_writes());
+
+    db.flush().unwrap();
+    assert!(!db.get_collection("users").unwrap().has_unflushed_writes());
+}
@@ -0,0 +1,269 @@
+use bson::doc;
+use fhedb_core::prelude::*;
+use std::{fs, io};
+use tempfile::tempdir;
+
+use super::super::common::{add_named_users, make_int_schema};
+
+const SECRET: &str = "Top secret biography";
+
+fn secret_name(i: i64) -> String {
+    format!("{} {}", SECRET, i)
+}
+
+fn collection_files(collection: &Collection) -> Vec<std::path::PathBuf> {
+    let mut paths = collection.segment_paths().unwrap();
+    paths.push(collection.metadata_path());
+    paths.push(collection.index_path());
+    paths
+}
+
+fn contains_secret(path: &std::path::Path) -> bool {
+    let contents = fs::read(path).unwrap();
+    contents
+        .windows(SECRET.len())
+        .any(|window| window == SECRET.as_bytes())
+}
+
+#[test]
+fn key_parsed_from_hex_and_file() {
+    let temp_dir = tempdir().unwrap();
+    let hex = "00112233445566778899aabbccddeeff00112233445566778899aabbccddeeff";
+    let key_path = temp_dir.path().join("fhedb.key");
+    fs::write(&key_path, format!("{}\n", hex)).unwrap();
+
+    assert!(EncryptionKey::from_hex(hex).is_ok());
+    assert!(EncryptionKey::from_file(&key_path).is_ok());
+    assert!(EncryptionKey::from_hex("0011").is_err());
+    assert!(EncryptionKey::from_hex(&"zz".repeat(KEY_SIZE)).is_err());
+    assert_eq!(
+        format!("{:?}", EncryptionKey::from_hex(hex).unwrap()),
+        "EncryptionKey(..)"
+    );
+}
+
+#[test]
+fn encrypted_files_reveal_no_data() {
+    let temp_dir = tempdir().unwrap();
+    let (key, _) = EncryptionKey::generate();
+    let mut collection = Collection::new("users", make_int_schema(), temp_dir.path()).unwrap();
+    collection.set_encryption_key(Some(key));
+    add_named_users(&mut collection, 5, secret_name);
+    collection.write_metadata().unwrap();
+
+    for path in collection_files(&collection) {
+        assert!(!contains_secret(&path), "{} leaks data", path.display());
+    }
+    let metadata = fs::read(collection.metadata_path()).unwrap();
+    assert!(!metadata.windows(5).any(|window| window == b"users"));
+
+    let document = collection.get_document(DocId::from_u64(3)).unwrap();
+    assert_eq!(
+        document.data.get_str("name").unwrap(),
+        format!("{} 3", SECRET)
+    );
+}
+
+#[test]
+fn encrypted_collection_reloads_with_key() {
+    let temp_dir = tempdir().unwrap();
+    let (key, _) = EncryptionKey::generate();
+    let mut collection = Collection::new("users", make_int_schema(), temp_dir.path()).unwrap();
+    collection.set_encryption_key(Some(key.clone()));
+    collection.set_compression(Compression::Lz4);
+    collection.set_max_segment_size(512);
+    add_named_users(&mut collection, 10, secret_name);
+    collection
+        .update_document(DocId::from_u64(4), doc! { "age": 99i64 })
+        .unwrap();
+    collection.remove_document(DocId::from_u64(7)).unwrap();
+    collection.write_metadata().unwrap();
+
+    let loaded = Collection::from_files_with_key(temp_dir.path(), "users", Some(key)).unwrap();
+
+    assert!(loaded.recovery_report().is_clean());
+    assert!(loaded.encryption_key().is_some());
+    assert_eq!(loaded.document_count().unwrap(), 9);
+    let updated = loaded.get_document(DocId::from_u64(4)).unwrap();
+    assert_eq!(updated.data.get_i64("age").unwrap(), 99);
+    assert_eq!(
+        updated.data.get_str("name").unwrap(),
+        format!("{} 4", SECRET)
+    );
+    assert!(loaded.get_document(DocId::from_u64(7)).is_none());
+}
+
+#[test]
+fn encrypted_collection_rejects_missing_or_wrong_key() {
+    let temp_dir = tempdir().unwrap();
+    let (key, _) = EncryptionKey::generate();
+    let mut collection = Collection::new("users", make_int_schema(), temp_dir.path()).unwrap();
+    collection.set_encryption_key(Some(key));
+    add_named_users(&mut collection, 3, secret_name);
+    collection.write_metadata().unwrap();
+    let segment = fs::read(collection.logfile_path()).unwrap();
+
+    let missing = Collection::from_files(temp_dir.path(), "users");
+    assert_eq!(missing.unwrap_err().kind(), io::ErrorKind::PermissionDenied);
+
+    let (wrong_key, _) = EncryptionKey::generate();
+    let wrong = Collection::from_files_with_key(temp_dir.path(), "users", Some(wrong_key));
+    assert_eq!(wrong.unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+    assert_eq!(fs::read(collection.logfile_path()).unwrap(), segment);
+}
+
+#[test]
+fn tampered_entry_is_not_read() {
+    let temp_dir = tempdir().unwrap();
+    let (key, _) = EncryptionKey::generate();
+    let mut collection = Collection::new("users", make_int_schema(), temp_dir.path()).unwrap();
+    collection.set_encryption_key(Some(key));
+    add_named_users(&mut collection, 1, secret_name);
+    let position = collection.read_log_entries().unwrap()[0].1;
+
+    let mut contents = fs::read(collection.logfile_path()).unwrap();
+    let last = contents.len() - 1;
+    contents[last] ^= 1;
+    let payload = &contents[position.offset + FRAME_HEADER_SIZE..];
+    let checksum = crc32c::crc32c(payload).to_le_bytes();
+    contents[position.offset + 4..position.offset + 8].copy_from_slice(&checksum);
+    fs::write(collection.logfile_path(), contents).unwrap();
+
+    let result = collection.read_log_entries();
+    assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn moved_entry_is_not_read() {
+    let temp_dir = tempdir().unwrap();
+    let (key, _) = EncryptionKey::generate();
+    let mut users = Collection::new("users", make_int_schema(), temp_dir.path()).unwrap();
+    let mut admins = Collection::new("admins", make_int_schema(), temp_dir.path()).unwrap();
+    for collection in [&mut users, &mut admins] {
+        collection.set_encryption_key(Some(key.clone()));
+        add_named_users(collection, 2, secret_name);
+    }
+    let first = users.read_log_entries().unwrap()[0].1;
+    let second = users.read_log_entries().unwrap()[1].1;
+
+    let mut contents = fs::read(users.logfile_path()).unwrap();
+    let frame = contents[first.offset..second.offset].to_vec();
+    contents.extend_from_slice(&frame);
+    fs::write(users.logfile_path(), &contents).unwrap();
+    let replayed = users.read_log_entries();
+    assert_eq!(replayed.unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+    fs::write(admins.logfile_path(), &contents[..second.offset]).unwrap();
+    let copied = admins.read_log_entries();
+    assert_eq!(copied.unwrap_err().kind(), io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn swapped_metadata_is_not_read() {
+    let temp_dir = tempdir().unwrap();
+    let (key, _) = EncryptionKey::generate();
+    let mut users = Collection::new("users", make_int_schema(), temp_dir.path()).unwrap();
+    let mut admins = Collection::new("admins", make_int_schema(), temp_dir.path()).unwrap();
+    for collection in [&mut users, &mut admins] {
+        collection.set_encryption_key(Some(key.clone()));
+        add_named_users(collection, 2, secret_name);
+        collection.write_metadata().unwrap();
+    }
+
+    fs::copy(users.metadata_path(), admins.metadata_path()).unwrap();
+    fs::remove_file(admins.base_path().join("metadata.prev.bin")).unwrap();
+    let swapped = Collection::from_files_with_key(temp_dir.path(), "admins", Some(key));
+    assert_eq!(swapped.unwrap_err().kind(), io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn plaintext_collection_encrypted_on_load_with_key() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection = Collection::new("users", make_int_schema(), temp_dir.path()).unwrap();
+    collection.set_max_segment_size(512);
+    add_named_users(&mut collection, 6, secret_name);
+    collection.write_metadata().unwrap();
+    collection.write_metadata().unwrap();
+    assert!(contains_secret(&collection.logfile_path()));
+
+    let (key, _) = EncryptionKey::generate();
+    let loaded =
+        Collection::from_files_with_key(temp_dir.path(), "users", Some(key.clone())).unwrap();
+
+    assert_eq!(loaded.document_count().unwrap(), 6);
+    assert!(!loaded.previous_metadata_path().exists());
+    for path in collection_files(&loaded) {
+        assert!(!contains_secret(&path), "{} leaks data", path.display());
+    }
+
+    let reloaded = Collection::from_files_with_key(temp_dir.path(), "users", Some(key)).unwrap();
+    assert_eq!(
+        reloaded
+            .get_document(DocId::from_u64(5))
+            .unwrap()
+            .data
+            .get_i64("age")
+            .unwrap(),
+        25
+    );
+}
+
+#[test]
+fn setting_key_rolls_over_to_encrypted_segment() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection = Collection::new("users", make_int_schema(), temp_dir.path()).unwrap();
+    add_named_users(&mut collection, 2, secret_name);
+
+    let (key, _) = EncryptionKey::generate();
+    collection.set_encryption_key(Some(key.clone()));
+    collection
+        .update_document(DocId::from_u64(0), doc! { "age": 40i64 })
+        .unwrap();
+
+    let segments = collection.segment_paths().unwrap();
+    assert_eq!(segments.len(), 2);
+    assert!(!contains_secret(&segments[1]));
+    assert_eq!(collection.read_log_entries().unwrap().len(), 3);
+    assert_eq!(
+        collection
+            .get_document(DocId::from_u64(0))
+            .unwrap()
+            .data
+            .get_i64("age")
+            .unwrap(),
+        40
+    );
+
+    collection.compact().unwrap();
+    for path in collection.segment_paths().unwrap() {
+        assert!(!contains_secret(&path), "{} leaks data", path.display());
+    }
+    assert_eq!(collection.document_count().unwrap(), 2);
+}
+
+#[test]
+fn database_applies_encryption_key() {
+    let temp_dir = tempdir().unwrap();
+    let (key, _) = EncryptionKey::generate();
+    let mut db = Database::new("test_db", temp_dir.path());
+    db.set_encryption_key(Some(key.clone()));
+    db.create_collection("users", make_int_schema()).unwrap();
+    add_named_users(db.get_collection_mut("users").unwrap(), 3, secret_name);
+    db.get_collection("users")
+        .unwrap()
+        .write_metadata()
+        .unwrap();
+
+    assert!(Database::from_files("test_db", temp_dir.path()).is_err());
+    let loaded = Database::from_files_with_key("test_db", temp_dir.path(), Some(key)).unwrap();
+    assert!(loaded.encryption_key().is_some());
+    assert_eq!(
+        loaded
+            .get_collection("users")
+            .unwrap()
+            .document_count()
+            .unwrap(),
+        3
+    );
+}
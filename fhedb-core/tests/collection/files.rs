@@ -15,7 +15,7 @@ fn from_files_empty_collection() {
 
     assert_eq!(loaded_collection.name, "test_collection");
     assert_eq!(loaded_collection.inserts(), 0);
-    assert_eq!(loaded_collection.document_indices().len(), 0);
+    assert_eq!(loaded_collection.document_count().unwrap(), 0);
     assert_eq!(loaded_collection.id_field_name(), "id");
 }
 
@@ -55,7 +55,7 @@ fn from_files_with_documents() {
 
     assert_eq!(loaded_collection.name, "test_collection");
     assert_eq!(loaded_collection.inserts(), 3);
-    assert_eq!(loaded_collection.document_indices().len(), 3);
+    assert_eq!(loaded_collection.document_count().unwrap(), 3);
 
     let retrieved_doc1 = loaded_collection.get_document(id1).unwrap();
     let retrieved_doc2 = loaded_collection.get_document(id2).unwrap();
@@ -65,7 +65,7 @@ fn from_files_with_documents() {
     assert_eq!(retrieved_doc2.data.get_str("name").unwrap(), "Bob");
     assert_eq!(retrieved_doc3.data.get_str("name").unwrap(), "Charlie");
 
-    let all_docs = loaded_collection.get_documents();
+    let all_docs = loaded_collection.get_documents().unwrap();
     assert_eq!(all_docs.len(), 3);
 }
 
@@ -100,19 +100,19 @@ fn from_files_with_deleted_documents() {
     let id2 = original_collection.add_document(doc2).unwrap();
     let id3 = original_collection.add_document(doc3).unwrap();
 
-    original_collection.remove_document(id2.clone());
+    original_collection.remove_document(id2.clone()).unwrap();
     original_collection.write_metadata().unwrap();
     let loaded_collection = Collection::from_files(temp_dir.path(), "test_collection").unwrap();
 
     assert_eq!(loaded_collection.name, "test_collection");
     assert_eq!(loaded_collection.inserts(), 3);
-    assert_eq!(loaded_collection.document_indices().len(), 2);
+    assert_eq!(loaded_collection.document_count().unwrap(), 2);
 
     assert!(loaded_collection.get_document(id1).is_some());
     assert!(loaded_collection.get_document(id2).is_none());
     assert!(loaded_collection.get_document(id3).is_some());
 
-    let all_docs = loaded_collection.get_documents();
+    let all_docs = loaded_collection.get_documents().unwrap();
     assert_eq!(all_docs.len(), 2);
 }
 
@@ -145,20 +145,16 @@ fn from_files_missing_document_id() {
 
     original_collection.write_metadata().unwrap();
 
-    let logfile_path = original_collection.logfile_path();
-    let corrupt_entry = doc! {
-        "timestamp": "2024-01-01T00:00:00Z",
-        "operation": "INSERT",
-        "document": {
-            "name": "Bob",
-            "age": 25,
-            "active": false
-
-        }
+    std::fs::remove_file(original_collection.logfile_path()).unwrap();
+    std::fs::remove_file(original_collection.index_path()).unwrap();
+    let corrupt_document = doc! {
+        "name": "Bob",
+        "age": 25,
+        "active": false
     };
-
-    let bson_bytes = corrupt_entry.to_vec().unwrap();
-    std::fs::write(&logfile_path, bson_bytes).unwrap();
+    original_collection
+        .append_to_log(&Operation::Insert, &corrupt_document)
+        .unwrap();
 
     let result = Collection::from_files(temp_dir.path(), "test_collection");
 
@@ -191,7 +187,7 @@ fn from_files_complex_operations() {
 
     let id1 = original_collection.add_document(doc1).unwrap();
     let id2 = original_collection.add_document(doc2).unwrap();
-    original_collection.remove_document(id1.clone());
+    original_collection.remove_document(id1.clone()).unwrap();
 
     let doc3 = doc! {
         "id": "user3",
@@ -204,12 +200,212 @@ fn from_files_complex_operations() {
 
     let loaded_collection = Collection::from_files(temp_dir.path(), "test_collection").unwrap();
     assert_eq!(loaded_collection.inserts(), 3);
-    assert_eq!(loaded_collection.document_indices().len(), 2);
+    assert_eq!(loaded_collection.document_count().unwrap(), 2);
 
     assert!(loaded_collection.get_document(id1).is_none());
     assert!(loaded_collection.get_document(id2).is_some());
     assert!(loaded_collection.get_document(id3).is_some());
 
-    let all_docs = loaded_collection.get_documents();
+    let all_docs = loaded_collection.get_documents().unwrap();
     assert_eq!(all_docs.len(), 2);
 }
+
+#[test]
+fn from_files_creates_primary_index() {
+    let temp_dir = tempdir().unwrap();
+    let schema = make_string_schema();
+
+    let mut collection = Collection::new("test_collection", schema, temp_dir.path()).unwrap();
+    let id = collection
+        .add_document(doc! { "id": "user1", "name": "Alice", "age": 30 })
+        .unwrap();
+
+    assert!(collection.index_path().exists());
+    let log_len = std::fs::metadata(collection.logfile_path()).unwrap().len() as usize;
+    assert_eq!(
+        collection.primary_index().checkpoint().unwrap(),
+        LogPosition::new(0, log_len)
+    );
+
+    let loaded_collection = Collection::from_files(temp_dir.path(), "test_collection").unwrap();
+    assert!(loaded_collection.primary_index().contains(&id).unwrap());
+    assert_eq!(
+        loaded_collection
+            .get_document(id)
+            .unwrap()
+            .data
+            .get_str("name")
+            .unwrap(),
+        "Alice"
+    );
+}
+
+#[test]
+fn from_files_replays_entries_after_checkpoint() {
+    let temp_dir = tempdir().unwrap();
+    let schema = make_string_schema();
+
+    let mut collection = Collection::new("test_collection", schema, temp_dir.path()).unwrap();
+    collection
+        .add_document(doc! { "id": "user1", "name": "Alice", "age": 30 })
+        .unwrap();
+    collection.write_metadata().unwrap();
+
+    collection
+        .append_to_log(
+            &Operation::Insert,
+            &doc! { "id": "user2", "name": "Bob", "age": 25 },
+        )
+        .unwrap();
+    collection
+        .append_to_log(
+            &Operation::Delete,
+            &doc! { "id": "user1", "name": "Alice", "age": 30 },
+        )
+        .unwrap();
+
+    let loaded_collection = Collection::from_files(temp_dir.path(), "test_collection").unwrap();
+
+    assert_eq!(loaded_collection.document_count().unwrap(), 1);
+    assert!(
+        loaded_collection
+            .get_document(DocId::from_string("user1".to_string()))
+            .is_none()
+    );
+    assert!(
+        loaded_collection
+            .get_document(DocId::from_string("user2".to_string()))
+            .is_some()
+    );
+
+    let log_len = std::fs::metadata(loaded_collection.logfile_path())
+        .unwrap()
+        .len() as usize;
+    assert_eq!(
+        loaded_collection.primary_index().checkpoint().unwrap(),
+        LogPosition::new(0, log_len)
+    );
+}
+
+#[test]
+fn from_files_rebuilds_missing_primary_index() {
+    let temp_dir = tempdir().unwrap();
+    let schema = make_string_schema();
+
+    let mut collection = Collection::new("test_collection", schema, temp_dir.path()).unwrap();
+    let id1 = collection
+        .add_document(doc! { "id": "user1", "name": "Alice", "age": 30 })
+        .unwrap();
+    let id2 = collection
+        .add_document(doc! { "id": "user2", "name": "Bob", "age": 25 })
+        .unwrap();
+    collection.remove_document(id1.clone()).unwrap();
+    collection.write_metadata().unwrap();
+
+    std::fs::remove_file(collection.index_path()).unwrap();
+
+    let loaded_collection = Collection::from_files(temp_dir.path(), "test_collection").unwrap();
+
+    assert!(loaded_collection.index_path().exists());
+    assert_eq!(loaded_collection.document_count().unwrap(), 1);
+    assert!(loaded_collection.get_document(id1).is_none());
+    assert!(loaded_collection.get_document(id2).is_some());
+}
+
+#[test]
+fn from_files_rebuilds_damaged_primary_index() {
+    let temp_dir = tempdir().unwrap();
+    let schema = make_string_schema();
+
+    let mut collection = Collection::new("test_collection", schema, temp_dir.path()).unwrap();
+    let id1 = collection
+        .add_document(doc! { "id": "user1", "name": "Alice", "age": 30 })
+        .unwrap();
+    let id2 = collection
+        .add_document(doc! { "id": "user2", "name": "Bob", "age": 25 })
+        .unwrap();
+    collection.write_metadata().unwrap();
+
+    let mut contents = std::fs::read(collection.index_path()).unwrap();
+    let leaf = PAGE_SIZE + PAGE_CHECKSUM_SIZE;
+    contents[leaf..leaf + 64].fill(0xFF);
+    std::fs::write(collection.index_path(), contents).unwrap();
+    assert!(collection.primary_index().verify().is_err());
+
+    let loaded_collection = Collection::from_files(temp_dir.path(), "test_collection").unwrap();
+
+    assert!(loaded_collection.recovery_report().index_rebuilt);
+    assert_eq!(loaded_collection.document_count().unwrap(), 2);
+    assert!(loaded_collection.get_document(id1).is_some());
+    assert!(loaded_collection.get_document(id2).is_some());
+    assert!(loaded_collection.primary_index().verify().is_ok());
+}
+
+#[test]
+fn reads_report_unreadable_primary_index() {
+    let temp_dir = tempdir().unwrap();
+    let schema = make_string_schema();
+
+    let mut collection = Collection::new("test_collection", schema, temp_dir.path()).unwrap();
+    collection
+        .add_document(doc! { "id": "user1", "name": "Alice", "age": 30 })
+        .unwrap();
+    assert_eq!(collection.get_documents().unwrap().len(), 1);
+
+    let mut contents = std::fs::read(collection.index_path()).unwrap();
+    let leaf = PAGE_SIZE + PAGE_CHECKSUM_SIZE;
+    contents[leaf..leaf + 64].fill(0xFF);
+    std::fs::write(collection.index_path(), contents).unwrap();
+
+    assert!(collection.get_documents().is_err());
+    assert!(collection.document_ids().is_err());
+    assert!(collection.filter(&[]).unwrap_err().contains("checksum"));
+}
+
+#[test]
+fn add_document_rejects_ids_too_long_to_index() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection =
+        Collection::new("test_collection", make_string_schema(), temp_dir.path()).unwrap();
+    let long_id = "x".repeat(5000);
+
+    let errors = collection
+        .add_document(doc! { "id": long_id.as_str(), "name": "Alice", "age": 30 })
+        .unwrap_err();
+    assert!(errors[0].contains("too long to be indexed"));
+    assert!(collection.read_log_entries().unwrap().is_empty());
+
+    collection
+        .add_document(doc! { "id": "x".repeat(2000), "name": "Bob", "age": 25 })
+        .unwrap();
+    let loaded = Collection::from_files(temp_dir.path(), "test_collection").unwrap();
+    assert_eq!(loaded.document_count().unwrap(), 1);
+    assert!(loaded.recovery_report().is_clean());
+}
+
+#[test]
+fn from_files_skips_entries_too_long_to_index() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection =
+        Collection::new("test_collection", make_string_schema(), temp_dir.path()).unwrap();
+    collection
+        .add_document(doc! { "id": "user1", "name": "Alice", "age": 30 })
+        .unwrap();
+    collection.write_metadata().unwrap();
+    let position = collection
+        .append_to_log(
+            &Operation::Insert,
+            &doc! { "id": "x".repeat(5000), "name": "Bob", "age": 25 },
+        )
+        .unwrap();
+
+    let loaded = Collection::from_files(temp_dir.path(), "test_collection").unwrap();
+    assert_eq!(loaded.document_count().unwrap(), 1);
+    assert_eq!(loaded.recovery_report().unindexed, vec![position]);
+    assert!(!loaded.recovery_report().is_clean());
+
+    std::fs::remove_file(loaded.index_path()).unwrap();
+    let rebuilt = Collection::from_files(temp_dir.path(), "test_collection").unwrap();
+    assert_eq!(rebuilt.document_count().unwrap(), 1);
+    assert_eq!(rebuilt.recovery_report().unindexed.len(), 1);
+}
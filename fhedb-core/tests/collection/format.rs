@@ -0,0 +1,197 @@
+use bson::{Bson, doc};
+use fhedb_core::{format::upgrade::collection_format_version, prelude::*};
+use std::{fs, io, path::Path};
+use tempfile::tempdir;
+
+use super::super::common::{add_named_users, bio, make_int_schema};
+
+fn frame(payload: &[u8]) -> Vec<u8> {
+    let mut frame = (payload.len() as u32).to_le_bytes().to_vec();
+    frame.extend_from_slice(&crc32c::crc32c(payload).to_le_bytes());
+    frame.extend_from_slice(payload);
+    frame
+}
+
+/// Rewrites a collection's files the way they were stored before format headers.
+fn downgrade_to_version_zero(collection: &Collection) {
+    for path in collection.segment_paths().unwrap() {
+        let contents = fs::read(&path).unwrap();
+        let mut entries = &contents[FORMAT_HEADER_SIZE..];
+        let mut downgraded = Vec::new();
+        while !entries.is_empty() {
+            let length = u32::from_le_bytes(entries[0..4].try_into().unwrap()) as usize;
+            let mut entry = bson::Document::from_reader(&entries[8..8 + length]).unwrap();
+            if let Ok(base) = entry.get_document_mut("base") {
+                let offset = base.get_i64("offset").unwrap() - FORMAT_HEADER_SIZE as i64;
+                base.insert("offset", Bson::Int64(offset));
+            }
+            downgraded.extend(frame(&entry.to_vec().unwrap()));
+            entries = &entries[8 + length..];
+        }
+        fs::write(&path, downgraded).unwrap();
+    }
+
+    let contents = fs::read(collection.metadata_path()).unwrap();
+    let mut metadata = bson::Document::from_reader(&contents[FORMAT_HEADER_SIZE..]).unwrap();
+    let log_length = metadata.get_i64("log_length").unwrap() - FORMAT_HEADER_SIZE as i64;
+    metadata.insert("log_length", Bson::Int64(log_length));
+    fs::write(collection.metadata_path(), metadata.to_vec().unwrap()).unwrap();
+    let _ = fs::remove_file(collection.previous_metadata_path());
+}
+
+fn set_version(path: &Path, version: u16) {
+    let mut contents = fs::read(path).unwrap();
+    contents[4..6].copy_from_slice(&version.to_le_bytes());
+    fs::write(path, contents).unwrap();
+}
+
+#[test]
+fn storage_files_start_with_format_header() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection = Collection::new("users", make_int_schema(), temp_dir.path()).unwrap();
+    add_named_users(&mut collection, 3, bio);
+    collection.write_metadata().unwrap();
+
+    for (path, kind) in [
+        (collection.logfile_path(), FileKind::LogSegment),
+        (collection.metadata_path(), FileKind::Metadata),
+        (collection.index_path(), FileKind::Index),
+    ] {
+        let contents = fs::read(path).unwrap();
+        assert_eq!(&contents[0..4], kind.magic());
+        assert_eq!(
+            u16::from_le_bytes([contents[4], contents[5]]),
+            FORMAT_VERSION
+        );
+    }
+    assert_eq!(
+        collection_format_version(collection.base_path()).unwrap(),
+        Some(FORMAT_VERSION)
+    );
+}
+
+#[test]
+fn version_zero_collection_upgraded_on_load() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection = Collection::new("users", make_int_schema(), temp_dir.path()).unwrap();
+    collection.set_max_segment_size(1024);
+    add_named_users(&mut collection, 6, bio);
+    collection
+        .update_document(DocId::from_u64(5), doc! { "age": 50i64 })
+        .unwrap();
+    collection.remove_document(DocId::from_u64(1)).unwrap();
+    collection.write_metadata().unwrap();
+    downgrade_to_version_zero(&collection);
+    assert_eq!(
+        collection_format_version(collection.base_path()).unwrap(),
+        Some(0)
+    );
+
+    let loaded = Collection::from_files(temp_dir.path(), "users").unwrap();
+
+    assert!(loaded.recovery_report().is_clean());
+    assert_eq!(
+        collection_format_version(loaded.base_path()).unwrap(),
+        Some(FORMAT_VERSION)
+    );
+    assert_eq!(loaded.document_count().unwrap(), 5);
+    let updated = loaded.get_document(DocId::from_u64(5)).unwrap();
+    assert_eq!(updated.data.get_i64("age").unwrap(), 50);
+    assert_eq!(updated.data.get_str("name").unwrap(), bio(5));
+    assert!(loaded.get_document(DocId::from_u64(1)).is_none());
+    for path in loaded.segment_paths().unwrap() {
+        assert_eq!(&fs::read(path).unwrap()[0..4], FileKind::LogSegment.magic());
+    }
+}
+
+#[test]
+fn upgrade_collection_is_idempotent() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection = Collection::new("users", make_int_schema(), temp_dir.path()).unwrap();
+    add_named_users(&mut collection, 3, bio);
+    collection.write_metadata().unwrap();
+    downgrade_to_version_zero(&collection);
+
+    assert!(upgrade_collection(collection.base_path()).unwrap());
+    let segment = fs::read(collection.logfile_path()).unwrap();
+    assert!(!upgrade_collection(collection.base_path()).unwrap());
+
+    assert_eq!(fs::read(collection.logfile_path()).unwrap(), segment);
+    let loaded = Collection::from_files(temp_dir.path(), "users").unwrap();
+    assert_eq!(loaded.document_count().unwrap(), 3);
+}
+
+#[test]
+fn upgrade_data_dir_upgrades_every_database() {
+    let temp_dir = tempdir().unwrap();
+    let mut db = Database::new("test_db", temp_dir.path());
+    db.create_collection("users", make_int_schema()).unwrap();
+    db.create_collection("products", make_int_schema()).unwrap();
+    add_named_users(db.get_collection_mut("users").unwrap(), 3, |_| {
+        "x".repeat(256)
+    });
+    add_named_users(db.get_collection_mut("products").unwrap(), 2, |_| {
+        "x".repeat(256)
+    });
+    let users = db.get_collection("users").unwrap();
+    users.write_metadata().unwrap();
+    downgrade_to_version_zero(users);
+
+    let report = upgrade_data_dir(temp_dir.path()).unwrap();
+
+    assert_eq!(report.databases, 1);
+    assert_eq!(report.collections, 2);
+    assert_eq!(report.upgraded, vec!["test_db/users".to_string()]);
+    assert!(
+        upgrade_data_dir(temp_dir.path())
+            .unwrap()
+            .upgraded
+            .is_empty()
+    );
+
+    let loaded = Database::from_files("test_db", temp_dir.path()).unwrap();
+    assert_eq!(
+        loaded
+            .get_collection("users")
+            .unwrap()
+            .document_count()
+            .unwrap(),
+        3
+    );
+    assert_eq!(
+        loaded
+            .get_collection("products")
+            .unwrap()
+            .document_count()
+            .unwrap(),
+        2
+    );
+}
+
+#[test]
+fn newer_metadata_version_rejected() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection = Collection::new("users", make_int_schema(), temp_dir.path()).unwrap();
+    add_named_users(&mut collection, 3, bio);
+    collection.write_metadata().unwrap();
+    set_version(&collection.metadata_path(), FORMAT_VERSION + 1);
+
+    let result = Collection::from_files(temp_dir.path(), "users");
+
+    assert_eq!(result.unwrap_err().kind(), io::ErrorKind::Unsupported);
+    assert!(upgrade_collection(collection.base_path()).is_err());
+}
+
+#[test]
+fn newer_segment_version_rejected() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection = Collection::new("users", make_int_schema(), temp_dir.path()).unwrap();
+    add_named_users(&mut collection, 3, bio);
+    collection.write_metadata().unwrap();
+    set_version(&collection.logfile_path(), FORMAT_VERSION + 1);
+    fs::remove_file(collection.index_path()).unwrap();
+
+    let result = Collection::from_files(temp_dir.path(), "users");
+
+    assert_eq!(result.unwrap_err().kind(), io::ErrorKind::Unsupported);
+}
@@ -0,0 +1,343 @@
+use bson::doc;
+use chrono::{DateTime, Utc};
+use fhedb_core::prelude::*;
+use fhedb_types::{FieldCondition, QueryOperator};
+use std::{thread, time::Duration};
+use tempfile::tempdir;
+
+use super::super::common::make_int_schema;
+
+/// Returns the current instant, with the log entries written before it strictly older.
+fn checkpoint() -> DateTime<Utc> {
+    thread::sleep(Duration::from_millis(2));
+    let now = Utc::now();
+    thread::sleep(Duration::from_millis(2));
+    now
+}
+
+fn names(documents: &[Document]) -> Vec<&str> {
+    documents
+        .iter()
+        .map(|doc| doc.data.get_str("name").unwrap())
+        .collect()
+}
+
+#[test]
+fn replays_log_up_to_instant() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection = Collection::new("users", make_int_schema(), temp_dir.path()).unwrap();
+
+    let before = checkpoint();
+    let alice = collection
+        .add_document(doc! { "name": "Alice", "age": 30i64 })
+        .unwrap();
+    let bob = collection
+        .add_document(doc! { "name": "Bob", "age": 25i64 })
+        .unwrap();
+    let inserted = checkpoint();
+    collection
+        .update_document(alice.clone(), doc! { "name": "Alicia" })
+        .unwrap();
+    let updated = checkpoint();
+    collection.remove_document(bob).unwrap().unwrap();
+    let removed = checkpoint();
+    collection
+        .add_document(doc! { "name": "Carol", "age": 41i64 })
+        .unwrap();
+
+    assert!(collection.documents_as_of(before).unwrap().is_empty());
+    assert_eq!(
+        names(&collection.documents_as_of(inserted).unwrap()),
+        vec!["Alice", "Bob"]
+    );
+    let documents = collection.documents_as_of(updated).unwrap();
+    assert_eq!(names(&documents), vec!["Alicia", "Bob"]);
+    assert_eq!(documents[0].id, alice);
+    assert_eq!(documents[0].data.get_i64("age").unwrap(), 30);
+    assert_eq!(
+        names(&collection.documents_as_of(removed).unwrap()),
+        vec!["Alicia"]
+    );
+    assert_eq!(
+        names(&collection.documents_as_of(Utc::now()).unwrap()),
+        vec!["Alicia", "Carol"]
+    );
+}
+
+#[test]
+fn filters_past_state() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection = Collection::new("users", make_int_schema(), temp_dir.path()).unwrap();
+
+    let alice = collection
+        .add_document(doc! { "name": "Alice", "age": 30i64 })
+        .unwrap();
+    collection
+        .add_document(doc! { "name": "Bob", "age": 17i64 })
+        .unwrap();
+    let at = checkpoint();
+    collection
+        .update_document(alice, doc! { "age": 12i64 })
+        .unwrap();
+
+    let conditions = vec![FieldCondition {
+        field_name: "age".to_string(),
+        operator: QueryOperator::GreaterThanOrEqual,
+        value: "18".to_string(),
+    }];
+    assert_eq!(
+        names(&collection.filter_as_of(&conditions, at).unwrap()),
+        vec!["Alice"]
+    );
+    assert!(collection.filter(&conditions).unwrap().is_empty());
+}
+
+#[test]
+fn compacted_history() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection = Collection::new("users", make_int_schema(), temp_dir.path()).unwrap();
+
+    let alice = collection
+        .add_document(doc! { "name": "Alice", "age": 30i64 })
+        .unwrap();
+    let inserted = checkpoint();
+    collection
+        .update_document(alice, doc! { "name": "Alicia" })
+        .unwrap();
+    let updated = checkpoint();
+    collection.compact().unwrap();
+
+    assert_eq!(
+        names(&collection.documents_as_of(updated).unwrap()),
+        vec!["Alicia"]
+    );
+    let error = collection.documents_as_of(inserted).unwrap_err();
+    assert!(error.to_string().contains("discarded by compaction"));
+}
+
+#[test]
+fn compacted_deletes() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection = Collection::new("users", make_int_schema(), temp_dir.path()).unwrap();
+
+    collection
+        .add_document(doc! { "name": "Alice", "age": 30i64 })
+        .unwrap();
+    let bob = collection
+        .add_document(doc! { "name": "Bob", "age": 25i64 })
+        .unwrap();
+    let inserted = checkpoint();
+    collection.remove_document(bob).unwrap().unwrap();
+    let removed = checkpoint();
+    assert!(collection.history_complete_from().is_none());
+    collection.compact().unwrap();
+
+    assert!(collection.history_complete_from().unwrap() <= removed);
+    assert_eq!(
+        names(&collection.documents_as_of(removed).unwrap()),
+        vec!["Alice"]
+    );
+    let error = collection.documents_as_of(inserted).unwrap_err();
+    assert!(error.to_string().contains("discarded by compaction"));
+
+    let conditions = vec![FieldCondition {
+        field_name: "age".to_string(),
+        operator: QueryOperator::LessThan,
+        value: "30".to_string(),
+    }];
+    assert!(collection.filter_as_of(&conditions, inserted).is_err());
+
+    let loaded = Collection::from_files(temp_dir.path(), "users").unwrap();
+    assert_eq!(
+        loaded.history_complete_from(),
+        collection.history_complete_from()
+    );
+    assert!(loaded.documents_as_of(inserted).is_err());
+}
+
+#[test]
+fn parses_timestamps() {
+    let at = parse_timestamp("2026-10-01T02:00:00+02:00").unwrap();
+    assert_eq!(at, parse_timestamp("2026-10-01T00:00:00Z").unwrap());
+
+    assert!(parse_timestamp("2026-10-01").is_err());
+    assert!(parse_timestamp("yesterday").is_err());
+}
+
+#[test]
+fn document_history() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection = Collection::new("users", make_int_schema(), temp_dir.path()).unwrap();
+
+    let alice = collection
+        .add_document(doc! { "name": "Alice", "age": 30i64 })
+        .unwrap();
+    let bob = collection
+        .add_document(doc! { "name": "Bob", "age": 25i64 })
+        .unwrap();
+    collection
+        .update_document(alice.clone(), doc! { "age": 31i64 })
+        .unwrap();
+    collection
+        .update_document(bob.clone(), doc! { "name": "Robert" })
+        .unwrap();
+    collection.remove_document(alice.clone()).unwrap().unwrap();
+
+    let history = collection.document_history(&alice).unwrap();
+    let operations: Vec<_> = history.iter().map(|entry| &entry.operation).collect();
+    assert_eq!(
+        operations,
+        vec![&Operation::Insert, &Operation::Update, &Operation::Delete]
+    );
+    assert_eq!(
+        history[1].document,
+        doc! { "id": 0i64, "name": "Alice", "age": 31i64 }
+    );
+    assert!(history.iter().all(|entry| entry.base.is_none()));
+    assert_eq!(history[2].operation, Operation::Delete);
+    assert!(history[2].timestamp >= history[1].timestamp);
+    assert!(history[2].inserted_at.is_none());
+    assert_eq!(
+        history[2].document,
+        doc! { "id": 0i64, "name": "Alice", "age": 31i64 }
+    );
+
+    let history = collection.document_history(&bob).unwrap();
+    assert_eq!(history.len(), 2);
+    assert_eq!(history[1].document.get_str("name").unwrap(), "Robert");
+    assert!(
+        collection
+            .document_history(&DocId::from_u64(7))
+            .unwrap()
+            .is_empty()
+    );
+}
+
+#[test]
+fn filter_history_by_id() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection = Collection::new("users", make_int_schema(), temp_dir.path()).unwrap();
+    collection
+        .add_document(doc! { "name": "Alice", "age": 30i64 })
+        .unwrap();
+
+    let condition = |field: &str, operator, value: &str| FieldCondition {
+        field_name: field.to_string(),
+        operator,
+        value: value.to_string(),
+    };
+
+    let history = collection
+        .filter_history(&[condition("id", QueryOperator::Equal, "0")])
+        .unwrap();
+    assert_eq!(history.len(), 1);
+
+    assert!(collection.filter_history(&[]).is_err());
+    assert!(
+        collection
+            .filter_history(&[condition("name", QueryOperator::Equal, "\"Alice\"")])
+            .is_err()
+    );
+    assert!(
+        collection
+            .filter_history(&[condition("id", QueryOperator::GreaterThan, "0")])
+            .is_err()
+    );
+    assert!(
+        collection
+            .filter_history(&[condition("id", QueryOperator::Equal, "\"zero\"")])
+            .is_err()
+    );
+}
+
+#[test]
+fn compaction_discards_history_by_default() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection = Collection::new("users", make_int_schema(), temp_dir.path()).unwrap();
+    assert_eq!(collection.history_retention(), None);
+
+    let alice = collection
+        .add_document(doc! { "name": "Alice", "age": 30i64 })
+        .unwrap();
+    collection
+        .update_document(alice.clone(), doc! { "age": 31i64 })
+        .unwrap();
+    collection.compact().unwrap();
+
+    let history = collection.document_history(&alice).unwrap();
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].operation, Operation::Insert);
+    assert_eq!(history[0].document.get_i64("age").unwrap(), 31);
+    assert!(history[0].inserted_at.is_some());
+}
+
+#[test]
+fn compaction_retains_history_within_window() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection = Collection::new("users", make_int_schema(), temp_dir.path()).unwrap();
+    collection.set_max_segment_size(1);
+    collection.set_history_retention(Some(Duration::from_secs(3600)));
+
+    let alice = collection
+        .add_document(doc! { "name": "Alice", "age": 30i64 })
+        .unwrap();
+    collection
+        .update_document(alice.clone(), doc! { "age": 31i64 })
+        .unwrap();
+    collection.remove_document(alice.clone()).unwrap().unwrap();
+    collection
+        .add_document(doc! { "name": "Bob", "age": 25i64 })
+        .unwrap();
+
+    let report = collection.compact().unwrap();
+    assert_eq!(report.entries_removed, 0);
+    assert_eq!(collection.document_history(&alice).unwrap().len(), 3);
+    assert_eq!(names(&collection.get_documents().unwrap()), vec!["Bob"]);
+}
+
+#[test]
+fn compaction_merges_history_past_window() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection = Collection::new("users", make_int_schema(), temp_dir.path()).unwrap();
+    collection.set_max_segment_size(1);
+    let retention = Duration::from_millis(200);
+    collection.set_history_retention(Some(retention));
+
+    let alice = collection
+        .add_document(doc! { "name": "Alice", "age": 30i64 })
+        .unwrap();
+    collection
+        .update_document(alice.clone(), doc! { "age": 31i64 })
+        .unwrap();
+    let bob = collection
+        .add_document(doc! { "name": "Bob", "age": 25i64 })
+        .unwrap();
+    collection.remove_document(bob.clone()).unwrap().unwrap();
+    let merged = checkpoint();
+    thread::sleep(retention);
+
+    collection
+        .update_document(alice.clone(), doc! { "age": 32i64 })
+        .unwrap();
+    collection
+        .add_document(doc! { "name": "Carol", "age": 41i64 })
+        .unwrap();
+
+    let report = collection.compact().unwrap();
+    assert_eq!(report.entries_removed, 3);
+
+    let history = collection.document_history(&alice).unwrap();
+    assert_eq!(history.len(), 2);
+    assert_eq!(history[0].operation, Operation::Insert);
+    assert_eq!(history[0].document.get_i64("age").unwrap(), 31);
+    assert_eq!(history[1].operation, Operation::Update);
+    assert_eq!(history[1].document.get_i64("age").unwrap(), 32);
+    assert!(collection.document_history(&bob).unwrap().is_empty());
+
+    let documents = collection.documents_as_of(merged).unwrap();
+    assert_eq!(documents[0].data.get_i64("age").unwrap(), 31);
+    assert_eq!(names(&documents), vec!["Alice"]);
+    let documents = collection.get_documents().unwrap();
+    assert_eq!(documents[0].data.get_i64("age").unwrap(), 32);
+    assert_eq!(names(&documents), vec!["Alice", "Carol"]);
+}
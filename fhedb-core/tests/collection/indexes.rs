@@ -0,0 +1,561 @@
+use bson::{Bson, doc};
+use fhedb_core::prelude::*;
+use fhedb_types::{FieldCondition, QueryOperator};
+use std::{collections::HashMap, fs};
+use tempfile::tempdir;
+
+use super::super::common::make_int_schema;
+
+fn make_indexable_schema() -> Schema {
+    let mut fields = HashMap::new();
+    fields.insert("id".to_string(), FieldDefinition::new(FieldType::IdInt));
+    fields.insert("name".to_string(), FieldDefinition::new(FieldType::String));
+    fields.insert("age".to_string(), FieldDefinition::new(FieldType::Int));
+    fields.insert(
+        "email".to_string(),
+        FieldDefinition::new(FieldType::Nullable(Box::new(FieldType::String))),
+    );
+    fields.insert(
+        "tags".to_string(),
+        FieldDefinition::new(FieldType::Array(Box::new(FieldType::String))),
+    );
+    Schema { fields }
+}
+
+fn add_indexed_users(collection: &mut Collection) {
+    for (name, age, email) in [
+        (
+            "Alice",
+            30i64,
+            Bson::String("alice@example.com".to_string()),
+        ),
+        ("Bob", 25, Bson::String("bob@example.com".to_string())),
+        ("Carol", 30, Bson::Null),
+        ("Dave", 41, Bson::String("dave@example.com".to_string())),
+    ] {
+        collection
+            .add_document(doc! { "name": name, "age": age, "email": email, "tags": [] })
+            .unwrap();
+    }
+}
+
+fn equal(field: &str, value: &str) -> FieldCondition {
+    FieldCondition {
+        field_name: field.to_string(),
+        operator: QueryOperator::Equal,
+        value: value.to_string(),
+    }
+}
+
+fn names(documents: &[Document]) -> Vec<String> {
+    documents
+        .iter()
+        .map(|document| document.data.get_str("name").unwrap().to_string())
+        .collect()
+}
+
+fn indexed_names(collection: &Collection, field: &str, value: Bson) -> Vec<String> {
+    let ids = collection
+        .secondary_index(field)
+        .unwrap()
+        .lookup(&value)
+        .unwrap();
+    ids.into_iter()
+        .map(|id| {
+            let document = collection.get_document(id).unwrap();
+            document.data.get_str("name").unwrap().to_string()
+        })
+        .collect()
+}
+
+#[test]
+fn create_and_drop_index() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection =
+        Collection::new("users", make_indexable_schema(), temp_dir.path()).unwrap();
+    add_indexed_users(&mut collection);
+
+    collection.create_index("age").unwrap();
+    collection.create_index("email").unwrap();
+    assert_eq!(collection.indexed_fields(), vec!["age", "email"]);
+    assert!(collection.has_index("age"));
+    let path = collection
+        .secondary_index("age")
+        .unwrap()
+        .path()
+        .to_path_buf();
+    assert!(path.exists());
+    assert_eq!(collection.secondary_index("age").unwrap().len().unwrap(), 4);
+    assert_eq!(
+        indexed_names(&collection, "age", Bson::Int64(30)),
+        vec!["Alice", "Carol"]
+    );
+
+    collection.drop_index("age").unwrap();
+    assert!(!collection.has_index("age"));
+    assert!(!path.exists());
+    assert_eq!(collection.indexed_fields(), vec!["email"]);
+}
+
+#[test]
+fn create_index_rejects_invalid_fields() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection =
+        Collection::new("users", make_indexable_schema(), temp_dir.path()).unwrap();
+
+    let missing = collection.create_index("missing").unwrap_err();
+    assert!(missing.contains("does not exist"));
+    let id = collection.create_index("id").unwrap_err();
+    assert!(id.contains("ID field"));
+    let array = collection.create_index("tags").unwrap_err();
+    assert!(array.contains("cannot be indexed"));
+
+    collection.create_index("name").unwrap();
+    let duplicate = collection.create_index("name").unwrap_err();
+    assert!(duplicate.contains("already exists"));
+
+    let not_indexed = collection.drop_index("age").unwrap_err();
+    assert!(not_indexed.contains("No index"));
+}
+
+#[test]
+fn index_follows_writes() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection =
+        Collection::new("users", make_indexable_schema(), temp_dir.path()).unwrap();
+    collection.create_index("age").unwrap();
+    add_indexed_users(&mut collection);
+    assert_eq!(
+        indexed_names(&collection, "age", Bson::Int64(30)),
+        vec!["Alice", "Carol"]
+    );
+
+    collection
+        .update_document(DocId::from_u64(0), doc! { "age": 26i64 })
+        .unwrap();
+    collection
+        .update_document(DocId::from_u64(1), doc! { "name": "Robert" })
+        .unwrap();
+    collection.remove_document(DocId::from_u64(2)).unwrap();
+
+    assert!(indexed_names(&collection, "age", Bson::Int64(30)).is_empty());
+    assert_eq!(
+        indexed_names(&collection, "age", Bson::Int64(26)),
+        vec!["Alice"]
+    );
+    assert_eq!(
+        indexed_names(&collection, "age", Bson::Int64(25)),
+        vec!["Robert"]
+    );
+    assert_eq!(collection.secondary_index("age").unwrap().len().unwrap(), 3);
+}
+
+#[test]
+fn filter_uses_index_for_equality() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection =
+        Collection::new("users", make_indexable_schema(), temp_dir.path()).unwrap();
+    add_indexed_users(&mut collection);
+
+    let queries = [
+        vec![equal("age", "30")],
+        vec![equal("age", "30"), equal("name", "\"Carol\"")],
+        vec![equal("email", "\"bob@example.com\"")],
+        vec![equal("email", "null")],
+        vec![equal("age", "99")],
+    ];
+    let scanned: Vec<Vec<String>> = queries
+        .iter()
+        .map(|conditions| names(&collection.filter(conditions).unwrap()))
+        .collect();
+
+    collection.create_index("age").unwrap();
+    collection.create_index("email").unwrap();
+    for (conditions, expected) in queries.iter().zip(&scanned) {
+        assert_eq!(&names(&collection.filter(conditions).unwrap()), expected);
+    }
+    assert_eq!(scanned[0], vec!["Alice", "Carol"]);
+    assert_eq!(scanned[3], vec!["Carol"]);
+}
+
+#[test]
+fn long_strings_are_looked_up_by_prefix() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection = Collection::new("users", make_int_schema(), temp_dir.path()).unwrap();
+    collection.create_index("name").unwrap();
+    let prefix = "x".repeat(MAX_INDEXED_VALUE_SIZE);
+    let first = format!("{}a", prefix);
+    let second = format!("{}b", prefix);
+    for name in [&first, &second] {
+        collection
+            .add_document(doc! { "name": name, "age": 1i64 })
+            .unwrap();
+    }
+
+    let found = collection
+        .filter(&[equal("name", &format!("\"{}\"", second))])
+        .unwrap();
+    assert_eq!(names(&found), vec![second.clone()]);
+    assert_eq!(
+        collection
+            .secondary_index("name")
+            .unwrap()
+            .lookup(&Bson::String(first))
+            .unwrap()
+            .len(),
+        2
+    );
+}
+
+#[test]
+fn indexes_persist_across_loads() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection =
+        Collection::new("users", make_indexable_schema(), temp_dir.path()).unwrap();
+    add_indexed_users(&mut collection);
+    collection.create_index("age").unwrap();
+    collection
+        .update_document(DocId::from_u64(3), doc! { "age": 30i64 })
+        .unwrap();
+
+    let loaded = Collection::from_files(temp_dir.path(), "users").unwrap();
+    assert_eq!(loaded.indexed_fields(), vec!["age"]);
+    assert_eq!(
+        indexed_names(&loaded, "age", Bson::Int64(30)),
+        vec!["Alice", "Carol", "Dave"]
+    );
+}
+
+#[test]
+fn missing_index_file_is_rebuilt() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection =
+        Collection::new("users", make_indexable_schema(), temp_dir.path()).unwrap();
+    add_indexed_users(&mut collection);
+    collection.create_index("age").unwrap();
+    fs::remove_file(collection.secondary_index("age").unwrap().path()).unwrap();
+
+    let loaded = Collection::from_files(temp_dir.path(), "users").unwrap();
+    assert_eq!(
+        indexed_names(&loaded, "age", Bson::Int64(30)),
+        vec!["Alice", "Carol"]
+    );
+
+    fs::remove_file(loaded.index_path()).unwrap();
+    let rebuilt = Collection::from_files(temp_dir.path(), "users").unwrap();
+    assert_eq!(rebuilt.secondary_index("age").unwrap().len().unwrap(), 4);
+}
+
+#[test]
+fn schema_changes_carry_indexes() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection =
+        Collection::new("users", make_indexable_schema(), temp_dir.path()).unwrap();
+    add_indexed_users(&mut collection);
+    collection.create_index("age").unwrap();
+    collection.create_index("email").unwrap();
+
+    collection.rename_field("age", "years".to_string()).unwrap();
+    assert_eq!(collection.indexed_fields(), vec!["email", "years"]);
+    assert_eq!(
+        indexed_names(&collection, "years", Bson::Int64(30)),
+        vec!["Alice", "Carol"]
+    );
+
+    let array = FieldDefinition::new(FieldType::Nullable(Box::new(FieldType::Array(Box::new(
+        FieldType::String,
+    )))));
+    let rejected = collection.modify_field("email", array).unwrap_err();
+    assert!(rejected.contains("cannot be indexed"));
+
+    collection.remove_field("email").unwrap();
+    assert_eq!(collection.indexed_fields(), vec!["years"]);
+}
+
+fn condition(field: &str, operator: QueryOperator, value: &str) -> FieldCondition {
+    FieldCondition {
+        field_name: field.to_string(),
+        operator,
+        value: value.to_string(),
+    }
+}
+
+#[test]
+fn filter_uses_index_for_ranges() {
+    use QueryOperator::{GreaterThan, GreaterThanOrEqual, LessThan, LessThanOrEqual};
+
+    let temp_dir = tempdir().unwrap();
+    let mut collection =
+        Collection::new("users", make_indexable_schema(), temp_dir.path()).unwrap();
+    add_indexed_users(&mut collection);
+
+    let queries = [
+        vec![condition("age", GreaterThanOrEqual, "25")],
+        vec![condition("age", GreaterThan, "25")],
+        vec![
+            condition("age", GreaterThanOrEqual, "18"),
+            condition("age", LessThan, "41"),
+        ],
+        vec![
+            condition("age", LessThanOrEqual, "41"),
+            condition("age", GreaterThan, "25"),
+            condition("age", LessThan, "99"),
+            condition("age", GreaterThanOrEqual, "30"),
+        ],
+        vec![
+            condition("age", GreaterThan, "30"),
+            condition("age", LessThan, "30"),
+        ],
+        vec![condition("email", GreaterThan, "\"b\"")],
+        vec![condition("email", LessThanOrEqual, "\"bob@example.com\"")],
+        vec![
+            condition("name", GreaterThanOrEqual, "\"B\""),
+            condition("age", LessThanOrEqual, "30"),
+        ],
+    ];
+    let scanned: Vec<Vec<String>> = queries
+        .iter()
+        .map(|conditions| names(&collection.filter(conditions).unwrap()))
+        .collect();
+
+    collection.create_index("age").unwrap();
+    collection.create_index("email").unwrap();
+    for (conditions, expected) in queries.iter().zip(&scanned) {
+        assert_eq!(&names(&collection.filter(conditions).unwrap()), expected);
+    }
+    assert_eq!(scanned[2], vec!["Alice", "Bob", "Carol"]);
+    assert_eq!(scanned[3], vec!["Alice", "Carol", "Dave"]);
+    assert!(scanned[4].is_empty());
+    assert_eq!(scanned[6], vec!["Alice", "Bob"]);
+}
+
+#[test]
+fn long_string_ranges_keep_bounds() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection = Collection::new("users", make_int_schema(), temp_dir.path()).unwrap();
+    collection.create_index("name").unwrap();
+    let prefix = "x".repeat(MAX_INDEXED_VALUE_SIZE);
+    let values = [
+        format!("{}a", prefix),
+        format!("{}b", prefix),
+        format!("{}c", prefix),
+        prefix.clone(),
+        "y".to_string(),
+    ];
+    for name in &values {
+        collection
+            .add_document(doc! { "name": name, "age": 1i64 })
+            .unwrap();
+    }
+
+    let quoted = |value: &str| format!("\"{}\"", value);
+    let found = collection
+        .filter(&[
+            condition("name", QueryOperator::GreaterThan, &quoted(&values[0])),
+            condition("name", QueryOperator::LessThan, &quoted(&values[2])),
+        ])
+        .unwrap();
+    assert_eq!(names(&found), vec![values[1].clone()]);
+
+    let found = collection
+        .filter(&[condition(
+            "name",
+            QueryOperator::GreaterThanOrEqual,
+            &quoted(&prefix),
+        )])
+        .unwrap();
+    assert_eq!(found.len(), 5);
+}
+
+fn fields(names: &[&str]) -> Vec<String> {
+    names.iter().map(|name| name.to_string()).collect()
+}
+
+#[test]
+fn create_and_drop_compound_index() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection =
+        Collection::new("users", make_indexable_schema(), temp_dir.path()).unwrap();
+    add_indexed_users(&mut collection);
+
+    collection
+        .create_compound_index(&fields(&["age", "name"]))
+        .unwrap();
+    collection.create_index("age").unwrap();
+    assert_eq!(collection.indexed_fields(), vec!["age"]);
+    assert_eq!(
+        collection.list_indexes(),
+        vec![fields(&["age"]), fields(&["age", "name"])]
+    );
+    let index = collection
+        .compound_index(&fields(&["age", "name"]))
+        .unwrap();
+    assert_eq!(index.fields(), fields(&["age", "name"]));
+    assert_eq!(index.len().unwrap(), 4);
+    assert!(
+        collection
+            .compound_index(&fields(&["name", "age"]))
+            .is_none()
+    );
+
+    for (field_names, message) in [
+        (fields(&[]), "at least one field"),
+        (fields(&["age", "age"]), "more than once"),
+        (fields(&["age", "tags"]), "cannot be indexed"),
+        (fields(&["age", "missing"]), "does not exist"),
+        (fields(&["age", "name"]), "already exists"),
+    ] {
+        let error = collection.create_compound_index(&field_names).unwrap_err();
+        assert!(error.contains(message), "{}", error);
+    }
+
+    collection
+        .drop_compound_index(&fields(&["age", "name"]))
+        .unwrap();
+    assert_eq!(collection.list_indexes(), vec![fields(&["age"])]);
+    assert!(
+        collection
+            .drop_compound_index(&fields(&["age", "name"]))
+            .is_err()
+    );
+}
+
+#[test]
+fn filter_uses_compound_index_for_prefix_and_range() {
+    use QueryOperator::{GreaterThan, LessThan, LessThanOrEqual, NotEqual};
+
+    let temp_dir = tempdir().unwrap();
+    let mut collection =
+        Collection::new("users", make_indexable_schema(), temp_dir.path()).unwrap();
+    add_indexed_users(&mut collection);
+    collection
+        .add_document(
+            doc! { "name": "Erin", "age": 30i64, "email": "erin@example.com", "tags": [] },
+        )
+        .unwrap();
+
+    let queries = [
+        vec![equal("age", "30")],
+        vec![equal("age", "30"), equal("name", "\"Carol\"")],
+        vec![equal("name", "\"Carol\""), equal("age", "30")],
+        vec![
+            equal("age", "30"),
+            condition("name", GreaterThan, "\"Alice\""),
+        ],
+        vec![
+            condition("name", LessThan, "\"Erin\""),
+            equal("age", "30"),
+            condition("name", GreaterThan, "\"Alice\""),
+        ],
+        vec![
+            equal("age", "30"),
+            equal("name", "\"Erin\""),
+            condition("email", LessThanOrEqual, "\"erin@example.com\""),
+        ],
+        vec![condition("age", LessThan, "41")],
+        vec![equal("name", "\"Bob\"")],
+        vec![equal("age", "30"), condition("name", NotEqual, "\"Alice\"")],
+    ];
+    let scanned: Vec<Vec<String>> = queries
+        .iter()
+        .map(|conditions| names(&collection.filter(conditions).unwrap()))
+        .collect();
+
+    collection
+        .create_compound_index(&fields(&["age", "name", "email"]))
+        .unwrap();
+    for (conditions, expected) in queries.iter().zip(&scanned) {
+        assert_eq!(&names(&collection.filter(conditions).unwrap()), expected);
+    }
+    assert_eq!(scanned[1], vec!["Carol"]);
+    assert_eq!(scanned[3], vec!["Carol", "Erin"]);
+    assert_eq!(scanned[4], vec!["Carol"]);
+    assert_eq!(scanned[5], vec!["Erin"]);
+    assert_eq!(scanned[8], vec!["Carol", "Erin"]);
+
+    collection
+        .update_document(DocId::from_u64(2), doc! { "name": "Cleo" })
+        .unwrap();
+    collection
+        .remove_document(DocId::from_u64(4))
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        names(&collection.filter(&queries[3]).unwrap()),
+        vec!["Cleo"]
+    );
+    assert!(collection.filter(&queries[1]).unwrap().is_empty());
+}
+
+#[test]
+fn compound_indexes_persist_across_loads() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection =
+        Collection::new("users", make_indexable_schema(), temp_dir.path()).unwrap();
+    add_indexed_users(&mut collection);
+    collection.create_index("name").unwrap();
+    collection
+        .create_compound_index(&fields(&["age", "name"]))
+        .unwrap();
+    let path = collection
+        .compound_index(&fields(&["age", "name"]))
+        .unwrap()
+        .path()
+        .to_path_buf();
+
+    let loaded = Collection::from_files(temp_dir.path(), "users").unwrap();
+    assert_eq!(
+        loaded.list_indexes(),
+        vec![fields(&["age", "name"]), fields(&["name"])]
+    );
+    assert_eq!(
+        names(
+            &loaded
+                .filter(&[equal("age", "30"), equal("name", "\"Carol\"")])
+                .unwrap()
+        ),
+        vec!["Carol"]
+    );
+
+    fs::remove_file(path).unwrap();
+    let rebuilt = Collection::from_files(temp_dir.path(), "users").unwrap();
+    let index = rebuilt.compound_index(&fields(&["age", "name"])).unwrap();
+    assert_eq!(index.len().unwrap(), 4);
+}
+
+#[test]
+fn schema_changes_carry_compound_indexes() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection =
+        Collection::new("users", make_indexable_schema(), temp_dir.path()).unwrap();
+    add_indexed_users(&mut collection);
+    collection
+        .create_compound_index(&fields(&["age", "name"]))
+        .unwrap();
+    collection
+        .create_compound_index(&fields(&["email", "age"]))
+        .unwrap();
+
+    collection.rename_field("age", "years".to_string()).unwrap();
+    assert_eq!(
+        collection.list_indexes(),
+        vec![fields(&["email", "years"]), fields(&["years", "name"])]
+    );
+    assert_eq!(
+        names(
+            &collection
+                .filter(&[equal("years", "30"), equal("name", "\"Alice\"")])
+                .unwrap()
+        ),
+        vec!["Alice"]
+    );
+
+    let array = FieldDefinition::new(FieldType::Nullable(Box::new(FieldType::Array(Box::new(
+        FieldType::String,
+    )))));
+    let rejected = collection.modify_field("name", array).unwrap_err();
+    assert!(rejected.contains("cannot be indexed"));
+
+    collection.remove_field("email").unwrap();
+    assert_eq!(collection.list_indexes(), vec![fields(&["years", "name"])]);
+}
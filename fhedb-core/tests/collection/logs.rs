@@ -79,7 +79,7 @@ fn append_and_read_entries() {
     let entries1 = collection.read_log_entries().unwrap();
     assert_eq!(entries1.len(), 1);
     assert_eq!(entries1[0].0.document, doc1);
-    assert_eq!(offset1, 0);
+    assert_eq!(offset1, LogPosition::new(0, FORMAT_HEADER_SIZE));
 
     let offset2 = collection.append_to_log(&Operation::Update, &doc2).unwrap();
     let entries2 = collection.read_log_entries().unwrap();
@@ -140,7 +140,7 @@ fn logfile_path() {
     let temp_dir = tempdir().unwrap();
     let collection = Collection::new("users", schema, temp_dir.path()).unwrap();
 
-    let expected_path = collection.base_path().join("logfile.log");
+    let expected_path = collection.base_path().join("logfile.000000.log");
     assert_eq!(collection.logfile_path(), expected_path);
 }
 
@@ -259,13 +259,146 @@ fn read_log_entry_at_offset() {
     assert_eq!(entry3.document, doc3);
 
     let empty_collection = Collection::new("empty", make_int_schema(), temp_dir.path()).unwrap();
-    let result = empty_collection.read_log_entry_at_offset(0);
+    let result = empty_collection.read_log_entry_at_offset(LogPosition::default());
     assert!(result.is_err());
 
-    let invalid_offset_result = collection.read_log_entry_at_offset(99999);
+    let invalid_offset_result = collection.read_log_entry_at_offset(LogPosition::new(0, 99999));
     assert!(invalid_offset_result.is_err());
 }
 
+#[test]
+fn read_log_entry_at_offset_sees_later_appends() {
+    let schema = make_int_schema();
+    let temp_dir = tempdir().unwrap();
+    let collection = Collection::new("users", schema, temp_dir.path()).unwrap();
+
+    let doc1 = doc! { "id": 1i64, "name": "Alice", "age": 30i64 };
+    let doc2 = doc! { "id": 2i64, "name": "Bob", "age": 25i64 };
+
+    let offset1 = collection.append_to_log(&Operation::Insert, &doc1).unwrap();
+    assert_eq!(
+        collection
+            .read_log_entry_at_offset(offset1)
+            .unwrap()
+            .document,
+        doc1
+    );
+
+    let offset2 = collection.append_to_log(&Operation::Insert, &doc2).unwrap();
+    assert_eq!(
+        collection
+            .read_log_entry_at_offset(offset2)
+            .unwrap()
+            .document,
+        doc2
+    );
+}
+
+#[test]
+fn read_log_entry_at_offset_after_compaction() {
+    let schema = make_int_schema();
+    let temp_dir = tempdir().unwrap();
+    let mut collection = Collection::new("users", schema, temp_dir.path()).unwrap();
+
+    let id1 = collection
+        .add_document(doc! { "id": 1i64, "name": "Alice", "age": 30i64 })
+        .unwrap();
+    let id2 = collection
+        .add_document(doc! { "id": 2i64, "name": "Bob", "age": 25i64 })
+        .unwrap();
+    assert!(collection.get_document(id1.clone()).is_some());
+
+    collection.remove_document(id1.clone()).unwrap();
+    collection.compact_logfile().unwrap();
+
+    assert!(collection.get_document(id1).is_none());
+    let doc2 = collection.get_document(id2).unwrap();
+    assert_eq!(doc2.data.get_str("name").unwrap(), "Bob");
+}
+
+#[test]
+fn scan_log_entries_yields_offsets_in_order() {
+    let schema = make_int_schema();
+    let temp_dir = tempdir().unwrap();
+    let collection = Collection::new("users", schema, temp_dir.path()).unwrap();
+
+    assert_eq!(collection.scan_log_entries().unwrap().count(), 0);
+
+    let mut offsets = Vec::new();
+    for i in 0..5i64 {
+        let document = doc! { "id": i, "name": format!("User {}", i), "age": 20i64 + i };
+        offsets.push(
+            collection
+                .append_to_log(&Operation::Insert, &document)
+                .unwrap(),
+        );
+    }
+
+    let scanned: Vec<_> = collection
+        .scan_log_entries()
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(scanned.len(), 5);
+    for (i, (entry, offset)) in scanned.iter().enumerate() {
+        assert_eq!(*offset, offsets[i]);
+        assert_eq!(entry.document.get_i64("id").unwrap(), i as i64);
+    }
+
+    let tail = collection.read_log_entries_from(offsets[3]).unwrap();
+    assert_eq!(tail.len(), 2);
+    assert_eq!(tail[0].1, offsets[3]);
+}
+
+#[test]
+fn scan_log_entries_skips_corrupt_entries() {
+    let schema = make_int_schema();
+    let temp_dir = tempdir().unwrap();
+    let collection = Collection::new("users", schema, temp_dir.path()).unwrap();
+
+    let doc1 = doc! { "id": 1i64, "name": "Alice", "age": 30i64 };
+    let doc2 = doc! { "id": 2i64, "name": "Bob", "age": 25i64 };
+    collection.append_to_log(&Operation::Insert, &doc1).unwrap();
+
+    let mut file = fs::OpenOptions::new()
+        .append(true)
+        .open(collection.logfile_path())
+        .unwrap();
+    std::io::Write::write_all(&mut file, &[12, 0, 0, 0, 0xff, 0xff, 0xff, 0xff, b'\n']).unwrap();
+
+    let offset2 = collection.append_to_log(&Operation::Insert, &doc2).unwrap();
+
+    let entries = collection.read_log_entries().unwrap();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].0.document, doc1);
+    assert_eq!(entries[1].0.document, doc2);
+    assert_eq!(entries[1].1, offset2);
+}
+
+#[test]
+fn get_documents_ordered_by_id() {
+    let schema = make_int_schema();
+    let temp_dir = tempdir().unwrap();
+    let mut collection = Collection::new("users", schema, temp_dir.path()).unwrap();
+
+    for id in [5i64, 1, 3] {
+        collection
+            .add_document(doc! { "id": id, "name": "User", "age": 20i64 })
+            .unwrap();
+    }
+    collection
+        .update_document(DocId::from_u64(5), doc! { "name": "Updated" })
+        .unwrap();
+
+    let documents = collection.get_documents().unwrap();
+    let ids: Vec<_> = documents.iter().map(|doc| doc.id.clone()).collect();
+    assert_eq!(
+        ids,
+        vec![DocId::from_u64(1), DocId::from_u64(3), DocId::from_u64(5)]
+    );
+    assert_eq!(documents[2].data.get_str("name").unwrap(), "Updated");
+}
+
 #[test]
 fn update_document_logs_correctly() {
     let schema = make_int_schema();
@@ -350,6 +483,6 @@ fn collection_from_files_handles_update_operations() {
     assert_eq!(retrieved_doc.data.get_f64("salary").unwrap(), 65000.0);
     assert!(!retrieved_doc.data.get_bool("active").unwrap());
 
-    let all_docs = loaded_collection.get_documents();
+    let all_docs = loaded_collection.get_documents().unwrap();
     assert_eq!(all_docs.len(), 1);
 }
@@ -118,7 +118,7 @@ fn metadata_paths() {
     let expected_metadata_path = collection.base_path().join("metadata.bin");
     assert_eq!(collection.metadata_path(), expected_metadata_path);
 
-    let expected_logfile_path = collection.base_path().join("logfile.log");
+    let expected_logfile_path = collection.base_path().join("logfile.000000.log");
     assert_eq!(collection.logfile_path(), expected_logfile_path);
 }
 
@@ -180,9 +180,11 @@ fn metadata_file_size() {
 
     let metadata_path = collection.metadata_path();
     let metadata = fs::read(&metadata_path).unwrap();
-    assert!(!metadata.is_empty());
+    assert!(metadata.len() > FORMAT_HEADER_SIZE);
+    assert_eq!(&metadata[0..4], FileKind::Metadata.magic());
 
-    let parsed: bson::Document = bson::Document::from_reader(&mut metadata.as_slice()).unwrap();
+    let parsed: bson::Document =
+        bson::Document::from_reader(&mut &metadata[FORMAT_HEADER_SIZE..]).unwrap();
     assert!(parsed.contains_key("name"));
     assert!(parsed.contains_key("inserts"));
     assert!(parsed.contains_key("schema"));
@@ -221,3 +223,154 @@ fn multiple_collections_metadata_isolation() {
         read_collection2.schema().fields
     );
 }
+
+#[test]
+fn write_keeps_previous_generation() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection = Collection::new("users", make_int_schema(), temp_dir.path()).unwrap();
+    collection.write_metadata().unwrap();
+    assert!(!collection.previous_metadata_path().exists());
+
+    collection
+        .add_document(doc! { "id": 0i64, "name": "Alice", "age": 30i64 })
+        .unwrap();
+    collection.write_metadata().unwrap();
+
+    assert!(collection.metadata_path().exists());
+    assert!(collection.previous_metadata_path().exists());
+    assert!(!collection.base_path().join("metadata.tmp").exists());
+}
+
+#[test]
+fn read_falls_back_to_previous_generation() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection = Collection::new("users", make_int_schema(), temp_dir.path()).unwrap();
+    collection
+        .add_document(doc! { "id": 0i64, "name": "Alice", "age": 30i64 })
+        .unwrap();
+    collection.write_metadata().unwrap();
+    collection.write_metadata().unwrap();
+
+    let contents = fs::read(collection.metadata_path()).unwrap();
+    fs::write(collection.metadata_path(), &contents[..contents.len() / 2]).unwrap();
+
+    let read_collection = Collection::read_metadata(temp_dir.path(), "users").unwrap();
+    assert_eq!(read_collection.inserts(), 1);
+    assert_eq!(read_collection.schema(), collection.schema());
+    assert!(read_collection.recovery_report().metadata_restored);
+}
+
+#[test]
+fn read_completes_interrupted_replacement() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection = Collection::new("users", make_int_schema(), temp_dir.path()).unwrap();
+    collection.write_metadata().unwrap();
+    collection
+        .add_document(doc! { "id": 0i64, "name": "Alice", "age": 30i64 })
+        .unwrap();
+    collection.write_metadata().unwrap();
+
+    fs::rename(
+        collection.metadata_path(),
+        collection.base_path().join("metadata.tmp"),
+    )
+    .unwrap();
+
+    let loaded = Collection::from_files(temp_dir.path(), "users").unwrap();
+    assert_eq!(loaded.inserts(), 1);
+    assert!(loaded.recovery_report().metadata_restored);
+    assert!(loaded.metadata_path().exists());
+
+    let reloaded = Collection::from_files(temp_dir.path(), "users").unwrap();
+    assert!(reloaded.recovery_report().is_clean());
+}
+
+#[test]
+fn read_fails_when_every_generation_is_damaged() {
+    let temp_dir = tempdir().unwrap();
+    let collection = Collection::new("users", make_int_schema(), temp_dir.path()).unwrap();
+    collection.write_metadata().unwrap();
+    collection.write_metadata().unwrap();
+
+    fs::write(collection.metadata_path(), [1, 2, 3]).unwrap();
+    fs::write(collection.previous_metadata_path(), [4, 5, 6]).unwrap();
+
+    let result = Collection::read_metadata(temp_dir.path(), "users");
+    assert_eq!(result.unwrap_err().kind(), std::io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn inserts_do_not_rewrite_metadata() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection = Collection::new("users", make_int_schema(), temp_dir.path()).unwrap();
+    collection
+        .add_document(doc! { "name": "Alice", "age": 30i64 })
+        .unwrap();
+    let metadata = fs::read(collection.metadata_path()).unwrap();
+
+    for i in 0..10i64 {
+        collection
+            .add_document(doc! { "name": "User", "age": i })
+            .unwrap();
+    }
+
+    assert_eq!(fs::read(collection.metadata_path()).unwrap(), metadata);
+    assert_eq!(collection.inserts(), 11);
+}
+
+#[test]
+fn inserts_recovered_from_logfile() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection = Collection::new("users", make_string_schema(), temp_dir.path()).unwrap();
+    for i in 0..5i64 {
+        collection
+            .add_document(doc! { "name": "User", "age": i })
+            .unwrap();
+    }
+
+    let read_collection = Collection::read_metadata(temp_dir.path(), "users").unwrap();
+    assert_eq!(read_collection.inserts(), 5);
+
+    let loaded = Collection::from_files(temp_dir.path(), "users").unwrap();
+    assert_eq!(loaded.inserts(), 5);
+}
+
+#[test]
+fn deleted_ids_not_reused_after_reload() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection = Collection::new("users", make_int_schema(), temp_dir.path()).unwrap();
+    collection
+        .add_document(doc! { "name": "Alice", "age": 30i64 })
+        .unwrap();
+    collection
+        .add_document(doc! { "name": "Bob", "age": 25i64 })
+        .unwrap();
+    let last_id = collection
+        .add_document(doc! { "name": "Charlie", "age": 35i64 })
+        .unwrap();
+    collection.remove_document(last_id.clone()).unwrap();
+
+    let mut loaded = Collection::from_files(temp_dir.path(), "users").unwrap();
+    assert_eq!(loaded.inserts(), 3);
+
+    let new_id = loaded
+        .add_document(doc! { "name": "Dave", "age": 40i64 })
+        .unwrap();
+    assert_ne!(new_id, last_id);
+    assert_eq!(new_id, DocId::from_u64(3));
+}
+
+#[test]
+fn inserts_recovered_past_explicit_ids() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection = Collection::new("users", make_int_schema(), temp_dir.path()).unwrap();
+    collection
+        .add_document(doc! { "name": "Alice", "age": 30i64 })
+        .unwrap();
+    collection
+        .add_document(doc! { "id": 41i64, "name": "Bob", "age": 25i64 })
+        .unwrap();
+
+    let loaded = Collection::from_files(temp_dir.path(), "users").unwrap();
+    assert_eq!(loaded.inserts(), 42);
+}
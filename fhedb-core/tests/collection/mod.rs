@@ -1,8 +1,20 @@
+mod changes;
 mod compaction;
+mod compression;
 mod core;
+mod deltas;
+mod durability;
+mod encryption;
 mod files;
+mod format;
+mod history;
 mod id_integer;
 mod id_string;
+mod indexes;
 mod logs;
 mod metadata;
+mod online_compaction;
+mod recovery;
 mod schema_ops;
+mod segments;
+mod unique;
@@ -0,0 +1,290 @@
+use bson::doc;
+use fhedb_core::prelude::*;
+use std::fs;
+use tempfile::tempdir;
+
+use super::super::common::{add_users, make_int_schema, small_segment_collection};
+
+fn age_of(collection: &Collection, id: u64) -> i64 {
+    collection
+        .get_document(DocId::from_u64(id))
+        .unwrap()
+        .data
+        .get_i64("age")
+        .unwrap()
+}
+
+#[test]
+fn log_stats_count_superseded_entries() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection = small_segment_collection(temp_dir.path());
+    add_users(&mut collection, 4);
+    collection
+        .update_document(DocId::from_u64(0), doc! { "age": 50i64 })
+        .unwrap();
+    collection.remove_document(DocId::from_u64(1)).unwrap();
+
+    let stats = collection.log_stats();
+    assert_eq!(stats.entries, 6);
+    assert_eq!(stats.garbage, 3);
+    assert_eq!(collection.garbage_ratio(), 0.5);
+}
+
+#[test]
+fn needs_compaction_above_threshold() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection = small_segment_collection(temp_dir.path());
+    add_users(&mut collection, 4);
+    assert_eq!(
+        collection.compaction_threshold(),
+        DEFAULT_COMPACTION_THRESHOLD
+    );
+    assert!(!collection.needs_compaction());
+
+    collection
+        .update_document(DocId::from_u64(0), doc! { "age": 50i64 })
+        .unwrap();
+    assert!(!collection.needs_compaction());
+
+    collection.set_compaction_threshold(0.1);
+    assert!(collection.needs_compaction());
+}
+
+#[test]
+fn compact_removes_garbage() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection = small_segment_collection(temp_dir.path());
+    add_users(&mut collection, 20);
+    for i in 0..10u64 {
+        collection
+            .update_document(DocId::from_u64(i), doc! { "age": 100i64 })
+            .unwrap();
+    }
+    for i in 10..15u64 {
+        collection.remove_document(DocId::from_u64(i)).unwrap();
+    }
+
+    let report = collection.compact().unwrap();
+
+    assert!(report.segments_compacted + report.segments_removed > 0);
+    assert_eq!(report.entries_removed, 20);
+    assert!(report.bytes_reclaimed > 0);
+    assert_eq!(
+        collection.log_stats(),
+        LogStats {
+            entries: 15,
+            garbage: 0
+        }
+    );
+    assert_eq!(collection.read_log_entries().unwrap().len(), 15);
+    assert_eq!(collection.document_count().unwrap(), 15);
+    for i in 0..10u64 {
+        assert_eq!(age_of(&collection, i), 100);
+    }
+    for i in 10..15u64 {
+        assert!(collection.get_document(DocId::from_u64(i)).is_none());
+    }
+    for i in 15..20u64 {
+        assert_eq!(age_of(&collection, i), 20 + i as i64);
+    }
+}
+
+#[test]
+fn compact_without_garbage_changes_nothing() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection = small_segment_collection(temp_dir.path());
+    add_users(&mut collection, 10);
+
+    let report = collection.compact().unwrap();
+
+    assert_eq!(report, CompactionReport::default());
+    assert_eq!(collection.document_count().unwrap(), 10);
+    assert_eq!(collection.read_log_entries().unwrap().len(), 10);
+}
+
+#[test]
+fn writes_during_compaction_are_kept() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection = small_segment_collection(temp_dir.path());
+    add_users(&mut collection, 10);
+    for i in 0..5u64 {
+        collection
+            .update_document(DocId::from_u64(i), doc! { "age": 100i64 })
+            .unwrap();
+    }
+
+    let plan = collection.plan_compaction().unwrap().unwrap();
+    assert!(!plan.segments().is_empty());
+    let prepared = collection.prepare_compaction(plan).unwrap();
+
+    collection
+        .update_document(DocId::from_u64(0), doc! { "age": 200i64 })
+        .unwrap();
+    collection.remove_document(DocId::from_u64(6)).unwrap();
+    collection
+        .add_document(doc! { "id": 10i64, "name": "User 10", "age": 30i64 })
+        .unwrap();
+
+    collection.commit_compaction(prepared).unwrap();
+
+    assert_eq!(age_of(&collection, 0), 200);
+    assert_eq!(age_of(&collection, 1), 100);
+    assert!(collection.get_document(DocId::from_u64(6)).is_none());
+    assert_eq!(age_of(&collection, 10), 30);
+    assert_eq!(collection.document_count().unwrap(), 10);
+
+    let loaded = Collection::from_files(temp_dir.path(), "users").unwrap();
+    assert!(loaded.recovery_report().is_clean());
+    assert_eq!(loaded.document_count().unwrap(), 10);
+    assert_eq!(age_of(&loaded, 0), 200);
+    assert!(loaded.get_document(DocId::from_u64(6)).is_none());
+}
+
+#[test]
+fn only_one_compaction_at_a_time() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection = small_segment_collection(temp_dir.path());
+    add_users(&mut collection, 10);
+    collection
+        .update_document(DocId::from_u64(0), doc! { "age": 100i64 })
+        .unwrap();
+
+    let plan = collection.plan_compaction().unwrap().unwrap();
+    assert!(collection.plan_compaction().unwrap().is_none());
+    assert_eq!(collection.compact().unwrap(), CompactionReport::default());
+
+    drop(plan);
+    assert!(collection.plan_compaction().unwrap().is_some());
+}
+
+#[test]
+fn commit_rejects_other_collection() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection = small_segment_collection(temp_dir.path());
+    add_users(&mut collection, 10);
+    let mut other = Collection::new("other", make_int_schema(), temp_dir.path()).unwrap();
+
+    let plan = collection.plan_compaction().unwrap().unwrap();
+    let prepared = collection.prepare_compaction(plan).unwrap();
+
+    assert!(other.commit_compaction(prepared).is_err());
+}
+
+#[test]
+fn compacted_collection_reloads() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection = small_segment_collection(temp_dir.path());
+    add_users(&mut collection, 20);
+    for i in 0..20u64 {
+        collection
+            .update_document(DocId::from_u64(i), doc! { "age": 100i64 + i as i64 })
+            .unwrap();
+    }
+    collection.compact().unwrap();
+
+    let loaded = Collection::from_files(temp_dir.path(), "users").unwrap();
+
+    assert!(loaded.recovery_report().is_clean());
+    assert_eq!(loaded.log_stats(), collection.log_stats());
+    assert_eq!(loaded.document_count().unwrap(), 20);
+    for i in 0..20u64 {
+        assert_eq!(age_of(&loaded, i), 100 + i as i64);
+    }
+}
+
+#[test]
+fn log_stats_recovered_from_logfile() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection = small_segment_collection(temp_dir.path());
+    add_users(&mut collection, 4);
+    collection.write_metadata().unwrap();
+    collection
+        .update_document(DocId::from_u64(0), doc! { "age": 50i64 })
+        .unwrap();
+    collection.remove_document(DocId::from_u64(1)).unwrap();
+
+    let loaded = Collection::from_files(temp_dir.path(), "users").unwrap();
+
+    assert_eq!(
+        loaded.log_stats(),
+        LogStats {
+            entries: 6,
+            garbage: 3
+        }
+    );
+}
+
+#[test]
+fn stale_compaction_files_removed_on_load() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection = small_segment_collection(temp_dir.path());
+    add_users(&mut collection, 10);
+    collection.write_metadata().unwrap();
+
+    let stale_path = collection.base_path().join("logfile.000000.compact");
+    fs::write(&stale_path, b"partial").unwrap();
+
+    let loaded = Collection::from_files(temp_dir.path(), "users").unwrap();
+
+    assert!(!stale_path.exists());
+    assert_eq!(loaded.document_count().unwrap(), 10);
+}
+
+#[test]
+fn database_applies_compaction_threshold() {
+    let temp_dir = tempdir().unwrap();
+    let mut db = Database::new("test_db", temp_dir.path());
+    db.create_collection("users", make_int_schema()).unwrap();
+    db.set_compaction_threshold(0.25);
+    db.create_collection("products", make_int_schema()).unwrap();
+
+    assert_eq!(db.compaction_threshold(), 0.25);
+    assert_eq!(
+        db.get_collection("users").unwrap().compaction_threshold(),
+        0.25
+    );
+    assert_eq!(
+        db.get_collection("products")
+            .unwrap()
+            .compaction_threshold(),
+        0.25
+    );
+    assert!(db.collections_needing_compaction().is_empty());
+}
+
+#[test]
+fn compact_keeps_insertion_order_and_timestamps() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection = small_segment_collection(temp_dir.path());
+    add_users(&mut collection, 10);
+    for i in (0..10u64).rev() {
+        collection
+            .update_document(DocId::from_u64(i), doc! { "age": 100i64 })
+            .unwrap();
+    }
+    let original: Vec<_> = collection
+        .read_log_entries()
+        .unwrap()
+        .into_iter()
+        .map(|(entry, _)| entry.timestamp)
+        .collect();
+
+    collection.compact().unwrap();
+
+    let entries: Vec<_> = collection
+        .read_log_entries()
+        .unwrap()
+        .into_iter()
+        .map(|(entry, _)| entry)
+        .collect();
+    assert_eq!(entries.len(), 10);
+    for (i, entry) in entries.iter().enumerate() {
+        assert_eq!(entry.document.get_i64("id").unwrap(), i as i64);
+        assert_eq!(entry.document.get_i64("age").unwrap(), 100);
+        assert_eq!(entry.inserted_at(), original[i]);
+        assert_eq!(entry.timestamp, original[19 - i]);
+    }
+    for i in 0..10u64 {
+        assert_eq!(age_of(&collection, i), 100);
+    }
+}
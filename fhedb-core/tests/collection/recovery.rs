@@ -0,0 +1,131 @@
+use bson::doc;
+use fhedb_core::prelude::*;
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+};
+use tempfile::tempdir;
+
+use super::super::common::make_int_schema;
+
+fn append_raw_bytes(collection: &Collection, bytes: &[u8]) {
+    let mut file = OpenOptions::new()
+        .append(true)
+        .open(collection.logfile_path())
+        .unwrap();
+    file.write_all(bytes).unwrap();
+}
+
+#[test]
+fn clean_logfile_reports_no_damage() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection = Collection::new("users", make_int_schema(), temp_dir.path()).unwrap();
+    collection
+        .add_document(doc! { "id": 1i64, "name": "Alice", "age": 30i64 })
+        .unwrap();
+
+    let loaded = Collection::from_files(temp_dir.path(), "users").unwrap();
+    assert!(loaded.recovery_report().is_clean());
+    assert_eq!(loaded.recovery_report().bytes_lost(), 0);
+}
+
+#[test]
+fn torn_tail_is_truncated() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection = Collection::new("users", make_int_schema(), temp_dir.path()).unwrap();
+    let id = collection
+        .add_document(doc! { "id": 1i64, "name": "Alice", "age": 30i64 })
+        .unwrap();
+
+    let intact_len = fs::metadata(collection.logfile_path()).unwrap().len() as usize;
+    append_raw_bytes(&collection, &[200, 0, 0, 0, 1, 2, 3, 4, 5, 6]);
+
+    let loaded = Collection::from_files(temp_dir.path(), "users").unwrap();
+    let report = loaded.recovery_report();
+
+    assert_eq!(report.truncated_at, Some(LogPosition::new(0, intact_len)));
+    assert_eq!(report.bytes_truncated, 10);
+    assert!(report.skipped.is_empty());
+    assert_eq!(
+        fs::metadata(loaded.logfile_path()).unwrap().len() as usize,
+        intact_len
+    );
+    assert!(loaded.get_document(id).is_some());
+
+    let reloaded = Collection::from_files(temp_dir.path(), "users").unwrap();
+    assert!(reloaded.recovery_report().is_clean());
+}
+
+#[test]
+fn partial_header_is_truncated() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection = Collection::new("users", make_int_schema(), temp_dir.path()).unwrap();
+    collection
+        .add_document(doc! { "id": 1i64, "name": "Alice", "age": 30i64 })
+        .unwrap();
+
+    append_raw_bytes(&collection, &[7, 0, 0]);
+
+    let loaded = Collection::from_files(temp_dir.path(), "users").unwrap();
+    assert_eq!(loaded.recovery_report().bytes_truncated, 3);
+    assert_eq!(loaded.document_count().unwrap(), 1);
+}
+
+#[test]
+fn damaged_entry_followed_by_valid_entries_is_skipped() {
+    let temp_dir = tempdir().unwrap();
+    let collection = Collection::new("users", make_int_schema(), temp_dir.path()).unwrap();
+
+    let doc1 = doc! { "id": 1i64, "name": "Alice", "age": 30i64 };
+    let doc2 = doc! { "id": 2i64, "name": "Bob", "age": 25i64 };
+    let offset1 = collection.append_to_log(&Operation::Insert, &doc1).unwrap();
+    let offset2 = collection.append_to_log(&Operation::Insert, &doc2).unwrap();
+
+    let mut contents = fs::read(collection.logfile_path()).unwrap();
+    contents[offset1.offset + FRAME_HEADER_SIZE + 10] ^= 0xff;
+    fs::write(collection.logfile_path(), contents).unwrap();
+
+    let result = collection.read_log_entry_at_offset(offset1);
+    assert_eq!(result.unwrap_err().kind(), std::io::ErrorKind::InvalidData);
+
+    collection.write_metadata().unwrap();
+    let loaded = Collection::from_files(temp_dir.path(), "users").unwrap();
+    let report = loaded.recovery_report();
+
+    assert_eq!(report.entries_verified, 1);
+    assert_eq!(
+        report.skipped,
+        vec![SkippedRegion {
+            position: offset1,
+            length: offset2.offset - offset1.offset
+        }]
+    );
+    assert_eq!(report.truncated_at, None);
+    assert_eq!(loaded.document_count().unwrap(), 1);
+    assert!(loaded.get_document(DocId::from_u64(2)).is_some());
+}
+
+#[test]
+fn database_collects_recovery_reports() {
+    let temp_dir = tempdir().unwrap();
+    let mut db = Database::new("test_db", temp_dir.path());
+    db.create_collection("users", make_int_schema()).unwrap();
+    db.create_collection("products", make_int_schema()).unwrap();
+
+    let users = db.get_collection_mut("users").unwrap();
+    users
+        .add_document(doc! { "id": 1i64, "name": "Alice", "age": 30i64 })
+        .unwrap();
+    append_raw_bytes(users, &[1, 2, 3, 4, 5]);
+
+    let products = db.get_collection_mut("products").unwrap();
+    products
+        .add_document(doc! { "id": 1i64, "name": "Widget", "age": 1i64 })
+        .unwrap();
+
+    let loaded = Database::from_files("test_db", temp_dir.path()).unwrap();
+    let reports = loaded.recovery_reports();
+
+    assert_eq!(reports.len(), 1);
+    assert_eq!(reports["users"].bytes_truncated, 5);
+}
@@ -119,6 +119,23 @@ fn add_field_without_default_with_existing_docs() {
     assert!(result.unwrap_err().contains("without a default value"));
 }
 
+#[test]
+fn add_field_fails_when_index_unreadable() {
+    let (mut collection, _temp_dir) = create_test_collection_with_data();
+
+    let mut contents = std::fs::read(collection.index_path()).unwrap();
+    let leaf = PAGE_SIZE + PAGE_CHECKSUM_SIZE;
+    contents[leaf..leaf + 64].fill(0xFF);
+    std::fs::write(collection.index_path(), contents).unwrap();
+
+    let field_def = FieldDefinition::new(FieldType::String);
+    let result = collection.add_field("email".to_string(), field_def);
+
+    assert!(result.unwrap_err().contains("checksum"));
+    assert!(!collection.has_field("email"));
+    assert!(collection.document_count().is_err());
+}
+
 #[test]
 fn add_field_nullable_auto_default() {
     let (mut collection, _temp_dir) = create_test_collection_with_data();
@@ -129,7 +146,7 @@ fn add_field_nullable_auto_default() {
     assert!(result.is_ok());
     assert!(collection.has_field("email"));
 
-    let docs = collection.get_documents();
+    let docs = collection.get_documents().unwrap();
     for doc in docs {
         assert!(doc.data.contains_key("email"));
         assert_eq!(doc.data.get("email").unwrap(), &Bson::Null);
@@ -148,7 +165,7 @@ fn add_field_with_default_applies_to_existing_docs() {
 
     assert!(result.is_ok());
 
-    let docs = collection.get_documents();
+    let docs = collection.get_documents().unwrap();
     for doc in docs {
         assert!(doc.data.contains_key("email"));
         assert_eq!(
@@ -167,7 +184,7 @@ fn remove_field_success() {
     assert!(result.is_ok());
     assert!(!collection.has_field("age"));
 
-    let docs = collection.get_documents();
+    let docs = collection.get_documents().unwrap();
     for doc in docs {
         assert!(!doc.data.contains_key("age"));
         assert!(doc.data.contains_key("name"));
@@ -217,7 +234,7 @@ fn rename_field_success() {
     assert!(!collection.has_field("name"));
     assert!(collection.has_field("full_name"));
 
-    let docs = collection.get_documents();
+    let docs = collection.get_documents().unwrap();
     for doc in docs {
         assert!(!doc.data.contains_key("name"));
         assert!(doc.data.contains_key("full_name"));
@@ -280,7 +297,7 @@ fn cleanup_removed_field() {
     let updated_ids = result.unwrap();
     assert_eq!(updated_ids.len(), 2);
 
-    let docs = collection.get_documents();
+    let docs = collection.get_documents().unwrap();
     for doc in docs {
         assert!(!doc.data.contains_key("age"));
         assert!(doc.data.contains_key("name"));
@@ -297,7 +314,7 @@ fn rename_field_in_documents() {
     let updated_ids = result.unwrap();
     assert_eq!(updated_ids.len(), 2);
 
-    let docs = collection.get_documents();
+    let docs = collection.get_documents().unwrap();
     for doc in docs {
         assert!(!doc.data.contains_key("name"));
         assert!(doc.data.contains_key("full_name"));
@@ -327,7 +344,7 @@ fn add_ids_to_all_documents() {
 
     assert_eq!(collection.inserts(), 2);
 
-    let docs = collection.get_documents();
+    let docs = collection.get_documents().unwrap();
     for doc in docs {
         assert!(doc.data.contains_key("new_id"));
         assert!(doc.data.contains_key("name"));
@@ -380,7 +397,7 @@ fn string_id_collection_operations() {
     let result = collection.add_field("email".to_string(), field_def);
     assert!(result.is_ok());
 
-    let docs = collection.get_documents();
+    let docs = collection.get_documents().unwrap();
     assert_eq!(docs.len(), 1);
     assert!(docs[0].data.contains_key("email"));
 }
@@ -405,7 +422,7 @@ fn integration_add_modify_remove_field() {
         .modify_field("email", new_def)
         .expect("Failed to modify field");
 
-    let docs = collection.get_documents();
+    let docs = collection.get_documents().unwrap();
     for doc in docs {
         assert_eq!(
             doc.data.get("email").unwrap(),
@@ -417,7 +434,7 @@ fn integration_add_modify_remove_field() {
         .remove_field("email")
         .expect("Failed to remove field");
 
-    let docs = collection.get_documents();
+    let docs = collection.get_documents().unwrap();
     for doc in docs {
         assert!(!doc.data.contains_key("email"));
     }
@@ -455,7 +472,7 @@ fn schema_with_defaults() {
         })
         .expect("Failed to add document");
 
-    let docs = collection.get_documents();
+    let docs = collection.get_documents().unwrap();
     assert_eq!(docs.len(), 1);
     let doc = &docs[0];
 
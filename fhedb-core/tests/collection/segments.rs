@@ -0,0 +1,211 @@
+use bson::doc;
+use fhedb_core::{
+    collection::segment::{parse_segment_file_name, segment_file_name},
+    prelude::*,
+};
+use std::fs;
+use tempfile::tempdir;
+
+use super::super::common::{add_users, make_int_schema, small_segment_collection};
+
+#[test]
+fn segment_file_names_round_trip() {
+    assert_eq!(segment_file_name(0), "logfile.000000.log");
+    assert_eq!(segment_file_name(42), "logfile.000042.log");
+    assert_eq!(parse_segment_file_name("logfile.000042.log"), Some(42));
+    assert_eq!(parse_segment_file_name("logfile.log"), None);
+    assert_eq!(parse_segment_file_name("logfile.000001.tmp"), None);
+    assert_eq!(parse_segment_file_name("metadata.bin"), None);
+}
+
+#[test]
+fn log_positions_order_by_segment_first() {
+    assert!(LogPosition::new(0, 500) < LogPosition::new(1, 0));
+    assert!(LogPosition::new(1, 10) < LogPosition::new(1, 20));
+    assert_eq!(LogPosition::new(3, 128).to_string(), "3:128");
+}
+
+#[test]
+fn default_max_segment_size() {
+    let temp_dir = tempdir().unwrap();
+    let collection = Collection::new("users", make_int_schema(), temp_dir.path()).unwrap();
+
+    assert_eq!(collection.max_segment_size(), DEFAULT_MAX_SEGMENT_SIZE);
+}
+
+#[test]
+fn writes_roll_over_to_new_segments() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection = small_segment_collection(temp_dir.path());
+    add_users(&mut collection, 20);
+
+    let segment_paths = collection.segment_paths().unwrap();
+    assert!(segment_paths.len() > 1);
+    for path in &segment_paths {
+        assert!(fs::metadata(path).unwrap().len() <= 256);
+    }
+    assert_eq!(collection.logfile_path(), *segment_paths.last().unwrap());
+
+    let positions: Vec<_> = collection
+        .read_log_entries()
+        .unwrap()
+        .into_iter()
+        .map(|(_, position)| position)
+        .collect();
+    assert_eq!(positions.len(), 20);
+    assert!(positions.windows(2).all(|pair| pair[0] < pair[1]));
+    assert!(positions.last().unwrap().segment > 0);
+}
+
+#[test]
+fn oversized_entry_gets_its_own_segment() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection = small_segment_collection(temp_dir.path());
+    add_users(&mut collection, 1);
+
+    let long_name = "x".repeat(1024);
+    let id = collection
+        .add_document(doc! { "id": 1i64, "name": long_name.as_str(), "age": 40i64 })
+        .unwrap();
+
+    let position = collection.primary_index().get(&id).unwrap().unwrap();
+    assert_eq!(position, LogPosition::new(1, FORMAT_HEADER_SIZE));
+    assert_eq!(
+        collection
+            .get_document(id)
+            .unwrap()
+            .data
+            .get_str("name")
+            .unwrap(),
+        long_name
+    );
+}
+
+#[test]
+fn documents_readable_across_segments() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection = small_segment_collection(temp_dir.path());
+    add_users(&mut collection, 20);
+
+    collection
+        .update_document(DocId::from_u64(0), doc! { "age": 99i64 })
+        .unwrap();
+    collection.remove_document(DocId::from_u64(1)).unwrap();
+
+    let first = collection.get_document(DocId::from_u64(0)).unwrap();
+    assert_eq!(first.data.get_i64("age").unwrap(), 99);
+    assert!(collection.get_document(DocId::from_u64(1)).is_none());
+    assert_eq!(collection.get_documents().unwrap().len(), 19);
+
+    let loaded = Collection::from_files(temp_dir.path(), "users").unwrap();
+    assert!(loaded.recovery_report().is_clean());
+    assert_eq!(loaded.document_count().unwrap(), 19);
+    let first = loaded.get_document(DocId::from_u64(0)).unwrap();
+    assert_eq!(first.data.get_i64("age").unwrap(), 99);
+}
+
+#[test]
+fn missing_index_rebuilt_from_all_segments() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection = small_segment_collection(temp_dir.path());
+    add_users(&mut collection, 20);
+
+    fs::remove_file(collection.index_path()).unwrap();
+    let loaded = Collection::from_files(temp_dir.path(), "users").unwrap();
+
+    assert_eq!(loaded.document_count().unwrap(), 20);
+    for i in 0..20u64 {
+        assert!(loaded.get_document(DocId::from_u64(i)).is_some());
+    }
+}
+
+#[test]
+fn compaction_removes_emptied_segments() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection = small_segment_collection(temp_dir.path());
+    add_users(&mut collection, 20);
+    for i in 0..10u64 {
+        collection.remove_document(DocId::from_u64(i)).unwrap();
+    }
+    let segments_before = collection.segment_paths().unwrap();
+    collection.compact_logfile().unwrap();
+
+    let segments_after = collection.segment_paths().unwrap();
+    assert!(segments_after.len() < segments_before.len());
+    assert_eq!(segments_after.last(), segments_before.last());
+    assert_eq!(collection.read_log_entries().unwrap().len(), 10);
+    for i in 10..20u64 {
+        assert!(collection.get_document(DocId::from_u64(i)).is_some());
+    }
+
+    collection
+        .add_document(doc! { "id": 20i64, "name": "User 20", "age": 40i64 })
+        .unwrap();
+    let loaded = Collection::from_files(temp_dir.path(), "users").unwrap();
+    assert_eq!(loaded.document_count().unwrap(), 11);
+}
+
+#[test]
+fn torn_tail_in_sealed_segment_is_skipped() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection = small_segment_collection(temp_dir.path());
+    add_users(&mut collection, 20);
+    collection.write_metadata().unwrap();
+
+    let segment_paths = collection.segment_paths().unwrap();
+    let first_segment = &segment_paths[0];
+    let intact_len = fs::metadata(first_segment).unwrap().len() as usize;
+    let mut contents = fs::read(first_segment).unwrap();
+    contents.extend_from_slice(&[9, 0, 0, 0, 1, 2, 3]);
+    fs::write(first_segment, contents).unwrap();
+    fs::remove_file(collection.index_path()).unwrap();
+
+    let loaded = Collection::from_files(temp_dir.path(), "users").unwrap();
+    let report = loaded.recovery_report();
+
+    assert_eq!(report.truncated_at, None);
+    assert_eq!(
+        report.skipped,
+        vec![SkippedRegion {
+            position: LogPosition::new(0, intact_len),
+            length: 7
+        }]
+    );
+    assert_eq!(loaded.document_count().unwrap(), 20);
+}
+
+#[test]
+fn legacy_logfile_moved_into_first_segment() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection = Collection::new("users", make_int_schema(), temp_dir.path()).unwrap();
+    add_users(&mut collection, 3);
+    collection.write_metadata().unwrap();
+
+    let segment_path = collection.logfile_path();
+    let legacy_path = collection.base_path().join("logfile.log");
+    fs::rename(&segment_path, &legacy_path).unwrap();
+
+    let loaded = Collection::from_files(temp_dir.path(), "users").unwrap();
+
+    assert!(!legacy_path.exists());
+    assert!(segment_path.exists());
+    assert!(loaded.recovery_report().is_clean());
+    assert_eq!(loaded.document_count().unwrap(), 3);
+    assert!(loaded.get_document(DocId::from_u64(2)).is_some());
+}
+
+#[test]
+fn database_applies_max_segment_size() {
+    let temp_dir = tempdir().unwrap();
+    let mut db = Database::new("test_db", temp_dir.path());
+    db.create_collection("users", make_int_schema()).unwrap();
+    db.set_max_segment_size(256);
+    db.create_collection("products", make_int_schema()).unwrap();
+
+    assert_eq!(db.max_segment_size(), 256);
+    assert_eq!(db.get_collection("users").unwrap().max_segment_size(), 256);
+    assert_eq!(
+        db.get_collection("products").unwrap().max_segment_size(),
+        256
+    );
+}
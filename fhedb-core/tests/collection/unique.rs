@@ -0,0 +1,203 @@
+use bson::{Bson, doc};
+use fhedb_core::prelude::*;
+use std::collections::HashMap;
+use tempfile::tempdir;
+
+fn make_unique_schema() -> Schema {
+    let mut fields = HashMap::new();
+    fields.insert("id".to_string(), FieldDefinition::new(FieldType::IdInt));
+    fields.insert("name".to_string(), FieldDefinition::new(FieldType::String));
+    fields.insert(
+        "email".to_string(),
+        FieldDefinition::new(FieldType::Nullable(Box::new(FieldType::String))).with_unique(true),
+    );
+    Schema { fields }
+}
+
+fn add_user(collection: &mut Collection, name: &str, email: Bson) -> Result<DocId, Vec<String>> {
+    collection.add_document(doc! { "name": name, "email": email })
+}
+
+#[test]
+fn unique_fields_are_indexed() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection = Collection::new("users", make_unique_schema(), temp_dir.path()).unwrap();
+    assert_eq!(collection.indexed_fields(), vec!["email"]);
+
+    let rejected = collection.drop_index("email").unwrap_err();
+    assert!(rejected.contains("unique"));
+
+    let mut fields = HashMap::new();
+    fields.insert(
+        "tags".to_string(),
+        FieldDefinition::new(FieldType::Array(Box::new(FieldType::String))).with_unique(true),
+    );
+    assert!(Collection::new("tagged", Schema { fields }, temp_dir.path()).is_err());
+}
+
+#[test]
+fn inserts_reject_duplicate_values() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection = Collection::new("users", make_unique_schema(), temp_dir.path()).unwrap();
+    add_user(
+        &mut collection,
+        "Alice",
+        Bson::String("a@example.com".into()),
+    )
+    .unwrap();
+    add_user(&mut collection, "Bob", Bson::Null).unwrap();
+    add_user(&mut collection, "Carol", Bson::Null).unwrap();
+
+    let errors = add_user(
+        &mut collection,
+        "Dave",
+        Bson::String("a@example.com".into()),
+    )
+    .unwrap_err();
+    assert_eq!(
+        errors,
+        vec![
+            "Unique field 'email' already holds value \"a@example.com\" in document '0'."
+                .to_string()
+        ]
+    );
+    assert_eq!(collection.document_count().unwrap(), 3);
+
+    collection
+        .remove_document(DocId::from_u64(0))
+        .unwrap()
+        .unwrap();
+    add_user(
+        &mut collection,
+        "Dave",
+        Bson::String("a@example.com".into()),
+    )
+    .unwrap();
+}
+
+#[test]
+fn updates_reject_duplicate_values() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection = Collection::new("users", make_unique_schema(), temp_dir.path()).unwrap();
+    let alice = add_user(
+        &mut collection,
+        "Alice",
+        Bson::String("a@example.com".into()),
+    )
+    .unwrap();
+    let bob = add_user(&mut collection, "Bob", Bson::String("b@example.com".into())).unwrap();
+
+    let errors = collection
+        .update_document(bob.clone(), doc! { "email": "a@example.com" })
+        .unwrap_err();
+    assert!(errors[0].contains("in document '0'"));
+
+    collection
+        .update_document(
+            alice.clone(),
+            doc! { "email": "a@example.com", "name": "Al" },
+        )
+        .unwrap();
+    collection
+        .update_document(alice, doc! { "email": "c@example.com" })
+        .unwrap();
+    collection
+        .update_document(bob, doc! { "email": "a@example.com" })
+        .unwrap();
+}
+
+#[test]
+fn long_values_are_compared_in_full() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection = Collection::new("users", make_unique_schema(), temp_dir.path()).unwrap();
+    let prefix = "x".repeat(2048);
+    add_user(&mut collection, "Alice", Bson::String(format!("{prefix}a"))).unwrap();
+    add_user(&mut collection, "Bob", Bson::String(format!("{prefix}b"))).unwrap();
+
+    assert!(add_user(&mut collection, "Carol", Bson::String(format!("{prefix}b"))).is_err());
+}
+
+#[test]
+fn constraint_persists_across_loads() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection = Collection::new("users", make_unique_schema(), temp_dir.path()).unwrap();
+    add_user(
+        &mut collection,
+        "Alice",
+        Bson::String("a@example.com".into()),
+    )
+    .unwrap();
+
+    let document = schema_to_document(collection.schema());
+    assert!(
+        document
+            .get_document("email")
+            .unwrap()
+            .get_bool("unique")
+            .unwrap()
+    );
+    assert_eq!(schema_from_document(document), *collection.schema());
+
+    let mut loaded = Collection::from_files(temp_dir.path(), "users").unwrap();
+    assert!(loaded.schema().fields["email"].unique);
+    assert_eq!(loaded.indexed_fields(), vec!["email"]);
+    assert!(add_user(&mut loaded, "Bob", Bson::String("a@example.com".into())).is_err());
+}
+
+#[test]
+fn schema_changes_add_constraints() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection = Collection::new("users", make_unique_schema(), temp_dir.path()).unwrap();
+    add_user(&mut collection, "Alice", Bson::Null).unwrap();
+    add_user(&mut collection, "Bob", Bson::Null).unwrap();
+
+    let shared_default =
+        FieldDefinition::with_default(FieldType::String, Bson::String("none".into()))
+            .with_unique(true);
+    assert!(
+        collection
+            .add_field("handle".to_string(), shared_default.clone())
+            .is_err()
+    );
+    assert!(collection.modify_field("name", shared_default).is_err());
+    assert_eq!(
+        collection.schema().fields["name"].field_type,
+        FieldType::String
+    );
+
+    let nullable = FieldDefinition::new(FieldType::Nullable(Box::new(FieldType::String)));
+    collection
+        .add_field("handle".to_string(), nullable.clone().with_unique(true))
+        .unwrap();
+    collection
+        .modify_field("name", nullable.with_unique(true))
+        .unwrap();
+    assert_eq!(collection.indexed_fields(), vec!["email", "handle", "name"]);
+
+    collection
+        .update_document(DocId::from_u64(0), doc! { "handle": "al" })
+        .unwrap();
+    let errors = collection
+        .update_document(DocId::from_u64(1), doc! { "handle": "al" })
+        .unwrap_err();
+    assert!(errors[0].contains("'handle'"));
+
+    collection
+        .rename_field("handle", "nick".to_string())
+        .unwrap();
+    assert!(
+        collection
+            .update_document(DocId::from_u64(1), doc! { "nick": "al" })
+            .is_err()
+    );
+    collection.remove_field("nick").unwrap();
+    assert_eq!(collection.indexed_fields(), vec!["email", "name"]);
+
+    collection
+        .modify_field(
+            "email",
+            FieldDefinition::new(FieldType::Nullable(Box::new(FieldType::String))),
+        )
+        .unwrap();
+    collection.drop_index("email").unwrap();
+}
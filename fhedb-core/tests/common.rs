@@ -1,6 +1,7 @@
 #![allow(dead_code)]
+use bson::doc;
 use fhedb_core::prelude::*;
-use std::collections::HashMap;
+use std::{collections::HashMap, path::Path};
 
 pub fn make_simple_schema() -> Schema {
     let mut fields = HashMap::new();
@@ -88,3 +89,25 @@ pub fn make_schema_with_defaults() -> Schema {
 
     Schema { fields }
 }
+
+pub fn add_users(collection: &mut Collection, count: i64) {
+    add_named_users(collection, count, |i| format!("User {}", i));
+}
+
+pub fn add_named_users(collection: &mut Collection, count: i64, name: impl Fn(i64) -> String) {
+    for i in 0..count {
+        collection
+            .add_document(doc! { "id": i, "name": name(i), "age": 20i64 + i })
+            .unwrap();
+    }
+}
+
+pub fn bio(i: i64) -> String {
+    format!("User {} likes long repetitive bios. ", i).repeat(20)
+}
+
+pub fn small_segment_collection(base_path: &Path) -> Collection {
+    let mut collection = Collection::new("users", make_int_schema(), base_path).unwrap();
+    collection.set_max_segment_size(256);
+    collection
+}
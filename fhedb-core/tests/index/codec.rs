@@ -0,0 +1,192 @@
+use bson::Bson;
+use fhedb_core::{
+    prelude::{
+        BsonComparable, DocId, decode_doc_id, decode_tuple, decode_value, encode_comparable,
+        encode_doc_id, encode_tuple, encode_value,
+    },
+    schema::QueryOperator,
+};
+use proptest::prelude::*;
+
+fn scalar() -> impl Strategy<Value = Bson> {
+    prop_oneof![
+        Just(Bson::Null),
+        any::<bool>().prop_map(Bson::Boolean),
+        any::<i32>().prop_map(Bson::Int32),
+        any::<i64>().prop_map(Bson::Int64),
+        any::<f64>().prop_map(Bson::Double),
+        any::<String>().prop_map(Bson::String),
+    ]
+}
+
+fn number() -> impl Strategy<Value = Bson> {
+    prop_oneof![
+        any::<i64>().prop_map(Bson::Int64),
+        (-1_000i64..1_000).prop_map(Bson::Int64),
+        (-1_000i64..1_000).prop_map(|value| Bson::Double(value as f64)),
+        (-1e3f64..1e3).prop_map(Bson::Double),
+        (prop::num::f64::NORMAL
+            | prop::num::f64::SUBNORMAL
+            | prop::num::f64::ZERO
+            | prop::num::f64::INFINITE)
+            .prop_map(Bson::Double),
+    ]
+}
+
+fn doc_id() -> impl Strategy<Value = DocId> {
+    prop_oneof![
+        any::<u64>().prop_map(DocId::from_u64),
+        any::<String>().prop_map(DocId::from),
+    ]
+}
+
+fn same_value(a: &Bson, b: &Bson) -> bool {
+    match (a, b) {
+        (Bson::Double(x), Bson::Double(y)) if x.is_nan() => y.is_nan(),
+        _ => a == b,
+    }
+}
+
+proptest! {
+    #[test]
+    fn values_round_trip(value in scalar()) {
+        let bytes = encode_value(&value).unwrap();
+        let (decoded, length) = decode_value(&bytes).unwrap();
+        prop_assert!(same_value(&decoded, &value), "{decoded:?} != {value:?}");
+        prop_assert_eq!(length, bytes.len());
+    }
+
+    #[test]
+    fn numbers_order_like_comparisons(a in number(), b in number()) {
+        let (left, right) = (encode_value(&a).unwrap(), encode_value(&b).unwrap());
+        if a.compare_to(&b, &QueryOperator::LessThan).unwrap() {
+            prop_assert!(left < right);
+        }
+        if left < right {
+            prop_assert!(a.compare_to(&b, &QueryOperator::LessThanOrEqual).unwrap());
+        }
+        let (left, right) = (encode_comparable(&a).unwrap(), encode_comparable(&b).unwrap());
+        prop_assert_eq!(a.compare_to(&b, &QueryOperator::LessThan).unwrap(), left < right);
+        prop_assert_eq!(a.compare_to(&b, &QueryOperator::GreaterThan).unwrap(), left > right);
+        let same = encode_comparable(&a) == encode_comparable(&b);
+        let exact = match (&a, &b) {
+            (Bson::Int64(x), Bson::Int64(y)) => x == y,
+            (Bson::Double(x), Bson::Double(y)) => x == y,
+            (Bson::Int64(x), Bson::Double(y)) | (Bson::Double(y), Bson::Int64(x)) => {
+                *y == *x as f64 && (*y as i128) == *x as i128
+            }
+            _ => unreachable!(),
+        };
+        prop_assert_eq!(same, exact);
+    }
+
+    #[test]
+    fn strings_order_like_comparisons(a in any::<String>(), b in any::<String>()) {
+        let left = encode_value(&Bson::String(a.clone())).unwrap();
+        let right = encode_value(&Bson::String(b.clone())).unwrap();
+        prop_assert_eq!(left.cmp(&right), a.cmp(&b));
+    }
+
+    #[test]
+    fn doc_ids_round_trip_in_order(a in doc_id(), b in doc_id()) {
+        let (left, right) = (encode_doc_id(&a), encode_doc_id(&b));
+        prop_assert_eq!(decode_doc_id(&left).unwrap(), (a.clone(), left.len()));
+        let expected = match (&a, &b) {
+            (DocId::U64(x), DocId::U64(y)) => x.cmp(y),
+            (DocId::String(x), DocId::String(y)) => x.cmp(y),
+            (DocId::U64(_), DocId::String(_)) => std::cmp::Ordering::Less,
+            (DocId::String(_), DocId::U64(_)) => std::cmp::Ordering::Greater,
+        };
+        prop_assert_eq!(left.cmp(&right), expected);
+    }
+
+    #[test]
+    fn tuples_round_trip_in_order(
+        a in prop::collection::vec(scalar(), 0..4),
+        b in prop::collection::vec(scalar(), 0..4),
+    ) {
+        let (left, right) = (encode_tuple(&a).unwrap(), encode_tuple(&b).unwrap());
+        let decoded = decode_tuple(&left).unwrap();
+        prop_assert_eq!(decoded.len(), a.len());
+        for (decoded, value) in decoded.iter().zip(&a) {
+            prop_assert!(same_value(decoded, value));
+        }
+
+        let expected = a
+            .iter()
+            .zip(&b)
+            .map(|(x, y)| encode_value(x).unwrap().cmp(&encode_value(y).unwrap()))
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or_else(|| a.len().cmp(&b.len()));
+        prop_assert_eq!(left.cmp(&right), expected);
+    }
+}
+
+#[test]
+fn integers_sort_before_equal_doubles() {
+    let int32 = encode_value(&Bson::Int32(1)).unwrap();
+    let int64 = encode_value(&Bson::Int64(1)).unwrap();
+    let double = encode_value(&Bson::Double(1.0)).unwrap();
+    let next = encode_value(&Bson::Double(1.0 + f64::EPSILON)).unwrap();
+    assert!(int32 < int64 && int64 < double && double < next);
+    assert_eq!(
+        encode_comparable(&Bson::Int64(1)),
+        encode_comparable(&Bson::Double(1.0))
+    );
+}
+
+#[test]
+fn negative_zero_equals_zero() {
+    let negative = encode_value(&Bson::Double(-0.0)).unwrap();
+    assert_eq!(negative, encode_value(&Bson::Double(0.0)).unwrap());
+    let (decoded, _) = decode_value(&negative).unwrap();
+    assert_eq!(decoded, Bson::Double(0.0));
+}
+
+#[test]
+fn numbers_order_across_classes() {
+    let values = [
+        Bson::Double(f64::NEG_INFINITY),
+        Bson::Int64(i64::MIN),
+        Bson::Double(-1.5),
+        Bson::Int64(-1),
+        Bson::Double(-f64::MIN_POSITIVE / 2.0),
+        Bson::Int64(0),
+        Bson::Double(f64::MIN_POSITIVE / 2.0),
+        Bson::Int32(1),
+        Bson::Double(2.5),
+        Bson::Int32(i32::MAX),
+        Bson::Int64(i64::MAX),
+        Bson::Double(f64::MAX),
+        Bson::Double(f64::INFINITY),
+        Bson::Double(f64::NAN),
+    ];
+    let encoded: Vec<_> = values.iter().map(|v| encode_value(v).unwrap()).collect();
+    assert!(encoded.windows(2).all(|pair| pair[0] < pair[1]));
+}
+
+#[test]
+fn types_order_by_tag() {
+    let encoded: Vec<_> = [
+        Bson::Null,
+        Bson::Boolean(false),
+        Bson::Boolean(true),
+        Bson::Double(f64::NEG_INFINITY),
+        Bson::String(String::new()),
+    ]
+    .iter()
+    .map(|v| encode_value(v).unwrap())
+    .collect();
+    assert!(encoded.windows(2).all(|pair| pair[0] < pair[1]));
+    assert!(encode_value(&Bson::Array(vec![])).is_none());
+}
+
+#[test]
+fn invalid_encodings_are_rejected() {
+    assert!(decode_value(&[]).is_err());
+    assert!(decode_value(&[0xFF]).is_err());
+    assert!(decode_value(&[0x02, 0x07]).is_err());
+    assert!(decode_value(&[0x04, b'a']).is_err());
+    assert!(decode_tuple(&[0x01, 0x04]).is_err());
+    assert!(decode_doc_id(&[0x01, 0x00]).is_err());
+}
@@ -1,3 +1,6 @@
+pub mod codec;
 pub mod node;
 pub mod pager;
+pub mod primary;
+pub mod secondary;
 pub mod tree;
@@ -3,9 +3,14 @@ use std::{
     io::{Read, Seek, SeekFrom, Write},
 };
 
-use fhedb_core::prelude::{PAGE_SIZE, Pager};
+use fhedb_core::prelude::{
+    ENCRYPTION_OVERHEAD, EncryptionKey, FORMAT_VERSION, FileKind, PAGE_CHECKSUM_SIZE, PAGE_SIZE,
+    Pager,
+};
 use tempfile::tempdir;
 
+const STRIDE: usize = PAGE_SIZE + PAGE_CHECKSUM_SIZE;
+
 #[test]
 fn create_file_and_metadata_page() {
     let dir = tempdir().unwrap();
@@ -15,7 +20,7 @@ fn create_file_and_metadata_page() {
     assert_eq!(pager.page_count(), 1);
     assert_eq!(pager.root_page_num(), 0);
     assert_eq!(pager.free_page_num(), 0);
-    assert_eq!(path.metadata().unwrap().len(), PAGE_SIZE as u64);
+    assert_eq!(path.metadata().unwrap().len(), STRIDE as u64);
 }
 
 #[test]
@@ -82,6 +87,47 @@ fn persist_metadata_across_reopens() {
     assert_eq!(pager.page_count(), 3);
 }
 
+#[test]
+fn persist_checkpoint_across_reopens() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("test.idx");
+
+    {
+        let mut pager = Pager::new(&path).unwrap();
+        assert_eq!(pager.checkpoint(), [0; 16]);
+        pager.set_checkpoint([7; 16]).unwrap();
+        pager.set_root(1).unwrap();
+    }
+
+    let pager = Pager::new(&path).unwrap();
+    assert_eq!(pager.checkpoint(), [7; 16]);
+    assert_eq!(pager.root_page_num(), 1);
+}
+
+#[test]
+fn staged_checkpoint_persisted_on_commit() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("test.idx");
+
+    {
+        let mut pager = Pager::new(&path).unwrap();
+        pager.set_checkpoint([1; 16]).unwrap();
+        pager.stage_checkpoint([2; 16]);
+        pager.set_root(1).unwrap();
+        assert_eq!(pager.checkpoint(), [2; 16]);
+    }
+
+    let mut pager = Pager::new(&path).unwrap();
+    assert_eq!(pager.checkpoint(), [1; 16]);
+    assert_eq!(pager.root_page_num(), 1);
+    pager.stage_checkpoint([2; 16]);
+    pager.commit_checkpoint().unwrap();
+    drop(pager);
+
+    let pager = Pager::new(&path).unwrap();
+    assert_eq!(pager.checkpoint(), [2; 16]);
+}
+
 #[test]
 fn write_page_stores_data_on_disk() {
     let dir = tempdir().unwrap();
@@ -94,11 +140,33 @@ fn write_page_stores_data_on_disk() {
     pager.write_page(page_num, &page_data).unwrap();
 
     let mut file = File::open(&path).unwrap();
-    file.seek(SeekFrom::Start(page_num as u64 * PAGE_SIZE as u64))
+    file.seek(SeekFrom::Start(page_num as u64 * STRIDE as u64))
         .unwrap();
-    let mut buf = [0u8; PAGE_SIZE];
+    let mut buf = [0u8; STRIDE];
     file.read_exact(&mut buf).unwrap();
-    assert_eq!(buf, page_data);
+    assert_eq!(buf[..PAGE_SIZE], page_data);
+    assert_eq!(buf[PAGE_SIZE..], crc32c::crc32c(&page_data).to_le_bytes());
+}
+
+#[test]
+fn read_page_rejects_torn_pages() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("test.idx");
+
+    let mut pager = Pager::new(&path).unwrap();
+    let page_num = pager.allocate_page().unwrap();
+    pager.write_page(page_num, &[0xABu8; PAGE_SIZE]).unwrap();
+
+    {
+        let mut file = File::options().write(true).open(&path).unwrap();
+        file.seek(SeekFrom::Start(page_num as u64 * STRIDE as u64 + 100))
+            .unwrap();
+        file.write_all(&[0u8; 8]).unwrap();
+    }
+
+    let result = pager.read_page(page_num);
+    assert_eq!(result.unwrap_err().kind(), std::io::ErrorKind::InvalidData);
+    assert!(pager.read_page(0).is_ok());
 }
 
 #[test]
@@ -124,9 +192,11 @@ fn read_page_loads_data_from_disk() {
     let page_data = [0xCDu8; PAGE_SIZE];
     {
         let mut file = File::options().write(true).open(&path).unwrap();
-        file.seek(SeekFrom::Start(page_num as u64 * PAGE_SIZE as u64))
+        file.seek(SeekFrom::Start(page_num as u64 * STRIDE as u64))
             .unwrap();
         file.write_all(&page_data).unwrap();
+        file.write_all(&crc32c::crc32c(&page_data).to_le_bytes())
+            .unwrap();
     }
 
     let result = pager.read_page(page_num).unwrap();
@@ -161,7 +231,7 @@ fn allocate_page_appends_sequentially() {
     assert_eq!(p2, 2);
     assert_eq!(p3, 3);
     assert_eq!(pager.page_count(), 4);
-    assert_eq!(path.metadata().unwrap().len(), 4 * PAGE_SIZE as u64);
+    assert_eq!(path.metadata().unwrap().len(), 4 * STRIDE as u64);
 }
 
 #[test]
@@ -215,3 +285,147 @@ fn free_page_rejects_invalid_pages() {
     assert!(result.is_err());
     assert_eq!(result.unwrap_err().kind(), std::io::ErrorKind::InvalidInput);
 }
+
+#[test]
+fn metadata_page_starts_with_format_header() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("test.idx");
+    Pager::new(&path).unwrap();
+
+    let contents = std::fs::read(&path).unwrap();
+    assert_eq!(&contents[0..4], FileKind::Index.magic());
+    assert_eq!(
+        u16::from_le_bytes([contents[4], contents[5]]),
+        FORMAT_VERSION
+    );
+}
+
+#[test]
+fn upgrade_version_zero_metadata_page() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("test.idx");
+
+    let mut page = [0u8; PAGE_SIZE * 2];
+    page[0..4].copy_from_slice(&1u32.to_le_bytes());
+    page[8..24].copy_from_slice(&[7; 16]);
+    std::fs::write(&path, page).unwrap();
+
+    let pager = Pager::new(&path).unwrap();
+    assert_eq!(pager.root_page_num(), 1);
+    assert_eq!(pager.free_page_num(), 0);
+    assert_eq!(pager.checkpoint(), [7; 16]);
+    assert_eq!(pager.page_count(), 2);
+    drop(pager);
+
+    let contents = std::fs::read(&path).unwrap();
+    assert_eq!(&contents[0..4], FileKind::Index.magic());
+    let mut pager = Pager::new(&path).unwrap();
+    assert_eq!(pager.root_page_num(), 1);
+    assert_eq!(pager.checkpoint(), [7; 16]);
+
+    pager.write_page(1, &[0xCDu8; PAGE_SIZE]).unwrap();
+    assert_eq!(pager.read_page(1).unwrap(), [0xCDu8; PAGE_SIZE]);
+    assert_eq!(path.metadata().unwrap().len(), 2 * PAGE_SIZE as u64);
+}
+
+#[test]
+fn reject_newer_format_version() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("test.idx");
+    Pager::new(&path).unwrap();
+
+    let mut file = File::options().write(true).open(&path).unwrap();
+    file.seek(SeekFrom::Start(4)).unwrap();
+    file.write_all(&(FORMAT_VERSION + 1).to_le_bytes()).unwrap();
+    drop(file);
+
+    let result = Pager::new(&path);
+    assert_eq!(result.unwrap_err().kind(), std::io::ErrorKind::Unsupported);
+}
+
+#[test]
+fn encrypted_pages_round_trip() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("test.idx");
+    let (key, _) = EncryptionKey::generate();
+
+    {
+        let mut pager = Pager::open(&path, Some(key.clone())).unwrap();
+        let page_num = pager.allocate_page().unwrap();
+        let mut page = pager.new_page();
+        page[0..11].copy_from_slice(b"secret data");
+        pager.write_page(page_num, &page).unwrap();
+        pager.set_checkpoint([3; 16]).unwrap();
+    }
+
+    let contents = std::fs::read(&path).unwrap();
+    assert_eq!(contents.len(), 2 * (PAGE_SIZE + ENCRYPTION_OVERHEAD));
+    assert_eq!(&contents[0..4], FileKind::Index.magic());
+    assert!(!contents.windows(11).any(|window| window == b"secret data"));
+
+    let mut pager = Pager::open(&path, Some(key)).unwrap();
+    assert!(pager.is_encrypted());
+    assert_eq!(pager.checkpoint(), [3; 16]);
+    assert_eq!(&pager.read_page(1).unwrap()[0..11], b"secret data");
+}
+
+#[test]
+fn encrypted_pages_require_the_key() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("test.idx");
+    let (key, _) = EncryptionKey::generate();
+    Pager::open(&path, Some(key)).unwrap();
+
+    let missing = Pager::new(&path);
+    assert_eq!(
+        missing.unwrap_err().kind(),
+        std::io::ErrorKind::PermissionDenied
+    );
+
+    let (wrong_key, _) = EncryptionKey::generate();
+    let wrong = Pager::open(&path, Some(wrong_key));
+    assert_eq!(wrong.unwrap_err().kind(), std::io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn encrypted_pages_are_bound_to_their_file_and_place() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("test.idx");
+    let (key, _) = EncryptionKey::generate();
+    {
+        let mut pager = Pager::open(&path, Some(key.clone())).unwrap();
+        for _ in 0..2 {
+            let page_num = pager.allocate_page().unwrap();
+            pager.write_page(page_num, &pager.new_page()).unwrap();
+        }
+    }
+
+    let copy_path = dir.path().join("copy.idx");
+    std::fs::copy(&path, &copy_path).unwrap();
+    let copied = Pager::open(&copy_path, Some(key.clone()));
+    assert_eq!(copied.unwrap_err().kind(), std::io::ErrorKind::InvalidData);
+
+    let stride = PAGE_SIZE + ENCRYPTION_OVERHEAD;
+    let mut contents = std::fs::read(&path).unwrap();
+    contents.copy_within(stride..2 * stride, 2 * stride);
+    std::fs::write(&path, contents).unwrap();
+    let mut pager = Pager::open(&path, Some(key)).unwrap();
+    assert!(pager.read_page(1).is_ok());
+    assert_eq!(
+        pager.read_page(2).unwrap_err().kind(),
+        std::io::ErrorKind::InvalidData
+    );
+}
+
+#[test]
+fn plaintext_file_stays_plaintext_with_key() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("test.idx");
+    Pager::new(&path).unwrap().set_root(2).unwrap();
+
+    let (key, _) = EncryptionKey::generate();
+    let pager = Pager::open(&path, Some(key)).unwrap();
+
+    assert!(!pager.is_encrypted());
+    assert_eq!(pager.root_page_num(), 2);
+}
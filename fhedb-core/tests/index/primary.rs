@@ -0,0 +1,131 @@
+use fhedb_core::prelude::{DocId, LogPosition, PrimaryIndex};
+use tempfile::tempdir;
+
+#[test]
+fn missing_file_behaves_as_empty() {
+    let dir = tempdir().unwrap();
+    let index = PrimaryIndex::new(dir.path().join("index.bin"));
+
+    assert!(!index.exists());
+    assert_eq!(index.get(&DocId::from_u64(1)).unwrap(), None);
+    assert!(index.is_empty().unwrap());
+    assert_eq!(index.len().unwrap(), 0);
+    assert_eq!(index.checkpoint().unwrap(), LogPosition::default());
+    assert!(index.entries().unwrap().is_empty());
+
+    index.remove(&DocId::from_u64(1)).unwrap();
+    assert!(!index.exists());
+}
+
+#[test]
+fn insert_creates_file_and_parent_dirs() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("nested").join("index.bin");
+    let index = PrimaryIndex::new(&path);
+
+    index
+        .insert(&DocId::from_u64(1), LogPosition::default())
+        .unwrap();
+
+    assert!(index.exists());
+    assert!(path.exists());
+    assert_eq!(
+        index.get(&DocId::from_u64(1)).unwrap(),
+        Some(LogPosition::default())
+    );
+}
+
+#[test]
+fn insert_overwrites_existing_offset() {
+    let dir = tempdir().unwrap();
+    let index = PrimaryIndex::new(dir.path().join("index.bin"));
+    let id = DocId::from_string("alpha".to_string());
+
+    index.insert(&id, LogPosition::new(0, 10)).unwrap();
+    index.insert(&id, LogPosition::new(3, 250)).unwrap();
+
+    assert_eq!(index.get(&id).unwrap(), Some(LogPosition::new(3, 250)));
+    assert_eq!(index.len().unwrap(), 1);
+}
+
+#[test]
+fn remove_entry() {
+    let dir = tempdir().unwrap();
+    let index = PrimaryIndex::new(dir.path().join("index.bin"));
+
+    index
+        .insert(&DocId::from_u64(1), LogPosition::new(0, 0))
+        .unwrap();
+    index
+        .insert(&DocId::from_u64(2), LogPosition::new(0, 100))
+        .unwrap();
+    index.remove(&DocId::from_u64(1)).unwrap();
+
+    assert!(!index.contains(&DocId::from_u64(1)).unwrap());
+    assert!(index.contains(&DocId::from_u64(2)).unwrap());
+    assert_eq!(index.len().unwrap(), 1);
+}
+
+#[test]
+fn entries_are_ordered_by_key() {
+    let dir = tempdir().unwrap();
+    let index = PrimaryIndex::new(dir.path().join("index.bin"));
+
+    for id in [300u64, 2, 1_000_000, 17] {
+        index
+            .insert(&DocId::from_u64(id), LogPosition::new(0, id as usize * 10))
+            .unwrap();
+    }
+
+    let entries = index.entries().unwrap();
+    let ids: Vec<_> = entries.iter().map(|(id, _)| id.clone()).collect();
+    assert_eq!(
+        ids,
+        vec![
+            DocId::from_u64(2),
+            DocId::from_u64(17),
+            DocId::from_u64(300),
+            DocId::from_u64(1_000_000)
+        ]
+    );
+    assert_eq!(entries[0].1, LogPosition::new(0, 20));
+}
+
+#[test]
+fn persists_across_reopens() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("index.bin");
+
+    {
+        let index = PrimaryIndex::new(&path);
+        for i in 0..500u64 {
+            let position = LogPosition::new(i / 100, (i % 100) as usize * 64);
+            index.insert(&DocId::from_u64(i), position).unwrap();
+        }
+        index.set_checkpoint(LogPosition::new(4, 6_400)).unwrap();
+    }
+
+    let index = PrimaryIndex::new(&path);
+    assert_eq!(index.len().unwrap(), 500);
+    assert_eq!(
+        index.get(&DocId::from_u64(499)).unwrap(),
+        Some(LogPosition::new(4, 99 * 64))
+    );
+    assert_eq!(index.checkpoint().unwrap(), LogPosition::new(4, 6_400));
+}
+
+#[test]
+fn clear_removes_file() {
+    let dir = tempdir().unwrap();
+    let index = PrimaryIndex::new(dir.path().join("index.bin"));
+
+    index
+        .insert(&DocId::from_u64(1), LogPosition::default())
+        .unwrap();
+    index.set_checkpoint(LogPosition::new(0, 10)).unwrap();
+    index.clear().unwrap();
+
+    assert!(!index.exists());
+    assert!(index.is_empty().unwrap());
+    assert_eq!(index.checkpoint().unwrap(), LogPosition::default());
+}
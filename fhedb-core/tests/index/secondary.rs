@@ -0,0 +1,430 @@
+use bson::Bson;
+use fhedb_core::prelude::{DocId, EncryptionKey, SecondaryIndex};
+use std::ops::Bound;
+use tempfile::tempdir;
+
+#[test]
+fn missing_file_behaves_as_empty() {
+    let dir = tempdir().unwrap();
+    let index = SecondaryIndex::new("age", dir.path().join("index.age.bin"));
+
+    assert_eq!(index.field(), "age");
+    assert!(!index.exists());
+    assert!(index.is_empty().unwrap());
+    assert!(index.lookup(&Bson::Int64(1)).unwrap().is_empty());
+
+    index.remove(&Bson::Int64(1), &DocId::from_u64(1)).unwrap();
+    assert!(!index.exists());
+}
+
+#[test]
+fn lookup_returns_ids_holding_value() {
+    let dir = tempdir().unwrap();
+    let index = SecondaryIndex::new("name", dir.path().join("index.name.bin"));
+
+    index
+        .insert(&Bson::String("bob".into()), &DocId::from_u64(2))
+        .unwrap();
+    index
+        .insert(&Bson::String("bob".into()), &DocId::from("b-1".to_string()))
+        .unwrap();
+    index
+        .insert(&Bson::String("bo".into()), &DocId::from_u64(1))
+        .unwrap();
+    index
+        .insert(&Bson::String("bob\0".into()), &DocId::from_u64(3))
+        .unwrap();
+    index
+        .insert(&Bson::String("bob".into()), &DocId::from_u64(2))
+        .unwrap();
+
+    assert_eq!(index.len().unwrap(), 4);
+    assert_eq!(
+        index.lookup(&Bson::String("bob".into())).unwrap(),
+        vec![DocId::from_u64(2), DocId::from("b-1".to_string())]
+    );
+    assert_eq!(
+        index.lookup(&Bson::String("bo".into())).unwrap(),
+        vec![DocId::from_u64(1)]
+    );
+
+    index
+        .remove(&Bson::String("bob".into()), &DocId::from_u64(2))
+        .unwrap();
+    assert_eq!(
+        index.lookup(&Bson::String("bob".into())).unwrap(),
+        vec![DocId::from("b-1".to_string())]
+    );
+}
+
+#[test]
+fn values_of_different_types_are_distinct() {
+    let dir = tempdir().unwrap();
+    let index = SecondaryIndex::new("value", dir.path().join("index.value.bin"));
+
+    index.insert(&Bson::Int32(1), &DocId::from_u64(1)).unwrap();
+    index.insert(&Bson::Int64(-1), &DocId::from_u64(2)).unwrap();
+    index
+        .insert(&Bson::Double(1.0), &DocId::from_u64(3))
+        .unwrap();
+    index
+        .insert(&Bson::Boolean(true), &DocId::from_u64(4))
+        .unwrap();
+    index.insert(&Bson::Null, &DocId::from_u64(5)).unwrap();
+    index
+        .insert(&Bson::Array(vec![]), &DocId::from_u64(6))
+        .unwrap();
+
+    assert_eq!(index.len().unwrap(), 5);
+    assert_eq!(
+        index.lookup(&Bson::Int32(1)).unwrap(),
+        vec![DocId::from_u64(1)]
+    );
+    assert!(index.lookup(&Bson::Int64(1)).unwrap().is_empty());
+    assert_eq!(
+        index.lookup(&Bson::Double(1.0)).unwrap(),
+        vec![DocId::from_u64(3)]
+    );
+    assert_eq!(index.lookup(&Bson::Null).unwrap(), vec![DocId::from_u64(5)]);
+    assert!(index.lookup(&Bson::Array(vec![])).is_err());
+}
+
+#[test]
+fn range_mixes_integers_and_floats() {
+    let dir = tempdir().unwrap();
+    let index = SecondaryIndex::new("value", dir.path().join("index.value.bin"));
+    for (id, value) in [
+        (1, Bson::Double(-0.5)),
+        (2, Bson::Int64(0)),
+        (3, Bson::Double(0.5)),
+        (4, Bson::Int64(1)),
+        (5, Bson::Double(1.0)),
+        (6, Bson::Double(1.5)),
+        (7, Bson::Int64(2)),
+    ] {
+        index.insert(&value, &DocId::from_u64(id)).unwrap();
+    }
+
+    assert_eq!(
+        index
+            .range(
+                Bound::Excluded(&Bson::Int64(0)),
+                Bound::Included(&Bson::Double(1.0))
+            )
+            .unwrap(),
+        ids(&[3, 4, 5])
+    );
+    assert_eq!(
+        index
+            .range(Bound::Excluded(&Bson::Double(1.0)), Bound::Unbounded)
+            .unwrap(),
+        ids(&[6, 7])
+    );
+    assert_eq!(
+        index
+            .range(Bound::Unbounded, Bound::Excluded(&Bson::Int64(1)))
+            .unwrap(),
+        ids(&[1, 2, 3])
+    );
+}
+
+#[test]
+fn encrypted_index_reopens_with_key() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("index.age.bin");
+    let (key, _) = EncryptionKey::generate();
+    let mut index = SecondaryIndex::new("age", &path);
+    index.set_encryption_key(Some(key.clone()));
+    index.insert(&Bson::Int64(7), &DocId::from_u64(1)).unwrap();
+    assert!(index.is_encrypted().unwrap());
+
+    let mut reopened = SecondaryIndex::new("age", &path);
+    reopened.set_encryption_key(Some(key));
+    assert_eq!(
+        reopened.lookup(&Bson::Int64(7)).unwrap(),
+        vec![DocId::from_u64(1)]
+    );
+
+    reopened.clear().unwrap();
+    assert!(!path.exists());
+}
+
+fn ids(values: &[u64]) -> Vec<DocId> {
+    values.iter().copied().map(DocId::from_u64).collect()
+}
+
+#[test]
+fn range_honors_bounds() {
+    let dir = tempdir().unwrap();
+    let index = SecondaryIndex::new("age", dir.path().join("index.age.bin"));
+    for (id, age) in [
+        (1, -40i64),
+        (2, -1),
+        (3, 0),
+        (4, 18),
+        (5, 18),
+        (6, 64),
+        (7, 65),
+    ] {
+        index
+            .insert(&Bson::Int64(age), &DocId::from_u64(id))
+            .unwrap();
+    }
+    index.insert(&Bson::Null, &DocId::from_u64(8)).unwrap();
+
+    let eighteen = Bson::Int64(18);
+    let sixty_five = Bson::Int64(65);
+    assert_eq!(
+        index
+            .range(Bound::Included(&eighteen), Bound::Excluded(&sixty_five))
+            .unwrap(),
+        ids(&[4, 5, 6])
+    );
+    assert_eq!(
+        index
+            .range(Bound::Excluded(&eighteen), Bound::Included(&sixty_five))
+            .unwrap(),
+        ids(&[6, 7])
+    );
+    assert_eq!(
+        index
+            .range(Bound::Unbounded, Bound::Excluded(&eighteen))
+            .unwrap(),
+        ids(&[1, 2, 3])
+    );
+    assert_eq!(
+        index
+            .range(Bound::Excluded(&Bson::Int64(-1)), Bound::Unbounded)
+            .unwrap(),
+        ids(&[3, 4, 5, 6, 7])
+    );
+    assert!(
+        index
+            .range(Bound::Excluded(&eighteen), Bound::Excluded(&eighteen))
+            .unwrap()
+            .is_empty()
+    );
+    assert!(
+        index
+            .range(Bound::Included(&sixty_five), Bound::Included(&eighteen))
+            .unwrap()
+            .is_empty()
+    );
+    assert_eq!(
+        index
+            .range(Bound::Unbounded, Bound::Unbounded)
+            .unwrap()
+            .len(),
+        8
+    );
+}
+
+#[test]
+fn range_orders_floats() {
+    let dir = tempdir().unwrap();
+    let index = SecondaryIndex::new("score", dir.path().join("index.score.bin"));
+    for (id, score) in [
+        (1, -2.5f64),
+        (2, -0.0),
+        (3, 0.5),
+        (4, f64::INFINITY),
+        (5, -1e300),
+    ] {
+        index
+            .insert(&Bson::Double(score), &DocId::from_u64(id))
+            .unwrap();
+    }
+
+    assert_eq!(
+        index
+            .range(Bound::Included(&Bson::Double(0.0)), Bound::Unbounded)
+            .unwrap(),
+        ids(&[2, 3, 4])
+    );
+    assert_eq!(
+        index
+            .range(Bound::Unbounded, Bound::Excluded(&Bson::Double(0.0)))
+            .unwrap(),
+        ids(&[5, 1])
+    );
+    assert_eq!(index.lookup(&Bson::Double(0.0)).unwrap(), ids(&[2]));
+}
+
+#[test]
+fn range_orders_strings() {
+    let dir = tempdir().unwrap();
+    let index = SecondaryIndex::new("name", dir.path().join("index.name.bin"));
+    for (id, name) in [(1, "b"), (2, "a\0"), (3, "a"), (4, "ab"), (5, "")] {
+        index
+            .insert(&Bson::String(name.into()), &DocId::from_u64(id))
+            .unwrap();
+    }
+
+    assert_eq!(
+        index
+            .range(
+                Bound::Excluded(&Bson::String("a".into())),
+                Bound::Excluded(&Bson::String("b".into()))
+            )
+            .unwrap(),
+        ids(&[2, 4])
+    );
+    assert_eq!(
+        index
+            .range(Bound::Unbounded, Bound::Included(&Bson::String("a".into())))
+            .unwrap(),
+        ids(&[5, 3])
+    );
+    assert!(
+        index
+            .range(
+                Bound::Included(&Bson::String("a".into())),
+                Bound::Included(&Bson::Int64(1))
+            )
+            .is_err()
+    );
+}
+
+#[test]
+fn range_bounds_cover_equal_numbers_of_both_types() {
+    let dir = tempdir().unwrap();
+    let index = SecondaryIndex::new("value", dir.path().join("index.value.bin"));
+    index.insert(&Bson::Int64(1), &DocId::from_u64(1)).unwrap();
+    index
+        .insert(&Bson::Double(1.0), &DocId::from_u64(2))
+        .unwrap();
+
+    let one = Bson::Int64(1);
+    let one_float = Bson::Double(1.0);
+    assert_eq!(
+        index
+            .range(Bound::Included(&one_float), Bound::Included(&one))
+            .unwrap(),
+        ids(&[1, 2])
+    );
+    assert!(
+        index
+            .range(Bound::Excluded(&one), Bound::Unbounded)
+            .unwrap()
+            .is_empty()
+    );
+    assert!(
+        index
+            .range(Bound::Unbounded, Bound::Excluded(&one_float))
+            .unwrap()
+            .is_empty()
+    );
+}
+
+#[test]
+fn compound_lookup_matches_prefixes() {
+    let dir = tempdir().unwrap();
+    let index = SecondaryIndex::compound(
+        vec!["city".to_string(), "age".to_string()],
+        dir.path().join("index.city,age.bin"),
+    );
+    assert_eq!(index.field(), "city");
+    assert_eq!(index.fields(), ["city".to_string(), "age".to_string()]);
+
+    let paris = Bson::String("Paris".into());
+    let rome = Bson::String("Rome".into());
+    for (id, city, age) in [
+        (1, &paris, 30i64),
+        (2, &rome, 30),
+        (3, &paris, 25),
+        (4, &paris, 30),
+        (5, &rome, 41),
+    ] {
+        index
+            .insert_values(&[city.clone(), Bson::Int64(age)], &DocId::from_u64(id))
+            .unwrap();
+    }
+
+    assert_eq!(
+        index
+            .lookup_values(&[paris.clone(), Bson::Int64(30)])
+            .unwrap(),
+        ids(&[1, 4])
+    );
+    assert_eq!(
+        index.lookup_values(std::slice::from_ref(&paris)).unwrap(),
+        ids(&[3, 1, 4])
+    );
+    assert_eq!(index.lookup(&rome).unwrap(), ids(&[2, 5]));
+    assert!(
+        index
+            .lookup_values(&[paris.clone(), Bson::Int64(30), Bson::Int64(1)])
+            .is_err()
+    );
+
+    index
+        .remove_values(&[paris.clone(), Bson::Int64(30)], &DocId::from_u64(1))
+        .unwrap();
+    assert_eq!(
+        index
+            .lookup_values(&[paris.clone(), Bson::Int64(30)])
+            .unwrap(),
+        ids(&[4])
+    );
+}
+
+#[test]
+fn compound_range_scans_after_prefix() {
+    let dir = tempdir().unwrap();
+    let index = SecondaryIndex::compound(
+        vec!["city".to_string(), "age".to_string()],
+        dir.path().join("index.city,age.bin"),
+    );
+    let paris = Bson::String("Paris".into());
+    let rome = Bson::String("Rome".into());
+    for (id, city, age) in [
+        (1, &paris, Bson::Int64(18)),
+        (2, &paris, Bson::Double(30.5)),
+        (3, &paris, Bson::Int64(65)),
+        (4, &rome, Bson::Int64(30)),
+        (5, &paris, Bson::Null),
+    ] {
+        index
+            .insert_values(&[city.clone(), age], &DocId::from_u64(id))
+            .unwrap();
+    }
+
+    let eighteen = Bson::Int64(18);
+    let sixty_five = Bson::Int64(65);
+    assert_eq!(
+        index
+            .range_after(
+                std::slice::from_ref(&paris),
+                Bound::Excluded(&eighteen),
+                Bound::Included(&sixty_five)
+            )
+            .unwrap(),
+        ids(&[2, 3])
+    );
+    assert_eq!(
+        index
+            .range_after(
+                std::slice::from_ref(&paris),
+                Bound::Unbounded,
+                Bound::Excluded(&sixty_five)
+            )
+            .unwrap(),
+        ids(&[1, 2])
+    );
+    assert_eq!(
+        index
+            .range_after(
+                std::slice::from_ref(&rome),
+                Bound::Included(&eighteen),
+                Bound::Unbounded
+            )
+            .unwrap(),
+        ids(&[4])
+    );
+    assert_eq!(
+        index
+            .range(Bound::Included(&paris), Bound::Excluded(&rome))
+            .unwrap(),
+        ids(&[5, 1, 2, 3])
+    );
+}
@@ -312,6 +312,50 @@ fn mixed_float_lte_int_false() {
     assert_eq!(a.compare_to(&b, &QueryOperator::LessThanOrEqual), Ok(false));
 }
 
+#[test]
+fn mixed_large_int_lt_next_float_true() {
+    let a = Bson::Int64(i64::MAX);
+    let b = Bson::Double(9_223_372_036_854_775_808.0);
+    assert_eq!(a.compare_to(&b, &QueryOperator::LessThan), Ok(true));
+    assert_eq!(b.compare_to(&a, &QueryOperator::GreaterThan), Ok(true));
+}
+
+#[test]
+fn mixed_large_int_gt_rounded_float_true() {
+    let a = Bson::Int64(9_007_199_254_740_993);
+    let b = Bson::Double(9_007_199_254_740_992.0);
+    assert_eq!(a.compare_to(&b, &QueryOperator::GreaterThan), Ok(true));
+    assert_eq!(a.compare_to(&b, &QueryOperator::LessThanOrEqual), Ok(false));
+}
+
+#[test]
+fn mixed_large_int_equals_float_at_boundary() {
+    let a = Bson::Int64(9_007_199_254_740_992);
+    let b = Bson::Double(9_007_199_254_740_992.0);
+    assert_eq!(a.compare_to(&b, &QueryOperator::LessThanOrEqual), Ok(true));
+    assert_eq!(
+        a.compare_to(&b, &QueryOperator::GreaterThanOrEqual),
+        Ok(true)
+    );
+    assert_eq!(a.compare_to(&b, &QueryOperator::LessThan), Ok(false));
+}
+
+#[test]
+fn mixed_int32_lt_int64_true() {
+    let a = Bson::Int32(7);
+    let b = Bson::Int64(i64::MAX);
+    assert_eq!(a.compare_to(&b, &QueryOperator::LessThan), Ok(true));
+    assert_eq!(b.compare_to(&a, &QueryOperator::GreaterThan), Ok(true));
+}
+
+#[test]
+fn mixed_nan_vs_int_false() {
+    let a = Bson::Double(f64::NAN);
+    let b = Bson::Int64(5);
+    assert_eq!(a.compare_to(&b, &QueryOperator::GreaterThan), Ok(false));
+    assert_eq!(b.compare_to(&a, &QueryOperator::LessThanOrEqual), Ok(false));
+}
+
 #[test]
 fn string_gt_true() {
     let a = Bson::String("banana".to_string());
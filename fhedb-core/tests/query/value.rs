@@ -164,6 +164,20 @@ fn parse_array_with_escaped_quotes() {
     );
 }
 
+#[test]
+fn parse_array_with_commas_and_other_quotes_in_strings() {
+    let result = "[\"say \\\"hi, 'you'\\\"\", 'it\\'s, \"fine\"']"
+        .parse_as_bson(&FieldType::Array(Box::new(FieldType::String)));
+    assert!(result.is_ok());
+    assert_eq!(
+        result.unwrap(),
+        Bson::Array(vec![
+            Bson::String("say \"hi, 'you'\"".to_string()),
+            Bson::String("it's, \"fine\"".to_string())
+        ])
+    );
+}
+
 #[test]
 fn parse_array_with_brackets_in_strings() {
     let result =
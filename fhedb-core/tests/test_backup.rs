@@ -0,0 +1,240 @@
+use bson::doc;
+use fhedb_core::prelude::*;
+use std::{fs, io};
+use tempfile::tempdir;
+
+mod common;
+use common::make_int_schema;
+
+fn make_database(base: &std::path::Path) -> Database {
+    let mut db = Database::new("test_db", base);
+    db.set_max_segment_size(1024);
+    db.create_collection("users", make_int_schema()).unwrap();
+    db.create_collection("products", make_int_schema()).unwrap();
+    for i in 0..20i64 {
+        db.get_collection_mut("users")
+            .unwrap()
+            .add_document(doc! { "id": i, "name": format!("User {}", i), "age": 20i64 + i })
+            .unwrap();
+    }
+    db.get_collection_mut("products")
+        .unwrap()
+        .add_document(doc! { "id": 0i64, "name": "Widget", "age": 1i64 })
+        .unwrap();
+    db
+}
+
+#[test]
+fn backup_and_restore_under_new_name() {
+    let temp_dir = tempdir().unwrap();
+    let mut db = make_database(temp_dir.path());
+    db.get_collection_mut("users")
+        .unwrap()
+        .update_document(DocId::from_u64(3), doc! { "age": 99i64 })
+        .unwrap();
+    db.get_collection_mut("users")
+        .unwrap()
+        .remove_document(DocId::from_u64(4))
+        .unwrap();
+    let archive = temp_dir.path().join("backup.fhdb");
+
+    let report = db.backup(&archive).unwrap();
+
+    assert_eq!(report.collections, 2);
+    assert_eq!(report.bytes, fs::metadata(&archive).unwrap().len());
+    let restored = Database::restore(&archive, "copy_db", temp_dir.path()).unwrap();
+    assert_eq!(restored.name, "copy_db");
+    assert_eq!(restored.collection_count(), 2);
+    let users = restored.get_collection("users").unwrap();
+    assert_eq!(users.document_count().unwrap(), 19);
+    assert_eq!(
+        users
+            .get_document(DocId::from_u64(3))
+            .unwrap()
+            .data
+            .get_i64("age")
+            .unwrap(),
+        99
+    );
+    assert!(users.get_document(DocId::from_u64(4)).is_none());
+    assert_eq!(
+        restored
+            .get_collection("products")
+            .unwrap()
+            .document_count()
+            .unwrap(),
+        1
+    );
+    assert_eq!(
+        db.get_collection("users")
+            .unwrap()
+            .document_count()
+            .unwrap(),
+        19
+    );
+}
+
+#[test]
+fn snapshot_excludes_later_writes() {
+    let temp_dir = tempdir().unwrap();
+    let mut db = make_database(temp_dir.path());
+
+    let snapshot = db.snapshot().unwrap();
+    let users = db.get_collection_mut("users").unwrap();
+    for i in 20..40i64 {
+        users
+            .add_document(doc! { "id": i, "name": "Late", "age": i })
+            .unwrap();
+    }
+    users.compact().unwrap();
+    let archive = temp_dir.path().join("backup.fhdb");
+    snapshot.write_archive(&archive).unwrap();
+
+    let restored = Database::restore(&archive, "copy_db", temp_dir.path()).unwrap();
+    let users = restored.get_collection("users").unwrap();
+    assert_eq!(users.document_count().unwrap(), 20);
+    assert!(users.get_document(DocId::from_u64(25)).is_none());
+    assert!(users.get_document(DocId::from_u64(19)).is_some());
+}
+
+#[test]
+fn restore_replaces_existing_database() {
+    let temp_dir = tempdir().unwrap();
+    let db = make_database(temp_dir.path());
+    let archive = temp_dir.path().join("backup.fhdb");
+    db.backup(&archive).unwrap();
+
+    let mut changed = Database::from_files("test_db", temp_dir.path()).unwrap();
+    changed.drop_collection("products").unwrap();
+    changed
+        .create_collection("orders", make_int_schema())
+        .unwrap();
+    drop(changed);
+
+    let restored = Database::restore(&archive, "test_db", temp_dir.path()).unwrap();
+
+    let mut names = restored.collection_names();
+    names.sort();
+    assert_eq!(names, vec!["products".to_string(), "users".to_string()]);
+    assert!(!temp_dir.path().join(".test_db.restore").exists());
+    assert!(!temp_dir.path().join(".test_db.replaced").exists());
+}
+
+#[test]
+fn restore_encrypted_database() {
+    let temp_dir = tempdir().unwrap();
+    let (key, _) = EncryptionKey::generate();
+    let mut db = Database::new("test_db", temp_dir.path());
+    db.set_encryption_key(Some(key.clone()));
+    db.create_collection("users", make_int_schema()).unwrap();
+    db.get_collection_mut("users")
+        .unwrap()
+        .add_document(doc! { "id": 1i64, "name": "Secret", "age": 30i64 })
+        .unwrap();
+    let archive = temp_dir.path().join("backup.fhdb");
+    db.backup(&archive).unwrap();
+
+    let contents = fs::read(&archive).unwrap();
+    assert!(!contents.windows(6).any(|window| window == b"Secret"));
+    let restored =
+        Database::restore_with_key(&archive, "copy_db", temp_dir.path(), Some(key)).unwrap();
+    let users = restored.get_collection("users").unwrap();
+    assert_eq!(
+        users
+            .get_document(DocId::from_u64(1))
+            .unwrap()
+            .data
+            .get_str("name")
+            .unwrap(),
+        "Secret"
+    );
+}
+
+#[test]
+fn failed_load_keeps_existing_database() {
+    let temp_dir = tempdir().unwrap();
+    make_database(temp_dir.path());
+    let (key, _) = EncryptionKey::generate();
+    let mut encrypted = Database::new("secret_db", temp_dir.path());
+    encrypted.set_encryption_key(Some(key));
+    encrypted
+        .create_collection("users", make_int_schema())
+        .unwrap();
+    encrypted
+        .get_collection_mut("users")
+        .unwrap()
+        .add_document(doc! { "id": 1i64, "name": "Secret", "age": 30i64 })
+        .unwrap();
+    let archive = temp_dir.path().join("backup.fhdb");
+    encrypted.backup(&archive).unwrap();
+
+    let (wrong_key, _) = EncryptionKey::generate();
+    assert!(
+        Database::restore_with_key(&archive, "test_db", temp_dir.path(), Some(wrong_key)).is_err()
+    );
+    assert!(Database::restore(&archive, "test_db", temp_dir.path()).is_err());
+
+    assert!(!temp_dir.path().join(".test_db.restore").exists());
+    assert!(!temp_dir.path().join(".test_db.replaced").exists());
+    let existing = Database::from_files("test_db", temp_dir.path()).unwrap();
+    assert_eq!(
+        existing
+            .get_collection("users")
+            .unwrap()
+            .document_count()
+            .unwrap(),
+        20
+    );
+}
+
+#[test]
+fn corrupted_archive_rejected() {
+    let temp_dir = tempdir().unwrap();
+    let db = make_database(temp_dir.path());
+    let archive = temp_dir.path().join("backup.fhdb");
+    db.backup(&archive).unwrap();
+    let mut contents = fs::read(&archive).unwrap();
+    let middle = contents.len() / 2;
+    contents[middle] ^= 0xFF;
+    fs::write(&archive, &contents).unwrap();
+
+    let result = Database::restore(&archive, "test_db", temp_dir.path());
+
+    assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidData);
+    assert!(!temp_dir.path().join(".test_db.restore").exists());
+    let existing = Database::from_files("test_db", temp_dir.path()).unwrap();
+    assert_eq!(
+        existing
+            .get_collection("users")
+            .unwrap()
+            .document_count()
+            .unwrap(),
+        20
+    );
+
+    fs::write(&archive, &contents[..middle]).unwrap();
+    assert!(Database::restore(&archive, "copy_db", temp_dir.path()).is_err());
+    assert!(!temp_dir.path().join("copy_db").exists());
+}
+
+#[test]
+fn archive_path_traversal_rejected() {
+    let temp_dir = tempdir().unwrap();
+    let archive = temp_dir.path().join("backup.fhdb");
+    let path = b"../escaped";
+    let mut contents = b"FHDB".to_vec();
+    contents.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+    contents.extend_from_slice(&0u16.to_le_bytes());
+    contents.extend_from_slice(&(path.len() as u32).to_le_bytes());
+    contents.extend_from_slice(path);
+    contents.extend_from_slice(&1u64.to_le_bytes());
+    contents.push(b'x');
+    contents.extend_from_slice(&crc32c::crc32c(b"x").to_le_bytes());
+    contents.extend_from_slice(&0u32.to_le_bytes());
+    fs::write(&archive, contents).unwrap();
+
+    let result = Database::restore(&archive, "test_db", temp_dir.path());
+
+    assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidData);
+    assert!(!temp_dir.path().join("escaped").exists());
+}
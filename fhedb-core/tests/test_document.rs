@@ -17,6 +17,30 @@ fn docid_new_is_unique() {
     assert_ne!(id1, id2);
 }
 
+#[test]
+fn docid_key_bytes_round_trip() {
+    let int_id = DocId::from_u64(42);
+    let string_id = DocId::from_string("user-1".to_string());
+
+    assert_eq!(DocId::from_key_bytes(&int_id.to_key_bytes()), Some(int_id));
+    assert_eq!(
+        DocId::from_key_bytes(&string_id.to_key_bytes()),
+        Some(string_id)
+    );
+    assert_eq!(DocId::from_key_bytes(&[]), None);
+    assert_eq!(DocId::from_key_bytes(&[0xff, 1, 2]), None);
+}
+
+#[test]
+fn docid_key_bytes_preserve_integer_order() {
+    let ids = [0u64, 1, 255, 256, 65_536, u64::MAX];
+    for pair in ids.windows(2) {
+        let lower = DocId::from_u64(pair[0]).to_key_bytes();
+        let higher = DocId::from_u64(pair[1]).to_key_bytes();
+        assert!(lower < higher);
+    }
+}
+
 #[test]
 fn document_new_and_fields() {
     let id = DocId::new();
@@ -0,0 +1,546 @@
+use bson::{Bson, doc};
+use fhedb_core::{
+    fhe::{ENCRYPTED_INT_PREFIX, ENCRYPTED_STRING_PREFIX, MAX_MODULUS_BITS, paillier::SecretKey},
+    prelude::*,
+    schema::{FieldCondition, QueryOperator},
+};
+use num_bigint::BigUint;
+use std::{collections::HashMap, sync::OnceLock};
+use tempfile::tempdir;
+
+/// Generating keys is slow, so the tests share a small one.
+fn test_key() -> &'static FheKey {
+    static KEY: OnceLock<FheKey> = OnceLock::new();
+    KEY.get_or_init(|| FheKey::generate_with_bits(512).unwrap())
+}
+
+fn make_accounts_schema() -> Schema {
+    let mut fields = HashMap::new();
+    fields.insert("id".to_string(), FieldDefinition::new(FieldType::IdInt));
+    fields.insert("owner".to_string(), FieldDefinition::new(FieldType::String));
+    fields.insert(
+        "balance".to_string(),
+        FieldDefinition::new(FieldType::EncryptedInt),
+    );
+    Schema { fields }
+}
+
+fn condition(field_name: &str, operator: QueryOperator, value: String) -> FieldCondition {
+    FieldCondition {
+        field_name: field_name.to_string(),
+        operator,
+        value,
+    }
+}
+
+fn add_accounts(collection: &mut Collection, balances: &[(&str, i64)]) {
+    for (i, (owner, balance)) in balances.iter().enumerate() {
+        collection
+            .add_document(doc! {
+                "id": i as i64,
+                "owner": *owner,
+                "balance": test_key().encrypt_int(*balance).to_string(),
+            })
+            .unwrap();
+    }
+}
+
+#[test]
+fn paillier_addition_is_homomorphic() {
+    let secret = SecretKey::generate(256);
+    let public = secret.public_key();
+    let a = public.encrypt(&BigUint::from(1234u32));
+    let b = public.encrypt(&BigUint::from(4321u32));
+
+    assert_ne!(a, public.encrypt(&BigUint::from(1234u32)));
+    assert_eq!(
+        secret.decrypt(&public.add(&a, &b)).unwrap(),
+        BigUint::from(5555u32)
+    );
+    assert_eq!(secret.decrypt_i64(&public.encrypt_i64(-42)).unwrap(), -42);
+}
+
+#[test]
+fn non_coprime_ciphertexts_rejected() {
+    let key = test_key();
+    let public = key.public_key();
+    let n = public.modulus();
+
+    for ciphertext in [n.clone(), n * 2u32, n * n - n] {
+        assert!(!public.is_ciphertext(&ciphertext));
+
+        let token = format!(
+            "{}{}:{}:",
+            ENCRYPTED_INT_PREFIX,
+            n.to_str_radix(16),
+            ciphertext.to_str_radix(16)
+        );
+        assert!(token.parse::<EncryptedInt>().is_err());
+        assert!(validate_bson_type(&Bson::String(token), &FieldType::EncryptedInt).is_err());
+    }
+
+    let secret = SecretKey::generate(256);
+    let n = secret.public_key().modulus().clone();
+    assert!(secret.decrypt(&n).is_err());
+    assert!(secret.decrypt_i64(&(&n * 3u32)).is_err());
+}
+
+#[test]
+fn encrypted_int_round_trip() {
+    let key = test_key();
+    for value in [0, 1, -1, 42, i64::MAX, i64::MIN] {
+        let encrypted = key.encrypt_int(value);
+        let token = encrypted.to_string();
+        assert!(token.starts_with(ENCRYPTED_INT_PREFIX));
+
+        let parsed: EncryptedInt = token.parse().unwrap();
+        assert_eq!(parsed, encrypted);
+        assert_eq!(key.decrypt_int(&parsed).unwrap(), value);
+    }
+}
+
+#[test]
+fn encrypted_int_sum_and_equality() {
+    let key = test_key();
+    let a = key.encrypt_int(100);
+    let b = key.encrypt_int(-30);
+
+    let sum = a.add(&b).unwrap();
+
+    assert_eq!(key.decrypt_int(&sum).unwrap(), 70);
+    assert!(!sum.has_tag());
+    assert!(a.matches(&key.encrypt_int(100)).unwrap());
+    assert!(!a.matches(&b).unwrap());
+    assert!(a.matches(&sum).is_err());
+    assert!(
+        key.decrypt_int(&key.encrypt_int(i64::MAX).add(&key.encrypt_int(1)).unwrap())
+            .is_err()
+    );
+}
+
+#[test]
+fn values_under_other_keys_rejected() {
+    let other = FheKey::generate_with_bits(256).unwrap();
+    let a = test_key().encrypt_int(1);
+    let b = other.encrypt_int(1);
+
+    assert!(a.add(&b).is_err());
+    assert!(!a.matches(&b).unwrap());
+    assert!(!b.matches(&a).unwrap());
+    assert!(other.decrypt_int(&a).is_err());
+    assert!(FheKey::generate_with_bits(128).is_err());
+    assert!(FheKey::generate_with_bits(MAX_MODULUS_BITS + 1).is_err());
+}
+
+#[test]
+fn oversized_moduli_rejected() {
+    let modulus = (BigUint::from(1u32) << MAX_MODULUS_BITS) + 1u32;
+    for (modulus, ciphertext) in [
+        (modulus.to_str_radix(16), "2".to_string()),
+        ("f".repeat(MAX_MODULUS_BITS as usize), "2".to_string()),
+        ("f".repeat(64), "f".repeat(MAX_MODULUS_BITS as usize)),
+    ] {
+        let token = format!("{}{}:{}:", ENCRYPTED_INT_PREFIX, modulus, ciphertext);
+        assert!(token.parse::<EncryptedInt>().is_err());
+    }
+}
+
+#[test]
+fn exported_key_decrypts_stored_values() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection =
+        Collection::new("accounts", make_accounts_schema(), temp_dir.path()).unwrap();
+    add_accounts(&mut collection, &[("alice", 10), ("bob", -25)]);
+    let name = test_key().encrypt_string("alice").unwrap().to_string();
+    let exported = test_key().to_bytes();
+    drop(collection);
+
+    let key = FheKey::from_bytes(&exported).unwrap();
+    assert_eq!(key.to_bytes(), exported);
+    assert_eq!(key.public_key(), test_key().public_key());
+
+    let collection = Collection::from_files(temp_dir.path(), "accounts").unwrap();
+    let (sum, _) = collection.sum("balance", &[]).unwrap();
+    let sum: EncryptedInt = sum.as_str().unwrap().parse().unwrap();
+    assert_eq!(key.decrypt_int(&sum).unwrap(), -15);
+
+    let matching = collection
+        .filter(&[condition(
+            "balance",
+            QueryOperator::Equal,
+            format!("\"{}\"", key.encrypt_int(-25)),
+        )])
+        .unwrap();
+    assert_eq!(matching.len(), 1);
+    assert_eq!(key.decrypt_string(&name.parse().unwrap()).unwrap(), "alice");
+    assert_eq!(key.blind_index("alice"), test_key().blind_index("alice"));
+}
+
+#[test]
+fn exported_key_validation() {
+    let exported = test_key().to_bytes();
+    assert!(FheKey::from_bytes(&exported).is_ok());
+
+    let mut other_magic = exported.clone();
+    other_magic[0] = b'X';
+    let mut other_version = exported.clone();
+    other_version[4] = 2;
+    let mut trailing = exported.clone();
+    trailing.push(0);
+    for invalid in [
+        &[][..],
+        &other_magic,
+        &other_version,
+        &trailing,
+        &exported[..exported.len() - 1],
+        &exported[..6 + 64],
+    ] {
+        assert!(FheKey::from_bytes(invalid).is_err());
+    }
+    assert!(
+        FheKey::from_bytes(&other_version)
+            .unwrap_err()
+            .contains("version 2")
+    );
+}
+
+#[test]
+fn exported_key_with_composite_factors_rejected() {
+    let exported = test_key().to_bytes();
+    let secret = SecretKey::generate(512);
+    let (p, q) = secret.primes();
+    let composite = p * q;
+    assert!(SecretKey::from_primes(composite.clone(), p.clone()).is_err());
+
+    let mut bytes = exported[..6 + 64].to_vec();
+    for factor in [composite, q.clone()] {
+        let factor = factor.to_bytes_be();
+        bytes.extend_from_slice(&(factor.len() as u16).to_le_bytes());
+        bytes.extend_from_slice(&factor);
+    }
+    assert!(FheKey::from_bytes(&bytes).is_err());
+}
+
+#[test]
+fn encrypted_int_validation() {
+    let token = test_key().encrypt_int(7).to_string();
+
+    assert!(validate_bson_type(&Bson::String(token.clone()), &FieldType::EncryptedInt).is_ok());
+    assert!(validate_bson_type(&Bson::Int64(7), &FieldType::EncryptedInt).is_err());
+    for invalid in [
+        "7",
+        "fhe:int:",
+        "fhe:int:zz:1:",
+        "fhe:int:f:1:abc",
+        "fhe:int:f:0:",
+        "fhe:int:f:3:",
+        &token[..token.len() - 2],
+    ] {
+        assert!(
+            validate_bson_type(&Bson::String(invalid.to_string()), &FieldType::EncryptedInt)
+                .is_err(),
+            "{} should be rejected",
+            invalid
+        );
+    }
+
+    let schema = make_accounts_schema();
+    let document = schema_to_document(&schema);
+    assert_eq!(document.get_str("balance").unwrap(), "encrypted_int");
+    assert_eq!(schema_from_document(document), schema);
+}
+
+#[test]
+fn encrypted_equality_filter() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection =
+        Collection::new("accounts", make_accounts_schema(), temp_dir.path()).unwrap();
+    add_accounts(
+        &mut collection,
+        &[("alice", 10), ("bob", 20), ("carol", 10)],
+    );
+    let ten = format!("\"{}\"", test_key().encrypt_int(10));
+
+    let equal = collection
+        .filter(&[condition("balance", QueryOperator::Equal, ten.clone())])
+        .unwrap();
+    let not_equal = collection
+        .filter(&[condition("balance", QueryOperator::NotEqual, ten.clone())])
+        .unwrap();
+
+    let mut owners: Vec<&str> = equal
+        .iter()
+        .map(|doc| doc.data.get_str("owner").unwrap())
+        .collect();
+    owners.sort();
+    assert_eq!(owners, vec!["alice", "carol"]);
+    assert_eq!(not_equal.len(), 1);
+    assert_eq!(not_equal[0].data.get_str("owner").unwrap(), "bob");
+
+    let other_ten = format!(
+        "\"{}\"",
+        FheKey::generate_with_bits(256).unwrap().encrypt_int(10)
+    );
+    assert!(
+        collection
+            .filter(&[condition("balance", QueryOperator::Equal, other_ten)])
+            .unwrap()
+            .is_empty()
+    );
+
+    for operator in [
+        QueryOperator::GreaterThan,
+        QueryOperator::LessThanOrEqual,
+        QueryOperator::Similar,
+    ] {
+        let result = collection.filter(&[condition("balance", operator, ten.clone())]);
+        assert!(
+            result
+                .unwrap_err()
+                .contains("not supported on encrypted field")
+        );
+    }
+    assert!(
+        collection
+            .filter(&[condition("balance", QueryOperator::Equal, "10".to_string())])
+            .is_err()
+    );
+}
+
+#[test]
+fn sum_encrypted_field() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection =
+        Collection::new("accounts", make_accounts_schema(), temp_dir.path()).unwrap();
+    add_accounts(
+        &mut collection,
+        &[("alice", 10), ("bob", -25), ("alice", 100)],
+    );
+
+    let (sum, count) = collection.sum("balance", &[]).unwrap();
+    let (alice_sum, alice_count) = collection
+        .sum(
+            "balance",
+            &[condition(
+                "owner",
+                QueryOperator::Equal,
+                "\"alice\"".to_string(),
+            )],
+        )
+        .unwrap();
+
+    assert_eq!(count, 3);
+    let sum: EncryptedInt = sum.as_str().unwrap().parse().unwrap();
+    assert_eq!(test_key().decrypt_int(&sum).unwrap(), 85);
+    assert_eq!(alice_count, 2);
+    let alice_sum: EncryptedInt = alice_sum.as_str().unwrap().parse().unwrap();
+    assert_eq!(test_key().decrypt_int(&alice_sum).unwrap(), 110);
+
+    let (empty, empty_count) = collection
+        .sum(
+            "balance",
+            &[condition(
+                "owner",
+                QueryOperator::Equal,
+                "\"dave\"".to_string(),
+            )],
+        )
+        .unwrap();
+    assert_eq!(empty, Bson::Null);
+    assert_eq!(empty_count, 0);
+    assert!(collection.sum("owner", &[]).is_err());
+    assert!(collection.sum("missing", &[]).is_err());
+}
+
+#[test]
+fn sum_plain_fields() {
+    let temp_dir = tempdir().unwrap();
+    let mut fields = HashMap::new();
+    fields.insert("id".to_string(), FieldDefinition::new(FieldType::IdInt));
+    fields.insert("count".to_string(), FieldDefinition::new(FieldType::Int));
+    fields.insert(
+        "price".to_string(),
+        FieldDefinition::new(FieldType::Nullable(Box::new(FieldType::Float))),
+    );
+    let mut collection = Collection::new("items", Schema { fields }, temp_dir.path()).unwrap();
+    collection
+        .add_document(doc! { "id": 1i64, "count": 3i64, "price": 1.5 })
+        .unwrap();
+    collection
+        .add_document(doc! { "id": 2i64, "count": 4i64, "price": Bson::Null })
+        .unwrap();
+
+    assert_eq!(collection.sum("count", &[]).unwrap(), (Bson::Int64(7), 2));
+    assert_eq!(
+        collection.sum("price", &[]).unwrap(),
+        (Bson::Double(1.5), 1)
+    );
+}
+
+fn make_users_schema() -> Schema {
+    let mut fields = HashMap::new();
+    fields.insert("id".to_string(), FieldDefinition::new(FieldType::IdInt));
+    fields.insert(
+        "email".to_string(),
+        FieldDefinition::new(FieldType::EncryptedString),
+    );
+    fields.insert(
+        "national_id".to_string(),
+        FieldDefinition::new(FieldType::Nullable(Box::new(FieldType::EncryptedString))),
+    );
+    Schema { fields }
+}
+
+fn encrypted_string(value: &str) -> String {
+    test_key().encrypt_string(value).unwrap().to_string()
+}
+
+#[test]
+fn encrypted_string_round_trip() {
+    let key = test_key();
+    let encrypted = key.encrypt_string("alice@example.com").unwrap();
+    let token = encrypted.to_string();
+
+    assert!(token.starts_with(ENCRYPTED_STRING_PREFIX));
+    assert!(!token.contains("alice"));
+    let parsed: EncryptedString = token.parse().unwrap();
+    assert_eq!(parsed, encrypted);
+    assert_eq!(key.decrypt_string(&parsed).unwrap(), "alice@example.com");
+
+    let again = key.encrypt_string("alice@example.com").unwrap();
+    assert_ne!(again.to_string(), token);
+    assert!(again.matches(&parsed));
+    assert!(!parsed.matches(&key.encrypt_string("bob@example.com").unwrap()));
+
+    let other = FheKey::generate_with_bits(256).unwrap();
+    assert!(other.decrypt_string(&parsed).is_err());
+    assert!(
+        !other
+            .encrypt_string("alice@example.com")
+            .unwrap()
+            .matches(&parsed)
+    );
+}
+
+#[test]
+fn encrypted_string_validation() {
+    let token = encrypted_string("secret");
+
+    assert!(validate_bson_type(&Bson::String(token.clone()), &FieldType::EncryptedString).is_ok());
+    assert!(
+        validate_bson_type(
+            &Bson::String("secret".to_string()),
+            &FieldType::EncryptedString
+        )
+        .is_err()
+    );
+    assert!(
+        validate_bson_type(
+            &Bson::String(test_key().encrypt_int(1).to_string()),
+            &FieldType::EncryptedString
+        )
+        .is_err()
+    );
+    assert!(
+        validate_bson_type(
+            &Bson::String(token[..token.len() - 2].to_string()),
+            &FieldType::EncryptedString
+        )
+        .is_err()
+    );
+
+    let mut tampered = token.into_bytes();
+    let position = ENCRYPTED_STRING_PREFIX.len() + 60;
+    tampered[position] = if tampered[position] == b'0' {
+        b'1'
+    } else {
+        b'0'
+    };
+    let tampered: EncryptedString = String::from_utf8(tampered).unwrap().parse().unwrap();
+    assert!(test_key().decrypt_string(&tampered).is_err());
+
+    let schema = make_users_schema();
+    assert_eq!(schema_from_document(schema_to_document(&schema)), schema);
+}
+
+#[test]
+fn blind_index_equality_filter() {
+    let temp_dir = tempdir().unwrap();
+    let mut collection = Collection::new("users", make_users_schema(), temp_dir.path()).unwrap();
+    for (i, (email, national_id)) in [
+        ("alice@example.com", Some("123")),
+        ("bob@example.com", None),
+        ("alice@example.com", Some("456")),
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        collection
+            .add_document(doc! {
+                "id": i as i64,
+                "email": encrypted_string(email),
+                "national_id": national_id.map_or(Bson::Null, |id| encrypted_string(id).into()),
+            })
+            .unwrap();
+    }
+    let alice = format!("\"{}\"", encrypted_string("alice@example.com"));
+
+    let equal = collection
+        .filter(&[condition("email", QueryOperator::Equal, alice.clone())])
+        .unwrap();
+    let not_equal = collection
+        .filter(&[condition("email", QueryOperator::NotEqual, alice.clone())])
+        .unwrap();
+    let by_national_id = collection
+        .filter(&[condition(
+            "national_id",
+            QueryOperator::Equal,
+            format!("\"{}\"", encrypted_string("456")),
+        )])
+        .unwrap();
+    let without_national_id = collection
+        .filter(&[condition(
+            "national_id",
+            QueryOperator::Equal,
+            "null".to_string(),
+        )])
+        .unwrap();
+
+    let mut ids: Vec<i64> = equal
+        .iter()
+        .map(|doc| doc.data.get_i64("id").unwrap())
+        .collect();
+    ids.sort();
+    assert_eq!(ids, vec![0, 2]);
+    assert_eq!(not_equal.len(), 1);
+    assert_eq!(not_equal[0].data.get_i64("id").unwrap(), 1);
+    assert_eq!(by_national_id.len(), 1);
+    assert_eq!(by_national_id[0].data.get_i64("id").unwrap(), 2);
+    assert_eq!(without_national_id.len(), 1);
+    assert_eq!(without_national_id[0].data.get_i64("id").unwrap(), 1);
+    let decrypted = test_key()
+        .decrypt_string(&equal[0].data.get_str("email").unwrap().parse().unwrap())
+        .unwrap();
+    assert_eq!(decrypted, "alice@example.com");
+
+    for (operator, symbol) in [
+        (QueryOperator::GreaterThan, "'>'"),
+        (QueryOperator::LessThan, "'<'"),
+        (QueryOperator::Similar, "'=='"),
+    ] {
+        let error = collection
+            .filter(&[condition("email", operator, alice.clone())])
+            .unwrap_err();
+        assert!(error.contains(symbol), "{}", error);
+        assert!(error.contains("encrypted field 'email'"), "{}", error);
+    }
+    assert!(
+        collection
+            .filter(&[condition(
+                "email",
+                QueryOperator::Equal,
+                "\"alice@example.com\"".to_string(),
+            )])
+            .is_err()
+    );
+}
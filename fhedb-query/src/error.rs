@@ -29,13 +29,20 @@ const QUERY_CONTEXTS: &[&str] = &[
     "modify collection",
     "list collections",
     "get collection schema",
+    "compact collection",
+    "create index",
+    "drop index",
     "create database",
     "drop database",
     "list databases",
+    "backup database",
+    "restore database",
     "insert document",
     "update document",
     "delete document",
     "get document",
+    "sum documents",
+    "get document history",
 ];
 
 /// Structural context labels that represent parts of a query, not query types.
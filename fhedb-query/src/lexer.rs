@@ -7,6 +7,8 @@ use chumsky::{extra, prelude::*};
 /// A token in the FHEDB query language.
 ///
 /// Keywords are case-insensitive during lexing but stored as distinct token variants.
+/// Keywords that only have a meaning within specific clauses, such as `INDEX` or `SUM`,
+/// are lexed as identifiers instead, so that they remain usable as names.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Token {
     /// The CREATE keyword.
@@ -73,6 +75,10 @@ pub enum Token {
     TypeIdInt,
     /// The ID_STRING field type keyword.
     TypeIdString,
+    /// The ENCRYPTED_INT field type keyword.
+    TypeEncryptedInt,
+    /// The ENCRYPTED_STRING field type keyword.
+    TypeEncryptedString,
     /// The NULLABLE constraint keyword.
     Nullable,
     /// The DEFAULT constraint keyword.
@@ -162,6 +168,8 @@ impl std::fmt::Display for Token {
             Token::TypeRef => write!(f, "REF"),
             Token::TypeIdInt => write!(f, "ID_INT"),
             Token::TypeIdString => write!(f, "ID_STRING"),
+            Token::TypeEncryptedInt => write!(f, "ENCRYPTED_INT"),
+            Token::TypeEncryptedString => write!(f, "ENCRYPTED_STRING"),
             Token::Nullable => write!(f, "NULLABLE"),
             Token::Default => write!(f, "DEFAULT"),
             Token::True => write!(f, "TRUE"),
@@ -243,6 +251,8 @@ pub fn lexer<'src>()
     let type_kw = choice((
         keyword_ci("id_string").to(Token::TypeIdString),
         keyword_ci("id_int").to(Token::TypeIdInt),
+        keyword_ci("encrypted_int").to(Token::TypeEncryptedInt),
+        keyword_ci("encrypted_string").to(Token::TypeEncryptedString),
         keyword_ci("boolean").to(Token::TypeBoolean),
         keyword_ci("string").to(Token::TypeString),
         keyword_ci("float").to(Token::TypeFloat),
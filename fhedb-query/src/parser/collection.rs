@@ -10,6 +10,7 @@ use crate::{
     lexer::{Span, Token},
     parser::common::{
         drop_if_exists_parser, field_modifier_parser, field_type_parser, identifier_parser,
+        keyword_parser,
     },
 };
 
@@ -38,17 +39,18 @@ where
         .labelled("field type")
         .as_context()
         .try_map(|(name, (field_type, modifier)), span| {
-            let (nullable, default) = modifier.unwrap_or((false, None));
-            let base_type = if nullable {
+            let modifier = modifier.unwrap_or_default();
+            let base_type = if modifier.nullable {
                 FieldType::Nullable(Box::new(field_type))
             } else {
                 field_type
             };
-            if let Some(ref default_value) = default {
+            if let Some(ref default_value) = modifier.default {
                 validate_bson_type(default_value, &base_type)
                     .map_err(|e| Rich::custom(span, format!("invalid default value: {}", e)))?;
             }
-            let field_def = FieldDefinition::with_optional_default(base_type, default);
+            let field_def = FieldDefinition::with_optional_default(base_type, modifier.default)
+                .with_unique(modifier.unique);
             Ok((name, field_def))
         })
 }
@@ -143,6 +145,66 @@ where
         .as_context()
 }
 
+/// Parses a COMPACT COLLECTION query.
+fn compact_collection_parser<'tokens, 'src: 'tokens, I>()
+-> impl Parser<'tokens, I, CollectionQuery, extra::Err<Rich<'tokens, Token, Span>>> + Clone
+where
+    I: ValueInput<'tokens, Token = Token, Span = Span>,
+{
+    keyword_parser("COMPACT")
+        .ignore_then(just(Token::Collection))
+        .ignore_then(identifier_parser("collection name"))
+        .map(|name| CollectionQuery::Compact { name })
+        .labelled("compact collection")
+        .as_context()
+}
+
+/// Parses the collection and fields of an index, as in `ON users (email)` or
+/// `ON users (last_name, first_name)`.
+fn index_target_parser<'tokens, 'src: 'tokens, I>()
+-> impl Parser<'tokens, I, (String, Vec<String>), extra::Err<Rich<'tokens, Token, Span>>> + Clone
+where
+    I: ValueInput<'tokens, Token = Token, Span = Span>,
+{
+    keyword_parser("ON")
+        .ignore_then(identifier_parser("collection name"))
+        .then(
+            identifier_parser("field name")
+                .separated_by(just(Token::Comma))
+                .at_least(1)
+                .collect::<Vec<_>>()
+                .delimited_by(just(Token::OpenParen), just(Token::CloseParen)),
+        )
+}
+
+/// Parses a CREATE INDEX query.
+fn create_index_parser<'tokens, 'src: 'tokens, I>()
+-> impl Parser<'tokens, I, CollectionQuery, extra::Err<Rich<'tokens, Token, Span>>> + Clone
+where
+    I: ValueInput<'tokens, Token = Token, Span = Span>,
+{
+    just(Token::Create)
+        .ignore_then(keyword_parser("INDEX"))
+        .ignore_then(index_target_parser())
+        .map(|(name, field_names)| CollectionQuery::CreateIndex { name, field_names })
+        .labelled("create index")
+        .as_context()
+}
+
+/// Parses a DROP INDEX query.
+fn drop_index_parser<'tokens, 'src: 'tokens, I>()
+-> impl Parser<'tokens, I, CollectionQuery, extra::Err<Rich<'tokens, Token, Span>>> + Clone
+where
+    I: ValueInput<'tokens, Token = Token, Span = Span>,
+{
+    just(Token::Drop)
+        .ignore_then(keyword_parser("INDEX"))
+        .ignore_then(index_target_parser())
+        .map(|(name, field_names)| CollectionQuery::DropIndex { name, field_names })
+        .labelled("drop index")
+        .as_context()
+}
+
 /// Parses a field modification in a MODIFY COLLECTION query.
 fn field_modification_parser<'tokens, 'src: 'tokens, I>()
 -> impl Parser<'tokens, I, (String, FieldModification), extra::Err<Rich<'tokens, Token, Span>>> + Clone
@@ -167,17 +229,18 @@ where
     let type_and_modifier = choice((id_type, regular_type)).labelled("field type");
 
     let set_modification = type_and_modifier.try_map(|(field_type, modifier), span| {
-        let (nullable, default) = modifier.unwrap_or((false, None));
-        let base_type = if nullable {
+        let modifier = modifier.unwrap_or_default();
+        let base_type = if modifier.nullable {
             FieldType::Nullable(Box::new(field_type))
         } else {
             field_type
         };
-        if let Some(ref default_value) = default {
+        if let Some(ref default_value) = modifier.default {
             validate_bson_type(default_value, &base_type)
                 .map_err(|e| Rich::custom(span, format!("invalid default value: {}", e)))?;
         }
-        let field_def = FieldDefinition::with_optional_default(base_type, default);
+        let field_def = FieldDefinition::with_optional_default(base_type, modifier.default)
+            .with_unique(modifier.unique);
         Ok(FieldModification::Set(field_def))
     });
 
@@ -250,6 +313,9 @@ where
         list_collections_parser(),
         get_schema_parser(),
         modify_collection_parser(),
+        compact_collection_parser(),
+        create_index_parser(),
+        drop_index_parser(),
     ))
     .labelled("collection query")
     .as_context()
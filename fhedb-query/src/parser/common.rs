@@ -43,6 +43,23 @@ where
     select! { Token::Ident(name) => name }.labelled(label)
 }
 
+/// Creates a parser that matches a contextual keyword case-insensitively.
+///
+/// Contextual keywords are lexed as identifiers, so that names spelled like them remain
+/// valid everywhere else.
+///
+/// ## Arguments
+///
+/// * `keyword` - The keyword to match, in upper case.
+pub(crate) fn keyword_parser<'tokens, 'src: 'tokens, I>(
+    keyword: &'static str,
+) -> impl Parser<'tokens, I, (), extra::Err<Rich<'tokens, Token, Span>>> + Clone
+where
+    I: ValueInput<'tokens, Token = Token, Span = Span>,
+{
+    select! { Token::Ident(name) if name.eq_ignore_ascii_case(keyword) => () }.labelled(keyword)
+}
+
 /// Creates a parser for the `DROP IF EXISTS` clause.
 pub(crate) fn drop_if_exists_parser<'tokens, 'src: 'tokens, I>()
 -> impl Parser<'tokens, I, Option<()>, extra::Err<Rich<'tokens, Token, Span>>> + Clone
@@ -86,27 +103,77 @@ where
     })
 }
 
-/// Creates a parser for field type modifiers (nullable, default value).
+/// The modifiers of a field type, as in `string(nullable, unique, default = "x")`.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct FieldModifiers {
+    /// Whether the field can be null.
+    pub nullable: bool,
+    /// Whether no two documents may hold the same non-null value in the field.
+    pub unique: bool,
+    /// The default value of the field, if any.
+    pub default: Option<Bson>,
+}
+
+/// A single field type modifier.
+#[derive(Debug, Clone)]
+enum FieldModifier {
+    /// The `nullable` modifier.
+    Nullable,
+    /// The `unique` modifier.
+    Unique,
+    /// The `default = value` modifier.
+    Default(Bson),
+}
+
+/// Creates a parser for field type modifiers (nullable, unique, default value), each of
+/// which may be given once, in any order.
 pub(crate) fn field_modifier_parser<'tokens, 'src: 'tokens, I>()
--> impl Parser<'tokens, I, (bool, Option<Bson>), extra::Err<Rich<'tokens, Token, Span>>> + Clone
+-> impl Parser<'tokens, I, FieldModifiers, extra::Err<Rich<'tokens, Token, Span>>> + Clone
 where
     I: ValueInput<'tokens, Token = Token, Span = Span>,
 {
-    let nullable = just(Token::Nullable).to(true).labelled("nullable");
+    let nullable = just(Token::Nullable)
+        .to(FieldModifier::Nullable)
+        .labelled("nullable");
+
+    let unique = keyword_parser("UNIQUE")
+        .to(FieldModifier::Unique)
+        .labelled("unique");
 
     let default = just(Token::Default)
         .ignore_then(just(Token::Equals))
         .ignore_then(bson_value_parser().labelled("default value"))
+        .map(FieldModifier::Default)
         .labelled("default");
 
-    just(Token::OpenParen)
-        .ignore_then(
-            nullable
-                .then(just(Token::Comma).ignore_then(default.clone()).or_not())
-                .map(|(_, default)| (true, default))
-                .or(default.map(|d| (false, Some(d)))),
-        )
-        .then_ignore(just(Token::CloseParen))
+    choice((nullable, unique, default))
+        .separated_by(just(Token::Comma))
+        .at_least(1)
+        .collect::<Vec<_>>()
+        .delimited_by(just(Token::OpenParen), just(Token::CloseParen))
+        .try_map(|modifiers, span| {
+            let mut result = FieldModifiers::default();
+            for modifier in modifiers {
+                let (name, duplicate) = match modifier {
+                    FieldModifier::Nullable => {
+                        ("nullable", std::mem::replace(&mut result.nullable, true))
+                    }
+                    FieldModifier::Unique => {
+                        ("unique", std::mem::replace(&mut result.unique, true))
+                    }
+                    FieldModifier::Default(value) => {
+                        ("default", result.default.replace(value).is_some())
+                    }
+                };
+                if duplicate {
+                    return Err(Rich::custom(
+                        span,
+                        format!("duplicate field constraint: {}", name),
+                    ));
+                }
+            }
+            Ok(result)
+        })
         .labelled("field constraint")
         .as_context()
 }
@@ -123,6 +190,8 @@ where
             Token::TypeFloat => FieldType::Float,
             Token::TypeBoolean => FieldType::Boolean,
             Token::TypeString => FieldType::String,
+            Token::TypeEncryptedInt => FieldType::EncryptedInt,
+            Token::TypeEncryptedString => FieldType::EncryptedString,
         }
         .labelled("field type");
 
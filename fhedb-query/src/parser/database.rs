@@ -10,7 +10,7 @@ use crate::{
     lexer::{Span, Token},
 };
 
-use super::common::{drop_if_exists_parser, identifier_parser, lex_input};
+use super::common::{drop_if_exists_parser, identifier_parser, keyword_parser, lex_input};
 
 /// Creates a parser for database-level queries.
 fn database_query_parser<'tokens, 'src: 'tokens, I>()
@@ -42,12 +42,39 @@ where
         .labelled("list databases")
         .as_context();
 
-    choice((create_db, drop_db, list_dbs))
+    let backup_db = keyword_parser("BACKUP")
+        .ignore_then(just(Token::Database))
+        .ignore_then(identifier_parser("database name"))
+        .then_ignore(keyword_parser("TO"))
+        .then(archive_path_parser())
+        .map(|(name, path)| DatabaseQuery::Backup { name, path })
+        .labelled("backup database")
+        .as_context();
+
+    let restore_db = keyword_parser("RESTORE")
+        .ignore_then(just(Token::Database))
+        .ignore_then(identifier_parser("database name"))
+        .then_ignore(just(Token::From))
+        .then(archive_path_parser())
+        .map(|(name, path)| DatabaseQuery::Restore { name, path })
+        .labelled("restore database")
+        .as_context();
+
+    choice((create_db, drop_db, list_dbs, backup_db, restore_db))
         .labelled("database query")
         .as_context()
         .then_ignore(end())
 }
 
+/// Creates a parser for the quoted path of a backup archive.
+fn archive_path_parser<'tokens, 'src: 'tokens, I>()
+-> impl Parser<'tokens, I, String, extra::Err<Rich<'tokens, Token, Span>>> + Clone
+where
+    I: ValueInput<'tokens, Token = Token, Span = Span>,
+{
+    select! { Token::StringLit(path) => path }.labelled("archive path")
+}
+
 /// Parses a database query string into a [`DatabaseQuery`] AST node.
 ///
 /// ## Arguments
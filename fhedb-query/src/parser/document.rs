@@ -7,9 +7,10 @@ use std::collections::HashMap;
 use chumsky::{extra, input::ValueInput, prelude::*};
 
 use crate::lexer::{Span, Token};
+use fhedb_core::collection::history::parse_timestamp;
 use fhedb_types::{DocumentQuery, FieldCondition, FieldSelector, ParsedDocContent, QueryOperator};
 
-use super::common::identifier_parser;
+use super::common::{identifier_parser, keyword_parser};
 
 /// Parses a value that can be used in assignments and conditions.
 fn value_parser<'tokens, 'src: 'tokens, I>()
@@ -263,8 +264,9 @@ where
         )))
         .ignore_then(just(Token::From))
         .ignore_then(identifier_parser("collection name"))
+        .then(as_of_parser().or_not())
         .then(document_body_parser())
-        .try_map(|(collection_name, body), span| {
+        .try_map(|((collection_name, as_of), body), span| {
             if !body.assignments.is_empty() {
                 return Err(Rich::custom(
                     span,
@@ -281,12 +283,109 @@ where
                 collection_name,
                 conditions: body.conditions,
                 selectors: body.selectors,
+                as_of,
             })
         })
         .labelled("get document")
         .as_context()
 }
 
+/// Parses an `AS OF "<timestamp>"` clause, validating the RFC 3339 timestamp.
+fn as_of_parser<'tokens, 'src: 'tokens, I>()
+-> impl Parser<'tokens, I, String, extra::Err<Rich<'tokens, Token, Span>>> + Clone
+where
+    I: ValueInput<'tokens, Token = Token, Span = Span>,
+{
+    keyword_parser("AS")
+        .ignore_then(keyword_parser("OF"))
+        .ignore_then(
+            select! { Token::StringLit(timestamp) => timestamp }
+                .try_map(|timestamp, span| {
+                    parse_timestamp(&timestamp)
+                        .map(|_| timestamp)
+                        .map_err(|e| Rich::custom(span, e))
+                })
+                .labelled("timestamp"),
+        )
+}
+
+/// Parses a GET SUM query, which sums a field over the matching documents.
+fn sum_document_parser<'tokens, 'src: 'tokens, I>()
+-> impl Parser<'tokens, I, DocumentQuery, extra::Err<Rich<'tokens, Token, Span>>> + Clone
+where
+    I: ValueInput<'tokens, Token = Token, Span = Span>,
+{
+    just(Token::Get)
+        .ignore_then(keyword_parser("SUM"))
+        .ignore_then(
+            identifier_parser("field name")
+                .delimited_by(just(Token::OpenParen), just(Token::CloseParen)),
+        )
+        .then_ignore(just(Token::From))
+        .then(identifier_parser("collection name"))
+        .then(document_body_parser())
+        .try_map(|((field_name, collection_name), body), span| {
+            if !body.assignments.is_empty() {
+                return Err(Rich::custom(
+                    span,
+                    "assignments are not allowed in SUM queries",
+                ));
+            }
+            if !body.selectors.is_empty() {
+                return Err(Rich::custom(
+                    span,
+                    "selectors are not allowed in SUM queries",
+                ));
+            }
+            Ok(DocumentQuery::Sum {
+                collection_name,
+                field_name,
+                conditions: body.conditions,
+            })
+        })
+        .labelled("sum documents")
+        .as_context()
+}
+
+/// Parses a GET HISTORY query, which lists the log entries of a document.
+fn history_document_parser<'tokens, 'src: 'tokens, I>()
+-> impl Parser<'tokens, I, DocumentQuery, extra::Err<Rich<'tokens, Token, Span>>> + Clone
+where
+    I: ValueInput<'tokens, Token = Token, Span = Span>,
+{
+    just(Token::Get)
+        .ignore_then(keyword_parser("HISTORY"))
+        .ignore_then(just(Token::From))
+        .ignore_then(identifier_parser("collection name"))
+        .then(document_body_parser())
+        .try_map(|(collection_name, body), span| {
+            if !body.assignments.is_empty() {
+                return Err(Rich::custom(
+                    span,
+                    "assignments are not allowed in HISTORY queries",
+                ));
+            }
+            if !body.selectors.is_empty() {
+                return Err(Rich::custom(
+                    span,
+                    "selectors are not allowed in HISTORY queries",
+                ));
+            }
+            if body.conditions.is_empty() {
+                return Err(Rich::custom(
+                    span,
+                    "HISTORY query must have a condition on the ID field",
+                ));
+            }
+            Ok(DocumentQuery::History {
+                collection_name,
+                conditions: body.conditions,
+            })
+        })
+        .labelled("get document history")
+        .as_context()
+}
+
 /// Parses an UPDATE document query.
 fn update_document_parser<'tokens, 'src: 'tokens, I>()
 -> impl Parser<'tokens, I, DocumentQuery, extra::Err<Rich<'tokens, Token, Span>>> + Clone
@@ -368,6 +467,8 @@ where
 {
     choice((
         insert_document_parser(),
+        sum_document_parser(),
+        history_document_parser(),
         get_document_parser(),
         update_document_parser(),
         delete_document_parser(),
@@ -0,0 +1,159 @@
+use fhedb_query::prelude::parse_contextual_query;
+use fhedb_types::{CollectionQuery, ContextualQuery};
+
+#[test]
+fn basic() {
+    let input = "COMPACT COLLECTION test_collection";
+    let result = parse_contextual_query(input);
+    assert!(result.is_ok());
+
+    let Ok(ContextualQuery::Collection(query)) = result else {
+        panic!("Expected Ok result");
+    };
+
+    assert!(matches!(query, CollectionQuery::Compact { .. }));
+
+    let CollectionQuery::Compact { name } = query else {
+        panic!("Expected Compact variant");
+    };
+
+    assert_eq!(name, "test_collection");
+}
+
+#[test]
+fn case_insensitive() {
+    let input = "CoMpAcT cOlLeCtIoN MyCollection";
+    let result = parse_contextual_query(input);
+    assert!(result.is_ok());
+
+    let Ok(ContextualQuery::Collection(query)) = result else {
+        panic!("Expected Ok result");
+    };
+
+    assert!(matches!(query, CollectionQuery::Compact { .. }));
+
+    let CollectionQuery::Compact { name } = query else {
+        panic!("Expected Compact variant");
+    };
+
+    assert_eq!(name, "MyCollection");
+}
+
+#[test]
+fn with_extra_whitespace() {
+    let input = "   COMPACT    COLLECTION    test_collection   ";
+    let result = parse_contextual_query(input);
+    assert!(result.is_ok());
+
+    let Ok(ContextualQuery::Collection(query)) = result else {
+        panic!("Expected Ok result");
+    };
+
+    assert!(matches!(query, CollectionQuery::Compact { .. }));
+
+    let CollectionQuery::Compact { name } = query else {
+        panic!("Expected Compact variant");
+    };
+
+    assert_eq!(name, "test_collection");
+}
+
+#[test]
+fn invalid_empty() {
+    let input = "";
+    let result = parse_contextual_query(input);
+    assert!(result.is_err());
+
+    let Err(errors) = result else {
+        panic!("Expected Err result");
+    };
+
+    assert!(!errors.is_empty());
+    for error in errors {
+        assert!(error.span.start == 0 && error.span.end == 0);
+        assert!(error.found.is_none());
+        assert!(error.message.to_lowercase().contains("unknown query"));
+    }
+}
+
+#[test]
+fn invalid_missing_name() {
+    let input = "COMPACT COLLECTION";
+    let result = parse_contextual_query(input);
+    assert!(result.is_err());
+
+    let Err(errors) = result else {
+        panic!("Expected Err result");
+    };
+
+    assert!(!errors.is_empty());
+    for error in errors {
+        assert!(error.context.contains(&"compact collection".to_string()));
+        assert!(error.context.contains(&"collection query".to_string()));
+        assert!(error.expected.contains(&"collection name".to_string()));
+        assert!(
+            error
+                .message
+                .to_lowercase()
+                .contains("invalid compact collection query")
+        );
+    }
+}
+
+#[test]
+fn invalid_extra_input() {
+    let input = "COMPACT COLLECTION test_collection EXTRA_STUFF";
+    let result = parse_contextual_query(input);
+    assert!(result.is_err());
+
+    let Err(errors) = result else {
+        panic!("Expected Err result");
+    };
+
+    assert!(!errors.is_empty());
+    for error in errors {
+        assert!(error.expected.contains(&"end of input".to_string()));
+        assert!(error.found == Some("EXTRA_STUFF".to_string()));
+        assert!(error.message.to_lowercase().contains("unexpected input"));
+    }
+}
+
+#[test]
+fn invalid_no_keyword() {
+    let input = "COMPACT test_collection";
+    let result = parse_contextual_query(input);
+    assert!(result.is_err());
+
+    let Err(errors) = result else {
+        panic!("Expected Err result");
+    };
+
+    assert!(!errors.is_empty());
+    for error in errors {
+        assert!(error.expected.contains(&"COLLECTION".to_string()));
+        assert!(error.context.contains(&"compact collection".to_string()));
+        assert!(
+            error
+                .message
+                .to_lowercase()
+                .contains("invalid compact collection query")
+        );
+    }
+}
+
+#[test]
+fn invalid_wrong_order() {
+    let input = "COLLECTION COMPACT test_collection";
+    let result = parse_contextual_query(input);
+    assert!(result.is_err());
+
+    let Err(errors) = result else {
+        panic!("Expected Err result");
+    };
+
+    assert!(!errors.is_empty());
+    for error in errors {
+        assert_eq!(error.span.start, 0);
+        assert!(error.message.to_lowercase().contains("unknown query"));
+    }
+}
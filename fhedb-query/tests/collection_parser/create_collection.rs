@@ -428,3 +428,56 @@ fn invalid_missing_braces() {
         assert!(error.expected.contains(&"DROP".to_string()));
     }
 }
+
+#[test]
+fn encrypted_fields() {
+    let input = "CREATE COLLECTION accounts {
+        id: id_int,
+        balance: encrypted_int,
+        limit: ENCRYPTED_INT(nullable),
+        email: encrypted_string
+    }";
+    let result = parse_contextual_query(input);
+    assert!(result.is_ok());
+    let Ok(ContextualQuery::Collection(CollectionQuery::Create { schema, .. })) = result else {
+        panic!("Expected Create variant");
+    };
+
+    assert_eq!(schema.fields["balance"].field_type, FieldType::EncryptedInt);
+    assert_eq!(
+        schema.fields["limit"].field_type,
+        FieldType::Nullable(Box::new(FieldType::EncryptedInt))
+    );
+    assert_eq!(
+        schema.fields["email"].field_type,
+        FieldType::EncryptedString
+    );
+}
+
+#[test]
+fn unique_fields() {
+    let input = "CREATE COLLECTION users {
+        id: id_int,
+        email: string(nullable, unique),
+        code: int(UNIQUE, default = 1),
+        name: string
+    }";
+    let result = parse_contextual_query(input);
+    assert!(result.is_ok());
+    let Ok(ContextualQuery::Collection(CollectionQuery::Create { schema, .. })) = result else {
+        panic!("Expected Create variant");
+    };
+
+    assert!(schema.fields["email"].unique);
+    assert_eq!(
+        schema.fields["email"].field_type,
+        FieldType::Nullable(Box::new(FieldType::String))
+    );
+    assert!(schema.fields["code"].unique);
+    assert_eq!(
+        schema.fields["code"].default_value,
+        Some(bson::Bson::Int64(1))
+    );
+    assert!(!schema.fields["name"].unique);
+    assert!(!schema.fields["id"].unique);
+}
@@ -0,0 +1,183 @@
+use fhedb_query::prelude::parse_contextual_query;
+use fhedb_types::{CollectionQuery, ContextualQuery};
+
+#[test]
+fn basic() {
+    let input = "CREATE INDEX ON users (email)";
+    let result = parse_contextual_query(input);
+    assert!(result.is_ok());
+
+    let Ok(ContextualQuery::Collection(query)) = result else {
+        panic!("Expected Ok result");
+    };
+
+    let CollectionQuery::CreateIndex { name, field_names } = query else {
+        panic!("Expected CreateIndex variant");
+    };
+
+    assert_eq!(name, "users");
+    assert_eq!(field_names, vec!["email"]);
+}
+
+#[test]
+fn case_insensitive() {
+    let input = "cReAtE iNdEx On MyCollection (MyField)";
+    let result = parse_contextual_query(input);
+    assert!(result.is_ok());
+
+    let Ok(ContextualQuery::Collection(query)) = result else {
+        panic!("Expected Ok result");
+    };
+
+    let CollectionQuery::CreateIndex { name, field_names } = query else {
+        panic!("Expected CreateIndex variant");
+    };
+
+    assert_eq!(name, "MyCollection");
+    assert_eq!(field_names, vec!["MyField"]);
+}
+
+#[test]
+fn with_extra_whitespace() {
+    let input = "   CREATE   INDEX  ON   users(  email  )   ";
+    let result = parse_contextual_query(input);
+    assert!(result.is_ok());
+
+    let Ok(ContextualQuery::Collection(query)) = result else {
+        panic!("Expected Ok result");
+    };
+
+    let CollectionQuery::CreateIndex { name, field_names } = query else {
+        panic!("Expected CreateIndex variant");
+    };
+
+    assert_eq!(name, "users");
+    assert_eq!(field_names, vec!["email"]);
+}
+
+#[test]
+fn compound() {
+    let input = "CREATE INDEX ON users (last_name, first_name, age)";
+    let result = parse_contextual_query(input);
+    assert!(result.is_ok());
+
+    let Ok(ContextualQuery::Collection(query)) = result else {
+        panic!("Expected Ok result");
+    };
+
+    let CollectionQuery::CreateIndex { name, field_names } = query else {
+        panic!("Expected CreateIndex variant");
+    };
+
+    assert_eq!(name, "users");
+    assert_eq!(field_names, vec!["last_name", "first_name", "age"]);
+}
+
+#[test]
+fn invalid_missing_field_after_comma() {
+    let input = "CREATE INDEX ON users (email, )";
+    let result = parse_contextual_query(input);
+    assert!(result.is_err());
+
+    let Err(errors) = result else {
+        panic!("Expected Err result");
+    };
+
+    assert!(!errors.is_empty());
+    for error in errors {
+        assert!(error.context.contains(&"create index".to_string()));
+        assert!(error.expected.contains(&"field name".to_string()));
+    }
+}
+
+#[test]
+fn invalid_missing_on() {
+    let input = "CREATE INDEX users (email)";
+    let result = parse_contextual_query(input);
+    assert!(result.is_err());
+
+    let Err(errors) = result else {
+        panic!("Expected Err result");
+    };
+
+    assert!(!errors.is_empty());
+    for error in errors {
+        assert!(error.context.contains(&"create index".to_string()));
+        assert!(error.expected.contains(&"ON".to_string()));
+        assert!(
+            error
+                .message
+                .to_lowercase()
+                .contains("invalid create index query")
+        );
+    }
+}
+
+#[test]
+fn invalid_missing_field() {
+    let input = "CREATE INDEX ON users";
+    let result = parse_contextual_query(input);
+    assert!(result.is_err());
+
+    let Err(errors) = result else {
+        panic!("Expected Err result");
+    };
+
+    assert!(!errors.is_empty());
+    for error in errors {
+        assert!(error.context.contains(&"create index".to_string()));
+        assert!(error.expected.contains(&"(".to_string()));
+    }
+}
+
+#[test]
+fn invalid_empty_parentheses() {
+    let input = "CREATE INDEX ON users ()";
+    let result = parse_contextual_query(input);
+    assert!(result.is_err());
+
+    let Err(errors) = result else {
+        panic!("Expected Err result");
+    };
+
+    assert!(!errors.is_empty());
+    for error in errors {
+        assert!(error.context.contains(&"create index".to_string()));
+        assert!(error.expected.contains(&"field name".to_string()));
+    }
+}
+
+#[test]
+fn invalid_unclosed_parenthesis() {
+    let input = "CREATE INDEX ON users (email";
+    let result = parse_contextual_query(input);
+    assert!(result.is_err());
+
+    let Err(errors) = result else {
+        panic!("Expected Err result");
+    };
+
+    assert!(!errors.is_empty());
+    for error in errors {
+        assert!(error.context.contains(&"create index".to_string()));
+        assert!(error.expected.contains(&")".to_string()));
+    }
+}
+
+#[test]
+fn invalid_extra_input() {
+    let input = "CREATE INDEX ON users (email) EXTRA_STUFF";
+    let result = parse_contextual_query(input);
+    assert!(result.is_err());
+
+    let Err(errors) = result else {
+        panic!("Expected Err result");
+    };
+
+    assert!(!errors.is_empty());
+    for error in errors {
+        assert!(error.expected.contains(&"end of input".to_string()));
+        assert!(error.found == Some("EXTRA_STUFF".to_string()));
+        assert!(error.message.to_lowercase().contains("unexpected input"));
+    }
+}
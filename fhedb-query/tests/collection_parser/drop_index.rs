@@ -0,0 +1,108 @@
+use fhedb_query::prelude::parse_contextual_query;
+use fhedb_types::{CollectionQuery, ContextualQuery};
+
+#[test]
+fn basic() {
+    let input = "DROP INDEX ON users (email)";
+    let result = parse_contextual_query(input);
+    assert!(result.is_ok());
+
+    let Ok(ContextualQuery::Collection(query)) = result else {
+        panic!("Expected Ok result");
+    };
+
+    let CollectionQuery::DropIndex { name, field_names } = query else {
+        panic!("Expected DropIndex variant");
+    };
+
+    assert_eq!(name, "users");
+    assert_eq!(field_names, vec!["email"]);
+}
+
+#[test]
+fn case_insensitive() {
+    let input = "drop index on MyCollection (MyField)";
+    let result = parse_contextual_query(input);
+    assert!(result.is_ok());
+
+    let Ok(ContextualQuery::Collection(query)) = result else {
+        panic!("Expected Ok result");
+    };
+
+    let CollectionQuery::DropIndex { name, field_names } = query else {
+        panic!("Expected DropIndex variant");
+    };
+
+    assert_eq!(name, "MyCollection");
+    assert_eq!(field_names, vec!["MyField"]);
+}
+
+#[test]
+fn compound() {
+    let input = "DROP INDEX ON users (city,age)";
+    let result = parse_contextual_query(input);
+    assert!(result.is_ok());
+
+    let Ok(ContextualQuery::Collection(query)) = result else {
+        panic!("Expected Ok result");
+    };
+
+    let CollectionQuery::DropIndex { name, field_names } = query else {
+        panic!("Expected DropIndex variant");
+    };
+
+    assert_eq!(name, "users");
+    assert_eq!(field_names, vec!["city", "age"]);
+}
+
+#[test]
+fn does_not_shadow_drop_collection() {
+    let input = "DROP COLLECTION users";
+    let result = parse_contextual_query(input);
+
+    let Ok(ContextualQuery::Collection(CollectionQuery::Drop { name })) = result else {
+        panic!("Expected Drop variant");
+    };
+
+    assert_eq!(name, "users");
+}
+
+#[test]
+fn invalid_missing_collection() {
+    let input = "DROP INDEX ON (email)";
+    let result = parse_contextual_query(input);
+    assert!(result.is_err());
+
+    let Err(errors) = result else {
+        panic!("Expected Err result");
+    };
+
+    assert!(!errors.is_empty());
+    for error in errors {
+        assert!(error.context.contains(&"drop index".to_string()));
+        assert!(error.expected.contains(&"collection name".to_string()));
+        assert!(
+            error
+                .message
+                .to_lowercase()
+                .contains("invalid drop index query")
+        );
+    }
+}
+
+#[test]
+fn invalid_keyword_as_field() {
+    let input = "DROP INDEX ON users (drop)";
+    let result = parse_contextual_query(input);
+    assert!(result.is_err());
+
+    let Err(errors) = result else {
+        panic!("Expected Err result");
+    };
+
+    assert!(!errors.is_empty());
+    for error in errors {
+        assert!(error.context.contains(&"drop index".to_string()));
+        assert!(error.expected.contains(&"field name".to_string()));
+    }
+}
@@ -1,5 +1,8 @@
+mod compact_collection;
 mod create_collection;
+mod create_index;
 mod drop_collection;
+mod drop_index;
 mod get_collection_schema;
 mod list_collections;
 mod modify_collection;
@@ -609,3 +609,23 @@ fn invalid_missing_modification_schema() {
     let result = parse_contextual_query(input);
     assert!(result.is_err());
 }
+
+#[test]
+fn unique_modifier() {
+    let input = "MODIFY COLLECTION test {email: string(unique, nullable)}";
+    let result = parse_contextual_query(input);
+    assert!(result.is_ok());
+
+    let Ok(ContextualQuery::Collection(CollectionQuery::Modify { modifications, .. })) = result
+    else {
+        panic!("Expected Modify variant");
+    };
+
+    assert_eq!(
+        modifications.get("email"),
+        Some(&FieldModification::Set(
+            FieldDefinition::new(FieldType::Nullable(Box::new(FieldType::String)))
+                .with_unique(true)
+        ))
+    );
+}
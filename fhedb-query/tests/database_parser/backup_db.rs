@@ -0,0 +1,114 @@
+use fhedb_query::prelude::parse_database_query;
+use fhedb_types::DatabaseQuery;
+
+#[test]
+fn basic() {
+    let input = r#"BACKUP DATABASE test_db TO "/backups/test_db.fhdb""#;
+    let result = parse_database_query(input);
+    assert!(result.is_ok());
+
+    let Ok(query) = result else {
+        panic!("Expected Ok result");
+    };
+
+    let DatabaseQuery::Backup { name, path } = query else {
+        panic!("Expected Backup variant");
+    };
+
+    assert_eq!(name, "test_db");
+    assert_eq!(path, "/backups/test_db.fhdb");
+}
+
+#[test]
+fn case_insensitive() {
+    let input = "BaCkUp DaTaBaSe MyDatabase tO 'backup.fhdb'";
+    let result = parse_database_query(input);
+    assert!(result.is_ok());
+
+    let Ok(query) = result else {
+        panic!("Expected Ok result");
+    };
+
+    let DatabaseQuery::Backup { name, path } = query else {
+        panic!("Expected Backup variant");
+    };
+
+    assert_eq!(name, "MyDatabase");
+    assert_eq!(path, "backup.fhdb");
+}
+
+#[test]
+fn with_extra_whitespace() {
+    let input = r#"   BACKUP    DATABASE   test_db    TO   "backup.fhdb"   "#;
+    let result = parse_database_query(input);
+    assert!(result.is_ok());
+
+    let Ok(query) = result else {
+        panic!("Expected Ok result");
+    };
+
+    assert_eq!(
+        query,
+        DatabaseQuery::Backup {
+            name: "test_db".to_string(),
+            path: "backup.fhdb".to_string(),
+        }
+    );
+}
+
+#[test]
+fn invalid_missing_path() {
+    let input = "BACKUP DATABASE test_db TO";
+    let result = parse_database_query(input);
+    assert!(result.is_err());
+
+    let Err(errors) = result else {
+        panic!("Expected Err result");
+    };
+
+    assert!(!errors.is_empty());
+    for error in errors {
+        assert!(error.context.contains(&"backup database".to_string()));
+        assert!(error.expected.contains(&"archive path".to_string()));
+        assert!(
+            error
+                .message
+                .to_lowercase()
+                .contains("invalid backup database query")
+        );
+    }
+}
+
+#[test]
+fn invalid_unquoted_path() {
+    let input = "BACKUP DATABASE test_db TO archive";
+    let result = parse_database_query(input);
+    assert!(result.is_err());
+
+    let Err(errors) = result else {
+        panic!("Expected Err result");
+    };
+
+    assert!(!errors.is_empty());
+    for error in errors {
+        assert!(error.expected.contains(&"archive path".to_string()));
+        assert!(error.found == Some("archive".to_string()));
+    }
+}
+
+#[test]
+fn invalid_missing_to() {
+    let input = r#"BACKUP DATABASE test_db "backup.fhdb""#;
+    let result = parse_database_query(input);
+    assert!(result.is_err());
+
+    let Err(errors) = result else {
+        panic!("Expected Err result");
+    };
+
+    assert!(!errors.is_empty());
+    for error in errors {
+        assert!(error.expected.contains(&"TO".to_string()));
+        assert!(error.context.contains(&"backup database".to_string()));
+    }
+}
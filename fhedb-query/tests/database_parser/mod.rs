@@ -1,3 +1,5 @@
+mod backup_db;
 mod create_db;
 mod drop_db;
 mod list_db;
+mod restore_db;
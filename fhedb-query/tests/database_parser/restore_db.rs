@@ -0,0 +1,78 @@
+use fhedb_query::prelude::parse_database_query;
+use fhedb_types::DatabaseQuery;
+
+#[test]
+fn basic() {
+    let input = r#"RESTORE DATABASE test_db FROM "/backups/test_db.fhdb""#;
+    let result = parse_database_query(input);
+    assert!(result.is_ok());
+
+    let Ok(query) = result else {
+        panic!("Expected Ok result");
+    };
+
+    let DatabaseQuery::Restore { name, path } = query else {
+        panic!("Expected Restore variant");
+    };
+
+    assert_eq!(name, "test_db");
+    assert_eq!(path, "/backups/test_db.fhdb");
+}
+
+#[test]
+fn case_insensitive() {
+    let input = r#"rEsToRe DaTaBaSe MyDatabase FrOm "backup.fhdb""#;
+    let result = parse_database_query(input);
+    assert!(result.is_ok());
+
+    let Ok(query) = result else {
+        panic!("Expected Ok result");
+    };
+
+    let DatabaseQuery::Restore { name, path } = query else {
+        panic!("Expected Restore variant");
+    };
+
+    assert_eq!(name, "MyDatabase");
+    assert_eq!(path, "backup.fhdb");
+}
+
+#[test]
+fn invalid_missing_from() {
+    let input = r#"RESTORE DATABASE test_db TO "backup.fhdb""#;
+    let result = parse_database_query(input);
+    assert!(result.is_err());
+
+    let Err(errors) = result else {
+        panic!("Expected Err result");
+    };
+
+    assert!(!errors.is_empty());
+    for error in errors {
+        assert!(error.expected.contains(&"FROM".to_string()));
+        assert!(error.found == Some("TO".to_string()));
+        assert!(
+            error
+                .message
+                .to_lowercase()
+                .contains("invalid restore database query")
+        );
+    }
+}
+
+#[test]
+fn invalid_missing_name() {
+    let input = r#"RESTORE DATABASE FROM "backup.fhdb""#;
+    let result = parse_database_query(input);
+    assert!(result.is_err());
+
+    let Err(errors) = result else {
+        panic!("Expected Err result");
+    };
+
+    assert!(!errors.is_empty());
+    for error in errors {
+        assert!(error.context.contains(&"restore database".to_string()));
+        assert!(error.expected.contains(&"database name".to_string()));
+    }
+}
@@ -17,6 +17,7 @@ fn basic() {
         collection_name,
         conditions,
         selectors,
+        ..
     } = query
     else {
         panic!("Expected Get variant");
@@ -45,6 +46,7 @@ fn variations() {
         collection_name: name1,
         conditions: conds1,
         selectors: sels1,
+        ..
     } = query1
     else {
         panic!("Expected Get variant");
@@ -69,6 +71,7 @@ fn variations() {
         collection_name: name2,
         conditions: conds2,
         selectors: sels2,
+        ..
     } = query2
     else {
         panic!("Expected Get variant");
@@ -92,6 +95,7 @@ fn variations() {
         collection_name: name3,
         conditions: conds3,
         selectors: sels3,
+        ..
     } = query3
     else {
         panic!("Expected Get variant");
@@ -126,6 +130,7 @@ fn all_operators() {
         collection_name,
         conditions,
         selectors,
+        ..
     } = query
     else {
         panic!("Expected Get variant");
@@ -219,6 +224,7 @@ fn simple_nested() {
         collection_name,
         conditions,
         selectors,
+        ..
     } = query
     else {
         panic!("Expected Get variant");
@@ -667,6 +673,56 @@ fn invalid_field_structure() {
     }
 }
 
+#[test]
+fn as_of() {
+    let input = "GET DOCUMENTS FROM users AS OF \"2026-10-01T00:00:00Z\" {age > 18, *}";
+    let Ok(ContextualQuery::Document(DocumentQuery::Get {
+        collection_name,
+        conditions,
+        selectors,
+        as_of,
+    })) = parse_contextual_query(input)
+    else {
+        panic!("Expected Ok result");
+    };
+
+    assert_eq!(collection_name, "users");
+    assert_eq!(conditions.len(), 1);
+    assert_eq!(selectors, vec![FieldSelector::AllFields]);
+    assert_eq!(as_of, Some("2026-10-01T00:00:00Z".to_string()));
+
+    let input = "get docs from users as of '2026-10-01T02:30:00.5+02:00' {id}";
+    let Ok(ContextualQuery::Document(DocumentQuery::Get { as_of, .. })) =
+        parse_contextual_query(input)
+    else {
+        panic!("Expected Ok result");
+    };
+    assert_eq!(as_of, Some("2026-10-01T02:30:00.5+02:00".to_string()));
+
+    let input = "GET DOCUMENTS FROM users {id}";
+    let Ok(ContextualQuery::Document(DocumentQuery::Get { as_of, .. })) =
+        parse_contextual_query(input)
+    else {
+        panic!("Expected Ok result");
+    };
+    assert_eq!(as_of, None);
+}
+
+#[test]
+fn invalid_as_of() {
+    let inputs = [
+        "GET DOCUMENTS FROM users AS OF \"yesterday\" {id}",
+        "GET DOCUMENTS FROM users AS OF \"2026-10-01\" {id}",
+        "GET DOCUMENTS FROM users AS OF 2026 {id}",
+        "GET DOCUMENTS FROM users AS \"2026-10-01T00:00:00Z\" {id}",
+        "GET DOCUMENTS FROM users AS OF \"2026-10-01T00:00:00Z\"",
+    ];
+
+    for input in inputs {
+        assert!(parse_contextual_query(input).is_err(), "{}", input);
+    }
+}
+
 #[test]
 fn invalid_assignments_in_get() {
     let input = "GET DOC FROM users {id = 1, name: 'John'}";
@@ -0,0 +1,72 @@
+use fhedb_query::prelude::parse_contextual_query;
+use fhedb_types::{ContextualQuery, DocumentQuery, QueryOperator};
+
+#[test]
+fn basic() {
+    let input = "GET HISTORY FROM users { id = 5 }";
+    let result = parse_contextual_query(input);
+    assert!(result.is_ok());
+
+    let Ok(ContextualQuery::Document(query)) = result else {
+        panic!("Expected Ok result");
+    };
+
+    let DocumentQuery::History {
+        collection_name,
+        conditions,
+    } = query
+    else {
+        panic!("Expected History variant");
+    };
+
+    assert_eq!(collection_name, "users");
+    assert_eq!(conditions.len(), 1);
+    assert_eq!(conditions[0].field_name, "id");
+    assert_eq!(conditions[0].operator, QueryOperator::Equal);
+    assert_eq!(conditions[0].value, "5");
+}
+
+#[test]
+fn case_insensitive() {
+    let input = "get history from users {id = 'abc'}";
+    let result = parse_contextual_query(input);
+
+    let Ok(ContextualQuery::Document(DocumentQuery::History { conditions, .. })) = result else {
+        panic!("Expected Ok result");
+    };
+    assert_eq!(conditions[0].value, "\"abc\"");
+}
+
+#[test]
+fn invalid_without_conditions() {
+    let input = "GET HISTORY FROM users {}";
+    assert!(parse_contextual_query(input).is_err());
+}
+
+#[test]
+fn invalid_selectors() {
+    let input = "GET HISTORY FROM users {id = 5, name}";
+    assert!(parse_contextual_query(input).is_err());
+}
+
+#[test]
+fn invalid_assignments() {
+    let input = "GET HISTORY FROM users {id: 5}";
+    assert!(parse_contextual_query(input).is_err());
+}
+
+#[test]
+fn invalid_missing_collection() {
+    let input = "GET HISTORY FROM {id = 5}";
+    let result = parse_contextual_query(input);
+
+    let Err(errors) = result else {
+        panic!("Expected Err result");
+    };
+
+    assert!(!errors.is_empty());
+    for error in errors {
+        assert!(error.context.contains(&"get document history".to_string()));
+        assert!(error.expected.contains(&"collection name".to_string()));
+    }
+}
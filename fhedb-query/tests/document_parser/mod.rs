@@ -1,4 +1,6 @@
 mod delete_doc;
 mod get_doc;
+mod history_doc;
 mod insert_doc;
+mod sum_doc;
 mod update_doc;
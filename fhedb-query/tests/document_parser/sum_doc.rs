@@ -0,0 +1,78 @@
+use fhedb_query::prelude::parse_contextual_query;
+use fhedb_types::{ContextualQuery, DocumentQuery, QueryOperator};
+
+#[test]
+fn basic() {
+    let input = "GET SUM(balance) FROM accounts {owner = 'alice'}";
+    let result = parse_contextual_query(input);
+    assert!(result.is_ok());
+
+    let Ok(ContextualQuery::Document(query)) = result else {
+        panic!("Expected Ok result");
+    };
+
+    let DocumentQuery::Sum {
+        collection_name,
+        field_name,
+        conditions,
+    } = query
+    else {
+        panic!("Expected Sum variant");
+    };
+
+    assert_eq!(collection_name, "accounts");
+    assert_eq!(field_name, "balance");
+    assert_eq!(conditions.len(), 1);
+    assert_eq!(conditions[0].field_name, "owner");
+    assert_eq!(conditions[0].operator, QueryOperator::Equal);
+}
+
+#[test]
+fn without_conditions() {
+    let input = "get sum ( balance ) from accounts {}";
+    let result = parse_contextual_query(input);
+    assert!(result.is_ok());
+
+    let Ok(ContextualQuery::Document(query)) = result else {
+        panic!("Expected Ok result");
+    };
+
+    assert_eq!(
+        query,
+        DocumentQuery::Sum {
+            collection_name: "accounts".to_string(),
+            field_name: "balance".to_string(),
+            conditions: vec![],
+        }
+    );
+}
+
+#[test]
+fn invalid_selectors() {
+    let input = "GET SUM(balance) FROM accounts {owner}";
+    let result = parse_contextual_query(input);
+    assert!(result.is_err());
+
+    let Err(errors) = result else {
+        panic!("Expected Err result");
+    };
+
+    assert!(!errors.is_empty());
+}
+
+#[test]
+fn invalid_missing_field() {
+    let input = "GET SUM() FROM accounts {}";
+    let result = parse_contextual_query(input);
+    assert!(result.is_err());
+
+    let Err(errors) = result else {
+        panic!("Expected Err result");
+    };
+
+    assert!(!errors.is_empty());
+    for error in errors {
+        assert!(error.context.contains(&"sum documents".to_string()));
+        assert!(error.expected.contains(&"field name".to_string()));
+    }
+}
@@ -1,5 +1,9 @@
 use chumsky::Parser;
-use fhedb_query::lexer::{Token, lexer};
+use fhedb_query::{
+    lexer::{Token, lexer},
+    prelude::{parse_contextual_query, parse_database_query},
+};
+use fhedb_types::{CollectionQuery, ContextualQuery, DatabaseQuery, DocumentQuery};
 
 fn parse_identifier(input: &str) -> Option<String> {
     let tokens = lexer().parse(input).into_result().ok()?;
@@ -122,3 +126,78 @@ fn keywords_as_substrings_allowed() {
     assert!(is_identifier("get_data"));
     assert!(is_identifier("create_user"));
 }
+
+#[test]
+fn contextual_keywords_are_identifiers() {
+    for keyword in [
+        "to", "as", "of", "on", "index", "history", "sum", "unique", "compact", "backup", "restore",
+    ] {
+        assert_eq!(parse_identifier(keyword), Some(keyword.to_string()));
+    }
+
+    assert_eq!(parse_identifier("Compact"), Some("Compact".to_string()));
+    assert_eq!(parse_identifier("Index"), Some("Index".to_string()));
+    assert_eq!(parse_identifier("SUM"), Some("SUM".to_string()));
+}
+
+#[test]
+fn contextual_keywords_as_field_names() {
+    let input =
+        "CREATE COLLECTION trips {id: id_int, to: string(unique), Of: int, history: array<int>}";
+    let Ok(ContextualQuery::Collection(CollectionQuery::Create { schema, .. })) =
+        parse_contextual_query(input)
+    else {
+        panic!("Expected Create variant");
+    };
+    assert!(schema.fields["to"].unique);
+    assert!(schema.fields.contains_key("Of"));
+    assert!(schema.fields.contains_key("history"));
+
+    let input = "COMPACT COLLECTION compact";
+    let Ok(ContextualQuery::Collection(CollectionQuery::Compact { name })) =
+        parse_contextual_query(input)
+    else {
+        panic!("Expected Compact variant");
+    };
+    assert_eq!(name, "compact");
+
+    let input = "GET SUM(sum) FROM index {on = 1}";
+    let Ok(ContextualQuery::Document(DocumentQuery::Sum {
+        collection_name,
+        field_name,
+        conditions,
+    })) = parse_contextual_query(input)
+    else {
+        panic!("Expected Sum variant");
+    };
+    assert_eq!(collection_name, "index");
+    assert_eq!(field_name, "sum");
+    assert_eq!(conditions[0].field_name, "on");
+
+    let input = "GET DOCS FROM trips AS OF '2024-01-01T00:00:00Z' {as, unique = 2}";
+    let Ok(ContextualQuery::Document(DocumentQuery::Get { as_of, .. })) =
+        parse_contextual_query(input)
+    else {
+        panic!("Expected Get variant");
+    };
+    assert_eq!(as_of.as_deref(), Some("2024-01-01T00:00:00Z"));
+
+    let input = "CREATE INDEX ON compact (backup, restore)";
+    let Ok(ContextualQuery::Collection(CollectionQuery::CreateIndex { name, field_names })) =
+        parse_contextual_query(input)
+    else {
+        panic!("Expected CreateIndex variant");
+    };
+    assert_eq!(name, "compact");
+    assert_eq!(
+        field_names,
+        vec!["backup".to_string(), "restore".to_string()]
+    );
+
+    let input = "BACKUP DATABASE to TO 'archive.fhdb'";
+    let Ok(DatabaseQuery::Backup { name, path }) = parse_database_query(input) else {
+        panic!("Expected Backup variant");
+    };
+    assert_eq!(name, "to");
+    assert_eq!(path, "archive.fhdb");
+}
@@ -31,6 +31,19 @@ fn nullable_constraint() {
     assert!(parse_schema("name: String(NULLABLE), active: Boolean(default = true)").is_ok());
 }
 
+#[test]
+fn unique_constraint() {
+    assert!(parse_schema("email: string(unique)").is_ok());
+    assert!(parse_schema("email: string(nullable, unique)").is_ok());
+    assert!(parse_schema("code: int(unique, default = 1), name: String(UNIQUE)").is_ok());
+    assert!(parse_schema("code: int(default = 1, nullable)").is_ok());
+    assert!(parse_schema("email: string(unique, unique)").is_err());
+    assert!(parse_schema("email: string(nullable, nullable)").is_err());
+    assert!(parse_schema("email: string(default = \"a\", default = \"b\")").is_err());
+    assert!(parse_schema("email: string(unique,)").is_err());
+    assert!(parse_schema("id: id_int(unique)").is_err());
+}
+
 #[test]
 fn default_values_valid() {
     assert!(parse_schema("age: int(default = 25), active: boolean(default = true)").is_ok());
@@ -123,7 +136,6 @@ fn invalid_field_types() {
 fn invalid_constraints() {
     assert!(parse_schema("name: string(required)").is_err());
     assert!(parse_schema("name: string(optional)").is_err());
-    assert!(parse_schema("name: string(unique)").is_err());
     assert!(parse_schema("name: string(indexed)").is_err());
     assert!(parse_schema("age: int(min = 0)").is_err());
     assert!(parse_schema("age: int(max = 100)").is_err());
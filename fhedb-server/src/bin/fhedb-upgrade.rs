@@ -0,0 +1,37 @@
+//! # Fhedb Upgrade
+//!
+//! Migrates a data directory to the storage format version of this build.
+//!
+//! Takes the data directory as its only argument, falling back to the base directory
+//! of the storage config. The server must not be running while the upgrade runs.
+
+use std::{env, path::PathBuf, process::ExitCode};
+
+use fhedb_core::prelude::{FORMAT_VERSION, upgrade_data_dir};
+use fhedb_server::prelude::CoreConfig;
+
+fn main() -> ExitCode {
+    let data_dir = match env::args_os().nth(1) {
+        Some(path) => PathBuf::from(path),
+        None => CoreConfig::read_from_file().storage.base_dir().clone(),
+    };
+
+    println!(
+        "Upgrading '{}' to format version {}.",
+        data_dir.display(),
+        FORMAT_VERSION
+    );
+    match upgrade_data_dir(&data_dir) {
+        Ok(report) => {
+            for collection in &report.upgraded {
+                println!("Upgraded collection '{}'.", collection);
+            }
+            println!("{}.", report);
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            eprintln!("Upgrade failed: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
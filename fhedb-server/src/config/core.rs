@@ -23,6 +23,7 @@ impl CoreConfig {
     /// Ensures that all directories required by the core config exist.
     pub fn ensure_dirs(&self) {
         self.storage.ensure_base_dir();
+        self.storage.ensure_backup_dir();
         self.logging.ensure_log_dir();
     }
 
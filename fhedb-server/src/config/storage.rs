@@ -1,13 +1,100 @@
 //! # Storage Configuration
 
 use dirs::data_local_dir;
+use fhedb_core::prelude::{
+    Compression, DEFAULT_COMPACTION_THRESHOLD, DEFAULT_GROUP_COMMIT_INTERVAL,
+    DEFAULT_MAX_SEGMENT_SIZE, Durability, EncryptionKey,
+};
 use serde::{Deserialize, Serialize};
-use std::{fs::create_dir_all, path::PathBuf};
+use std::{fs::create_dir_all, path::PathBuf, time::Duration};
+
+/// When writes are flushed to disk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DurabilityMode {
+    /// Flush every write before acknowledging it.
+    Fsync,
+    /// Flush writes together at a fixed interval.
+    #[default]
+    GroupCommit,
+    /// Never flush explicitly, leaving it to the operating system.
+    None,
+}
+
+/// The codec used to compress the log entries of new collections.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CompressionMode {
+    /// Log entries are written uncompressed.
+    #[default]
+    None,
+    /// Log entries are compressed with LZ4.
+    Lz4,
+}
+
+/// Returns the default group commit interval in milliseconds.
+fn default_group_commit_interval_ms() -> u64 {
+    DEFAULT_GROUP_COMMIT_INTERVAL.as_millis() as u64
+}
+
+/// Returns the default maximum log segment size in megabytes.
+fn default_max_segment_size_mb() -> u64 {
+    (DEFAULT_MAX_SEGMENT_SIZE / (1024 * 1024)) as u64
+}
+
+/// Returns the default compaction threshold.
+fn default_compaction_threshold() -> f64 {
+    DEFAULT_COMPACTION_THRESHOLD
+}
+
+/// Returns the default interval between background compaction checks, in seconds.
+fn default_compaction_interval_secs() -> u64 {
+    60
+}
+
+/// Returns the default directory holding database backups.
+fn default_backup_dir() -> PathBuf {
+    let mut backup_dir = data_local_dir().expect("Failed to locate local data directory.");
+    backup_dir.push("fhedb");
+    backup_dir.push("backups");
+    backup_dir
+}
 
 /// Data storage path configuration.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct StorageConfig {
     base_dir: PathBuf,
+    /// The directory that backup and restore paths are resolved against.
+    #[serde(default = "default_backup_dir")]
+    backup_dir: PathBuf,
+    /// When writes are flushed to disk.
+    #[serde(default)]
+    durability: DurabilityMode,
+    /// The interval between group commits, in milliseconds.
+    #[serde(default = "default_group_commit_interval_ms")]
+    group_commit_interval_ms: u64,
+    /// The size after which collection logs roll over to a new segment, in megabytes.
+    #[serde(default = "default_max_segment_size_mb")]
+    max_segment_size_mb: u64,
+    /// The share of superseded log entries above which a collection is compacted.
+    #[serde(default = "default_compaction_threshold")]
+    compaction_threshold: f64,
+    /// The interval between background compaction checks, in seconds. Zero disables them.
+    #[serde(default = "default_compaction_interval_secs")]
+    compaction_interval_secs: u64,
+    /// How long compaction keeps log entries untouched, in seconds.
+    /// Zero lets compaction discard the history of documents.
+    #[serde(default)]
+    history_retention_secs: u64,
+    /// The codec used to compress the log entries of new collections.
+    #[serde(default)]
+    compression: CompressionMode,
+    /// The hex encoded key encrypting the stored files.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    encryption_key: Option<String>,
+    /// The path to a file holding the hex encoded key encrypting the stored files.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    encryption_key_file: Option<PathBuf>,
 }
 
 impl Default for StorageConfig {
@@ -15,7 +102,19 @@ impl Default for StorageConfig {
         let mut base_dir = data_local_dir().expect("Failed to locate local data directory.");
         base_dir.push("fhedb");
         base_dir.push("data");
-        Self { base_dir }
+        Self {
+            base_dir,
+            backup_dir: default_backup_dir(),
+            durability: DurabilityMode::default(),
+            group_commit_interval_ms: default_group_commit_interval_ms(),
+            max_segment_size_mb: default_max_segment_size_mb(),
+            compaction_threshold: default_compaction_threshold(),
+            compaction_interval_secs: default_compaction_interval_secs(),
+            history_retention_secs: 0,
+            compression: CompressionMode::default(),
+            encryption_key: None,
+            encryption_key_file: None,
+        }
     }
 }
 
@@ -27,8 +126,79 @@ impl StorageConfig {
         }
     }
 
+    /// Ensures that the backup directory exists.
+    pub fn ensure_backup_dir(&self) {
+        if !self.backup_dir.exists() {
+            create_dir_all(&self.backup_dir).expect("Failed to create backup directory");
+        }
+    }
+
     /// Returns the base directory.
     pub fn base_dir(&self) -> &PathBuf {
         &self.base_dir
     }
+
+    /// Returns the directory that backup and restore paths are resolved against.
+    pub fn backup_dir(&self) -> &PathBuf {
+        &self.backup_dir
+    }
+
+    /// Returns the durability setting to apply to every database.
+    pub fn durability(&self) -> Durability {
+        match self.durability {
+            DurabilityMode::Fsync => Durability::Always,
+            DurabilityMode::GroupCommit => {
+                Durability::GroupCommit(Duration::from_millis(self.group_commit_interval_ms))
+            }
+            DurabilityMode::None => Durability::Never,
+        }
+    }
+
+    /// Returns the maximum log segment size in bytes to apply to every database.
+    pub fn max_segment_size(&self) -> usize {
+        (self.max_segment_size_mb as usize).saturating_mul(1024 * 1024)
+    }
+
+    /// Returns the compaction threshold to apply to every database.
+    pub fn compaction_threshold(&self) -> f64 {
+        self.compaction_threshold
+    }
+
+    /// Returns how long compaction keeps log entries untouched,
+    /// or [`None`] if compaction discards history.
+    pub fn history_retention(&self) -> Option<Duration> {
+        (self.history_retention_secs > 0).then(|| Duration::from_secs(self.history_retention_secs))
+    }
+
+    /// Returns the compression codec to apply to collections created in every database.
+    pub fn compression(&self) -> Compression {
+        match self.compression {
+            CompressionMode::None => Compression::None,
+            CompressionMode::Lz4 => Compression::Lz4,
+        }
+    }
+
+    /// Returns the key encrypting the files of every database,
+    /// or [`None`] if encryption at rest is disabled.
+    pub fn encryption_key(&self) -> Option<EncryptionKey> {
+        match (&self.encryption_key, &self.encryption_key_file) {
+            (Some(_), Some(_)) => {
+                panic!("Only one of encryption_key and encryption_key_file can be set.")
+            }
+            (Some(key), None) => {
+                Some(EncryptionKey::from_hex(key).expect("Invalid storage encryption key."))
+            }
+            (None, Some(path)) => {
+                Some(EncryptionKey::from_file(path).expect("Failed to read encryption key file."))
+            }
+            (None, None) => None,
+        }
+    }
+
+    /// Returns the interval between background compaction checks,
+    /// or [`None`] if background compaction is disabled.
+    pub fn compaction_interval(&self) -> Option<Duration> {
+        (self.compaction_interval_secs > 0)
+            .then(|| Duration::from_secs(self.compaction_interval_secs))
+    }
 }
@@ -91,6 +91,8 @@ fn log_query(query: &ParsedQuery, path: &str) {
             DatabaseQuery::Create { .. } => "Create database",
             DatabaseQuery::Drop { .. } => "Drop database",
             DatabaseQuery::List => "List database",
+            DatabaseQuery::Backup { .. } => "Backup database",
+            DatabaseQuery::Restore { .. } => "Restore database",
         },
         ParsedQuery::Context(ast) => match ast {
             ContextualQuery::Collection(coll) => match coll {
@@ -99,12 +101,17 @@ fn log_query(query: &ParsedQuery, path: &str) {
                 CollectionQuery::List => "List collections",
                 CollectionQuery::GetSchema { .. } => "Get collection schema",
                 CollectionQuery::Modify { .. } => "Modify collection",
+                CollectionQuery::Compact { .. } => "Compact collection",
+                CollectionQuery::CreateIndex { .. } => "Create index",
+                CollectionQuery::DropIndex { .. } => "Drop index",
             },
             ContextualQuery::Document(doc) => match doc {
                 DocumentQuery::Insert { .. } => "Insert document",
                 DocumentQuery::Delete { .. } => "Delete document",
                 DocumentQuery::Get { .. } => "Get/List documents",
                 DocumentQuery::Update { .. } => "Update document",
+                DocumentQuery::Sum { .. } => "Sum documents",
+                DocumentQuery::History { .. } => "Get document history",
             },
         },
     };
@@ -1,12 +1,16 @@
 //! # Database Query Handlers
 //!
-//! This module handles database operations such as creating, dropping, and listing databases.
+//! This module handles database operations such as creating, dropping, listing,
+//! backing up and restoring databases.
 
 use fhedb_core::prelude::Database;
 use fhedb_types::DatabaseQuery;
 use log::warn;
 use serde_json::json;
-use std::fs::{create_dir_all, remove_dir_all};
+use std::{
+    fs::{create_dir_all, remove_dir_all},
+    path::{Component, Path, PathBuf},
+};
 
 use crate::state::ServerState;
 
@@ -36,6 +40,8 @@ pub(crate) fn execute_base_query(
         }
         DatabaseQuery::Drop { name } => drop_db(name.clone(), state),
         DatabaseQuery::List => list_dbs(state),
+        DatabaseQuery::Backup { name, path } => backup_db(name, path, state),
+        DatabaseQuery::Restore { name, path } => restore_db(name, path, state),
     }
 }
 
@@ -55,7 +61,9 @@ fn list_dbs(state: &ServerState) -> Result<serde_json::Value, String> {
         if let Ok(entry) = entry {
             if entry.file_type().is_ok_and(|e| e.is_dir()) {
                 if let Ok(db_name) = entry.file_name().into_string() {
-                    dbs.push(db_name);
+                    if !db_name.starts_with('.') {
+                        dbs.push(db_name);
+                    }
                 } else {
                     warn!("Unable to read directory entry name at base data location.");
                 }
@@ -115,9 +123,132 @@ fn create_db(name: String, state: &ServerState) -> Result<serde_json::Value, Str
     if exists_in_memory || exists_on_disk {
         Err("Database already exists".to_string())
     } else {
-        let db = Database::new(&name, &state.data_dir);
+        let mut db = Database::new(&name, &state.data_dir);
+        state.configure_database(&mut db);
         create_dir_all(db.path()).map_err(|e| e.to_string())?;
         dbs.insert(name.clone(), db);
         Ok(json!({ "created": name }))
     }
 }
+
+/// Backs up a database into an archive, while it stays available for queries.
+///
+/// The snapshot is taken under the read lock, which only holds back writes briefly,
+/// and the archive is written once the lock is released.
+///
+/// ## Arguments
+///
+/// * `name` - The name of the database to back up.
+/// * `path` - The path of the archive to write, relative to the backup directory.
+/// * `state` - The [`ServerState`] containing database references.
+///
+/// ## Returns
+///
+/// Returns [`Ok`]\([`serde_json::Value`]) with a summary of the archive,
+/// or [`Err`]\([`String`]) if the path is invalid, the database doesn't exist or the backup failed.
+fn backup_db(name: String, path: String, state: &ServerState) -> Result<serde_json::Value, String> {
+    let archive_path = resolve_backup_path(&path, state)?;
+    let cached = {
+        let dbs = state.databases.read().map_err(|e| e.to_string())?;
+        dbs.get(&name)
+            .map(|db| db.snapshot().map_err(|e| e.to_string()))
+            .transpose()?
+    };
+
+    let snapshot = match cached {
+        Some(snapshot) => snapshot,
+        None => {
+            let mut dbs = state.databases.write().map_err(|e| e.to_string())?;
+            if !dbs.contains_key(&name) {
+                if !state.data_dir.join(&name).exists() {
+                    return Err("Database does not exist".to_string());
+                }
+                let mut db = Database::from_files_with_key(
+                    &name,
+                    &state.data_dir,
+                    state.encryption_key.clone(),
+                )
+                .map_err(|e| e.to_string())?;
+                state.configure_database(&mut db);
+                dbs.insert(name.clone(), db);
+            }
+            dbs[&name].snapshot().map_err(|e| e.to_string())?
+        }
+    };
+
+    if let Some(parent) = archive_path.parent() {
+        create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let report = snapshot
+        .write_archive(&archive_path)
+        .map_err(|e| e.to_string())?;
+    Ok(json!({
+        "backed_up": name,
+        "path": path,
+        "collections": report.collections,
+        "bytes": report.bytes,
+    }))
+}
+
+/// Restores a database from an archive, replacing any existing database of the same name.
+///
+/// ## Arguments
+///
+/// * `name` - The name to restore the database under.
+/// * `path` - The path of the archive to read, relative to the backup directory.
+/// * `state` - The [`ServerState`] containing database references.
+///
+/// ## Returns
+///
+/// Returns [`Ok`]\([`serde_json::Value`]) with the restored collections,
+/// or [`Err`]\([`String`]) if the path or archive is invalid or the restore failed.
+fn restore_db(
+    name: String,
+    path: String,
+    state: &ServerState,
+) -> Result<serde_json::Value, String> {
+    let archive_path = resolve_backup_path(&path, state)?;
+    let mut dbs = state.databases.write().map_err(|e| e.to_string())?;
+
+    // The cached database is only replaced once the restored one has loaded, so a failed
+    // restore leaves it usable.
+    let mut db = Database::restore_with_key(
+        &archive_path,
+        &name,
+        &state.data_dir,
+        state.encryption_key.clone(),
+    )
+    .map_err(|e| e.to_string())?;
+    state.configure_database(&mut db);
+    let mut collections = db.collection_names();
+    collections.sort();
+    dbs.insert(name.clone(), db);
+    Ok(json!({ "restored": name, "collections": collections }))
+}
+
+/// Resolves a backup path from a query against the server's backup directory.
+///
+/// Only plain relative paths are accepted, so queries can't reach files outside of it.
+///
+/// ## Arguments
+///
+/// * `path` - The path given in the query.
+/// * `state` - The [`ServerState`] holding the backup directory.
+///
+/// ## Returns
+///
+/// Returns [`Ok`]\([`PathBuf`]) with the path inside the backup directory,
+/// or [`Err`]\([`String`]) if the path is empty, absolute or contains `..`.
+fn resolve_backup_path(path: &str, state: &ServerState) -> Result<PathBuf, String> {
+    let relative = Path::new(path);
+    if relative.file_name().is_none() || relative.is_absolute() || relative.has_root() {
+        return Err("Backup path must be relative to the backup directory".to_string());
+    }
+    if relative
+        .components()
+        .any(|component| !matches!(component, Component::Normal(_) | Component::CurDir))
+    {
+        return Err("Backup path must not leave the backup directory".to_string());
+    }
+    Ok(state.backup_dir.join(relative))
+}
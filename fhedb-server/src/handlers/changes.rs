@@ -0,0 +1,178 @@
+//! # Change Stream Handler
+//!
+//! Streams the change feed of a database as server-sent events.
+//!
+//! Every event carries the epoch of the feed and its sequence number as the event ID, in the
+//! form `<epoch>-<sequence>`, so a client reconnecting with the `Last-Event-ID` header resumes
+//! right after the last event it received. Sequence numbers start over whenever the database
+//! is loaded again, such as after a restart or a restore, so an ID from an earlier feed is
+//! rejected rather than resumed from.
+
+use std::convert::Infallible;
+
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::{
+        IntoResponse, Response,
+        sse::{Event, KeepAlive, Sse},
+    },
+};
+use fhedb_core::prelude::{ChangeEvent, Subscription};
+use futures_util::stream::{self, Stream};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::{error, internal_error, state::ServerState};
+
+/// The query parameters of a change stream request.
+#[derive(Debug, Default, Deserialize)]
+pub struct ChangesParams {
+    /// The name of the collection to stream the changes of, or all of them if absent.
+    pub collection: Option<String>,
+    /// The sequence number of the first event to stream, or only new events if absent.
+    pub from: Option<u64>,
+    /// The epoch of the feed `from` was taken from, which must be the current one if given.
+    pub epoch: Option<u64>,
+}
+
+/// Handles requests to stream the changes of a database or one of its collections.
+///
+/// A `Last-Event-ID` header takes precedence over the `from` parameter.
+///
+/// ## Arguments
+///
+/// * `db_name` - The name of the database from the request path.
+/// * `state` - The [`ServerState`] containing database references.
+/// * `params` - The [`ChangesParams`] of the request.
+/// * `headers` - The request headers.
+///
+/// ## Returns
+///
+/// Returns a stream of server-sent events, or an error response if the collection does not
+/// exist, the requested epoch is not the current one, or the requested sequence number
+/// is no longer or not yet available.
+pub async fn handle_changes(
+    Path(db_name): Path<String>,
+    State(state): State<ServerState>,
+    Query(params): Query<ChangesParams>,
+    headers: HeaderMap,
+) -> Response {
+    let (epoch, from) = match headers.get("last-event-id") {
+        Some(value) => {
+            // An ID of `u64::MAX` has no event following it, so it is rejected like a malformed one.
+            let Some((epoch, from)) = value
+                .to_str()
+                .ok()
+                .and_then(parse_event_id)
+                .and_then(|(epoch, last_id)| Some((epoch, last_id.checked_add(1)?)))
+            else {
+                return error!("Invalid Last-Event-ID header.", StatusCode::BAD_REQUEST)
+                    .into_response();
+            };
+            (Some(epoch), Some(from))
+        }
+        None => (params.epoch, params.from),
+    };
+
+    let subscription = {
+        let dbs = match state.databases.read() {
+            Ok(dbs) => dbs,
+            Err(err) => return internal_error!(err.to_string()).into_response(),
+        };
+        let Some(db) = dbs.get(&db_name) else {
+            return error!(
+                format!("Database '{}' not found.", db_name),
+                StatusCode::NOT_FOUND
+            )
+            .into_response();
+        };
+        if epoch.is_some_and(|epoch| epoch != db.change_feed().epoch()) {
+            return error!(
+                "The change feed was restarted, so the requested changes are no longer available.",
+                StatusCode::GONE
+            )
+            .into_response();
+        }
+        if let Some(collection_name) = &params.collection
+            && !db.has_collection(collection_name)
+        {
+            return error!(
+                format!("Collection '{}' not found.", collection_name),
+                StatusCode::NOT_FOUND
+            )
+            .into_response();
+        }
+        match db.subscribe_changes(from, params.collection.as_deref()) {
+            Ok(subscription) => subscription,
+            Err(err) => return error!(err, StatusCode::GONE).into_response(),
+        }
+    };
+
+    Sse::new(change_events(subscription))
+        .keep_alive(KeepAlive::default())
+        .into_response()
+}
+
+/// Turns a subscription into a stream of server-sent events.
+///
+/// Events are waited for asynchronously, so an idle stream holds no thread. The stream ends
+/// with an `error` event if the subscription falls behind the feed.
+///
+/// ## Arguments
+///
+/// * `subscription` - The [`Subscription`] to read events from.
+fn change_events(subscription: Subscription) -> impl Stream<Item = Result<Event, Infallible>> {
+    stream::unfold(Some(subscription), |subscription| async move {
+        let mut subscription = subscription?;
+        match subscription.next_async().await {
+            Ok(change) => {
+                let event = change_event(subscription.epoch(), &change);
+                Some((Ok(event), Some(subscription)))
+            }
+            Err(err) => Some((Ok(error_event(err)), None)),
+        }
+    })
+}
+
+/// Parses an event ID into the epoch of its feed and its sequence number.
+///
+/// ## Arguments
+///
+/// * `id` - The event ID, in the form `<epoch>-<sequence>`.
+fn parse_event_id(id: &str) -> Option<(u64, u64)> {
+    let (epoch, sequence) = id.trim().split_once('-')?;
+    Some((epoch.parse().ok()?, sequence.parse().ok()?))
+}
+
+/// Converts a change into a server-sent event, identified by its feed's epoch
+/// and its sequence number.
+///
+/// ## Arguments
+///
+/// * `epoch` - The epoch of the feed the change was published to.
+/// * `change` - The [`ChangeEvent`] to convert.
+fn change_event(epoch: u64, change: &ChangeEvent) -> Event {
+    let data = json!({
+        "epoch": epoch,
+        "sequence": change.sequence,
+        "collection": change.collection,
+        "operation": change.operation.as_str(),
+        "timestamp": change.timestamp,
+        "id": change.id.to_bson(),
+        "document": change.document,
+    });
+    Event::default()
+        .id(format!("{}-{}", epoch, change.sequence))
+        .event("change")
+        .data(data.to_string())
+}
+
+/// Creates the event ending a stream that cannot continue.
+///
+/// ## Arguments
+///
+/// * `message` - The reason the stream ends.
+fn error_event(message: String) -> Event {
+    Event::default().event("error").data(message)
+}
@@ -1,11 +1,11 @@
 //! # Collection Query Handlers
 //!
 //! This module handles collection operations within a database context,
-//! such as creating, dropping, modifying, and listing collections.
+//! such as creating, dropping, modifying, and listing collections and their indexes.
 
 use crate::state::ServerState;
 use fhedb_core::prelude::{
-    FieldDefinition, FieldType, ReferenceChecker, Schema, SchemaReferenceValidator,
+    Collection, FieldDefinition, FieldType, ReferenceChecker, Schema, SchemaReferenceValidator,
 };
 use fhedb_types::{CollectionQuery, FieldModification};
 use serde::Serialize;
@@ -23,6 +23,8 @@ struct JsonFieldDefinition {
     default: Option<serde_json::Value>,
     /// Whether the field can be null.
     nullable: bool,
+    /// Whether no two documents may hold the same non-null value in the field.
+    unique: bool,
 }
 
 impl From<&FieldDefinition> for JsonFieldDefinition {
@@ -35,6 +37,7 @@ impl From<&FieldDefinition> for JsonFieldDefinition {
                 .as_ref()
                 .and_then(|v| serde_json::to_value(v).ok()),
             nullable,
+            unique: def.unique,
         }
     }
 }
@@ -67,6 +70,8 @@ fn format_field_type(ft: &FieldType) -> String {
         FieldType::String => "string".to_string(),
         FieldType::IdString => "id_string".to_string(),
         FieldType::IdInt => "id_int".to_string(),
+        FieldType::EncryptedInt => "encrypted_int".to_string(),
+        FieldType::EncryptedString => "encrypted_string".to_string(),
         FieldType::Array(inner) => format!("array({})", format_field_type(inner)),
         FieldType::Reference(r) => format!("reference({})", r),
         FieldType::Nullable(inner) => format!("nullable({})", format_field_type(inner)),
@@ -91,6 +96,23 @@ fn serialize_schema(schema: &Schema) -> Result<serde_json::Value, String> {
     serde_json::to_value(&schema_map).map_err(|e| e.to_string())
 }
 
+/// Serializes the schema of a collection along with its indexes, each listed as the
+/// ordered names of its fields.
+///
+/// ## Arguments
+///
+/// * `col` - The [`Collection`] to describe.
+///
+/// ## Returns
+///
+/// Returns [`Ok`]\([`serde_json::Value`]) on success, or [`Err`]\([`String`]) on failure.
+fn serialize_schema_with_indexes(col: &Collection) -> Result<serde_json::Value, String> {
+    Ok(json!({
+        "fields": serialize_schema(col.schema())?,
+        "indexes": col.list_indexes(),
+    }))
+}
+
 /// Executes a collection-level query and returns the result.
 ///
 /// ## Arguments
@@ -148,7 +170,7 @@ pub fn execute_collection_query(
             let col = db
                 .get_collection(&name)
                 .ok_or_else(|| format!("Collection '{}' not found", name))?;
-            serialize_schema(col.schema())
+            serialize_schema_with_indexes(col)
         }
         CollectionQuery::Modify {
             name,
@@ -182,5 +204,31 @@ pub fn execute_collection_query(
             }
             serialize_schema(col.schema())
         }
+        CollectionQuery::Compact { name } => {
+            // Compaction takes its own locks, so that reads are not blocked while it runs.
+            drop(dbs);
+            let report = state.compact_collection(&db_name, &name)?;
+            Ok(json!({
+                "compacted": name,
+                "segments_compacted": report.segments_compacted,
+                "segments_removed": report.segments_removed,
+                "entries_removed": report.entries_removed,
+                "bytes_reclaimed": report.bytes_reclaimed,
+            }))
+        }
+        CollectionQuery::CreateIndex { name, field_names } => {
+            let col = db
+                .get_collection_mut(&name)
+                .ok_or_else(|| format!("Collection '{}' not found", name))?;
+            col.create_compound_index(&field_names)?;
+            Ok(json!({ "collection": name, "indexed": field_names }))
+        }
+        CollectionQuery::DropIndex { name, field_names } => {
+            let col = db
+                .get_collection_mut(&name)
+                .ok_or_else(|| format!("Collection '{}' not found", name))?;
+            col.drop_compound_index(&field_names)?;
+            Ok(json!({ "collection": name, "dropped_index": field_names }))
+        }
     }
 }
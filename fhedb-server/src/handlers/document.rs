@@ -1,12 +1,13 @@
 //! # Document Query Handlers
 //!
-//! Handles document operations (INSERT, GET, UPDATE, DELETE) within a database context.
+//! Handles document operations (INSERT, GET, SUM, HISTORY, UPDATE, DELETE) within a database context.
 
 use std::collections::HashMap;
 
 use bson::{Bson, Document as BsonDocument};
 use fhedb_core::prelude::{
     Collection, Database, FieldType, ReferenceChecker, Schema, SchemaOps, ValueParseable,
+    parse_timestamp,
 };
 use fhedb_types::{DocumentQuery, FieldCondition, FieldSelector};
 use serde_json::{Value as JsonValue, json};
@@ -38,7 +39,15 @@ pub fn execute_document_query(
             collection_name,
             conditions,
             selectors,
-        } => execute_get(db_name, collection_name, conditions, selectors, state),
+            as_of,
+        } => execute_get(
+            db_name,
+            collection_name,
+            conditions,
+            selectors,
+            as_of,
+            state,
+        ),
         DocumentQuery::Update {
             collection_name,
             conditions,
@@ -57,6 +66,15 @@ pub fn execute_document_query(
             conditions,
             selectors,
         } => execute_delete(db_name, collection_name, conditions, selectors, state),
+        DocumentQuery::Sum {
+            collection_name,
+            field_name,
+            conditions,
+        } => execute_sum(db_name, collection_name, field_name, conditions, state),
+        DocumentQuery::History {
+            collection_name,
+            conditions,
+        } => execute_history(db_name, collection_name, conditions, state),
     }
 }
 
@@ -105,16 +123,19 @@ fn execute_insert(
 /// * `collection_name` - The name of the collection to query.
 /// * `conditions` - The filter conditions.
 /// * `selectors` - The fields to return.
+/// * `as_of` - The timestamp to read the collection at, if not its current state.
 /// * `state` - The server state.
 ///
 /// ## Returns
 ///
-/// Returns matching documents as a JSON array.
+/// Returns matching documents as a JSON array. Referenced documents are always
+/// resolved against the current state of their collections.
 fn execute_get(
     db_name: String,
     collection_name: String,
     conditions: Vec<FieldCondition>,
     selectors: Vec<FieldSelector>,
+    as_of: Option<String>,
     state: &ServerState,
 ) -> Result<JsonValue, String> {
     let dbs = state.databases.read().map_err(|e| e.to_string())?;
@@ -125,7 +146,10 @@ fn execute_get(
         .get_collection(&collection_name)
         .ok_or_else(|| format!("Collection '{}' not found.", collection_name))?;
 
-    let filtered = collection.filter(&conditions)?;
+    let filtered = match as_of {
+        Some(as_of) => collection.filter_as_of(&conditions, parse_timestamp(&as_of)?)?,
+        None => collection.filter(&conditions)?,
+    };
     let results: Result<Vec<_>, _> = filtered
         .iter()
         .map(|doc| select_fields(&doc.data, &selectors, collection, db, 1))
@@ -134,6 +158,85 @@ fn execute_get(
     Ok(JsonValue::Array(results?))
 }
 
+/// Executes a SUM document query.
+///
+/// ## Arguments
+///
+/// * `db_name` - The name of the database.
+/// * `collection_name` - The name of the collection to query.
+/// * `field_name` - The name of the field to sum.
+/// * `conditions` - The filter conditions.
+/// * `state` - The server state.
+///
+/// ## Returns
+///
+/// Returns the sum and the number of values summed. The sum of an encrypted field
+/// is itself encrypted, and can only be read by the client holding the key.
+fn execute_sum(
+    db_name: String,
+    collection_name: String,
+    field_name: String,
+    conditions: Vec<FieldCondition>,
+    state: &ServerState,
+) -> Result<JsonValue, String> {
+    let dbs = state.databases.read().map_err(|e| e.to_string())?;
+    let db = dbs
+        .get(&db_name)
+        .ok_or_else(|| format!("Database '{}' not found.", db_name))?;
+    let collection = db
+        .get_collection(&collection_name)
+        .ok_or_else(|| format!("Collection '{}' not found.", collection_name))?;
+
+    let (sum, count) = collection.sum(&field_name, &conditions)?;
+    Ok(json!({
+        "sum": serde_json::to_value(&sum).map_err(|e| e.to_string())?,
+        "count": count,
+    }))
+}
+
+/// Executes a GET HISTORY document query.
+///
+/// ## Arguments
+///
+/// * `db_name` - The name of the database.
+/// * `collection_name` - The name of the collection to query.
+/// * `conditions` - The condition on the ID field selecting the document.
+/// * `state` - The server state.
+///
+/// ## Returns
+///
+/// Returns the document's log entries as a JSON array, oldest first. Entries merged
+/// by compaction also carry the timestamp of the document's original insert.
+fn execute_history(
+    db_name: String,
+    collection_name: String,
+    conditions: Vec<FieldCondition>,
+    state: &ServerState,
+) -> Result<JsonValue, String> {
+    let dbs = state.databases.read().map_err(|e| e.to_string())?;
+    let db = dbs
+        .get(&db_name)
+        .ok_or_else(|| format!("Database '{}' not found.", db_name))?;
+    let collection = db
+        .get_collection(&collection_name)
+        .ok_or_else(|| format!("Collection '{}' not found.", collection_name))?;
+
+    let mut entries = Vec::new();
+    for log_entry in collection.filter_history(&conditions)? {
+        let mut entry = json!({
+            "timestamp": log_entry.timestamp,
+            "operation": log_entry.operation.as_str(),
+            "document": serde_json::to_value(&log_entry.document).map_err(|e| e.to_string())?,
+        });
+        if let Some(inserted_at) = log_entry.inserted_at {
+            entry["inserted_at"] = JsonValue::String(inserted_at);
+        }
+        entries.push(entry);
+    }
+
+    Ok(JsonValue::Array(entries))
+}
+
 /// Executes an UPDATE document query with rollback on failure.
 ///
 /// ## Arguments
@@ -170,22 +273,36 @@ fn execute_update(
         return Ok(json!([]));
     }
 
+    // The ID field cannot be updated, so it is left out of the values restored on rollback.
+    let id_field = collection.id_field_name().to_string();
     let originals: Vec<_> = matching
-        .iter()
-        .map(|d| (d.id.clone(), d.data.clone()))
+        .into_iter()
+        .map(|mut d| {
+            d.data.remove(&id_field);
+            (d.id, d.data)
+        })
         .collect();
-    let matching_ids: Vec<_> = matching.into_iter().map(|d| d.id).collect();
 
     let collection = db.get_collection_mut(&collection_name).unwrap();
     let update_doc = convert_fields_to_bson(&updates, collection.schema())?;
 
     let mut updated_docs = Vec::new();
-    for (idx, id) in matching_ids.iter().enumerate() {
+    for (idx, (id, _)) in originals.iter().enumerate() {
         match collection.update_document(id.clone(), update_doc.clone()) {
             Ok(doc) => updated_docs.push(doc),
             Err(errors) => {
-                for (orig_id, orig_data) in originals.iter().take(idx) {
-                    let _ = collection.update_document(orig_id.clone(), orig_data.clone());
+                let mut rollback_errors = Vec::new();
+                for (orig_id, orig_data) in originals.iter().take(idx).rev() {
+                    if let Err(e) = collection.update_document(orig_id.clone(), orig_data.clone()) {
+                        rollback_errors.extend(e);
+                    }
+                }
+                if !rollback_errors.is_empty() {
+                    return Err(format!(
+                        "Update failed: {}. Rolling back the documents already updated also failed: {}",
+                        errors.join("; "),
+                        rollback_errors.join("; ")
+                    ));
                 }
                 return Err(format!(
                     "Update failed and rolled back: {}",
@@ -205,7 +322,7 @@ fn execute_update(
     Ok(JsonValue::Array(results?))
 }
 
-/// Executes a DELETE document query.
+/// Executes a DELETE document query with rollback on failure.
 ///
 /// ## Arguments
 ///
@@ -246,8 +363,24 @@ fn execute_delete(
     let results = results?;
 
     let collection = db.get_collection_mut(&collection_name).unwrap();
-    for doc in matching {
-        collection.remove_document(doc.id);
+    for (idx, doc) in matching.iter().enumerate() {
+        if let Err(error) = collection.remove_document(doc.id.clone()) {
+            // The removed documents still hold their ID field, so they are restored under it.
+            let mut rollback_errors = Vec::new();
+            for removed in matching.iter().take(idx).rev() {
+                if let Err(e) = collection.add_document(removed.data.clone()) {
+                    rollback_errors.extend(e);
+                }
+            }
+            if !rollback_errors.is_empty() {
+                return Err(format!(
+                    "Delete failed: {}. Restoring the documents already deleted also failed: {}",
+                    error,
+                    rollback_errors.join("; ")
+                ));
+            }
+            return Err(format!("Delete failed and rolled back: {}", error));
+        }
     }
 
     Ok(JsonValue::Array(results))
@@ -1,12 +1,15 @@
 //! # Request Handlers
 //!
-//! HTTP request handlers for database and contextual operations.
+//! HTTP request handlers for database and contextual operations, and for change streams.
 
 mod base;
+mod changes;
 pub(crate) mod collection;
 mod contextual;
 mod document;
 
+pub use changes::{ChangesParams, handle_changes};
+
 use crate::{
     extractor::ParsedQuery, handlers::base::execute_base_query, internal_error, state::ServerState,
     success,
@@ -4,10 +4,11 @@ use axum::{
     middleware::{self},
     routing::{get, post},
 };
-use log::info;
+use fhedb_core::prelude::Durability;
+use log::{error, info};
 
 use fhedb_server::prelude::{
-    CoreConfig, ServerState, check_database, handle_base, handle_db, setup_logger,
+    CoreConfig, ServerState, check_database, handle_base, handle_changes, handle_db, setup_logger,
 };
 
 #[tokio::main]
@@ -18,15 +19,64 @@ async fn main() {
     setup_logger(core_config.logging.level(), core_config.logging.file())
         .expect("Unable to set up logging utility.");
 
-    let state = ServerState::new(core_config.storage.base_dir().clone());
+    let durability = core_config.storage.durability();
+    let state = ServerState::new(
+        core_config.storage.base_dir().clone(),
+        core_config.storage.backup_dir().clone(),
+        durability,
+        core_config.storage.max_segment_size(),
+        core_config.storage.compaction_threshold(),
+        core_config.storage.history_retention(),
+        core_config.storage.compression(),
+        core_config.storage.encryption_key(),
+    );
+
+    if let Durability::GroupCommit(interval) = durability
+        && !interval.is_zero()
+    {
+        let flush_state = state.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                let state = flush_state.clone();
+                if let Err(err) = tokio::task::spawn_blocking(move || state.flush_databases()).await
+                {
+                    error!("Group commit task failed: {:#?}", err);
+                }
+            }
+        });
+    }
+
+    if let Some(interval) = core_config.storage.compaction_interval() {
+        let compaction_state = state.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                let state = compaction_state.clone();
+                if let Err(err) =
+                    tokio::task::spawn_blocking(move || state.compact_databases()).await
+                {
+                    error!("Background compaction task failed: {:#?}", err);
+                }
+            }
+        });
+    }
+
     let layered_db_handler = handle_db.layer(middleware::from_fn_with_state(
         state.clone(),
         check_database,
     ));
+    let layered_changes_handler = handle_changes.layer(middleware::from_fn_with_state(
+        state.clone(),
+        check_database,
+    ));
     let app = Router::new()
         .route("/", get(|| async { "Hello, FHEDB!" }))
         .route("/", post(handle_base))
         .route("/{db_name}", post(layered_db_handler))
+        .route("/{db_name}/changes", get(layered_changes_handler))
         .with_state(state);
 
     let address = format!(
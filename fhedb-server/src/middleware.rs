@@ -9,7 +9,7 @@ use axum::{
     response::{IntoResponse, Response},
 };
 use fhedb_core::prelude::Database;
-use log::{debug, error};
+use log::{debug, error, warn};
 
 use crate::{error as api_error, internal_error, state::ServerState};
 
@@ -55,8 +55,19 @@ pub async fn check_database(
                 }
 
                 debug!("Loading database '{}' from disk into memory.", &db_name);
-                match Database::from_files(&db_name, &state.data_dir) {
-                    Ok(db) => {
+                match Database::from_files_with_key(
+                    &db_name,
+                    &state.data_dir,
+                    state.encryption_key.clone(),
+                ) {
+                    Ok(mut db) => {
+                        state.configure_database(&mut db);
+                        for (collection_name, report) in db.recovery_reports() {
+                            warn!(
+                                "Recovered damaged logfile of collection '{}' in database '{}': {}",
+                                collection_name, &db_name, report
+                            );
+                        }
                         let mut dbs = match state.databases.write() {
                             Ok(dbs) => dbs,
                             Err(err) => {
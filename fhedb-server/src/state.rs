@@ -2,11 +2,13 @@
 //!
 //! Shared state passed to all request handlers, including database cache and data directory.
 
-use fhedb_core::prelude::Database;
+use fhedb_core::prelude::{CompactionReport, Compression, Database, Durability, EncryptionKey};
+use log::{error, info};
 use std::{
     collections::HashMap,
     path::PathBuf,
     sync::{Arc, RwLock},
+    time::Duration,
 };
 
 /// Shared state for the fhedb server, cloned into each handler via Axum state.
@@ -16,6 +18,20 @@ pub struct ServerState {
     pub databases: Arc<RwLock<HashMap<String, Database>>>,
     /// The base directory path where database files are stored.
     pub data_dir: PathBuf,
+    /// The directory that backup and restore paths are resolved against.
+    pub backup_dir: PathBuf,
+    /// The durability setting applied to every loaded or created database.
+    pub durability: Durability,
+    /// The maximum log segment size in bytes applied to every loaded or created database.
+    pub max_segment_size: usize,
+    /// The compaction threshold applied to every loaded or created database.
+    pub compaction_threshold: f64,
+    /// The history retention window applied to every loaded or created database.
+    pub history_retention: Option<Duration>,
+    /// The compression codec applied to collections created in any database.
+    pub compression: Compression,
+    /// The key encrypting the files of every database, if encryption at rest is enabled.
+    pub encryption_key: Option<EncryptionKey>,
 }
 
 impl ServerState {
@@ -24,10 +40,158 @@ impl ServerState {
     /// ## Arguments
     ///
     /// * `data_dir` - The base [`PathBuf`] for database storage.
-    pub fn new(data_dir: PathBuf) -> Self {
+    /// * `backup_dir` - The [`PathBuf`] that backup and restore paths are resolved against.
+    /// * `durability` - The [`Durability`] applied to every database.
+    /// * `max_segment_size` - The maximum log segment size in bytes applied to every database.
+    /// * `compaction_threshold` - The compaction threshold applied to every database.
+    /// * `history_retention` - The history retention window applied to every database.
+    /// * `compression` - The compression codec applied to newly created collections.
+    /// * `encryption_key` - The key encrypting the files of every database, if any.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        data_dir: PathBuf,
+        backup_dir: PathBuf,
+        durability: Durability,
+        max_segment_size: usize,
+        compaction_threshold: f64,
+        history_retention: Option<Duration>,
+        compression: Compression,
+        encryption_key: Option<EncryptionKey>,
+    ) -> Self {
         Self {
             databases: Arc::new(RwLock::new(HashMap::new())),
             data_dir,
+            backup_dir,
+            durability,
+            max_segment_size,
+            compaction_threshold,
+            history_retention,
+            compression,
+            encryption_key,
+        }
+    }
+
+    /// Applies the server's storage settings to a loaded or created database.
+    ///
+    /// ## Arguments
+    ///
+    /// * `db` - The [`Database`] to configure.
+    pub fn configure_database(&self, db: &mut Database) {
+        db.set_durability(self.durability);
+        db.set_max_segment_size(self.max_segment_size);
+        db.set_compaction_threshold(self.compaction_threshold);
+        db.set_history_retention(self.history_retention);
+        db.set_compression(self.compression);
+        db.set_encryption_key(self.encryption_key.clone());
+    }
+
+    /// Compacts the log of a collection while keeping it available.
+    ///
+    /// The databases are only locked for writing while the compaction is planned and
+    /// committed. The live entries are copied under a read lock, so reads carry on meanwhile.
+    ///
+    /// ## Arguments
+    ///
+    /// * `db_name` - The name of the database holding the collection.
+    /// * `collection_name` - The name of the collection to compact.
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`Ok`]\([`CompactionReport`]) describing the space reclaimed,
+    /// or [`Err`]\([`String`]) if the collection was not found or could not be compacted.
+    pub fn compact_collection(
+        &self,
+        db_name: &str,
+        collection_name: &str,
+    ) -> Result<CompactionReport, String> {
+        let not_found = || format!("Collection '{}' not found", collection_name);
+
+        let plan = {
+            let mut dbs = self.databases.write().map_err(|e| e.to_string())?;
+            let db = dbs
+                .get_mut(db_name)
+                .ok_or_else(|| "Database not found".to_string())?;
+            let collection = db
+                .get_collection_mut(collection_name)
+                .ok_or_else(not_found)?;
+            collection.plan_compaction().map_err(|e| e.to_string())?
+        };
+        let Some(plan) = plan else {
+            return Ok(CompactionReport::default());
+        };
+
+        let prepared = {
+            let dbs = self.databases.read().map_err(|e| e.to_string())?;
+            let db = dbs
+                .get(db_name)
+                .ok_or_else(|| "Database not found".to_string())?;
+            let collection = db.get_collection(collection_name).ok_or_else(not_found)?;
+            collection
+                .prepare_compaction(plan)
+                .map_err(|e| e.to_string())?
+        };
+
+        let mut dbs = self.databases.write().map_err(|e| e.to_string())?;
+        let db = dbs
+            .get_mut(db_name)
+            .ok_or_else(|| "Database not found".to_string())?;
+        let collection = db
+            .get_collection_mut(collection_name)
+            .ok_or_else(not_found)?;
+        collection
+            .commit_compaction(prepared)
+            .map_err(|e| e.to_string())
+    }
+
+    /// Compacts every loaded collection whose garbage ratio crossed the compaction threshold.
+    ///
+    /// Failures are logged rather than returned, as this runs in the background.
+    pub fn compact_databases(&self) {
+        let candidates: Vec<(String, String)> = match self.databases.read() {
+            Ok(dbs) => dbs
+                .iter()
+                .flat_map(|(db_name, db)| {
+                    db.collections_needing_compaction()
+                        .into_iter()
+                        .map(|collection_name| (db_name.clone(), collection_name))
+                })
+                .collect(),
+            Err(err) => {
+                error!("Unable to acquire read lock on databases: {:#?}", err);
+                return;
+            }
+        };
+
+        for (db_name, collection_name) in candidates {
+            match self.compact_collection(&db_name, &collection_name) {
+                Ok(report) => info!(
+                    "Compacted collection '{}' in database '{}': {}",
+                    collection_name, db_name, report
+                ),
+                Err(err) => error!(
+                    "Unable to compact collection '{}' in database '{}': {}",
+                    collection_name, db_name, err
+                ),
+            }
+        }
+    }
+
+    /// Flushes the pending writes of every loaded database to disk.
+    ///
+    /// Failures are logged rather than returned, as this runs in the background.
+    pub fn flush_databases(&self) {
+        let dbs = match self.databases.read() {
+            Ok(dbs) => dbs,
+            Err(err) => {
+                error!("Unable to acquire read lock on databases: {:#?}", err);
+                return;
+            }
+        };
+
+        for (name, db) in dbs.iter() {
+            if let Err(err) = db.flush() {
+                error!("Unable to flush database '{}' to disk: {:#?}", name, err);
+            }
         }
     }
 }
@@ -26,6 +26,20 @@ pub enum DatabaseQuery {
     },
     /// Lists all databases.
     List,
+    /// Backs up a database into an archive file.
+    Backup {
+        /// The name of the database to back up.
+        name: String,
+        /// The path of the archive to write.
+        path: String,
+    },
+    /// Restores a database from an archive file.
+    Restore {
+        /// The name to restore the database under.
+        name: String,
+        /// The path of the archive to read.
+        path: String,
+    },
 }
 
 /// Represents queries that operate within a specific database context.
@@ -77,6 +91,26 @@ pub enum CollectionQuery {
         /// The name of the collection to get the schema for.
         name: String,
     },
+    /// Compacts the log of a specific collection, reclaiming the space of superseded entries.
+    Compact {
+        /// The name of the collection to compact.
+        name: String,
+    },
+    /// Creates a secondary index on a field of a collection, or a compound index on an
+    /// ordered list of fields.
+    CreateIndex {
+        /// The name of the collection to index.
+        name: String,
+        /// The names of the fields to index, in order.
+        field_names: Vec<String>,
+    },
+    /// Drops the secondary index on a field or an ordered list of fields of a collection.
+    DropIndex {
+        /// The name of the indexed collection.
+        name: String,
+        /// The names of the indexed fields, in order.
+        field_names: Vec<String>,
+    },
 }
 
 /// Represents queries on documents within a database's collections.
@@ -117,5 +151,23 @@ pub enum DocumentQuery {
         conditions: Vec<FieldCondition>,
         /// The fields to return in the response.
         selectors: Vec<FieldSelector>,
+        /// The RFC 3339 timestamp to read the collection at (`None` means its current state).
+        as_of: Option<String>,
+    },
+    /// Sum a numeric field over the document(s) of a collection.
+    Sum {
+        /// The name of the collection to query.
+        collection_name: String,
+        /// The name of the field to sum.
+        field_name: String,
+        /// The conditions to filter documents (empty means sum over all).
+        conditions: Vec<FieldCondition>,
+    },
+    /// Get the change history of a document of a collection.
+    History {
+        /// The name of the collection to query.
+        collection_name: String,
+        /// The condition on the ID field selecting the document.
+        conditions: Vec<FieldCondition>,
     },
 }
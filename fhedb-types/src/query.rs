@@ -2,7 +2,7 @@
 //!
 //! Type definitions for query operations and conditions.
 
-use std::collections::HashMap;
+use std::{collections::HashMap, fmt};
 
 /// Represents comparison operators for document field conditions.
 #[derive(Debug, Clone, PartialEq)]
@@ -23,6 +23,21 @@ pub enum QueryOperator {
     Similar,
 }
 
+impl fmt::Display for QueryOperator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let symbol = match self {
+            QueryOperator::Equal => "=",
+            QueryOperator::NotEqual => "!=",
+            QueryOperator::GreaterThan => ">",
+            QueryOperator::GreaterThanOrEqual => ">=",
+            QueryOperator::LessThanOrEqual => "<=",
+            QueryOperator::LessThan => "<",
+            QueryOperator::Similar => "==",
+        };
+        f.write_str(symbol)
+    }
+}
+
 /// Represents a condition on a document field for filtering/querying.
 #[derive(Debug, Clone, PartialEq)]
 pub struct FieldCondition {
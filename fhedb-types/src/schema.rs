@@ -35,6 +35,18 @@ pub enum FieldType {
     IdString,
     /// A document identifier that must be a u64 integer.
     IdInt,
+    /// A 64-bit integer encrypted client-side, which can be summed and compared for
+    /// equality without being decrypted.
+    ///
+    /// Equality is checked through a deterministic tag stored with every value, so the
+    /// server learns which documents hold equal values and how often each value occurs.
+    EncryptedInt,
+    /// A UTF-8 string encrypted client-side, which can be compared for equality through
+    /// its blind index without being decrypted.
+    ///
+    /// The blind index is deterministic, so the server learns which documents hold equal
+    /// values and how often each value occurs.
+    EncryptedString,
 }
 
 /// Represents a field definition in a document schema.
@@ -44,6 +56,8 @@ pub struct FieldDefinition {
     pub field_type: FieldType,
     /// The default value for the field. If None, the field is required.
     pub default_value: Option<Bson>,
+    /// Whether no two documents may hold the same non-null value in the field.
+    pub unique: bool,
 }
 
 impl FieldDefinition {
@@ -56,6 +70,7 @@ impl FieldDefinition {
         Self {
             field_type,
             default_value: None,
+            unique: false,
         }
     }
 
@@ -69,6 +84,7 @@ impl FieldDefinition {
         Self {
             field_type,
             default_value: Some(default_value),
+            unique: false,
         }
     }
 
@@ -82,8 +98,20 @@ impl FieldDefinition {
         Self {
             field_type,
             default_value,
+            unique: false,
         }
     }
+
+    /// Sets whether the field is unique, so that no two documents may hold the same
+    /// non-null value in it.
+    ///
+    /// ## Arguments
+    ///
+    /// * `unique` - Whether the field is unique.
+    pub fn with_unique(mut self, unique: bool) -> Self {
+        self.unique = unique;
+        self
+    }
 }
 
 /// Describes the schema for a document.